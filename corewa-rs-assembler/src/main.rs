@@ -1,10 +1,22 @@
 use corewa_rs::{
-    language::{read_champion, write_champion},
-    spec::HEADER_SIZE,
+    language::{
+        debug_info::DebugInfo, diagnostics::compile_with_diagnostics, loader::FileLoader,
+        report::Report,
+    },
+    spec::{VmConfig, HEADER_SIZE},
 };
 
+use std::{
+    fs::File,
+    io::{Read, Write},
+    path::PathBuf,
+};
+use structopt::StructOpt;
+
 fn main() {
-    let exit_code = match run() {
+    let opts = Options::from_args();
+
+    let exit_code = match run(&opts) {
         Ok(_) => 0,
         Err(err) => {
             eprintln!("{}", err);
@@ -15,17 +27,91 @@ fn main() {
     std::process::exit(exit_code)
 }
 
-fn run() -> Result<(), String> {
-    let champion =
-        read_champion(std::io::stdin()).map_err(|e| format!("Failed to read champion:\n{}", e))?;
+fn run(opts: &Options) -> Result<(), String> {
+    let mut source = String::new();
+    match &opts.input {
+        Some(path) => File::open(path).and_then(|mut file| file.read_to_string(&mut source)),
+        None => std::io::stdin().read_to_string(&mut source),
+    }
+    .map_err(|e| format!("Failed to read champion:\n{}", e))?;
+
+    // Included files are resolved relative to the input, or to the working
+    // directory when reading from stdin
+    let input = opts.input.as_deref();
+
+    // Report every error at once
+    let compiled = compile_with_diagnostics(&source, input, &FileLoader, &VmConfig::default())
+        .map_err(|diagnostics| {
+            let errors = diagnostics
+                .iter()
+                .map(|diagnostic| Report::from(diagnostic).render(&source, input, &FileLoader))
+                .collect::<Vec<_>>();
+            format!(
+                "{}\nFailed to compile champion: {} error{}",
+                errors.join("\n"),
+                errors.len(),
+                if errors.len() == 1 { "" } else { "s" }
+            )
+        })?;
 
-    let champion_name = champion.name.clone();
+    let warnings = &compiled.warnings;
+    for warning in warnings {
+        eprintln!("warning: {}", warning);
+    }
+    if opts.warnings_as_errors() && !warnings.is_empty() {
+        return Err(format!(
+            "Failed to compile champion: {} warnings treated as errors",
+            warnings.len()
+        ));
+    }
 
-    let size_written = write_champion(std::io::stdout(), champion)
+    std::io::stdout()
+        .write_all(&compiled.byte_code)
         .map_err(|e| format!("Failed to write champion:\n{}", e))?;
 
-    eprintln!("Successfully compiled '{}'", champion_name);
-    eprintln!("code section: {} bytes", size_written - HEADER_SIZE);
+    if opts.listing {
+        eprint!("{}", compiled.listing.render(&source));
+        eprintln!();
+    }
+    if let Some(path) = &opts.debug_info {
+        let file = opts
+            .input
+            .as_ref()
+            .map(|input| input.to_string_lossy().into_owned());
+        File::create(path)
+            .and_then(|out| DebugInfo::new(&compiled.listing, &source, file).write(out))
+            .map_err(|e| format!("Failed to write debug info:\n{}", e))?;
+    }
+
+    eprintln!("Successfully compiled '{}'", compiled.name);
+    eprintln!(
+        "code section: {} bytes",
+        compiled.byte_code.len() - HEADER_SIZE
+    );
 
     Ok(())
 }
+
+/// Compiles a champion's source into bytecode written to stdout
+#[derive(Debug, StructOpt)]
+struct Options {
+    /// The source file to compile, read from stdin if omitted
+    #[structopt(parse(from_os_str))]
+    input: Option<PathBuf>,
+    /// Writes a sidecar file mapping the code back to its source, for the
+    /// VM frontends to show which line a process is executing
+    #[structopt(long, parse(from_os_str))]
+    debug_info: Option<PathBuf>,
+    /// Prints what was emitted for each source line to stderr
+    #[structopt(long)]
+    listing: bool,
+    /// Warning options: `-Werror` fails the compilation on warnings
+    #[structopt(short = "W", possible_values = &["error"], number_of_values = 1)]
+    warning_options: Vec<String>,
+}
+
+impl Options {
+    fn warnings_as_errors(&self) -> bool {
+        self.warning_options.iter().any(|option| option == "error")
+    }
+}
@@ -0,0 +1,259 @@
+//! The debugger's commands, parsed from a line of input.
+//!
+//! Numbers are decimal or hexadecimal with a `0x` prefix. Addresses can also
+//! be given as a label of the champions' code, prefixed with a player number
+//! (`2:loop`) when several players define it.
+
+use corewa_rs::vm::{debugger::BreakpointId, types::*};
+
+use std::convert::TryFrom;
+
+/// The number of bytes examined by `x` without a count
+const DEFAULT_EXAMINE_COUNT: usize = 16;
+/// The number of instructions disassembled by `disas` without a count
+const DEFAULT_DISASSEMBLE_COUNT: usize = 8;
+
+pub const HELP: &str = "\
+step [n]                  run n cycles (1 by default), stopping at breakpoints
+continue                  run until a breakpoint is hit or the match is over
+break <addr>              break when a process reaches an address
+watch <addr>[..<end>]     break when memory in the range is written
+delete <id>               delete a breakpoint
+info breakpoints          list the breakpoints
+info processes            list the processes
+info regs <pid>           print the registers of a process
+x/<n> <addr>              print n bytes of memory (16 by default)
+disas <addr> [n]          disassemble n instructions (8 by default)
+set reg <pid> r<n> <val>  write to a register of a process
+set mem <addr> <byte>...  write bytes to memory
+quit                      exit the debugger";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Location {
+    Address(usize),
+    /// A label of the player's code, or of the only player defining it
+    Label {
+        player_id: Option<PlayerId>,
+        name: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Info {
+    Breakpoints,
+    Processes,
+    Registers(Pid),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Step(u32),
+    Continue,
+    Break(Location),
+    /// Watches a single byte when there is no `end`, which is excluded
+    Watch {
+        start: Location,
+        end: Option<Location>,
+    },
+    Delete(BreakpointId),
+    Info(Info),
+    Examine {
+        at: Location,
+        count: usize,
+    },
+    Disassemble {
+        at: Location,
+        count: usize,
+    },
+    /// Registers are numbered from 1
+    SetRegister {
+        pid: Pid,
+        register: usize,
+        value: i32,
+    },
+    SetMemory {
+        at: Location,
+        bytes: Vec<u8>,
+    },
+    Help,
+    Quit,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ParseError {
+    #[error("Unknown command '{0}', try 'help'")]
+    UnknownCommand(String),
+    #[error("Missing {0}")]
+    MissingArgument(&'static str),
+    #[error("Unexpected argument '{0}'")]
+    UnexpectedArgument(String),
+    #[error("Invalid number '{0}'")]
+    InvalidNumber(String),
+    #[error("Invalid register '{0}', expected r1, r2...")]
+    InvalidRegister(String),
+    #[error("Invalid label '{0}'")]
+    InvalidLabel(String),
+    #[error("Unknown info '{0}', expected breakpoints, processes or regs")]
+    UnknownInfo(String),
+}
+
+/// Parses a line of input, which holds no command when it is blank or a
+/// `#` comment
+pub fn parse(line: &str) -> Result<Option<Command>, ParseError> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let mut args = Args(line.split_whitespace());
+    let name = args.next("command")?;
+
+    // `x/16` carries its count in the command name
+    let (name, suffix) = match name.find('/') {
+        Some(idx) => (&name[..idx], Some(&name[idx + 1..])),
+        None => (name, None),
+    };
+    if suffix.is_some() && name != "x" {
+        return Err(ParseError::UnknownCommand(line.to_owned()));
+    }
+
+    let command = match name {
+        "step" | "s" => Command::Step(args.optional_number()?.unwrap_or(1)),
+        "continue" | "c" => Command::Continue,
+        "break" | "b" => Command::Break(args.location()?),
+        "watch" => {
+            let range = args.next("address range")?;
+            match range.find("..") {
+                Some(idx) => Command::Watch {
+                    start: parse_location(&range[..idx])?,
+                    end: Some(parse_location(&range[idx + 2..])?),
+                },
+                None => Command::Watch {
+                    start: parse_location(range)?,
+                    end: None,
+                },
+            }
+        }
+        "delete" | "d" => Command::Delete(args.number("breakpoint number")?),
+        "info" | "i" => {
+            let info = match args.next("what to print")? {
+                "breakpoints" | "b" => Info::Breakpoints,
+                "processes" | "p" => Info::Processes,
+                "regs" | "registers" | "r" => Info::Registers(args.number("process id")?),
+                other => return Err(ParseError::UnknownInfo(other.to_owned())),
+            };
+            Command::Info(info)
+        }
+        "x" => {
+            let count = match suffix {
+                Some(count) => parse_number(count)?,
+                None => DEFAULT_EXAMINE_COUNT,
+            };
+            Command::Examine {
+                at: args.location()?,
+                count,
+            }
+        }
+        "disas" | "disassemble" => Command::Disassemble {
+            at: args.location()?,
+            count: args.optional_number()?.unwrap_or(DEFAULT_DISASSEMBLE_COUNT),
+        },
+        "set" => match args.next("'reg' or 'mem'")? {
+            "reg" => Command::SetRegister {
+                pid: args.number("process id")?,
+                register: parse_register(args.next("register")?)?,
+                value: args.number("value")?,
+            },
+            "mem" => {
+                let at = args.location()?;
+                let bytes = args
+                    .0
+                    .by_ref()
+                    .map(parse_number)
+                    .collect::<Result<Vec<_>, _>>()?;
+                if bytes.is_empty() {
+                    return Err(ParseError::MissingArgument("bytes"));
+                }
+                Command::SetMemory { at, bytes }
+            }
+            other => return Err(ParseError::UnexpectedArgument(other.to_owned())),
+        },
+        "help" | "h" => Command::Help,
+        "quit" | "q" => Command::Quit,
+        _ => return Err(ParseError::UnknownCommand(name.to_owned())),
+    };
+
+    args.end()?;
+    Ok(Some(command))
+}
+
+struct Args<'a>(std::str::SplitWhitespace<'a>);
+
+impl<'a> Args<'a> {
+    fn next(&mut self, expected: &'static str) -> Result<&'a str, ParseError> {
+        self.0.next().ok_or(ParseError::MissingArgument(expected))
+    }
+
+    fn number<T: TryFrom<i64>>(&mut self, expected: &'static str) -> Result<T, ParseError> {
+        parse_number(self.next(expected)?)
+    }
+
+    fn optional_number<T: TryFrom<i64>>(&mut self) -> Result<Option<T>, ParseError> {
+        self.0.next().map(parse_number).transpose()
+    }
+
+    fn location(&mut self) -> Result<Location, ParseError> {
+        parse_location(self.next("address")?)
+    }
+
+    fn end(mut self) -> Result<(), ParseError> {
+        match self.0.next() {
+            Some(arg) => Err(ParseError::UnexpectedArgument(arg.to_owned())),
+            None => Ok(()),
+        }
+    }
+}
+
+fn parse_number<T: TryFrom<i64>>(text: &str) -> Result<T, ParseError> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16),
+        None => digits.parse(),
+    }
+    .ok()
+    .filter(|_| !digits.starts_with(['+', '-']));
+
+    value
+        .map(|value| if negative { -value } else { value })
+        .and_then(|value| T::try_from(value).ok())
+        .ok_or_else(|| ParseError::InvalidNumber(text.to_owned()))
+}
+
+fn parse_location(text: &str) -> Result<Location, ParseError> {
+    if text.starts_with(|c: char| c.is_ascii_digit() || c == '-') && !text.contains(':') {
+        return parse_number(text).map(Location::Address);
+    }
+
+    let (player_id, name) = match text.find(':') {
+        Some(idx) => (Some(parse_number(&text[..idx])?), &text[idx + 1..]),
+        None => (None, text),
+    };
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return Err(ParseError::InvalidLabel(text.to_owned()));
+    }
+
+    Ok(Location::Label {
+        player_id,
+        name: name.to_owned(),
+    })
+}
+
+fn parse_register(text: &str) -> Result<usize, ParseError> {
+    text.strip_prefix('r')
+        .and_then(|number| number.parse().ok())
+        .filter(|register| *register > 0)
+        .ok_or_else(|| ParseError::InvalidRegister(text.to_owned()))
+}
@@ -0,0 +1,173 @@
+mod command;
+mod session;
+
+use session::{Flow, Session};
+
+use corewa_rs::{
+    language::{
+        debug_info::DebugInfo, loader::FileLoader, read_champion_with_loader,
+        write_champion_with_listing,
+    },
+    spec::VmConfig,
+    vm::{loader::LoadError, types::PlayerId, VirtualMachine},
+};
+use std::{
+    fs,
+    io::{self, BufRead, IsTerminal, Write},
+    path::{Path, PathBuf},
+};
+use structopt::StructOpt;
+
+const PROMPT: &str = "(dbg) ";
+
+fn main() {
+    let opts = Options::from_args();
+
+    let exit_code = match run(&opts) {
+        Ok(_) => 0,
+        Err(err) => {
+            eprintln!("{}", err);
+            1
+        }
+    };
+
+    std::process::exit(exit_code)
+}
+
+fn run(opts: &Options) -> Result<(), DbgError> {
+    if opts.champion_files.is_empty() {
+        return Err(DbgError::NoChampions);
+    }
+
+    let mut players = Vec::new();
+    let mut debug_infos = Vec::new();
+    for (i, path) in opts.champion_files.iter().enumerate() {
+        let player_id = i as PlayerId + 1;
+        let (champion, debug_info) = load_champion(path)?;
+        players.push((player_id, champion));
+        if let Some(debug_info) = debug_info {
+            debug_infos.push((player_id, debug_info));
+        }
+    }
+
+    let mut vm = VirtualMachine::new();
+    vm.load_players(&players)?;
+    let mut session = Session::new(vm, debug_infos);
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    // Scripts echo their commands so that their output reads like a session
+    for script in &opts.scripts {
+        let commands =
+            fs::read_to_string(script).map_err(|err| DbgError::Read(script.clone(), err))?;
+        for (i, line) in commands.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            writeln!(out, "{}{}", PROMPT, line)?;
+            match session.run_line(line, &mut out) {
+                Ok(Flow::Continue) => (),
+                Ok(Flow::Quit) => return Ok(()),
+                Err(err) => {
+                    return Err(DbgError::Script {
+                        path: script.clone(),
+                        line: i + 1,
+                        message: err.to_string(),
+                    })
+                }
+            }
+        }
+    }
+
+    let stdin = io::stdin();
+    let interactive = stdin.is_terminal();
+    let mut lines = stdin.lock().lines();
+    loop {
+        if interactive {
+            write!(out, "{}", PROMPT)?;
+            out.flush()?;
+        }
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => return Ok(()),
+        };
+        match session.run_line(&line, &mut out) {
+            Ok(Flow::Continue) => (),
+            Ok(Flow::Quit) => return Ok(()),
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+}
+
+/// Reads a compiled champion along with its debug info sidecar if any
+/// (`zork.dbg` for `zork.cor`), or compiles a champion's source (`.s`)
+fn load_champion(path: &Path) -> Result<(Vec<u8>, Option<DebugInfo>), DbgError> {
+    if path.extension().and_then(|ext| ext.to_str()) == Some("s") {
+        let source =
+            fs::read_to_string(path).map_err(|err| DbgError::Read(path.to_owned(), err))?;
+        let compile_error = |message: String| DbgError::Compile(path.to_owned(), message);
+
+        let champion = read_champion_with_loader(source.as_bytes(), Some(path), &FileLoader)
+            .map_err(|err| compile_error(err.to_string()))?;
+        let mut code = Vec::new();
+        let (_, listing) = write_champion_with_listing(&mut code, champion, &VmConfig::default())
+            .map_err(|err| compile_error(err.to_string()))?;
+        let file = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+
+        return Ok((code, Some(DebugInfo::new(&listing, &source, file))));
+    }
+
+    let champion = fs::read(path).map_err(|err| DbgError::Read(path.to_owned(), err))?;
+
+    let debug_info_file = path.with_extension("dbg");
+    if !debug_info_file.is_file() {
+        return Ok((champion, None));
+    }
+    let debug_info = fs::File::open(&debug_info_file)
+        .map_err(|err| err.to_string())
+        .and_then(|file| DebugInfo::read(io::BufReader::new(file)).map_err(|err| err.to_string()));
+    match debug_info {
+        Ok(debug_info) => Ok((champion, Some(debug_info))),
+        Err(err) => {
+            eprintln!("Ignoring {}: {}", debug_info_file.display(), err);
+            Ok((champion, None))
+        }
+    }
+}
+
+/// Debugs a match between champions, reading commands from scripts and then
+/// from the standard input. Type `help` for the list of commands
+#[derive(Debug, StructOpt)]
+struct Options {
+    /// Between 1 and 4 champions, numbered from 1 in order: compiled (.cor),
+    /// with their labels when they have a debug info sidecar, or sources (.s)
+    champion_files: Vec<PathBuf>,
+    /// Runs the commands of this file first, one per line. Stops at the first
+    /// failing command
+    #[structopt(short = "x", long = "script")]
+    scripts: Vec<PathBuf>,
+}
+
+#[derive(Debug, thiserror::Error)]
+enum DbgError {
+    #[error("Failed to read {}: {1}", .0.display())]
+    Read(PathBuf, io::Error),
+    #[error("Failed to compile {}:\n{1}", .0.display())]
+    Compile(PathBuf, String),
+    #[error("At least one champion is required")]
+    NoChampions,
+    #[error("Failed to load the champions: {0}")]
+    Load(#[from] LoadError),
+    #[error("{}:{line}: {message}", .path.display())]
+    Script {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
@@ -0,0 +1,371 @@
+//! Runs the debugger's commands against a match
+
+use crate::command::{self, Command, Info, Location, ParseError};
+
+use corewa_rs::{
+    language::debug_info::DebugInfo,
+    vm::{
+        debugger::{Breakpoint, BreakpointId, Debugger, Hit, Stop},
+        decoder::Decode,
+        process::ProcessState,
+        types::*,
+        VirtualMachine,
+    },
+};
+use std::io::{self, Write};
+
+/// The number of bytes per line of `x`
+const EXAMINE_LINE_WIDTH: usize = 16;
+
+/// Whether to keep reading commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Continue,
+    Quit,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SessionError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("No label '{0}'")]
+    UnknownLabel(String),
+    #[error("Label '{0}' is defined by players {1} and {2}, prefix it with a player number")]
+    AmbiguousLabel(String, PlayerId, PlayerId),
+    #[error("No process #{0}")]
+    UnknownProcess(Pid),
+    #[error("No breakpoint {0}")]
+    UnknownBreakpoint(BreakpointId),
+    #[error("No register r{0}, registers go from r1 to r{1}")]
+    UnknownRegister(usize, usize),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+pub struct Session {
+    vm: VirtualMachine,
+    debugger: Debugger,
+    /// The debug information of the players that have some, for their labels
+    debug_infos: Vec<(PlayerId, DebugInfo)>,
+}
+
+impl Session {
+    pub fn new(vm: VirtualMachine, debug_infos: Vec<(PlayerId, DebugInfo)>) -> Self {
+        Self {
+            vm,
+            debugger: Debugger::new(),
+            debug_infos,
+        }
+    }
+
+    /// Parses and executes a line of input
+    pub fn run_line(&mut self, line: &str, out: &mut impl Write) -> Result<Flow, SessionError> {
+        match command::parse(line)? {
+            Some(command) => self.execute(command, out),
+            None => Ok(Flow::Continue),
+        }
+    }
+
+    pub fn execute(
+        &mut self,
+        command: Command,
+        out: &mut impl Write,
+    ) -> Result<Flow, SessionError> {
+        match command {
+            Command::Step(count) => self.step(count, out)?,
+            Command::Continue => {
+                if let Stop::Break(hits) = self.debugger.run(&mut self.vm, u32::MAX) {
+                    print_hits(&hits, out)?;
+                }
+                self.print_status(out)?;
+            }
+            Command::Break(at) => {
+                let addr = self.resolve(&at)?;
+                let id = self.debugger.add(Breakpoint::Address(addr));
+                writeln!(out, "Breakpoint {} at {}", id, addr)?;
+            }
+            Command::Watch { start, end } => {
+                let start = self.resolve(&start)?;
+                let end = match end {
+                    Some(end) => self.resolve(&end)?,
+                    None => start + 1,
+                };
+                // Ranges ending before their start wrap around the memory
+                let end = if end <= start {
+                    end + self.vm.memory.size()
+                } else {
+                    end
+                };
+                let breakpoint = Breakpoint::Watch {
+                    range: start..end,
+                    by: None,
+                };
+                writeln!(
+                    out,
+                    "Breakpoint {}: {}",
+                    self.debugger.add(breakpoint.clone()),
+                    breakpoint
+                )?;
+            }
+            Command::Delete(id) => {
+                self.debugger
+                    .remove(id)
+                    .ok_or(SessionError::UnknownBreakpoint(id))?;
+            }
+            Command::Info(Info::Breakpoints) => {
+                let mut breakpoints = self.debugger.breakpoints().peekable();
+                if breakpoints.peek().is_none() {
+                    writeln!(out, "No breakpoints")?;
+                }
+                for (id, breakpoint) in breakpoints {
+                    writeln!(out, "{:<4}{}", id, breakpoint)?;
+                }
+            }
+            Command::Info(Info::Processes) => self.print_processes(out)?,
+            Command::Info(Info::Registers(pid)) => self.print_registers(pid, out)?,
+            Command::Examine { at, count } => {
+                let addr = self.resolve(&at)?;
+                self.examine(addr, count, out)?;
+            }
+            Command::Disassemble { at, count } => {
+                let addr = self.resolve(&at)?;
+                self.disassemble(addr, count, out)?;
+            }
+            Command::SetRegister {
+                pid,
+                register,
+                value,
+            } => {
+                let reg_count = self.vm.config.reg_count;
+                if register > reg_count {
+                    return Err(SessionError::UnknownRegister(register, reg_count));
+                }
+                let process = self
+                    .vm
+                    .processes
+                    .iter_mut()
+                    .find(|process| process.pid == pid)
+                    .ok_or(SessionError::UnknownProcess(pid))?;
+                process.registers[register - 1] = value;
+            }
+            Command::SetMemory { at, bytes } => {
+                let addr = self.resolve(&at)?;
+                for (i, byte) in bytes.into_iter().enumerate() {
+                    self.vm.memory.values[addr + i] = byte;
+                }
+            }
+            Command::Help => writeln!(out, "{}", command::HELP)?,
+            Command::Quit => return Ok(Flow::Quit),
+        }
+
+        Ok(Flow::Continue)
+    }
+
+    fn step(&mut self, count: u32, out: &mut impl Write) -> io::Result<()> {
+        for _ in 0..count {
+            if self.vm.is_over() {
+                break;
+            }
+            let hits = self.debugger.step(&mut self.vm);
+            if !hits.is_empty() {
+                print_hits(&hits, out)?;
+                break;
+            }
+        }
+
+        self.print_status(out)
+    }
+
+    fn print_status(&self, out: &mut impl Write) -> io::Result<()> {
+        match self.vm.outcome() {
+            Some(outcome) => {
+                let winner = outcome
+                    .winner
+                    .and_then(|id| self.vm.players.iter().find(|player| player.id == id));
+                match winner {
+                    Some(player) => writeln!(
+                        out,
+                        "The match is over at cycle {}, player {} \"{}\" won",
+                        outcome.final_cycle, player.id, player.name
+                    ),
+                    None => writeln!(out, "The match is over at cycle {}", outcome.final_cycle),
+                }
+            }
+            None => {
+                let process_count = self.vm.processes.len();
+                writeln!(
+                    out,
+                    "Cycle {}, {} process{}",
+                    self.vm.cycles,
+                    process_count,
+                    if process_count == 1 { "" } else { "es" }
+                )
+            }
+        }
+    }
+
+    fn print_processes(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut processes = self.vm.processes.iter().collect::<Vec<_>>();
+        processes.sort_by_key(|process| process.pid);
+
+        writeln!(out, "  pid  player     pc  zf     last live  state")?;
+        for process in processes {
+            let state = match &process.state {
+                ProcessState::Idle => "idle".to_owned(),
+                ProcessState::Executing { op, exec_at } => format!(
+                    "executing '{}' at cycle {}",
+                    op.to_string().to_lowercase(),
+                    exec_at
+                ),
+            };
+            writeln!(
+                out,
+                "{:>5}  {:>6}  {:>5}  {:<5}  {:>9}  {}",
+                process.pid,
+                process.player_id,
+                process.pc.addr(),
+                process.zf,
+                process.last_live_cycle,
+                state
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn print_registers(&self, pid: Pid, out: &mut impl Write) -> Result<(), SessionError> {
+        let process = self
+            .vm
+            .processes
+            .iter()
+            .find(|process| process.pid == pid)
+            .ok_or(SessionError::UnknownProcess(pid))?;
+
+        writeln!(out, "pc   {}", process.pc.addr())?;
+        writeln!(out, "zf   {}", process.zf)?;
+        for (i, value) in process.registers.iter().enumerate() {
+            writeln!(
+                out,
+                "{:<4} 0x{:08x}  {}",
+                format!("r{}", i + 1),
+                value,
+                value
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn examine(&self, addr: usize, count: usize, out: &mut impl Write) -> io::Result<()> {
+        let mem_size = self.vm.memory.size();
+
+        for line_start in (0..count).step_by(EXAMINE_LINE_WIDTH) {
+            let line_addr = (addr + line_start) % mem_size;
+            let hex = (line_start..count.min(line_start + EXAMINE_LINE_WIDTH))
+                .map(|i| format!("{:02x}", self.vm.memory.values[addr + i]))
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(out, "0x{:04x} : {}", line_addr, hex)?;
+        }
+
+        Ok(())
+    }
+
+    /// Decodes instructions the way processes do, marking the addresses
+    /// where processes are about to read their next instruction
+    fn disassemble(&self, addr: usize, count: usize, out: &mut impl Write) -> io::Result<()> {
+        let memory = &self.vm.memory;
+        let mut addr = addr % memory.size();
+
+        for _ in 0..count {
+            for (player_id, name) in self.labels_at(addr) {
+                writeln!(out, "   <{}:{}>:", player_id, name)?;
+            }
+
+            let marker = if self.vm.processes.iter().any(|p| p.pc.addr() == addr) {
+                "=>"
+            } else {
+                "  "
+            };
+            let instr = memory
+                .decode_op(addr)
+                .ok()
+                .and_then(|op| memory.decode_instr(op, addr, self.vm.config.reg_count).ok());
+            let size = match instr {
+                Some(instr) => {
+                    writeln!(out, "{} {:>4}:  {}", marker, addr, instr)?;
+                    instr.byte_size
+                }
+                None => {
+                    writeln!(
+                        out,
+                        "{} {:>4}:  .code 0x{:02x}",
+                        marker, addr, memory.values[addr]
+                    )?;
+                    1
+                }
+            };
+            addr = (addr + size) % memory.size();
+        }
+
+        Ok(())
+    }
+
+    fn labels_at(&self, addr: usize) -> impl Iterator<Item = (PlayerId, &str)> + '_ {
+        self.debug_infos
+            .iter()
+            .flat_map(move |(player_id, debug_info)| {
+                let origin = self.player_origin(*player_id);
+                debug_info
+                    .labels
+                    .iter()
+                    .filter(move |(_, offset)| (origin + offset) % self.vm.memory.size() == addr)
+                    .map(move |(name, _)| (*player_id, name.as_str()))
+            })
+    }
+
+    fn player_origin(&self, player_id: PlayerId) -> usize {
+        self.vm
+            .players
+            .iter()
+            .find(|player| player.id == player_id)
+            .map_or(0, |player| player.origin)
+    }
+
+    /// The memory address of a location
+    fn resolve(&self, location: &Location) -> Result<usize, SessionError> {
+        let (player_id, name) = match location {
+            Location::Address(addr) => return Ok(addr % self.vm.memory.size()),
+            Location::Label { player_id, name } => (player_id, name),
+        };
+
+        let mut definitions = self
+            .debug_infos
+            .iter()
+            .filter(|(id, _)| player_id.is_none_or(|player_id| player_id == *id))
+            .filter_map(|(id, debug_info)| {
+                debug_info
+                    .labels
+                    .iter()
+                    .find(|(label, _)| label == name)
+                    .map(|(_, offset)| (*id, *offset))
+            });
+
+        match (definitions.next(), definitions.next()) {
+            (Some((id, offset)), None) => {
+                Ok((self.player_origin(id) + offset) % self.vm.memory.size())
+            }
+            (Some((first, _)), Some((second, _))) => {
+                Err(SessionError::AmbiguousLabel(name.clone(), first, second))
+            }
+            (None, _) => Err(SessionError::UnknownLabel(name.clone())),
+        }
+    }
+}
+
+fn print_hits(hits: &[Hit], out: &mut impl Write) -> io::Result<()> {
+    for hit in hits {
+        writeln!(out, "{}", hit)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+macro_rules! sample {
+    ($name:ident) => {
+        concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../corewa-rs/tests/vm/samples/",
+            stringify!($name),
+            ".cor"
+        )
+    };
+}
+
+/// `st` writes 4 bytes at 107 during the 10th cycle, right before the process
+/// reaches `loop`
+const PROGRAM: &str = "\
+.name \"program\"
+.comment \"debugged\"
+
+        ld      %5, r2
+        st      r2, 100
+loop:   live    %1
+        fork    %:loop
+";
+
+fn temp_file(name: &str, contents: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn dbg(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_corewa-rs-dbg"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run the debugger");
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// Runs a script against the program and returns the output
+fn script(name: &str, commands: &str) -> String {
+    let program = temp_file(&format!("{}.s", name), PROGRAM);
+    let script = temp_file(&format!("{}.cmd", name), commands);
+
+    let output = dbg(
+        &[program.to_str().unwrap(), "-x", script.to_str().unwrap()],
+        "",
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn break_at_label() {
+    let output = script(
+        "break_at_label",
+        "# Runs to the loop
+break loop
+continue
+info regs 0
+",
+    );
+
+    assert!(output.starts_with("(dbg) break loop\nBreakpoint 1 at 12\n"));
+    assert!(
+        output.contains("(dbg) continue\n[10] Breakpoint 1: #0 reached 12\nCycle 10, 1 process\n")
+    );
+    assert!(output.contains("pc   12\n"));
+    assert!(output.contains("r2   0x00000005  5\n"));
+}
+
+#[test]
+fn step_and_watch() {
+    let output = script(
+        "step_and_watch",
+        "step 5
+info processes
+watch 100..120
+step 100
+x/4 107
+",
+    );
+
+    assert!(output.contains("(dbg) step 5\nCycle 5, 1 process\n"));
+    assert!(output.contains("    0       1      7  false          0  idle\n"));
+    assert!(output.contains("Breakpoint 1: writes to 100..120\n"));
+    assert!(output
+        .contains("[10] Breakpoint 1: #0 (player 1) wrote 4 bytes at 107\nCycle 10, 1 process\n"));
+    assert!(output.contains("0x006b : 00 00 00 05\n"));
+}
+
+#[test]
+fn set_registers_and_memory() {
+    let output = script(
+        "set_registers_and_memory",
+        "set reg 0 r3 -1
+info regs 0
+set mem loop 0x0c 0x00 0x00
+disas 7 2
+",
+    );
+
+    assert!(output.contains("r3   0xffffffff  -1\n"));
+    assert!(output.contains("      7:  st r2, 100\n   <1:loop>:\n     12:  fork %0\n"));
+}
+
+#[test]
+fn disassemble_compiled_champion() {
+    let script = temp_file("disassemble_compiled_champion.cmd", "x/3 0\ndisas 0 1\n");
+    let output = dbg(&[sample!(zork), "-x", script.to_str().unwrap()], "");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("0x0000 : 0b 68 01\n"));
+    assert!(stdout.contains("=>    0:  sti r1, %15, %1\n"));
+}
+
+#[test]
+fn failing_script_command() {
+    let program = temp_file("failing_script_command.s", PROGRAM);
+    let script = temp_file("failing_script_command.cmd", "step\n\ninfo regs 7\nstep\n");
+    let output = dbg(
+        &[program.to_str().unwrap(), "-x", script.to_str().unwrap()],
+        "",
+    );
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .ends_with("(dbg) info regs 7\n"));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .ends_with("failing_script_command.cmd:3: No process #7\n"));
+}
+
+#[test]
+fn commands_from_stdin() {
+    let program = temp_file("commands_from_stdin.s", PROGRAM);
+    let program = program.to_str().unwrap();
+    let output = dbg(
+        &[program, program],
+        "break loop\nbreak 2:loop\njump 3\nquit\nstep\n",
+    );
+    assert!(output.status.success());
+
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Breakpoint 1 at 2060\n"
+    );
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Label 'loop' is defined by players 1 and 2, prefix it with a player number\n\
+         Unknown command 'jump', try 'help'\n"
+    );
+}
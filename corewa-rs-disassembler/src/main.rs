@@ -0,0 +1,89 @@
+use corewa_rs::{
+    language::{
+        analysis::analyze,
+        disassembler::{disassemble, ItemKind},
+    },
+    spec::VmConfig,
+};
+
+use std::io::{Read, Write};
+use structopt::StructOpt;
+
+fn main() {
+    let opts = Options::from_args();
+
+    let exit_code = match run(&opts) {
+        Ok(_) => 0,
+        Err(err) => {
+            eprintln!("{}", err);
+            1
+        }
+    };
+
+    std::process::exit(exit_code)
+}
+
+fn run(opts: &Options) -> Result<(), String> {
+    let mut program = Vec::new();
+    std::io::stdin()
+        .read_to_end(&mut program)
+        .map_err(|e| format!("Failed to read champion:\n{}", e))?;
+
+    let config = VmConfig::default();
+    let disassembly = disassemble(&program, &config)
+        .map_err(|e| format!("Failed to disassemble champion:\n{}", e))?;
+
+    if opts.dot {
+        return write_control_flow(&program, &disassembly.name, &config);
+    }
+
+    write!(std::io::stdout(), "{}", disassembly)
+        .map_err(|e| format!("Failed to write source:\n{}", e))?;
+
+    eprintln!("Successfully disassembled '{}'", disassembly.name);
+    let instr_count = disassembly
+        .items
+        .iter()
+        .filter(|item| matches!(item.kind, ItemKind::Instruction { .. }))
+        .count();
+    eprintln!("{} instructions", instr_count);
+
+    Ok(())
+}
+
+fn write_control_flow(program: &[u8], name: &str, config: &VmConfig) -> Result<(), String> {
+    let flow =
+        analyze(program, config).map_err(|e| format!("Failed to analyze champion:\n{}", e))?;
+
+    write!(std::io::stdout(), "{}", flow.to_dot(name))
+        .map_err(|e| format!("Failed to write graph:\n{}", e))?;
+
+    eprintln!(
+        "{} reachable instructions in {} blocks",
+        flow.nodes().count(),
+        flow.blocks.len()
+    );
+    for range in flow.unreachable() {
+        eprintln!("unreachable: bytes {}..{}", range.start, range.end);
+    }
+    for cycle in &flow.loops {
+        eprintln!(
+            "loop at {}: {} cycles per iteration",
+            cycle.blocks[0], cycle.cycles
+        );
+    }
+    for offset in flow.overwritten() {
+        eprintln!("overwritten: the instruction at {}", offset);
+    }
+
+    Ok(())
+}
+
+/// Turns a compiled champion read from stdin back into source code
+#[derive(Debug, StructOpt)]
+struct Options {
+    /// Prints the control flow graph of the champion in Graphviz's DOT
+    /// language instead, with its reachable code and loops
+    #[structopt(long)]
+    dot: bool,
+}
@@ -0,0 +1,92 @@
+use corewa_rs::language::formatter::format;
+
+use std::{
+    fs,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+use structopt::StructOpt;
+
+fn main() {
+    let opts = Options::from_args();
+
+    let exit_code = match run(&opts) {
+        Ok(_) => 0,
+        Err(err) => {
+            eprintln!("{}", err);
+            1
+        }
+    };
+
+    std::process::exit(exit_code)
+}
+
+fn run(opts: &Options) -> Result<(), String> {
+    if opts.files.is_empty() {
+        return run_stdin(opts);
+    }
+
+    // Every file is handled before reporting the failures
+    let mut failures = Vec::new();
+    for path in &opts.files {
+        if let Err(err) = format_file(path, opts.check) {
+            failures.push(err);
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures.join("\n"))
+    }
+}
+
+fn run_stdin(opts: &Options) -> Result<(), String> {
+    let mut source = String::new();
+    std::io::stdin()
+        .read_to_string(&mut source)
+        .map_err(|e| format!("Failed to read source:\n{}", e))?;
+
+    let formatted = format(&source).map_err(|e| format!("Failed to format <stdin>:\n{}", e))?;
+
+    if opts.check {
+        return if formatted == source {
+            Ok(())
+        } else {
+            Err(String::from("<stdin> is not formatted"))
+        };
+    }
+
+    std::io::stdout()
+        .write_all(formatted.as_bytes())
+        .map_err(|e| format!("Failed to write source:\n{}", e))
+}
+
+fn format_file(path: &Path, check: bool) -> Result<(), String> {
+    let source = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}:\n{}", path.display(), e))?;
+
+    let formatted =
+        format(&source).map_err(|e| format!("Failed to format {}:\n{}", path.display(), e))?;
+
+    if formatted == source {
+        Ok(())
+    } else if check {
+        Err(format!("{} is not formatted", path.display()))
+    } else {
+        fs::write(path, formatted)
+            .map_err(|e| format!("Failed to write {}:\n{}", path.display(), e))
+    }
+}
+
+/// Formats champion sources in place, or from stdin to stdout
+#[derive(Debug, StructOpt)]
+struct Options {
+    /// The source files to format, read from stdin if omitted
+    #[structopt(parse(from_os_str))]
+    files: Vec<PathBuf>,
+    /// Lists the sources that are not formatted instead of formatting them,
+    /// failing if there are any
+    #[structopt(long)]
+    check: bool,
+}
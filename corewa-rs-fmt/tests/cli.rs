@@ -0,0 +1,82 @@
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+const UNFORMATTED: &str = "start:live %1\n  zjmp %:start\n";
+const FORMATTED: &str = "start:  live  %1\n        zjmp  %:start\n";
+
+fn fmt(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_corewa-rs-fmt"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run the formatter");
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn source_file(name: &str, source: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::write(&path, source).unwrap();
+    path
+}
+
+#[test]
+fn stdin_to_stdout() {
+    let output = fmt(&[], UNFORMATTED);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), FORMATTED);
+
+    assert_eq!(fmt(&["--check"], UNFORMATTED).status.code(), Some(1));
+    assert_eq!(fmt(&["--check"], FORMATTED).status.code(), Some(0));
+}
+
+#[test]
+fn files_in_place() {
+    let path = source_file("in_place.s", UNFORMATTED);
+    let path = path.to_str().unwrap();
+
+    assert!(fmt(&[path], "").status.success());
+    assert_eq!(fs::read_to_string(path).unwrap(), FORMATTED);
+}
+
+#[test]
+fn check_files() {
+    let formatted = source_file("check_formatted.s", FORMATTED);
+    let unformatted = source_file("check_unformatted.s", UNFORMATTED);
+
+    let output = fmt(
+        &[
+            "--check",
+            formatted.to_str().unwrap(),
+            unformatted.to_str().unwrap(),
+        ],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(1));
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!stderr.contains("check_formatted.s"));
+    assert!(stderr.contains("check_unformatted.s is not formatted"));
+
+    // Left untouched
+    assert_eq!(fs::read_to_string(unformatted).unwrap(), UNFORMATTED);
+}
+
+#[test]
+fn invalid_sources() {
+    let output = fmt(&[], "ld \"unclosed, r1");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+}
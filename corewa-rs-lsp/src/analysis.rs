@@ -0,0 +1,449 @@
+//! What the server knows about a champion's source, computed from the tokens
+//! of each of its lines
+
+use corewa_rs::{
+    language::{
+        diagnostics::{compile_with_diagnostics, Diagnostic},
+        lexer::{Term, Token, Tokenizer},
+        loader::FileLoader,
+        warnings::CompileWarning,
+    },
+    spec::{op_spec, OpType, VmConfig, T_DIR, T_IND, T_REG},
+};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, DiagnosticSeverity, DocumentSymbol, Hover, HoverContents,
+    MarkupContent, MarkupKind, Position, Range, SymbolKind,
+};
+
+use std::{ops, path::Path};
+
+const OPS: [OpType; 16] = [
+    OpType::Live,
+    OpType::Ld,
+    OpType::St,
+    OpType::Add,
+    OpType::Sub,
+    OpType::And,
+    OpType::Or,
+    OpType::Xor,
+    OpType::Zjmp,
+    OpType::Ldi,
+    OpType::Sti,
+    OpType::Fork,
+    OpType::Lld,
+    OpType::Lldi,
+    OpType::Lfork,
+    OpType::Aff,
+];
+
+const DIAGNOSTIC_SOURCE: &str = "corewa-rs";
+
+/// An open champion source
+pub struct Document {
+    source: String,
+    lines: Vec<Line>,
+    occurrences: Vec<Occurrence>,
+}
+
+struct Line {
+    text: String,
+    /// Lexer errors are skipped: they are reported by the diagnostics
+    tokens: Vec<Token>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NameKind {
+    Label,
+    Constant,
+}
+
+/// A label or constant name written in the source
+struct Occurrence {
+    name: String,
+    kind: NameKind,
+    /// Starting at 0
+    line: usize,
+    /// The byte range of the name in its line
+    at: ops::Range<usize>,
+    is_definition: bool,
+}
+
+impl Document {
+    pub fn new(source: String) -> Self {
+        let lines = source
+            .lines()
+            .map(|text| Line {
+                tokens: Tokenizer::new(text).filter_map(Result::ok).collect(),
+                text: String::from(text),
+            })
+            .collect::<Vec<_>>();
+
+        let occurrences = find_occurrences(&lines);
+
+        Self {
+            source,
+            lines,
+            occurrences,
+        }
+    }
+
+    /// Compiles the document, reporting its errors or its warnings if it has
+    /// none. Included files are resolved relative to `path`
+    pub fn diagnostics(&self, path: Option<&Path>) -> Vec<lsp_types::Diagnostic> {
+        match compile_with_diagnostics(&self.source, path, &FileLoader, &VmConfig::default()) {
+            Ok(compiled) => compiled
+                .warnings
+                .iter()
+                .map(|warning| self.warning_diagnostic(warning))
+                .collect(),
+            Err(errors) => errors
+                .iter()
+                .map(|error| self.error_diagnostic(error))
+                .collect(),
+        }
+    }
+
+    fn error_diagnostic(&self, error: &Diagnostic) -> lsp_types::Diagnostic {
+        // Errors about the whole champion are shown at its start
+        let range = match &error.span {
+            Some(span) => self.range(span.line - 1, span.columns.clone()),
+            None => Range::default(),
+        };
+
+        lsp_types::Diagnostic {
+            range,
+            severity: Some(DiagnosticSeverity::ERROR),
+            source: Some(String::from(DIAGNOSTIC_SOURCE)),
+            message: error.to_string(),
+            ..lsp_types::Diagnostic::default()
+        }
+    }
+
+    fn warning_diagnostic(&self, warning: &CompileWarning) -> lsp_types::Diagnostic {
+        lsp_types::Diagnostic {
+            range: self.line_range(warning.line - 1),
+            severity: Some(DiagnosticSeverity::WARNING),
+            source: Some(String::from(DIAGNOSTIC_SOURCE)),
+            message: warning.kind.to_string(),
+            ..lsp_types::Diagnostic::default()
+        }
+    }
+
+    /// Where the label or constant under `position` is defined
+    pub fn definitions(&self, position: Position) -> Vec<Range> {
+        self.related_occurrences(position)
+            .filter(|occurrence| occurrence.is_definition)
+            .map(|occurrence| self.occurrence_range(occurrence))
+            .collect()
+    }
+
+    /// Where the label or constant under `position` is used, and optionally
+    /// defined
+    pub fn references(&self, position: Position, include_definitions: bool) -> Vec<Range> {
+        self.related_occurrences(position)
+            .filter(|occurrence| include_definitions || !occurrence.is_definition)
+            .map(|occurrence| self.occurrence_range(occurrence))
+            .collect()
+    }
+
+    /// The specification of the operation under `position`
+    pub fn hover(&self, position: Position) -> Option<Hover> {
+        let (line, byte) = self.offset(position)?;
+        let token = self.token_at(line, byte)?;
+        let text = &self.lines[line].text[token.range.clone()];
+
+        let op_type = match token.term {
+            Term::Ident => OPS.iter().find(|op_type| mnemonic(**op_type) == text)?,
+            _ => return None,
+        };
+
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: describe_op(*op_type),
+            }),
+            range: Some(self.range(line, token.range.clone())),
+        })
+    }
+
+    /// What can be written at `position`: labels after a `:`, mnemonics at
+    /// the start of an instruction and registers, labels or constants after
+    /// a mnemonic
+    pub fn completion(&self, position: Position) -> Vec<CompletionItem> {
+        let (line, byte) = match self.offset(position) {
+            Some(offset) => offset,
+            None => return Vec::new(),
+        };
+        let Line { text, tokens } = &self.lines[line];
+
+        let word_start = text[..byte]
+            .trim_end_matches(|c: char| c.is_ascii_alphanumeric() || c == '_')
+            .len();
+        let previous = tokens
+            .iter()
+            .take_while(|token| token.range.end <= word_start)
+            .map(|token| token.term)
+            .collect::<Vec<_>>();
+
+        if previous.contains(&Term::Comment) {
+            return Vec::new();
+        }
+
+        if text[..word_start].ends_with(':') {
+            self.name_completions(true)
+        } else if previous.iter().all(|term| *term == Term::LabelDef) {
+            OPS.iter()
+                .map(|op_type| CompletionItem {
+                    label: mnemonic(*op_type),
+                    kind: Some(CompletionItemKind::KEYWORD),
+                    detail: Some(format!("{} cycles", op_spec(*op_type).cycles)),
+                    ..CompletionItem::default()
+                })
+                .collect()
+        } else {
+            let registers = (1..=VmConfig::default().reg_count).map(|n| CompletionItem {
+                label: format!("r{}", n),
+                kind: Some(CompletionItemKind::VARIABLE),
+                ..CompletionItem::default()
+            });
+            registers.chain(self.name_completions(false)).collect()
+        }
+    }
+
+    /// The labels, and the constants unless the name follows a `:`
+    fn name_completions(&self, after_colon: bool) -> Vec<CompletionItem> {
+        self.occurrences
+            .iter()
+            .filter(|occurrence| occurrence.is_definition)
+            .filter_map(|occurrence| {
+                let (label, kind) = match occurrence.kind {
+                    NameKind::Label if after_colon => {
+                        (occurrence.name.clone(), CompletionItemKind::REFERENCE)
+                    }
+                    NameKind::Label => (
+                        format!(":{}", occurrence.name),
+                        CompletionItemKind::REFERENCE,
+                    ),
+                    NameKind::Constant if after_colon => return None,
+                    NameKind::Constant => (occurrence.name.clone(), CompletionItemKind::CONSTANT),
+                };
+
+                Some(CompletionItem {
+                    label,
+                    kind: Some(kind),
+                    detail: Some(format!("line {}", occurrence.line + 1)),
+                    ..CompletionItem::default()
+                })
+            })
+            .collect()
+    }
+
+    /// The labels and constants defined in the document
+    pub fn symbols(&self) -> Vec<DocumentSymbol> {
+        self.occurrences
+            .iter()
+            .filter(|occurrence| occurrence.is_definition)
+            .map(|occurrence| {
+                let kind = match occurrence.kind {
+                    NameKind::Label => SymbolKind::FUNCTION,
+                    NameKind::Constant => SymbolKind::CONSTANT,
+                };
+                let range = self.occurrence_range(occurrence);
+
+                #[allow(deprecated)] // `deprecated` is superseded by `tags` but still required
+                DocumentSymbol {
+                    name: occurrence.name.clone(),
+                    detail: None,
+                    kind,
+                    tags: None,
+                    deprecated: None,
+                    range: self.line_range(occurrence.line),
+                    selection_range: range,
+                    children: None,
+                }
+            })
+            .collect()
+    }
+
+    /// The occurrences of the name under `position`
+    fn related_occurrences(&self, position: Position) -> impl Iterator<Item = &Occurrence> {
+        let target = self.offset(position).and_then(|(line, byte)| {
+            self.occurrences.iter().find(|occurrence| {
+                occurrence.line == line && occurrence.at.start <= byte && byte <= occurrence.at.end
+            })
+        });
+
+        self.occurrences.iter().filter(move |occurrence| {
+            target.is_some_and(|target| {
+                occurrence.kind == target.kind && occurrence.name == target.name
+            })
+        })
+    }
+
+    /// The token containing the byte at `byte`, or ending right before it
+    fn token_at(&self, line: usize, byte: usize) -> Option<&Token> {
+        let tokens = &self.lines[line].tokens;
+
+        tokens
+            .iter()
+            .find(|token| token.range.contains(&byte))
+            .or_else(|| tokens.iter().find(|token| token.range.end == byte))
+    }
+
+    fn occurrence_range(&self, occurrence: &Occurrence) -> Range {
+        self.range(occurrence.line, occurrence.at.clone())
+    }
+
+    /// The range covering the code of a line, without its indentation
+    fn line_range(&self, line: usize) -> Range {
+        let text = self.lines.get(line).map_or("", |line| &line.text);
+        let start = text.len() - text.trim_start().len();
+        let end = text.trim_end().len().max(start);
+
+        self.range(line, start..end)
+    }
+
+    fn range(&self, line: usize, bytes: ops::Range<usize>) -> Range {
+        Range::new(
+            self.position(line, bytes.start),
+            self.position(line, bytes.end),
+        )
+    }
+
+    /// LSP positions count UTF-16 code units, byte offsets are clamped to
+    /// the line
+    fn position(&self, line: usize, byte: usize) -> Position {
+        let text = self.lines.get(line).map_or("", |line| &line.text);
+        let byte = floor_char_boundary(text, byte);
+        let character = text[..byte].encode_utf16().count();
+
+        Position::new(line as u32, character as u32)
+    }
+
+    /// The line and byte in that line of a position. Positions past the end
+    /// of a line are clamped to it
+    fn offset(&self, position: Position) -> Option<(usize, usize)> {
+        let line = position.line as usize;
+        let text = &self.lines.get(line)?.text;
+
+        let mut units = 0;
+        let byte = text
+            .char_indices()
+            .find(|(_, c)| {
+                units += c.len_utf16();
+                units > position.character as usize
+            })
+            .map_or(text.len(), |(idx, _)| idx);
+
+        Some((line, byte))
+    }
+}
+
+fn find_occurrences(lines: &[Line]) -> Vec<Occurrence> {
+    let mut occurrences = Vec::new();
+
+    for (line, Line { text, tokens }) in lines.iter().enumerate() {
+        let mut occurrence = |kind, at: ops::Range<usize>, is_definition| {
+            occurrences.push(Occurrence {
+                name: String::from(&text[at.clone()]),
+                kind,
+                line,
+                at,
+                is_definition,
+            })
+        };
+
+        for (idx, token) in tokens.iter().enumerate() {
+            let Token { term, range } = token.clone();
+            match term {
+                Term::LabelDef => occurrence(NameKind::Label, range.start..range.end - 1, true),
+                Term::LabelUse => occurrence(NameKind::Label, range.start + 1..range.end, false),
+                // `.define NAME ...`
+                Term::Ident if idx == 1 && tokens[0].term == Term::DefineCmd => {
+                    occurrence(NameKind::Constant, range, true)
+                }
+                // `NAME = ...`
+                Term::Ident
+                    if idx == 0 && tokens.get(1).map(|token| token.term) == Some(Term::Equals) =>
+                {
+                    occurrence(NameKind::Constant, range, true)
+                }
+                _ => (),
+            }
+        }
+    }
+
+    // Constants can only be recognized once they are all known
+    let constants = occurrences
+        .iter()
+        .filter(|occurrence| occurrence.kind == NameKind::Constant)
+        .map(|occurrence| occurrence.name.clone())
+        .collect::<Vec<_>>();
+
+    for (line, Line { text, tokens }) in lines.iter().enumerate() {
+        for token in tokens {
+            let name = &text[token.range.clone()];
+            let is_definition = occurrences.iter().any(|occurrence| {
+                occurrence.line == line && occurrence.at == token.range && occurrence.is_definition
+            });
+
+            if token.term == Term::Ident
+                && !is_definition
+                && constants.iter().any(|constant| constant == name)
+            {
+                occurrences.push(Occurrence {
+                    name: String::from(name),
+                    kind: NameKind::Constant,
+                    line,
+                    at: token.range.clone(),
+                    is_definition: false,
+                });
+            }
+        }
+    }
+
+    occurrences.sort_by_key(|occurrence| (occurrence.line, occurrence.at.start));
+    occurrences
+}
+
+fn mnemonic(op_type: OpType) -> String {
+    op_type.to_string().to_lowercase()
+}
+
+fn describe_op(op_type: OpType) -> String {
+    let spec = op_spec(op_type);
+
+    let params = spec.param_masks[..spec.param_count]
+        .iter()
+        .map(|mask| {
+            let types = [(T_REG, "T_REG"), (T_DIR, "T_DIR"), (T_IND, "T_IND")]
+                .iter()
+                .filter(|(flag, _)| mask & flag != 0)
+                .map(|(_, name)| *name)
+                .collect::<Vec<_>>();
+            format!("`{}`", types.join(" | "))
+        })
+        .collect::<Vec<_>>();
+
+    format!(
+        "**{}** (opcode 0x{:02x})\n\n\
+         - cycles: {}\n\
+         - parameters: {}\n\
+         - direct size: {} bytes\n\
+         - parameter code byte: {}",
+        mnemonic(op_type),
+        spec.code,
+        spec.cycles,
+        params.join(", "),
+        spec.dir_size as usize,
+        if spec.has_pcb { "yes" } else { "no" },
+    )
+}
+
+fn floor_char_boundary(text: &str, byte: usize) -> usize {
+    let mut byte = byte.min(text.len());
+    while !text.is_char_boundary(byte) {
+        byte -= 1;
+    }
+    byte
+}
@@ -0,0 +1,242 @@
+mod analysis;
+
+use analysis::Document;
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, RequestId, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+        Notification as LspNotification, PublishDiagnostics,
+    },
+    request::{
+        Completion, DocumentSymbolRequest, GotoDefinition, HoverRequest, References,
+        Request as LspRequest,
+    },
+    CompletionOptions, CompletionParams, CompletionResponse, DidChangeTextDocumentParams,
+    DidCloseTextDocumentParams, DidOpenTextDocumentParams, DocumentSymbolParams,
+    DocumentSymbolResponse, GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverParams,
+    HoverProviderCapability, Location, OneOf, PublishDiagnosticsParams, ReferenceParams,
+    ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+use serde_json::Value;
+
+use std::collections::HashMap;
+
+fn main() {
+    let exit_code = match run() {
+        Ok(_) => 0,
+        Err(err) => {
+            eprintln!("{}", err);
+            1
+        }
+    };
+
+    std::process::exit(exit_code)
+}
+
+fn run() -> Result<(), String> {
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = serde_json::to_value(capabilities()).map_err(|e| e.to_string())?;
+    connection
+        .initialize(capabilities)
+        .map_err(|e| format!("Failed to initialize:\n{}", e))?;
+
+    Server::default().serve(&connection)?;
+
+    // The writer thread only stops once the connection is dropped
+    drop(connection);
+    io_threads
+        .join()
+        .map_err(|e| format!("Failed to stop:\n{}", e))
+}
+
+fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        // Champions are small enough to be sent whole on every change
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec![String::from(":"), String::from("%")]),
+            ..CompletionOptions::default()
+        }),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        ..ServerCapabilities::default()
+    }
+}
+
+/// The open documents, by uri
+#[derive(Default)]
+struct Server {
+    documents: HashMap<Url, Document>,
+}
+
+impl Server {
+    fn serve(&mut self, connection: &Connection) -> Result<(), String> {
+        for message in &connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    let is_shutdown = connection
+                        .handle_shutdown(&request)
+                        .map_err(|e| format!("Failed to shut down:\n{}", e))?;
+                    if is_shutdown {
+                        return Ok(());
+                    }
+
+                    let response = self.respond(request);
+                    send(connection, Message::Response(response))?;
+                }
+                Message::Notification(notification) => {
+                    if let Some(published) = self.notify(notification) {
+                        send(connection, Message::Notification(published))?;
+                    }
+                }
+                Message::Response(_) => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn respond(&self, request: Request) -> Response {
+        let Request { id, method, params } = request;
+
+        let result = match method.as_str() {
+            GotoDefinition::METHOD => self.handle::<GotoDefinition>(params, Self::definition),
+            References::METHOD => self.handle::<References>(params, Self::references),
+            HoverRequest::METHOD => self.handle::<HoverRequest>(params, Self::hover),
+            Completion::METHOD => self.handle::<Completion>(params, Self::completion),
+            DocumentSymbolRequest::METHOD => {
+                self.handle::<DocumentSymbolRequest>(params, Self::symbols)
+            }
+            _ => {
+                let message = format!("Unsupported request: {}", method);
+                return Response::new_err(id, ErrorCode::MethodNotFound as i32, message);
+            }
+        };
+
+        response(id, result)
+    }
+
+    fn handle<R: LspRequest>(
+        &self,
+        params: Value,
+        handler: fn(&Self, R::Params) -> R::Result,
+    ) -> Result<Value, serde_json::Error> {
+        let params = serde_json::from_value(params)?;
+        serde_json::to_value(handler(self, params))
+    }
+
+    /// Updates the documents, returning their new diagnostics
+    fn notify(&mut self, notification: Notification) -> Option<Notification> {
+        let Notification { method, params } = notification;
+
+        let uri = match method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams = serde_json::from_value(params).ok()?;
+                let document = params.text_document;
+                self.documents
+                    .insert(document.uri.clone(), Document::new(document.text));
+                document.uri
+            }
+            DidChangeTextDocument::METHOD => {
+                let mut params: DidChangeTextDocumentParams =
+                    serde_json::from_value(params).ok()?;
+                // With full synchronization the last change holds the whole text
+                let text = params.content_changes.pop()?.text;
+                let uri = params.text_document.uri;
+                self.documents.insert(uri.clone(), Document::new(text));
+                uri
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams = serde_json::from_value(params).ok()?;
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                // Clears the diagnostics of the closed document
+                return Some(publish_diagnostics(uri, Vec::new()));
+            }
+            _ => return None,
+        };
+
+        let path = uri.to_file_path().ok();
+        let diagnostics = self.documents[&uri].diagnostics(path.as_deref());
+        Some(publish_diagnostics(uri, diagnostics))
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let position = params.text_document_position_params;
+        let uri = position.text_document.uri;
+
+        let locations = self
+            .documents
+            .get(&uri)?
+            .definitions(position.position)
+            .into_iter()
+            .map(|range| Location::new(uri.clone(), range))
+            .collect();
+
+        Some(GotoDefinitionResponse::Array(locations))
+    }
+
+    fn references(&self, params: ReferenceParams) -> Option<Vec<Location>> {
+        let position = params.text_document_position;
+        let uri = position.text_document.uri;
+
+        let locations = self
+            .documents
+            .get(&uri)?
+            .references(position.position, params.context.include_declaration)
+            .into_iter()
+            .map(|range| Location::new(uri.clone(), range))
+            .collect();
+
+        Some(locations)
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let position = params.text_document_position_params;
+
+        self.documents
+            .get(&position.text_document.uri)?
+            .hover(position.position)
+    }
+
+    fn completion(&self, params: CompletionParams) -> Option<CompletionResponse> {
+        let position = params.text_document_position;
+
+        let items = self
+            .documents
+            .get(&position.text_document.uri)?
+            .completion(position.position);
+
+        Some(CompletionResponse::Array(items))
+    }
+
+    fn symbols(&self, params: DocumentSymbolParams) -> Option<DocumentSymbolResponse> {
+        let symbols = self.documents.get(&params.text_document.uri)?.symbols();
+
+        Some(DocumentSymbolResponse::Nested(symbols))
+    }
+}
+
+fn response(id: RequestId, result: Result<Value, serde_json::Error>) -> Response {
+    match result {
+        Ok(value) => Response::new_ok(id, value),
+        Err(e) => Response::new_err(id, ErrorCode::InvalidParams as i32, e.to_string()),
+    }
+}
+
+fn publish_diagnostics(uri: Url, diagnostics: Vec<lsp_types::Diagnostic>) -> Notification {
+    let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+
+    Notification::new(String::from(PublishDiagnostics::METHOD), params)
+}
+
+fn send(connection: &Connection, message: Message) -> Result<(), String> {
+    connection
+        .sender
+        .send(message)
+        .map_err(|e| format!("Failed to send message:\n{}", e))
+}
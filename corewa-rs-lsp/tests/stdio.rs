@@ -0,0 +1,283 @@
+use serde_json::{json, Value};
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+const URI: &str = "file:///champions/test.s";
+
+const CHAMPION: &str = r#".name "test"
+.comment "scripted"
+
+SIZE = 4 * 2
+loop:
+    sti r1, %:live, %1
+live:
+    live %1
+    ld %SIZE, r2
+    zjmp %:loop
+"#;
+
+/// Speaks to the server the way an editor would
+struct Client {
+    server: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u64,
+    notifications: Vec<Value>,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut server = Command::new(env!("CARGO_BIN_EXE_corewa-rs-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("Failed to start the server");
+
+        let stdin = server.stdin.take().unwrap();
+        let stdout = BufReader::new(server.stdout.take().unwrap());
+
+        let mut client = Self {
+            server,
+            stdin,
+            stdout,
+            next_id: 0,
+            notifications: Vec::new(),
+        };
+
+        client.request("initialize", json!({ "capabilities": {} }));
+        client.notify("initialized", json!({}));
+        client
+    }
+
+    fn open(&mut self, text: &str) -> Value {
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": URI, "languageId": "corewar", "version": 1, "text": text }
+            }),
+        );
+        self.diagnostics()
+    }
+
+    fn send(&mut self, message: Value) {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            self.stdout.read_line(&mut header).unwrap();
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length: ") {
+                length = value.parse().unwrap();
+            }
+        }
+
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+
+        loop {
+            let mut message = self.receive();
+            if message["id"] == id {
+                return message["result"].take();
+            }
+            self.notifications.push(message);
+        }
+    }
+
+    /// The diagnostics published since the last call
+    fn diagnostics(&mut self) -> Value {
+        loop {
+            if let Some(idx) = self
+                .notifications
+                .iter()
+                .position(|n| n["method"] == "textDocument/publishDiagnostics")
+            {
+                let mut notification = self.notifications.remove(idx);
+                return notification["params"]["diagnostics"].take();
+            }
+            let message = self.receive();
+            self.notifications.push(message);
+        }
+    }
+
+    fn at(&mut self, method: &str, line: u32, character: u32) -> Value {
+        let mut params = json!({
+            "textDocument": { "uri": URI },
+            "position": { "line": line, "character": character },
+        });
+        if method == "textDocument/references" {
+            params["context"] = json!({ "includeDeclaration": true });
+        }
+
+        self.request(method, params)
+    }
+
+    fn shutdown(mut self) {
+        self.request("shutdown", Value::Null);
+        self.notify("exit", Value::Null);
+        assert!(self.server.wait().unwrap().success());
+    }
+}
+
+fn range(value: &Value) -> (u64, u64, u64, u64) {
+    let range = &value["range"];
+    (
+        range["start"]["line"].as_u64().unwrap(),
+        range["start"]["character"].as_u64().unwrap(),
+        range["end"]["line"].as_u64().unwrap(),
+        range["end"]["character"].as_u64().unwrap(),
+    )
+}
+
+fn labels(items: &Value) -> Vec<&str> {
+    items
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["label"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn diagnostics_have_exact_ranges() {
+    let mut client = Client::start();
+
+    let diagnostics = client.open(".name \"a\"\n.comment \"b\"\n\n  ld %1, r42\n");
+    let diagnostics = diagnostics.as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["severity"], 1);
+    assert_eq!(range(&diagnostics[0]), (3, 9, 3, 12));
+
+    // Fixing the error publishes the warnings instead
+    client.notify(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": URI, "version": 2 },
+            "contentChanges": [{ "text": ".name \"a\"\n.comment \"b\"\n\nunused:\n  ld %1, r2\n" }]
+        }),
+    );
+    let diagnostics = client.diagnostics();
+    let diagnostics = diagnostics.as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["severity"], 2);
+    assert_eq!(range(&diagnostics[0]), (3, 0, 3, 7));
+
+    client.notify(
+        "textDocument/didClose",
+        json!({ "textDocument": { "uri": URI } }),
+    );
+    assert_eq!(client.diagnostics(), json!([]));
+
+    client.shutdown();
+}
+
+#[test]
+fn labels_definitions_and_references() {
+    let mut client = Client::start();
+    assert_eq!(client.open(CHAMPION), json!([]));
+
+    // `%:live` on the `sti` line
+    let definitions = client.at("textDocument/definition", 5, 15);
+    let definitions = definitions.as_array().unwrap();
+    assert_eq!(definitions.len(), 1);
+    assert_eq!(range(&definitions[0]), (6, 0, 6, 4));
+
+    let references = client.at("textDocument/references", 4, 1);
+    let references = references
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(range)
+        .collect::<Vec<_>>();
+    assert_eq!(references, [(4, 0, 4, 4), (9, 11, 9, 15)]);
+
+    let definitions = client.at("textDocument/definition", 8, 9);
+    assert_eq!(range(&definitions[0]), (3, 0, 3, 4));
+
+    client.shutdown();
+}
+
+#[test]
+fn hover_shows_op_specs() {
+    let mut client = Client::start();
+    client.open(CHAMPION);
+
+    let hover = client.at("textDocument/hover", 5, 5);
+    let contents = hover["contents"]["value"].as_str().unwrap();
+    assert!(contents.contains("**sti**"));
+    assert!(contents.contains("cycles: 25"));
+    assert!(contents.contains("`T_REG`, `T_REG | T_DIR | T_IND`, `T_REG | T_DIR`"));
+    assert!(contents.contains("direct size: 2 bytes"));
+    assert_eq!(range(&hover), (5, 4, 5, 7));
+
+    assert_eq!(client.at("textDocument/hover", 5, 9), Value::Null);
+
+    client.shutdown();
+}
+
+#[test]
+fn completion_depends_on_context() {
+    let mut client = Client::start();
+    client.open(CHAMPION);
+
+    let mnemonics = client.at("textDocument/completion", 7, 6);
+    let mnemonics = labels(&mnemonics);
+    assert_eq!(mnemonics.len(), 16);
+    assert!(mnemonics.contains(&"live") && mnemonics.contains(&"lfork"));
+
+    let names = client.at("textDocument/completion", 5, 15);
+    assert_eq!(labels(&names), ["loop", "live"]);
+
+    let params = client.at("textDocument/completion", 8, 14);
+    let params = labels(&params);
+    assert!(params.contains(&"r1") && params.contains(&"r16"));
+    assert!(params.contains(&":loop") && params.contains(&"SIZE"));
+
+    client.shutdown();
+}
+
+#[test]
+fn document_symbols_list_labels_and_constants() {
+    let mut client = Client::start();
+    client.open(CHAMPION);
+
+    let symbols = client.request(
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": URI } }),
+    );
+    let symbols = symbols
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| {
+            (
+                symbol["name"].as_str().unwrap(),
+                symbol["kind"].as_u64().unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(symbols, [("SIZE", 14), ("loop", 12), ("live", 12)]);
+
+    client.shutdown();
+}
@@ -0,0 +1,453 @@
+use corewa_rs::{
+    language::debug_info::DebugInfo,
+    vm::{
+        loader::LoadError,
+        outcome::{MatchOutcome, Termination, TieBreak},
+        profiler::{Counters, HotSpot},
+        stats::Stats,
+        types::PlayerId,
+        VirtualMachine,
+    },
+};
+use serde_json::json;
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+use structopt::StructOpt;
+
+const DUMP_LINE_WIDTH: usize = 32;
+/// The number of hot spots printed for each player
+const HOT_SPOT_COUNT: usize = 10;
+
+fn main() {
+    let opts = Options::from_args();
+
+    let exit_code = match run(&opts) {
+        Ok(_) => 0,
+        Err(err) => {
+            eprintln!("{}", err);
+            err.exit_code()
+        }
+    };
+
+    std::process::exit(exit_code)
+}
+
+fn run(opts: &Options) -> Result<(), RunError> {
+    let players = opts
+        .champion_files
+        .iter()
+        .enumerate()
+        .map(|(i, path)| {
+            let champion = fs::read(path).map_err(|err| RunError::Read(path.clone(), err))?;
+            Ok((i as i32 + 1, champion))
+        })
+        .collect::<Result<Vec<_>, RunError>>()?;
+
+    if players.is_empty() {
+        return Err(RunError::NoChampions);
+    }
+
+    let mut vm = VirtualMachine::new();
+    vm.load_players(&players)?;
+    if opts.profile {
+        vm.enable_profiling();
+    }
+    if opts.stats.is_some() {
+        vm.enable_stats(opts.stats_interval);
+    }
+
+    let cycle_limit = match (opts.cycles, opts.dump) {
+        (Some(cycles), Some(dump)) => Some(cycles.min(dump)),
+        (cycles, dump) => cycles.or(dump),
+    };
+
+    let outcome = match cycle_limit {
+        Some(limit) => vm.run_until(limit),
+        None => vm.run_to_completion(),
+    };
+
+    // Matches ending before the dump cycle, or stopped before it by the
+    // cycle limit, have no dump
+    let dump = opts
+        .dump
+        .filter(|cycle| vm.cycles == *cycle)
+        .map(|cycle| Dump {
+            cycle,
+            lines: dump_memory(&vm),
+        });
+    if let (Some(cycle), None) = (opts.dump, &dump) {
+        eprintln!(
+            "The match stopped at cycle {} before the dump at cycle {}",
+            vm.cycles, cycle
+        );
+    }
+
+    if let (Some(path), Some(stats)) = (&opts.stats, vm.stats()) {
+        let write = |file| match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => {
+                let mut out = io::BufWriter::new(file);
+                serde_json::to_writer(&mut out, &stats_json(stats))?;
+                writeln!(out)
+            }
+            _ => stats.write_csv(io::BufWriter::new(file)),
+        };
+        fs::File::create(path)
+            .and_then(write)
+            .map_err(|err| RunError::Write(path.clone(), err))?;
+    }
+
+    let profiles = profile_players(&vm, &opts.champion_files);
+
+    if opts.json {
+        println!("{}", json_report(&vm, &outcome, dump, &profiles));
+    } else {
+        print_report(&vm, &outcome, dump, &profiles);
+    }
+
+    Ok(())
+}
+
+/// The memory at a given cycle, as hexadecimal lines
+struct Dump {
+    cycle: u32,
+    lines: Vec<String>,
+}
+
+fn dump_memory(vm: &VirtualMachine) -> Vec<String> {
+    vm.memory
+        .values
+        .inner()
+        .chunks(DUMP_LINE_WIDTH)
+        .enumerate()
+        .map(|(line, bytes)| {
+            let hex = bytes
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<Vec<_>>()
+                .join(" ");
+            format!("0x{:04x} : {}", line * DUMP_LINE_WIDTH, hex)
+        })
+        .collect()
+}
+
+/// Where a player's processes spent their cycles
+struct PlayerProfile {
+    player_id: PlayerId,
+    total: Counters,
+    /// By source line when the champion has debug info, hottest first
+    hot_spots: Vec<HotSpot>,
+    /// The champion's source, when its debug info names it
+    source_lines: Vec<String>,
+}
+
+fn profile_players(vm: &VirtualMachine, champion_files: &[PathBuf]) -> Vec<PlayerProfile> {
+    let profile = match vm.profile() {
+        Some(profile) => profile,
+        None => return Vec::new(),
+    };
+
+    vm.players
+        .iter()
+        .map(|player| {
+            let champion_file = &champion_files[player.id as usize - 1];
+            let (debug_info, source_lines) = load_debug_info(champion_file);
+            let hot_spots = match debug_info {
+                Some(debug_info) => {
+                    debug_info.hot_spots(&profile.offsets(player, vm.config.mem_size))
+                }
+                None => profile.hot_spots(player, vm.config.mem_size),
+            };
+
+            PlayerProfile {
+                player_id: player.id,
+                total: profile.total(player.id),
+                hot_spots,
+                source_lines,
+            }
+        })
+        .collect()
+}
+
+/// Loads the debug info sidecar of a champion (e.g. `zork.dbg` for
+/// `zork.cor`) if there is one, along with the source file it names relative
+/// to itself
+fn load_debug_info(champion_file: &Path) -> (Option<DebugInfo>, Vec<String>) {
+    let debug_info_file = champion_file.with_extension("dbg");
+    if !debug_info_file.is_file() {
+        return (None, Vec::new());
+    }
+
+    let debug_info = match fs::File::open(&debug_info_file)
+        .map_err(|e| e.to_string())
+        .and_then(|file| DebugInfo::read(io::BufReader::new(file)).map_err(|e| e.to_string()))
+    {
+        Ok(debug_info) => debug_info,
+        Err(e) => {
+            eprintln!("Ignoring {}: {}", debug_info_file.display(), e);
+            return (None, Vec::new());
+        }
+    };
+    let source_lines = debug_info
+        .file
+        .as_ref()
+        .and_then(|file| fs::read_to_string(debug_info_file.with_file_name(file)).ok())
+        .map(|source| source.lines().map(|line| line.trim().to_string()).collect())
+        .unwrap_or_default();
+
+    (Some(debug_info), source_lines)
+}
+
+fn print_profile(profile: &PlayerProfile) {
+    let total = &profile.total;
+    println!(
+        "  {} cycles: {} instructions executed, {} waiting, {} invalid op codes, {} decode failures",
+        total.cycles(),
+        total.executions,
+        total.wait_cycles,
+        total.invalid_op_codes,
+        total.decode_failures
+    );
+
+    for spot in profile.hot_spots.iter().take(HOT_SPOT_COUNT) {
+        let location = match spot.line {
+            Some(line) => match profile.source_lines.get(line - 1) {
+                Some(text) => format!("line {}: {}", line, text),
+                None => format!("line {}", line),
+            },
+            None => format!("offset {}", spot.offset),
+        };
+        println!(
+            "  {:>10} cycles {:>8} executions  {}",
+            spot.counters.cycles(),
+            spot.counters.executions,
+            location
+        );
+    }
+}
+
+fn counters_json(counters: &Counters) -> serde_json::Value {
+    json!({
+        "cycles": counters.cycles(),
+        "executions": counters.executions,
+        "wait_cycles": counters.wait_cycles,
+        "invalid_op_codes": counters.invalid_op_codes,
+        "decode_failures": counters.decode_failures,
+    })
+}
+
+fn profile_json(profile: &PlayerProfile) -> serde_json::Value {
+    let hot_spots = profile
+        .hot_spots
+        .iter()
+        .map(|spot| {
+            let mut value = counters_json(&spot.counters);
+            value["offset"] = json!(spot.offset);
+            value["line"] = json!(spot.line);
+            value
+        })
+        .collect::<Vec<_>>();
+
+    let mut value = counters_json(&profile.total);
+    value["hot_spots"] = json!(hot_spots);
+    value
+}
+
+/// The samples with each player's overwritten bytes keyed by opponent
+fn stats_json(stats: &Stats) -> serde_json::Value {
+    let samples = stats
+        .samples
+        .iter()
+        .map(|sample| {
+            let players = sample
+                .players
+                .iter()
+                .map(|player| {
+                    let overwritten = player
+                        .overwritten
+                        .iter()
+                        .map(|(player_id, bytes)| (player_id.to_string(), json!(bytes)))
+                        .collect::<serde_json::Map<_, _>>();
+                    json!({
+                        "id": player.player_id,
+                        "owned_cells": player.owned_cells,
+                        "processes": player.processes,
+                        "live_reports": player.live_reports,
+                        "overwritten": overwritten,
+                    })
+                })
+                .collect::<Vec<_>>();
+            json!({
+                "cycle": sample.cycle,
+                "check_interval": sample.check_interval,
+                "players": players,
+            })
+        })
+        .collect::<Vec<_>>();
+    let check_interval_changes = stats
+        .check_interval_changes
+        .iter()
+        .map(|change| json!({ "cycle": change.cycle, "check_interval": change.check_interval }))
+        .collect::<Vec<_>>();
+
+    json!({
+        "interval": stats.interval,
+        "samples": samples,
+        "check_interval_changes": check_interval_changes,
+    })
+}
+
+fn print_report(
+    vm: &VirtualMachine,
+    outcome: &MatchOutcome,
+    dump: Option<Dump>,
+    profiles: &[PlayerProfile],
+) {
+    if let Some(dump) = dump {
+        println!("Memory at cycle {}:", dump.cycle);
+        for line in dump.lines {
+            println!("{}", line);
+        }
+        println!();
+    }
+
+    let winner = outcome
+        .winner
+        .and_then(|id| vm.players.iter().find(|player| player.id == id));
+    match winner {
+        Some(player) => println!(
+            "Winner: player {} \"{}\" ({})",
+            player.id,
+            player.name,
+            match outcome.tie_break {
+                TieBreak::LastLive => "last reported alive",
+                TieBreak::LoadOrder => "tie broken by load order",
+            }
+        ),
+        None => println!("Winner: none"),
+    }
+
+    println!(
+        "Final cycle: {} ({})",
+        outcome.final_cycle,
+        match outcome.termination {
+            Termination::AllProcessesDead => "all processes dead",
+            Termination::CycleLimit => "cycle limit reached",
+        }
+    );
+
+    for player in &vm.players {
+        println!(
+            "Player {} \"{}\": {} bytes, last live at cycle {}, {} processes alive",
+            player.id,
+            player.name,
+            player.size,
+            outcome.last_live(player.id).unwrap_or(0),
+            vm.process_count_by_player_id
+                .get(&player.id)
+                .copied()
+                .unwrap_or(0),
+        );
+        if let Some(profile) = profiles.iter().find(|p| p.player_id == player.id) {
+            print_profile(profile);
+        }
+    }
+}
+
+fn json_report(
+    vm: &VirtualMachine,
+    outcome: &MatchOutcome,
+    dump: Option<Dump>,
+    profiles: &[PlayerProfile],
+) -> serde_json::Value {
+    let players = vm
+        .players
+        .iter()
+        .map(|player| {
+            let profile = profiles
+                .iter()
+                .find(|profile| profile.player_id == player.id)
+                .map(profile_json);
+            json!({
+                "id": player.id,
+                "name": player.name,
+                "comment": player.comment,
+                "size": player.size,
+                "last_live": outcome.last_live(player.id).unwrap_or(0),
+                "processes": vm.process_count_by_player_id.get(&player.id).copied().unwrap_or(0),
+                "output": vm.outputs.get(&player.id).cloned().unwrap_or_default(),
+                "profile": profile,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "winner": outcome.winner,
+        "tie_break": match outcome.tie_break {
+            TieBreak::LastLive => "last_live",
+            TieBreak::LoadOrder => "load_order",
+        },
+        "final_cycle": outcome.final_cycle,
+        "termination": match outcome.termination {
+            Termination::AllProcessesDead => "all_processes_dead",
+            Termination::CycleLimit => "cycle_limit",
+        },
+        "players": players,
+        "dump": dump.map(|dump| json!({ "cycle": dump.cycle, "memory": dump.lines })),
+    })
+}
+
+/// Runs a match without any user interface.
+///
+/// Exits with 1 when a champion file cannot be read and with 2 when the
+/// champions cannot be loaded in the arena
+#[derive(Debug, StructOpt)]
+struct Options {
+    /// Between 1 and 4 compiled champions (.cor), numbered from 1 in order
+    champion_files: Vec<PathBuf>,
+    /// Stops the match after this many cycles
+    #[structopt(short = "c", long)]
+    cycles: Option<u32>,
+    /// Stops the match at this cycle and dumps the memory, unless it ends or
+    /// is stopped by `--cycles` before
+    #[structopt(short = "d", long)]
+    dump: Option<u32>,
+    /// Prints the report as JSON
+    #[structopt(long)]
+    json: bool,
+    /// Counts where each player's processes spend their cycles and reports
+    /// the hottest code, by source line when the champion has a debug info
+    /// sidecar (`zork.dbg` for `zork.cor`)
+    #[structopt(long)]
+    profile: bool,
+    /// Writes territory statistics sampled during the match to this file, as
+    /// JSON if its extension is `.json` and as CSV otherwise
+    #[structopt(long)]
+    stats: Option<PathBuf>,
+    /// The number of cycles between statistics samples
+    #[structopt(long, default_value = "100")]
+    stats_interval: u32,
+}
+
+#[derive(Debug, thiserror::Error)]
+enum RunError {
+    #[error("Failed to read {}: {1}", .0.display())]
+    Read(PathBuf, io::Error),
+    #[error("Failed to write {}: {1}", .0.display())]
+    Write(PathBuf, io::Error),
+    #[error("At least one champion is required")]
+    NoChampions,
+    #[error("Failed to load the champions: {0}")]
+    Load(#[from] LoadError),
+}
+
+impl RunError {
+    fn exit_code(&self) -> i32 {
+        match self {
+            RunError::Read(..) | RunError::Write(..) | RunError::NoChampions => 1,
+            RunError::Load(_) => 2,
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use serde_json::Value;
+
+use std::{fs, path::PathBuf, process::Command};
+
+macro_rules! sample {
+    ($name:ident) => {
+        concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../corewa-rs/tests/vm/samples/",
+            stringify!($name),
+            ".cor"
+        )
+    };
+}
+
+fn run(args: &[&str]) -> (i32, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_corewa-rs-run"))
+        .args(args)
+        .output()
+        .expect("Failed to run the match runner");
+
+    let exit_code = output.status.code().expect("Killed by a signal");
+    (exit_code, String::from_utf8(output.stdout).unwrap())
+}
+
+fn run_json(args: &[&str]) -> Value {
+    let (exit_code, stdout) = run(&[args, &["--json"]].concat());
+    assert_eq!(exit_code, 0);
+    serde_json::from_str(&stdout).expect("Invalid JSON report")
+}
+
+#[test]
+fn json_report() {
+    let report = run_json(&[sample!(zork), sample!(bigzork)]);
+
+    assert_eq!(report["winner"], 2);
+    assert_eq!(report["tie_break"], "last_live");
+    assert_eq!(report["termination"], "all_processes_dead");
+    assert_eq!(report["dump"], Value::Null);
+
+    let players = report["players"].as_array().unwrap();
+    assert_eq!(players.len(), 2);
+    assert_eq!(players[0]["id"], 1);
+    assert_eq!(players[0]["name"], "zork");
+    assert_eq!(players[1]["id"], 2);
+    assert_eq!(players[1]["processes"], 0);
+}
+
+#[test]
+fn dump_at_cycle() {
+    let report = run_json(&[sample!(zork), "-d", "100"]);
+
+    assert_eq!(report["final_cycle"], 100);
+    assert_eq!(report["termination"], "cycle_limit");
+    assert_eq!(report["dump"]["cycle"], 100);
+
+    let memory = report["dump"]["memory"].as_array().unwrap();
+    assert_eq!(memory.len(), 4096 / 32);
+    assert!(memory[0].as_str().unwrap().starts_with("0x0000 : 0b 68 01"));
+
+    let (exit_code, stdout) = run(&[sample!(zork), "-d", "100"]);
+    assert_eq!(exit_code, 0);
+    assert!(stdout.starts_with("Memory at cycle 100:\n0x0000 : "));
+}
+
+#[test]
+fn no_dump_before_the_dump_cycle() {
+    // Stopped by the cycle limit
+    let report = run_json(&[sample!(zork), "-c", "100", "-d", "500"]);
+    assert_eq!(report["final_cycle"], 100);
+    assert_eq!(report["dump"], Value::Null);
+
+    // Ended on its own
+    let report = run_json(&[sample!(zork), "-d", "100000"]);
+    assert_eq!(report["termination"], "all_processes_dead");
+    assert_eq!(report["dump"], Value::Null);
+}
+
+#[test]
+fn profile() {
+    let report = run_json(&[sample!(zork), "-c", "1000", "--profile"]);
+
+    let profile = &report["players"][0]["profile"];
+    assert_eq!(profile["cycles"], 1000);
+    assert_eq!(profile["invalid_op_codes"], 0);
+
+    // The `zjmp` of the loop takes 20 cycles
+    let hottest = &profile["hot_spots"][0];
+    assert_eq!(hottest["offset"], 20);
+    assert_eq!(hottest["line"], Value::Null);
+    assert_eq!(hottest["executions"], 32);
+
+    let report = run_json(&[sample!(zork), "-c", "1000"]);
+    assert_eq!(report["players"][0]["profile"], Value::Null);
+
+    let (exit_code, stdout) = run(&[sample!(zork), "-c", "1000", "--profile"]);
+    assert_eq!(exit_code, 0);
+    assert!(stdout.contains("  1000 cycles: "));
+}
+
+#[test]
+fn profile_with_debug_info() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("profile");
+    fs::create_dir_all(&dir).unwrap();
+    let champion = dir.join("zork.cor");
+    fs::copy(sample!(zork), &champion).unwrap();
+    fs::write(
+        dir.join("zork.s"),
+        ".name \"zork\"\n.comment \"\"\n\nlive:\tlive %1\n\tzjmp %:live\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("zork.dbg"),
+        "corewa-rs debug info v1\nfile zork.s\nmap 15 20 4 6\nmap 20 23 5 1\n",
+    )
+    .unwrap();
+
+    let champion = champion.to_str().unwrap();
+    let report = run_json(&[champion, "-c", "1000", "--profile"]);
+    let hottest = &report["players"][0]["profile"]["hot_spots"][0];
+    assert_eq!(hottest["offset"], 20);
+    assert_eq!(hottest["line"], 5);
+
+    let (_, stdout) = run(&[champion, "-c", "1000", "--profile"]);
+    assert!(stdout.contains(" executions  line 5: zjmp %:live\n"));
+}
+
+#[test]
+fn stats_export() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let csv = dir.join("stats.csv");
+    let json = dir.join("stats.json");
+
+    let args = [sample!(zork), sample!(bigzork), "-c", "1000"];
+    let (exit_code, _) = run(&[&args[..], &["--stats", csv.to_str().unwrap()]].concat());
+    assert_eq!(exit_code, 0);
+    let csv = fs::read_to_string(csv).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some(
+            "cycle,check_interval,player_id,owned_cells,processes,live_reports,\
+             overwritten_1,overwritten_2"
+        )
+    );
+    // 2 players sampled every 100 cycles
+    assert_eq!(lines.count(), 2 * 10);
+
+    let stats_args = ["--stats", json.to_str().unwrap(), "--stats-interval", "500"];
+    let (exit_code, _) = run(&[&args[..], &stats_args].concat());
+    assert_eq!(exit_code, 0);
+    let json: Value = serde_json::from_str(&fs::read_to_string(json).unwrap()).unwrap();
+    assert_eq!(json["interval"], 500);
+    let samples = json["samples"].as_array().unwrap();
+    assert_eq!(samples.len(), 2);
+    assert_eq!(samples[1]["cycle"], 1000);
+    assert_eq!(samples[1]["players"][1]["owned_cells"], 430);
+    assert!(samples[1]["players"][1]["overwritten"]["1"].is_u64());
+    assert!(json["check_interval_changes"].is_array());
+}
+
+#[test]
+fn unreadable_champions() {
+    assert_eq!(run(&["does_not_exist.cor"]).0, 1);
+    assert_eq!(run(&[]).0, 1);
+}
+
+#[test]
+fn invalid_champions() {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("invalid.cor");
+    fs::write(&path, [0; 16]).unwrap();
+
+    assert_eq!(run(&[path.to_str().unwrap()]).0, 2);
+}
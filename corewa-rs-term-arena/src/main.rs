@@ -1,7 +1,26 @@
 mod util;
 
-use corewa_rs::vm::{types::PlayerId, VirtualMachine};
-use std::{collections::HashMap, error::Error, fs, io};
+use corewa_rs::{
+    language::debug_info::DebugInfo,
+    spec::VmConfig,
+    vm::{
+        debugger::{Breakpoint, Debugger, Hit},
+        events::{Event as VMEvent, EventKind},
+        replay::{Replay, ReplayRecorder, DEFAULT_CHECKPOINT_INTERVAL},
+        snapshot::History,
+        stats::{PlayerSample, Stats, DEFAULT_SAMPLE_INTERVAL},
+        types::{Pid, PlayerId},
+        VirtualMachine,
+    },
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    error::Error,
+    fs::{self, File},
+    io::{self, BufWriter},
+    ops::Range,
+    path::{Path, PathBuf},
+};
 use structopt::StructOpt;
 use termion::{event::Key, input::MouseTerminal, raw::IntoRawMode, screen::AlternateScreen};
 use tui::{
@@ -9,7 +28,7 @@ use tui::{
     buffer::Buffer,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Style},
-    widgets::{Block, Borders, Widget},
+    widgets::{Block, Borders, Sparkline, Widget},
     Terminal,
 };
 use util::{Event, Events};
@@ -29,22 +48,54 @@ fn main() {
 fn run() -> Result<(), Box<dyn Error>> {
     let opts = Options::from_args();
 
-    if opts.champion_files.is_empty() {
-        panic!("Require at least 1 champion");
-    }
+    let mut sources = SourceViews::new();
 
-    let mut vm = VirtualMachine::new();
-    let players: Vec<_> = opts
-        .champion_files
-        .iter()
-        .enumerate()
-        .map(|(i, file_name)| {
-            let champion = fs::read(&file_name)?;
-            Ok((i as i32 + 1, champion))
-        })
-        .collect::<Result<_, io::Error>>()?;
+    let mut session = match &opts.replay {
+        Some(replay_file) => {
+            if !opts.champion_files.is_empty() || opts.record.is_some() {
+                return Err("A replay cannot be combined with champions or a recording".into());
+            }
+
+            let mut replay = Replay::read(io::BufReader::new(File::open(replay_file)?))?;
+            replay.enable_events();
+            replay.enable_stats(DEFAULT_SAMPLE_INTERVAL);
+            Session::Playback(replay)
+        }
+        None => {
+            if opts.champion_files.is_empty() {
+                panic!("Require at least 1 champion");
+            }
+
+            let config = opts.arena.config();
+            config.validate()?;
+
+            let players: Vec<_> = opts
+                .champion_files
+                .iter()
+                .enumerate()
+                .map(|(i, file_name)| {
+                    let champion = fs::read(file_name)?;
+                    Ok((i as i32 + 1, champion))
+                })
+                .collect::<Result<_, io::Error>>()?;
+
+            for (i, file_name) in opts.champion_files.iter().enumerate() {
+                if let Some(view) = SourceView::load(Path::new(file_name))? {
+                    sources.insert(i as i32 + 1, view);
+                }
+            }
 
-    vm.load_players(&players);
+            let mut session = Session::Live {
+                vm: VirtualMachine::with_config(config.clone()),
+                config,
+                players,
+                recorder: None,
+                history: History::default(),
+            };
+            session.reset(opts.record.as_ref())?;
+            session
+        }
+    };
 
     let stdout = io::stdout().into_raw_mode()?;
     let stdout = MouseTerminal::from(stdout);
@@ -57,7 +108,8 @@ fn run() -> Result<(), Box<dyn Error>> {
 
     let colors = [Color::Yellow, Color::Magenta, Color::Green, Color::Cyan];
 
-    let player_colors = vm
+    let player_colors = session
+        .vm()
         .players
         .iter()
         .enumerate()
@@ -65,9 +117,23 @@ fn run() -> Result<(), Box<dyn Error>> {
         .collect();
 
     let mut controls = Controls::default();
+    let mut event_feed = EventFeed::default();
+    let mut followed_pid = None;
+
+    let mut debugger = Debugger::new();
+    for addr in &opts.break_at {
+        debugger.add(Breakpoint::Address(*addr));
+    }
+    for range in &opts.watch {
+        debugger.add(Breakpoint::Watch {
+            range: range.clone(),
+            by: None,
+        });
+    }
 
     loop {
         if controls.running {
+            let vm = session.vm();
             terminal.draw(|f| {
                 let chunks = Layout::default()
                     .direction(Direction::Horizontal)
@@ -81,12 +147,45 @@ fn run() -> Result<(), Box<dyn Error>> {
                 let info_chunks = Layout::default()
                     .direction(Direction::Vertical)
                     .margin(1)
-                    .constraints([Constraint::Percentage(20), Constraint::Percentage(80)].as_ref())
+                    .constraints(
+                        [
+                            Constraint::Percentage(10),
+                            Constraint::Percentage(30),
+                            Constraint::Percentage(15),
+                            Constraint::Percentage(20),
+                            Constraint::Percentage(25),
+                        ]
+                        .as_ref(),
+                    )
                     .split(chunks[0]);
 
                 let block = Block::default().borders(Borders::BOTTOM);
                 f.render_widget(block, info_chunks[0]);
 
+                let block = Block::default().borders(Borders::TOP).title("Output");
+                f.render_widget(block, info_chunks[2]);
+
+                let output_chunks = Layout::default()
+                    .constraints([Constraint::Percentage(100)].as_ref())
+                    .vertical_margin(1)
+                    .split(info_chunks[2]);
+
+                let block = Block::default().borders(Borders::TOP).title("Source");
+                f.render_widget(block, info_chunks[3]);
+
+                let source_chunks = Layout::default()
+                    .constraints([Constraint::Percentage(100)].as_ref())
+                    .vertical_margin(1)
+                    .split(info_chunks[3]);
+
+                let block = Block::default().borders(Borders::TOP).title("Events");
+                f.render_widget(block, info_chunks[4]);
+
+                let event_chunks = Layout::default()
+                    .constraints([Constraint::Percentage(100)].as_ref())
+                    .vertical_margin(1)
+                    .split(info_chunks[4]);
+
                 let block = Block::default().borders(Borders::ALL).title("Memory");
                 f.render_widget(block, chunks[1]);
 
@@ -96,8 +195,14 @@ fn run() -> Result<(), Box<dyn Error>> {
                     .split(chunks[1]);
 
                 f.render_widget(&controls, info_chunks[0]);
-                f.render_widget(VMStateWidget(&vm), info_chunks[1]);
-                f.render_widget(MemoryWidget(&vm, &player_colors, opts.chr), vm_chunks[0]);
+                f.render_widget(VMStateWidget(vm, &player_colors), info_chunks[1]);
+                f.render_widget(OutputWidget(vm, &player_colors), output_chunks[0]);
+                f.render_widget(
+                    SourceWidget(vm, &sources, followed_pid, &player_colors),
+                    source_chunks[0],
+                );
+                f.render_widget(&event_feed, event_chunks[0]);
+                f.render_widget(MemoryWidget(vm, &player_colors, opts.chr), vm_chunks[0]);
             })?;
         }
 
@@ -108,13 +213,24 @@ fn run() -> Result<(), Box<dyn Error>> {
                     '+' => controls.faster(),
                     '-' => controls.slower(),
                     ' ' => controls.toggle_running(),
+                    'l' => controls.run_until_live_check(),
+                    '\t' => followed_pid = next_process(session.vm(), followed_pid),
                     'r' => {
-                        vm = VirtualMachine::new();
-                        vm.load_players(&players)
+                        session.reset(opts.record.as_ref())?;
+                        event_feed.clear();
                     }
                     _ => (),
                 },
-                Key::Right => vm.tick(),
+                Key::Right => {
+                    let hits = session.step(&mut debugger)?;
+                    event_feed.push_hits(&hits);
+                }
+                Key::Left => {
+                    let rewound = session.step_back()?;
+                    if rewound {
+                        event_feed.clear();
+                    }
+                }
                 _ => (),
             },
             Event::Mouse(_ev) => {
@@ -123,19 +239,157 @@ fn run() -> Result<(), Box<dyn Error>> {
             Event::Tick => {
                 if controls.running {
                     for _ in 0..controls.speed {
-                        vm.tick();
+                        let hits = session.step(&mut debugger)?;
+                        let vm = session.vm();
+                        let live_checked = vm.last_live_check == vm.cycles;
+                        if !hits.is_empty() || (controls.until_live_check && live_checked) {
+                            // Events of the cycle come before the hits
+                            event_feed.extend(session.drain_events());
+                            event_feed.push_hits(&hits);
+                            controls.pause();
+                            break;
+                        }
                     }
                 }
             }
         }
+
+        event_feed.extend(session.drain_events());
     }
 
-    Ok(())
+    session.finish()
+}
+
+/// Either a match being played or the playback of a recorded one
+enum Session {
+    Live {
+        vm: VirtualMachine,
+        config: VmConfig,
+        players: Vec<(PlayerId, Vec<u8>)>,
+        recorder: Option<ReplayRecorder<BufWriter<File>>>,
+        history: History,
+    },
+    Playback(Replay),
+}
+
+impl Session {
+    fn vm(&self) -> &VirtualMachine {
+        match self {
+            Session::Live { vm, .. } => vm,
+            Session::Playback(replay) => replay.vm(),
+        }
+    }
+
+    /// Restarts the match. A live match being recorded starts a new recording
+    fn reset(&mut self, record_file: Option<&PathBuf>) -> Result<(), Box<dyn Error>> {
+        match self {
+            Session::Live {
+                vm,
+                config,
+                players,
+                recorder,
+                history,
+            } => {
+                *vm = VirtualMachine::with_config(config.clone());
+                vm.load_players(players)?;
+                vm.enable_events();
+                vm.enable_stats(DEFAULT_SAMPLE_INTERVAL);
+
+                history.clear();
+                history.record(vm);
+
+                *recorder = match record_file {
+                    Some(path) => Some(ReplayRecorder::new(
+                        BufWriter::new(File::create(path)?),
+                        config,
+                        players,
+                        DEFAULT_CHECKPOINT_INTERVAL,
+                    )?),
+                    None => None,
+                };
+            }
+            Session::Playback(replay) => {
+                replay.seek(0)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs a single cycle and returns the breakpoints it hit
+    fn step(&mut self, debugger: &mut Debugger) -> Result<Vec<Hit>, Box<dyn Error>> {
+        let hits = match self {
+            Session::Live {
+                vm,
+                recorder,
+                history,
+                ..
+            } => {
+                let hits = debugger.step(vm);
+                history.record(vm);
+                if let Some(recorder) = recorder {
+                    recorder.record(vm)?;
+                }
+                hits
+            }
+            Session::Playback(replay) => {
+                let next_cycle = replay.vm().cycles + 1;
+                debugger.before_tick(replay.vm());
+                replay.seek(next_cycle)?;
+                debugger.after_tick(replay.vm())
+            }
+        };
+
+        Ok(hits)
+    }
+
+    /// Goes back one cycle, as long as the history of a live match allows it.
+    /// Returns whether the match was rewound
+    fn step_back(&mut self) -> Result<bool, Box<dyn Error>> {
+        match self {
+            Session::Live { vm, history, .. } => {
+                let cycle = vm.cycles;
+                Ok(history
+                    .step_back(vm, 1)
+                    .is_some_and(|rewound_to| rewound_to < cycle))
+            }
+            Session::Playback(replay) => {
+                let cycle = replay.vm().cycles;
+                if cycle == 0 {
+                    return Ok(false);
+                }
+                replay.seek(cycle - 1)?;
+                Ok(true)
+            }
+        }
+    }
+
+    fn drain_events(&mut self) -> Box<dyn Iterator<Item = VMEvent> + '_> {
+        match self {
+            Session::Live { vm, .. } => Box::new(vm.drain_events()),
+            Session::Playback(replay) => Box::new(replay.drain_events()),
+        }
+    }
+
+    fn finish(self) -> Result<(), Box<dyn Error>> {
+        if let Session::Live {
+            vm,
+            recorder: Some(recorder),
+            ..
+        } = self
+        {
+            recorder.finish(&vm)?;
+        }
+
+        Ok(())
+    }
 }
 
 struct Controls {
     speed: u16,
     running: bool,
+    /// Pauses at the next live check
+    until_live_check: bool,
 }
 
 impl Default for Controls {
@@ -143,6 +397,7 @@ impl Default for Controls {
         Self {
             speed: 1,
             running: true,
+            until_live_check: false,
         }
     }
 }
@@ -157,13 +412,28 @@ impl Controls {
     }
 
     fn toggle_running(&mut self) {
-        self.running = !self.running
+        self.running = !self.running;
+        self.until_live_check = false;
+    }
+
+    fn run_until_live_check(&mut self) {
+        self.running = true;
+        self.until_live_check = true;
+    }
+
+    fn pause(&mut self) {
+        self.running = false;
+        self.until_live_check = false;
     }
 }
 
 impl Widget for &Controls {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let running_string = if self.running { "running" } else { "stopped" };
+        let running_string = match (self.running, self.until_live_check) {
+            (true, false) => "running",
+            (true, true) => "running until live check",
+            (false, _) => "stopped",
+        };
         buf.set_string(area.left(), area.top(), running_string, Style::default());
         buf.set_string(
             area.left(),
@@ -174,7 +444,65 @@ impl Widget for &Controls {
     }
 }
 
-struct VMStateWidget<'a>(&'a VirtualMachine);
+/// The most recent notable events.
+/// Executed instructions and memory writes are too frequent to be followed
+/// here so they are left out
+#[derive(Default)]
+struct EventFeed {
+    recent: VecDeque<String>,
+}
+
+impl EventFeed {
+    const CAPACITY: usize = 64;
+
+    fn extend(&mut self, events: impl Iterator<Item = VMEvent>) {
+        for event in events {
+            match event.kind {
+                EventKind::InstructionExecuted { .. }
+                | EventKind::MemoryWritten { .. }
+                | EventKind::LiveReported { .. } => continue,
+                _ => (),
+            }
+
+            self.push(event.to_string());
+        }
+    }
+
+    fn push_hits(&mut self, hits: &[Hit]) {
+        for hit in hits {
+            self.push(hit.to_string());
+        }
+    }
+
+    fn push(&mut self, line: String) {
+        if self.recent.len() == Self::CAPACITY {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(line);
+    }
+
+    fn clear(&mut self) {
+        self.recent.clear()
+    }
+}
+
+impl Widget for &EventFeed {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let lines = self.recent.iter().rev().take(usize::from(area.height));
+
+        for (line_offset, line) in lines.enumerate() {
+            buf.set_stringn(
+                area.left(),
+                area.top() + line_offset as u16,
+                line,
+                usize::from(area.width),
+                Style::default(),
+            );
+        }
+    }
+}
+
+struct VMStateWidget<'a>(&'a VirtualMachine, &'a PlayerColors);
 
 impl Widget for VMStateWidget<'_> {
     fn render(self, area: Rect, buf: &mut Buffer) {
@@ -203,6 +531,258 @@ impl Widget for VMStateWidget<'_> {
             "Checks passed:  {}",
             vm.checks_without_cycle_decrement
         ));
+
+        if let Some(outcome) = vm.outcome() {
+            let winner = outcome
+                .winner
+                .and_then(|id| vm.players.iter().find(|player| player.id == id));
+
+            show_line(String::new());
+            match winner {
+                Some(player) => show_line(format!("Winner:         {}", player.name)),
+                None => show_line(String::from("Winner:         none")),
+            }
+        }
+
+        if let Some(stats) = vm.stats() {
+            let area = Rect {
+                y: area.top() + line_offset + 1,
+                height: area.height.saturating_sub(line_offset + 1),
+                ..area
+            };
+            TerritoryWidget(vm, stats, self.1).render(area, buf);
+        }
+    }
+}
+
+/// Sparklines of the sampled territory statistics, one line per statistic and
+/// player. The players share the scale of each statistic
+struct TerritoryWidget<'a>(&'a VirtualMachine, &'a Stats, &'a PlayerColors);
+
+/// A labeled value of the players' samples
+type Statistic = (&'static str, fn(&PlayerSample) -> u64);
+
+impl TerritoryWidget<'_> {
+    const LABEL_WIDTH: u16 = 16;
+    const VALUE_WIDTH: u16 = 7;
+}
+
+impl Widget for TerritoryWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let TerritoryWidget(vm, stats, player_colors) = self;
+        if area.width < Self::LABEL_WIDTH + Self::VALUE_WIDTH {
+            return;
+        }
+        let spark_width = area.width - Self::LABEL_WIDTH - Self::VALUE_WIDTH;
+
+        let statistics: [Statistic; 2] = [
+            ("cells", |sample| sample.owned_cells as u64),
+            ("procs", |sample| u64::from(sample.processes)),
+        ];
+
+        let mut line_offset = 0;
+        for (label, value) in &statistics {
+            let all_series = vm
+                .players
+                .iter()
+                .map(|player| (player, stats.series(player.id, value)))
+                .collect::<Vec<_>>();
+            let max = all_series
+                .iter()
+                .flat_map(|(_, series)| series.iter().copied())
+                .max()
+                .unwrap_or(0);
+
+            for (player, series) in &all_series {
+                if line_offset >= area.height {
+                    return;
+                }
+                let y = area.top() + line_offset;
+                line_offset += 1;
+                let style = Style::default().fg(player_colors[&player.id]);
+
+                buf.set_stringn(
+                    area.left(),
+                    y,
+                    format!("{} {}", label, player.name),
+                    usize::from(Self::LABEL_WIDTH - 1),
+                    style,
+                );
+
+                let visible = &series[series.len().saturating_sub(usize::from(spark_width))..];
+                Sparkline::default()
+                    .data(visible)
+                    .max(max)
+                    .style(style)
+                    .render(
+                        Rect {
+                            x: area.left() + Self::LABEL_WIDTH,
+                            y,
+                            width: spark_width,
+                            height: 1,
+                        },
+                        buf,
+                    );
+
+                if let Some(last) = visible.last() {
+                    buf.set_stringn(
+                        area.left() + Self::LABEL_WIDTH + spark_width + 1,
+                        y,
+                        last.to_string(),
+                        usize::from(Self::VALUE_WIDTH - 1),
+                        style,
+                    );
+                }
+            }
+        }
+    }
+}
+
+struct OutputWidget<'a>(&'a VirtualMachine, &'a PlayerColors);
+
+impl Widget for OutputWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let width = usize::from(area.width);
+        let vm = &self.0;
+
+        for (line_offset, player) in vm.players.iter().enumerate() {
+            if line_offset >= usize::from(area.height) {
+                break;
+            }
+
+            // Only keep the most recent characters that fit on the line
+            let output = vm.outputs.get(&player.id).map_or("", String::as_str);
+            let printable = output
+                .chars()
+                .map(|c| if c.is_control() { ' ' } else { c })
+                .collect::<Vec<_>>();
+            let visible = &printable[printable.len().saturating_sub(width)..];
+
+            buf.set_string(
+                area.left(),
+                area.top() + line_offset as u16,
+                visible.iter().collect::<String>(),
+                Style::default().fg(self.1[&player.id]),
+            );
+        }
+    }
+}
+
+/// The process following `current` in the process list, wrapping around
+fn next_process(vm: &VirtualMachine, current: Option<Pid>) -> Option<Pid> {
+    let position =
+        current.and_then(|pid| vm.processes.iter().position(|process| process.pid == pid));
+    let next = position.map_or(0, |idx| idx + 1);
+
+    vm.processes
+        .get(next)
+        .or_else(|| vm.processes.first())
+        .map(|process| process.pid)
+}
+
+type SourceViews = HashMap<PlayerId, SourceView>;
+
+/// A champion's source along with the debug info mapping its code to it
+struct SourceView {
+    debug_info: DebugInfo,
+    lines: Vec<String>,
+}
+
+impl SourceView {
+    /// Loads the debug info sidecar of a champion (e.g. `zork.dbg` for
+    /// `zork.cor`) if there is one.
+    /// The source file it names is relative to the sidecar
+    fn load(champion_file: &Path) -> Result<Option<Self>, Box<dyn Error>> {
+        let debug_info_file = champion_file.with_extension("dbg");
+        if !debug_info_file.is_file() {
+            return Ok(None);
+        }
+
+        let debug_info = DebugInfo::read(io::BufReader::new(File::open(&debug_info_file)?))?;
+        let source_file = match &debug_info.file {
+            Some(file) => debug_info_file.with_file_name(file),
+            None => return Ok(None),
+        };
+        let lines = fs::read_to_string(source_file)?
+            .lines()
+            .map(|line| line.replace('\t', "    "))
+            .collect();
+
+        Ok(Some(Self { debug_info, lines }))
+    }
+}
+
+struct SourceWidget<'a>(
+    &'a VirtualMachine,
+    &'a SourceViews,
+    Option<Pid>,
+    &'a PlayerColors,
+);
+
+impl Widget for SourceWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let vm = &self.0;
+        let width = usize::from(area.width);
+        let mut show_line = |line_offset: u16, text: &str, style| {
+            if line_offset < area.height {
+                buf.set_stringn(area.left(), area.top() + line_offset, text, width, style);
+            }
+        };
+
+        let process = self
+            .2
+            .and_then(|pid| vm.processes.iter().find(|process| process.pid == pid));
+        let process = match process {
+            Some(process) => process,
+            None => return show_line(0, "Press Tab to follow a process", Style::default()),
+        };
+        let player = match vm
+            .players
+            .iter()
+            .find(|player| player.id == process.player_id)
+        {
+            Some(player) => player,
+            None => return,
+        };
+
+        show_line(
+            0,
+            &format!("Process {} ({})", process.pid, player.name),
+            Style::default().fg(self.3[&player.id]),
+        );
+
+        let offset = match player.code_offset(process.pc.addr(), vm.config.mem_size) {
+            Some(offset) => offset,
+            None => {
+                let text = format!("Outside of its code at 0x{:04x}", process.pc.addr());
+                return show_line(1, &text, Style::default());
+            }
+        };
+        let view = match self.1.get(&player.id) {
+            Some(view) => view,
+            None => {
+                let text = format!("No debug info, at code offset {}", offset);
+                return show_line(1, &text, Style::default());
+            }
+        };
+        let line = match view.debug_info.locate(offset) {
+            Some(mapping) => mapping.line,
+            None => return show_line(1, "Not generated by any line", Style::default()),
+        };
+
+        // Keep the executed line in the middle of the pane
+        let context = usize::from(area.height.saturating_sub(1)) / 2;
+        let first_line = line.saturating_sub(context).max(1);
+        let visible_lines = (first_line..=view.lines.len()).take(context * 2 + 1);
+        for (line_offset, line_no) in visible_lines.enumerate() {
+            let text = format!("{:4} {}", line_no, view.lines[line_no - 1]);
+            let style = if line_no == line {
+                Style::default().bg(Color::DarkGray)
+            } else {
+                Style::default()
+            };
+            show_line(line_offset as u16 + 1, &text, style);
+        }
     }
 }
 
@@ -253,4 +833,67 @@ struct Options {
     champion_files: Vec<String>,
     #[structopt(short = "c", default_value = "▮")]
     chr: char,
+    /// Records the match to a replay file
+    #[structopt(long, parse(from_os_str))]
+    record: Option<PathBuf>,
+    /// Plays back a recorded match instead of loading champions
+    #[structopt(long, parse(from_os_str))]
+    replay: Option<PathBuf>,
+    /// Pauses when a process is about to read the instruction at this address
+    #[structopt(long = "break-at")]
+    break_at: Vec<usize>,
+    /// Pauses when memory in this range is written, e.g. `100..120`
+    #[structopt(long, parse(try_from_str = parse_range))]
+    watch: Vec<Range<usize>>,
+    #[structopt(flatten)]
+    arena: ArenaOptions,
+}
+
+/// Overrides for the standard arena parameters
+#[derive(Debug, StructOpt)]
+struct ArenaOptions {
+    #[structopt(long)]
+    mem_size: Option<usize>,
+    #[structopt(long)]
+    idx_mod: Option<usize>,
+    #[structopt(long)]
+    champ_max_size: Option<usize>,
+    #[structopt(long)]
+    check_interval: Option<u32>,
+    #[structopt(long)]
+    cycle_delta: Option<u32>,
+    #[structopt(long)]
+    nbr_live: Option<u32>,
+    #[structopt(long)]
+    max_checks: Option<u32>,
+    #[structopt(long)]
+    reg_count: Option<usize>,
+}
+
+impl ArenaOptions {
+    fn config(&self) -> VmConfig {
+        let default = VmConfig::default();
+
+        VmConfig {
+            mem_size: self.mem_size.unwrap_or(default.mem_size),
+            idx_mod: self.idx_mod.unwrap_or(default.idx_mod),
+            champ_max_size: self.champ_max_size.unwrap_or(default.champ_max_size),
+            max_players: default.max_players,
+
+            check_interval: self.check_interval.unwrap_or(default.check_interval),
+            cycle_delta: self.cycle_delta.unwrap_or(default.cycle_delta),
+            nbr_live: self.nbr_live.unwrap_or(default.nbr_live),
+            max_checks: self.max_checks.unwrap_or(default.max_checks),
+
+            reg_count: self.reg_count.unwrap_or(default.reg_count),
+        }
+    }
+}
+
+fn parse_range(range: &str) -> Result<Range<usize>, String> {
+    let invalid = || format!("Invalid range '{}', expected e.g. '100..120'", range);
+    let (start, end) = range.split_once("..").ok_or_else(invalid)?;
+    let start = start.trim().parse().map_err(|_| invalid())?;
+    let end = end.trim().parse().map_err(|_| invalid())?;
+    Ok(start..end)
 }
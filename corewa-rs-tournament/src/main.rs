@@ -0,0 +1,225 @@
+use corewa_rs::tournament::{
+    play, ranking::Standing, Contender, Tournament, TournamentConfig, TournamentError,
+};
+use serde_json::json;
+use std::{fs, io, path::PathBuf, str::FromStr, thread};
+use structopt::StructOpt;
+
+fn main() {
+    let opts = Options::from_args();
+
+    let exit_code = match run(&opts) {
+        Ok(_) => 0,
+        Err(err) => {
+            eprintln!("{}", err);
+            1
+        }
+    };
+
+    std::process::exit(exit_code)
+}
+
+fn run(opts: &Options) -> Result<(), Error> {
+    let contenders = read_contenders(&opts.directory)?;
+
+    let config = TournamentConfig {
+        max_players: opts.players,
+        cycle_limit: opts.cycles,
+        threads: opts
+            .threads
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, |threads| threads.get())),
+        ..TournamentConfig::default()
+    };
+
+    let tournament = play(&contenders, &config)?;
+
+    match opts.format {
+        Format::Table => print_table(&contenders, &tournament),
+        Format::Csv => print_csv(&contenders, &tournament),
+        Format::Json => println!("{:#}", json_report(&contenders, &tournament)),
+    }
+
+    Ok(())
+}
+
+/// Every `.cor` file of the directory, sorted by name
+fn read_contenders(directory: &PathBuf) -> Result<Vec<Contender>, Error> {
+    let read_error = |err| Error::Read(directory.clone(), err);
+
+    let mut paths = fs::read_dir(directory)
+        .map_err(read_error)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(read_error)?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "cor"));
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let champion = fs::read(&path).map_err(|err| Error::Read(path.clone(), err))?;
+            let name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            Ok(Contender { name, champion })
+        })
+        .collect()
+}
+
+fn print_table(contenders: &[Contender], tournament: &Tournament) {
+    let name_width = contenders
+        .iter()
+        .map(|contender| contender.name.len())
+        .max()
+        .unwrap_or(0)
+        .max(4);
+
+    println!(
+        "{:>4}  {:<width$}  {:>6}  {:>6}  {:>6}  {:>6}  {:>7}",
+        "Rank",
+        "Name",
+        "Played",
+        "Wins",
+        "Losses",
+        "Ties",
+        "Rating",
+        width = name_width
+    );
+
+    for (rank, standing) in tournament.standings.iter().enumerate() {
+        println!(
+            "{:>4}  {:<width$}  {:>6}  {:>6}  {:>6}  {:>6}  {:>7.1}",
+            rank + 1,
+            contenders[standing.contender].name,
+            standing.played,
+            standing.wins,
+            standing.losses,
+            standing.ties,
+            standing.rating,
+            width = name_width
+        );
+    }
+}
+
+fn print_csv(contenders: &[Contender], tournament: &Tournament) {
+    println!("rank,name,played,wins,losses,ties,rating");
+
+    for (rank, standing) in tournament.standings.iter().enumerate() {
+        println!(
+            "{},{},{},{},{},{},{:.1}",
+            rank + 1,
+            csv_field(&contenders[standing.contender].name),
+            standing.played,
+            standing.wins,
+            standing.losses,
+            standing.ties,
+            standing.rating
+        );
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        String::from(field)
+    }
+}
+
+fn json_report(contenders: &[Contender], tournament: &Tournament) -> serde_json::Value {
+    let standing_json = |rank: usize, standing: &Standing| {
+        json!({
+            "rank": rank + 1,
+            "name": contenders[standing.contender].name,
+            "played": standing.played,
+            "wins": standing.wins,
+            "losses": standing.losses,
+            "ties": standing.ties,
+            "rating": standing.rating,
+        })
+    };
+
+    let matches = tournament
+        .matches
+        .iter()
+        .map(|result| {
+            let lineup = result
+                .lineup
+                .iter()
+                .map(|&contender| contenders[contender].name.as_str())
+                .collect::<Vec<_>>();
+            let winner = result
+                .outcome
+                .winner
+                .map(|player_id| lineup[player_id as usize - 1]);
+
+            json!({
+                "lineup": lineup,
+                "winner": winner,
+                "final_cycle": result.outcome.final_cycle,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "standings": tournament
+            .standings
+            .iter()
+            .enumerate()
+            .map(|(rank, standing)| standing_json(rank, standing))
+            .collect::<Vec<_>>(),
+        "matches": matches,
+    })
+}
+
+/// Plays a round robin tournament between the champions of a directory and
+/// ranks them
+#[derive(Debug, StructOpt)]
+struct Options {
+    /// A directory containing compiled champions (.cor)
+    #[structopt(parse(from_os_str))]
+    directory: PathBuf,
+    /// The maximum number of players per match (2 to 4).
+    /// Every lineup from duels up to that size is played
+    #[structopt(short = "p", long, default_value = "2")]
+    players: usize,
+    /// Stops matches after this many cycles
+    #[structopt(short = "c", long)]
+    cycles: Option<u32>,
+    /// The number of matches played in parallel, defaults to the number of
+    /// available cores
+    #[structopt(short = "j", long)]
+    threads: Option<usize>,
+    /// The output format: table, csv or json
+    #[structopt(short = "f", long, default_value = "table")]
+    format: Format,
+}
+
+#[derive(Debug)]
+enum Format {
+    Table,
+    Csv,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(Format::Table),
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("Unknown format: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum Error {
+    #[error("Failed to read {}: {1}", .0.display())]
+    Read(PathBuf, io::Error),
+    #[error(transparent)]
+    Tournament(#[from] TournamentError),
+}
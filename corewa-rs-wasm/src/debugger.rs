@@ -0,0 +1,102 @@
+use corewa_rs::vm::{
+    debugger::{Cause, Hit, Stop},
+    types::*,
+};
+
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct HitInfo(Hit);
+
+/// Why running stopped, along with the breakpoints hit if any
+#[wasm_bindgen]
+pub struct StopInfo(Stop);
+
+#[wasm_bindgen]
+impl HitInfo {
+    pub fn breakpoint(&self) -> usize {
+        self.0.breakpoint
+    }
+
+    pub fn cycle(&self) -> u32 {
+        self.0.cycle
+    }
+
+    pub fn pid(&self) -> Option<Pid> {
+        use Cause::*;
+
+        match self.0.cause {
+            Reached { pid, .. }
+            | Executed { pid, .. }
+            | Written { pid, .. }
+            | Register { pid, .. } => Some(pid),
+            LiveCheck { .. } => None,
+        }
+    }
+
+    pub fn player_id(&self) -> Option<PlayerId> {
+        use Cause::*;
+
+        match self.0.cause {
+            Reached { player_id, .. } | Executed { player_id, .. } | Register { player_id, .. } => {
+                Some(player_id)
+            }
+            Written { owner, .. } => Some(owner),
+            LiveCheck { .. } => None,
+        }
+    }
+
+    /// The address reached, executed or written
+    pub fn address(&self) -> Option<usize> {
+        use Cause::*;
+
+        match self.0.cause {
+            Reached { pc, .. } | Executed { pc, .. } => Some(pc),
+            Written { at, .. } => Some(at),
+            Register { .. } | LiveCheck { .. } => None,
+        }
+    }
+
+    #[allow(clippy::inherent_to_string)]
+    pub fn to_string(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+#[wasm_bindgen]
+impl StopInfo {
+    pub fn reason(&self) -> String {
+        let reason = match self.0 {
+            Stop::Break(_) => "break",
+            Stop::LiveCheck => "live_check",
+            Stop::CycleLimit => "cycle_limit",
+            Stop::MatchOver => "match_over",
+        };
+
+        reason.to_owned()
+    }
+
+    pub fn hit_count(&self) -> usize {
+        self.hits().len()
+    }
+
+    pub fn hit(&self, idx: usize) -> HitInfo {
+        HitInfo(self.hits()[idx].clone())
+    }
+}
+
+impl StopInfo {
+    fn hits(&self) -> &[Hit] {
+        match &self.0 {
+            Stop::Break(hits) => hits,
+            _ => &[],
+        }
+    }
+}
+
+impl From<Stop> for StopInfo {
+    fn from(stop: Stop) -> Self {
+        Self(stop)
+    }
+}
@@ -33,15 +33,19 @@ impl DecodeResult {
 }
 
 impl DecodeResult {
-    pub fn read(memory: &Memory<MEM_SIZE>, idx: usize) -> Self {
-        DecodeResult(DecodeResult::read_result(memory, idx))
+    pub fn read(memory: &Memory, idx: usize, reg_count: usize) -> Self {
+        DecodeResult(DecodeResult::read_result(memory, idx, reg_count))
     }
 
-    fn read_result(memory: &Memory<MEM_SIZE>, idx: usize) -> Result<Instruction, DecodeError> {
+    fn read_result(
+        memory: &Memory,
+        idx: usize,
+        reg_count: usize,
+    ) -> Result<Instruction, DecodeError> {
         let op = memory.decode_op(idx).map_err(DecodeError::InvalidOp)?;
 
         memory
-            .decode_instr(op, idx)
+            .decode_instr(op, idx, reg_count)
             .map_err(|err| DecodeError::OpOnly(op, err))
     }
 }
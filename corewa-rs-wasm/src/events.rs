@@ -0,0 +1,108 @@
+use corewa_rs::vm::{
+    events::{Event, EventKind},
+    types::*,
+};
+
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct EventInfo(Event);
+
+#[wasm_bindgen]
+pub struct EventCollection {
+    events: Vec<EventInfo>,
+}
+
+#[wasm_bindgen]
+impl EventInfo {
+    pub fn cycle(&self) -> u32 {
+        self.0.cycle
+    }
+
+    pub fn kind(&self) -> String {
+        use EventKind::*;
+
+        let kind = match self.0.kind {
+            InstructionExecuted { .. } => "instruction_executed",
+            MemoryWritten { .. } => "memory_written",
+            ProcessForked { .. } => "process_forked",
+            ProcessKilled { .. } => "process_killed",
+            LiveReported { .. } => "live_reported",
+            LiveCheck { .. } => "live_check",
+            Aff { .. } => "aff",
+        };
+
+        kind.to_owned()
+    }
+
+    /// The process at the origin of the event
+    pub fn pid(&self) -> Option<Pid> {
+        use EventKind::*;
+
+        match self.0.kind {
+            InstructionExecuted { pid, .. }
+            | MemoryWritten { pid, .. }
+            | ProcessKilled { pid, .. }
+            | LiveReported { pid, .. }
+            | Aff { pid, .. } => Some(pid),
+            ProcessForked { parent, .. } => Some(parent),
+            LiveCheck { .. } => None,
+        }
+    }
+
+    /// The player concerned by the event: either the owner of the process or
+    /// the player reported alive for `live` reports
+    pub fn player_id(&self) -> Option<PlayerId> {
+        use EventKind::*;
+
+        match self.0.kind {
+            InstructionExecuted { player_id, .. }
+            | ProcessForked { player_id, .. }
+            | ProcessKilled { player_id, .. }
+            | LiveReported { player_id, .. }
+            | Aff { player_id, .. } => Some(player_id),
+            MemoryWritten { owner, .. } => Some(owner),
+            LiveCheck { .. } => None,
+        }
+    }
+
+    /// The memory location concerned by the event
+    pub fn address(&self) -> Option<usize> {
+        use EventKind::*;
+
+        match self.0.kind {
+            InstructionExecuted { pc, .. } | ProcessForked { pc, .. } => Some(pc),
+            MemoryWritten { at, .. } => Some(at),
+            _ => None,
+        }
+    }
+
+    #[allow(clippy::inherent_to_string)]
+    pub fn to_string(&self) -> String {
+        self.0.kind.to_string()
+    }
+}
+
+#[wasm_bindgen]
+impl EventCollection {
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn at(&self, idx: usize) -> EventInfo {
+        self.events[idx].clone()
+    }
+}
+
+impl<T: Iterator<Item = Event>> From<T> for EventCollection {
+    fn from(events: T) -> Self {
+        let events = events.map(EventInfo).collect();
+
+        Self { events }
+    }
+}
@@ -1,21 +1,122 @@
-use corewa_rs::language;
+use corewa_rs::{
+    language::{
+        self, compiler,
+        debug_info::DebugInfo,
+        diagnostics::{self, Diagnostic, DiagnosticError},
+        loader::MemoryLoader,
+        span::Span,
+    },
+    spec::VmConfig,
+};
 
 use wasm_bindgen::prelude::*;
 
+/// Compiles a champion, failing with every error found in its source
 #[wasm_bindgen]
-pub fn compile_champion(input: &str) -> Result<Vec<u8>, JsValue> {
+pub fn compile_champion(input: &str) -> Result<Vec<u8>, Vec<CompileError>> {
     super::utils::set_panic_hook();
 
-    compile_champion_impl(input).map_err(JsValue::from)
+    compile_champion_impl(input, &MemoryLoader::default())
 }
 
-fn compile_champion_impl(input: &str) -> Result<Vec<u8>, CompileError> {
-    let parsed_champion = language::read_champion(input.as_bytes())?;
+/// Same as `compile_champion` but allows including any of `files`
+#[wasm_bindgen]
+pub fn compile_champion_with_files(
+    input: &str,
+    files: &SourceFiles,
+) -> Result<Vec<u8>, Vec<CompileError>> {
+    super::utils::set_panic_hook();
+
+    compile_champion_impl(input, &files.0)
+}
 
-    let mut byte_code = Vec::new();
-    language::write_champion(&mut byte_code, parsed_champion)?;
+fn compile_champion_impl(input: &str, loader: &MemoryLoader) -> Result<Vec<u8>, Vec<CompileError>> {
+    diagnostics::compile_with_diagnostics(input, None, loader, &VmConfig::default())
+        .map(|compiled| compiled.byte_code)
+        .map_err(|diagnostics| diagnostics.into_iter().map(CompileError::from).collect())
+}
+
+/// The suspicious parts of a champion's code. Champions that do not compile
+/// have no warnings
+#[wasm_bindgen]
+pub fn champion_warnings(input: &str) -> Vec<CompileWarning> {
+    super::utils::set_panic_hook();
 
-    Ok(byte_code)
+    let loader = MemoryLoader::default();
+    diagnostics::compile_with_diagnostics(input, None, &loader, &VmConfig::default())
+        .map(|compiled| {
+            compiled
+                .warnings
+                .into_iter()
+                .map(|warning| CompileWarning {
+                    line: warning.line as u32,
+                    reason: warning.kind.to_string(),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Compiles a champion and describes which source line generated each part
+/// of its code
+#[wasm_bindgen]
+pub fn champion_source_map(input: &str) -> Result<SourceMap, JsValue> {
+    super::utils::set_panic_hook();
+
+    source_map_impl(input).map(SourceMap).map_err(JsValue::from)
+}
+
+fn source_map_impl(input: &str) -> Result<DebugInfo, CompileError> {
+    let parsed_champion =
+        language::read_champion_with_loader(input.as_bytes(), None, &MemoryLoader::default())?;
+
+    let (_, listing) = language::write_champion_with_listing(
+        std::io::sink(),
+        parsed_champion,
+        &VmConfig::default(),
+    )?;
+
+    Ok(DebugInfo::new(&listing, input, None))
+}
+
+/// The files champions can include, by path
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct SourceFiles(MemoryLoader);
+
+#[wasm_bindgen]
+impl SourceFiles {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, path: &str, source: &str) {
+        self.0.insert(path, source)
+    }
+}
+
+#[wasm_bindgen]
+pub struct SourceMap(DebugInfo);
+
+#[wasm_bindgen]
+impl SourceMap {
+    /// Reads a debug info sidecar produced by the assembler
+    pub fn from_sidecar(bytes: &[u8]) -> Result<SourceMap, JsValue> {
+        DebugInfo::read(bytes)
+            .map(SourceMap)
+            .map_err(|e| JsValue::from(e.to_string()))
+    }
+
+    /// The line (starting at 1) that generated the code at `offset`
+    pub fn line_at(&self, offset: usize) -> Option<usize> {
+        self.0.locate(offset).map(|mapping| mapping.line)
+    }
+
+    /// The column (starting at 0) of the operation at `offset` in its line
+    pub fn column_at(&self, offset: usize) -> Option<usize> {
+        self.0.locate(offset).map(|mapping| mapping.column)
+    }
 }
 
 #[wasm_bindgen]
@@ -58,24 +159,52 @@ impl CompileError {
     }
 }
 
+#[wasm_bindgen]
+pub struct CompileWarning {
+    /// Starting at 1
+    pub line: u32,
+    reason: String,
+}
+
+#[wasm_bindgen]
+impl CompileWarning {
+    pub fn reason(&self) -> String {
+        self.reason.clone()
+    }
+}
+
+/// The region of a span, None for spans in included files which are not in the editor
+fn region(span: Option<Span>) -> Option<Region> {
+    span.filter(|span| span.file.is_none()).map(|span| {
+        let line = span.line as u32;
+        Region::new(
+            line,
+            span.columns.start as u32,
+            line,
+            span.columns.end as u32,
+        )
+    })
+}
+
 impl From<language::ReadError> for CompileError {
     fn from(err: language::ReadError) -> CompileError {
-        let (region, reason) = match err {
-            language::ReadError::ParseError(e, line) => {
-                let line = line as u32;
-                let (col_start, opt_col_end) = language::error_range(&e);
-                let region = Region::new(
-                    line,
-                    col_start as u32,
-                    line,
-                    opt_col_end.unwrap_or(20000) as u32,
-                );
-                (Some(region), format!("{}", e))
+        let region = region(err.span());
+        let reason = match err {
+            language::ReadError::ParseError(e, span) if span.file.is_some() => {
+                format!("{} on {}", e, span)
+            }
+            language::ReadError::ParseError(e, _) => format!("{}", e),
+            // The expanded text is not in the editor, so the whole call site is highlighted
+            language::ReadError::ExpandedParseError(e, _, expansion) => {
+                format!("{} {}", e, expansion)
             }
+            // Errors in included files can only be described
+            language::ReadError::PreprocessError(e) if e.span.file.is_some() => format!("{}", e),
+            language::ReadError::PreprocessError(e) => format!("{}", e.kind),
             language::ReadError::AssembleError(e) => {
-                (None, format!("Error while assembling champion: {}", e))
+                format!("Error while assembling champion: {}", e)
             }
-            language::ReadError::IOError(e) => (None, format!("Unexpected IO error: {}", e)),
+            language::ReadError::IOError(e) => format!("Unexpected IO error: {}", e),
         };
 
         CompileError { region, reason }
@@ -84,17 +213,36 @@ impl From<language::ReadError> for CompileError {
 
 impl From<language::WriteError> for CompileError {
     fn from(err: language::WriteError) -> CompileError {
+        let region = region(err.span());
         let reason = match err {
+            language::WriteError::CompileError(compiler::CompileError::ExprError(e))
+                if e.span.file.is_none() =>
+            {
+                format!("{}", e.kind)
+            }
             language::WriteError::CompileError(e) => {
                 format!("Error while compiling champion: {}", e)
             }
-            language::WriteError::IOError(e) => {
-                format!("Unexpected IO error: {}", e)
+            language::WriteError::IOError(e) => format!("Unexpected IO error: {}", e),
+        };
+
+        CompileError { region, reason }
+    }
+}
+
+impl From<Diagnostic> for CompileError {
+    fn from(diagnostic: Diagnostic) -> CompileError {
+        let Diagnostic { error, span } = diagnostic;
+
+        let reason = match error {
+            DiagnosticError::Read(e) => CompileError::from(e).reason,
+            DiagnosticError::Compile(e) => {
+                CompileError::from(language::WriteError::CompileError(e)).reason
             }
         };
 
         CompileError {
-            region: None,
+            region: region(span),
             reason,
         }
     }
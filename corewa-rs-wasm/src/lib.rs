@@ -1,7 +1,10 @@
 pub mod champion;
+pub mod debugger;
 pub mod decoder;
+pub mod events;
 pub mod language;
 pub mod memory;
+pub mod outcome;
 pub mod player;
 pub mod process;
 pub mod vm;
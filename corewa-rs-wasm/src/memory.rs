@@ -4,6 +4,7 @@ use corewa_rs::vm::types::PlayerId;
 
 #[wasm_bindgen]
 pub struct Memory {
+    pub size: usize,
     pub values_ptr: *const u8,
     pub ages_ptr: *const u16,
     pub owners_ptr: *const PlayerId,
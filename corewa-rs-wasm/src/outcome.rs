@@ -0,0 +1,38 @@
+use corewa_rs::vm::{
+    outcome::{MatchOutcome, Termination, TieBreak},
+    types::PlayerId,
+};
+
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub struct MatchResult(MatchOutcome);
+
+impl From<MatchOutcome> for MatchResult {
+    fn from(outcome: MatchOutcome) -> Self {
+        Self(outcome)
+    }
+}
+
+#[wasm_bindgen]
+impl MatchResult {
+    pub fn winner(&self) -> Option<PlayerId> {
+        self.0.winner
+    }
+
+    pub fn final_cycle(&self) -> u32 {
+        self.0.final_cycle
+    }
+
+    pub fn decided_by_load_order(&self) -> bool {
+        self.0.tie_break == TieBreak::LoadOrder
+    }
+
+    pub fn reached_cycle_limit(&self) -> bool {
+        self.0.termination == Termination::CycleLimit
+    }
+
+    pub fn last_live(&self, player_id: PlayerId) -> Option<u32> {
+        self.0.last_live(player_id)
+    }
+}
@@ -38,7 +38,7 @@ impl ProcessInfo {
             pid: process.pid,
             player_id: process.player_id,
             pc: process.pc.addr(),
-            registers: process.registers,
+            registers: process.registers.clone(),
             zf: process.zf,
             last_live_cycle: process.last_live_cycle,
             state: process.state.clone(),
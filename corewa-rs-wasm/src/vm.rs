@@ -1,52 +1,268 @@
-use corewa_rs::vm::{types::*, VirtualMachine as VMImpl};
+use corewa_rs::vm::{
+    debugger::{Breakpoint, BreakpointId, Comparison, Debugger, Hit, Stop},
+    decoder::op_from_code,
+    replay::Replay,
+    types::*,
+    VirtualMachine as VMImpl,
+};
 
 use super::{
-    champion::ChampionInfo, decoder::DecodeResult, memory::Memory, player::PlayerInfo,
-    process::ProcessCollection,
+    champion::ChampionInfo, debugger::StopInfo, decoder::DecodeResult, events::EventCollection,
+    memory::Memory, outcome::MatchResult, player::PlayerInfo, process::ProcessCollection,
 };
 
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
-pub struct VirtualMachine(VMImpl);
+pub struct VirtualMachine {
+    engine: Engine,
+    debugger: Debugger,
+}
+
+/// Either a match being played or the playback of a recorded one
+enum Engine {
+    Live(VMImpl),
+    Playback(Replay),
+}
+
+impl VirtualMachine {
+    fn new(engine: Engine) -> Self {
+        Self {
+            engine,
+            debugger: Debugger::new(),
+        }
+    }
+
+    fn vm(&self) -> &VMImpl {
+        match &self.engine {
+            Engine::Live(vm) => vm,
+            Engine::Playback(replay) => replay.vm(),
+        }
+    }
+
+    fn is_over(&self) -> bool {
+        match &self.engine {
+            Engine::Live(vm) => vm.is_over(),
+            Engine::Playback(replay) => {
+                replay.vm().is_over() || replay.vm().cycles >= replay.final_cycle()
+            }
+        }
+    }
+
+    /// Runs a single cycle under the debugger
+    fn debug_step(&mut self) -> Result<Vec<Hit>, JsValue> {
+        match &mut self.engine {
+            Engine::Live(vm) => Ok(self.debugger.step(vm)),
+            Engine::Playback(replay) => {
+                // Executions and writes are only seen through the events
+                if self.debugger.needs_events() {
+                    replay.enable_events();
+                }
+                let next_cycle = replay.vm().cycles + 1;
+                self.debugger.before_tick(replay.vm());
+                replay
+                    .seek(next_cycle)
+                    .map_err(|err| JsValue::from(err.to_string()))?;
+                Ok(self.debugger.after_tick(replay.vm()))
+            }
+        }
+    }
+
+    fn debug_run(&mut self, cycle_limit: u32, until_live_check: bool) -> Result<StopInfo, JsValue> {
+        loop {
+            if self.is_over() {
+                return Ok(StopInfo::from(Stop::MatchOver));
+            }
+            if self.vm().cycles >= cycle_limit {
+                return Ok(StopInfo::from(Stop::CycleLimit));
+            }
+
+            let hits = self.debug_step()?;
+            if !hits.is_empty() {
+                return Ok(StopInfo::from(Stop::Break(hits)));
+            }
+            if until_live_check && self.vm().last_live_check == self.vm().cycles {
+                return Ok(StopInfo::from(Stop::LiveCheck));
+            }
+        }
+    }
+}
 
 #[wasm_bindgen]
 impl VirtualMachine {
+    pub fn from_replay(replay: &[u8]) -> Result<VirtualMachine, JsValue> {
+        let replay = Replay::read(replay).map_err(|err| JsValue::from(err.to_string()))?;
+        Ok(VirtualMachine::new(Engine::Playback(replay)))
+    }
+
+    /// The number of cycles of a replay, `None` for a live match
+    pub fn replay_length(&self) -> Option<u32> {
+        match &self.engine {
+            Engine::Live(_) => None,
+            Engine::Playback(replay) => Some(replay.final_cycle()),
+        }
+    }
+
+    pub fn seek(&mut self, cycle: u32) -> Result<(), JsValue> {
+        match &mut self.engine {
+            Engine::Live(_) => Err(JsValue::from("Only replays can be sought")),
+            Engine::Playback(replay) => replay
+                .seek(cycle)
+                .map(drop)
+                .map_err(|err| JsValue::from(err.to_string())),
+        }
+    }
+
     pub fn cycles(&self) -> u32 {
-        self.0.cycles
+        self.vm().cycles
     }
 
     pub fn last_live_check(&self) -> u32 {
-        self.0.last_live_check
+        self.vm().last_live_check
     }
 
     pub fn check_interval(&self) -> u32 {
-        self.0.check_interval
+        self.vm().check_interval
     }
 
     pub fn live_count_since_last_check(&self) -> u32 {
-        self.0.live_count_since_last_check
+        self.vm().live_count_since_last_check
     }
 
     pub fn checks_without_cycle_decrement(&self) -> u32 {
-        self.0.checks_without_cycle_decrement
+        self.vm().checks_without_cycle_decrement
     }
 
-    pub fn tick(&mut self) -> bool {
-        self.0.tick();
-        self.0.processes.is_empty()
+    /// Returns whether the match is over
+    pub fn tick(&mut self) -> Result<bool, JsValue> {
+        match &mut self.engine {
+            Engine::Live(vm) => {
+                vm.tick();
+                Ok(vm.is_over())
+            }
+            Engine::Playback(replay) => {
+                let next_cycle = replay.vm().cycles + 1;
+                replay
+                    .seek(next_cycle)
+                    .map_err(|err| JsValue::from(err.to_string()))?;
+                Ok(replay.vm().is_over() || replay.vm().cycles >= replay.final_cycle())
+            }
+        }
+    }
+
+    pub fn enable_events(&mut self) {
+        match &mut self.engine {
+            Engine::Live(vm) => vm.enable_events(),
+            Engine::Playback(replay) => replay.enable_events(),
+        }
+    }
+
+    pub fn drain_events(&mut self) -> EventCollection {
+        match &mut self.engine {
+            Engine::Live(vm) => EventCollection::from(vm.drain_events()),
+            Engine::Playback(replay) => EventCollection::from(replay.drain_events()),
+        }
+    }
+
+    /// Breaks when a process is about to read the instruction at `addr`
+    pub fn break_at(&mut self, addr: usize) -> BreakpointId {
+        self.debugger.add(Breakpoint::Address(addr))
+    }
+
+    /// Breaks when an instruction with this op code is executed
+    pub fn break_on_op(&mut self, op_code: u8) -> Result<BreakpointId, JsValue> {
+        let op = op_from_code(op_code)
+            .ok_or_else(|| JsValue::from(format!("Invalid op code: {}", op_code)))?;
+        Ok(self.debugger.add(Breakpoint::OpCode(op)))
+    }
+
+    pub fn break_on_pid(&mut self, pid: Pid) -> BreakpointId {
+        self.debugger.add(Breakpoint::Pid(pid))
+    }
+
+    pub fn break_on_player(&mut self, player_id: PlayerId) -> BreakpointId {
+        self.debugger.add(Breakpoint::Player(player_id))
+    }
+
+    /// Breaks when memory in `start..end` is written, by a process of `by`
+    /// if any
+    pub fn watch(&mut self, start: usize, end: usize, by: Option<PlayerId>) -> BreakpointId {
+        self.debugger.add(Breakpoint::Watch {
+            range: start..end,
+            by,
+        })
+    }
+
+    /// Breaks when a register starts meeting a condition, `comparison` being
+    /// one of `==`, `!=`, `<` or `>`
+    pub fn break_on_register(
+        &mut self,
+        register: usize,
+        comparison: &str,
+        value: i32,
+        pid: Option<Pid>,
+    ) -> Result<BreakpointId, JsValue> {
+        let comparison = match comparison {
+            "==" => Comparison::Equal,
+            "!=" => Comparison::NotEqual,
+            "<" => Comparison::Less,
+            ">" => Comparison::Greater,
+            _ => {
+                let error = format!("Invalid comparison: '{}'", comparison);
+                return Err(JsValue::from(error));
+            }
+        };
+
+        Ok(self.debugger.add(Breakpoint::Register {
+            pid,
+            register,
+            comparison,
+            value,
+        }))
+    }
+
+    pub fn break_on_live_check(&mut self) -> BreakpointId {
+        self.debugger.add(Breakpoint::LiveCheck)
+    }
+
+    /// Returns whether the breakpoint existed
+    pub fn remove_breakpoint(&mut self, id: BreakpointId) -> bool {
+        self.debugger.remove(id).is_some()
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.debugger.clear()
+    }
+
+    /// Runs the match until a breakpoint is hit, until it is over or until
+    /// the cycle count reaches `cycle_limit`
+    pub fn run(&mut self, cycle_limit: u32) -> Result<StopInfo, JsValue> {
+        self.debug_run(cycle_limit, false)
+    }
+
+    /// Like `run`, but also stops after the next live check
+    pub fn run_until_live_check(&mut self, cycle_limit: u32) -> Result<StopInfo, JsValue> {
+        self.debug_run(cycle_limit, true)
+    }
+
+    pub fn outcome(&self) -> Option<MatchResult> {
+        self.vm().outcome().map(MatchResult::from)
     }
 
     pub fn process_count(&self) -> usize {
-        self.0.processes.len()
+        self.vm().processes.len()
     }
 
     pub fn player_count(&self) -> usize {
-        self.0.players.len()
+        self.vm().players.len()
+    }
+
+    pub fn player_ids(&self) -> Vec<PlayerId> {
+        self.vm().players.iter().map(|player| player.id).collect()
     }
 
     pub fn player_info(&self, player_id: PlayerId) -> JsValue {
-        self.0
+        self.vm()
             .players
             .iter()
             .find(|p| p.id == player_id)
@@ -55,35 +271,60 @@ impl VirtualMachine {
             .unwrap_or(JsValue::NULL)
     }
 
+    pub fn player_output(&self, player_id: PlayerId) -> String {
+        self.vm()
+            .outputs
+            .get(&player_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
     pub fn champion_info(&self, player_id: PlayerId) -> ChampionInfo {
         ChampionInfo {
             process_count: *self
-                .0
+                .vm()
                 .process_count_by_player_id
                 .get(&player_id)
                 .unwrap_or(&0),
-            last_live: *self.0.last_lives.get(&player_id).unwrap_or(&0),
+            last_live: *self.vm().last_lives.get(&player_id).unwrap_or(&0),
         }
     }
 
     pub fn processes_at(&self, idx: usize) -> ProcessCollection {
-        let cell_processes = self.0.processes.iter().filter(|p| p.pc.addr() == idx);
+        let cell_processes = self.vm().processes.iter().filter(|p| p.pc.addr() == idx);
 
         ProcessCollection::from(cell_processes)
     }
 
+    /// The offsets in its champion's code executed by the player's processes.
+    /// Processes running outside of their champion's code are left out
+    pub fn process_code_offsets(&self, player_id: PlayerId) -> Vec<usize> {
+        let vm = self.vm();
+        let player = match vm.players.iter().find(|p| p.id == player_id) {
+            Some(player) => player,
+            None => return Vec::new(),
+        };
+
+        vm.processes
+            .iter()
+            .filter(|p| p.player_id == player_id)
+            .filter_map(|p| player.code_offset(p.pc.addr(), vm.config.mem_size))
+            .collect()
+    }
+
     pub fn decode(&self, idx: usize) -> DecodeResult {
-        DecodeResult::read(&self.0.memory, idx)
+        DecodeResult::read(&self.vm().memory, idx, self.vm().config.reg_count)
     }
 
     pub fn memory(&self) -> Memory {
-        let mem = &self.0.memory;
+        let mem = &self.vm().memory;
 
         Memory {
+            size: mem.size(),
             values_ptr: mem.values.as_ptr(),
             ages_ptr: mem.ages.as_ptr(),
             owners_ptr: mem.owners.as_ptr(),
-            pc_count_ptr: self.0.process_count_per_cells.as_ptr(),
+            pc_count_ptr: self.vm().process_count_per_cells.as_ptr(),
         }
     }
 }
@@ -108,9 +349,10 @@ impl VMBuilder {
         self
     }
 
-    pub fn finish(self) -> VirtualMachine {
+    pub fn finish(self) -> Result<VirtualMachine, JsValue> {
         let mut vm = VMImpl::new();
-        vm.load_players(&self.players);
-        VirtualMachine(vm)
+        vm.load_players(&self.players)
+            .map_err(|err| JsValue::from(err.to_string()))?;
+        Ok(VirtualMachine::new(Engine::Live(vm)))
     }
 }
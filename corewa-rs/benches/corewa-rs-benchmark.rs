@@ -4,18 +4,14 @@ use corewa_rs::vm::VirtualMachine;
 
 fn fight_cycles(players: &[(i32, Vec<u8>)]) -> u32 {
     let mut vm = VirtualMachine::new();
-    vm.load_players(players);
+    vm.load_players(players).expect("Failed to load players");
 
-    while !vm.processes.is_empty() {
-        vm.tick();
-    }
-
-    vm.cycles
+    vm.run_to_completion().final_cycle
 }
 
 fn fast_fights(c: &mut Criterion) {
     c.bench_function("zork alone", |b| {
-        b.iter(|| fight_cycles(&[(1, include_bytes!("../../champs/examples/zork.cor").to_vec())]))
+        b.iter(|| fight_cycles(&[(1, include_bytes!("../tests/vm/samples/zork.cor").to_vec())]))
     });
 }
 
@@ -0,0 +1,578 @@
+//! Static control flow analysis of compiled champions.
+//!
+//! The code is decoded from its start by following what the VM executes:
+//! fall-through, `zjmp` jumps (taken when `zf` is set) and the processes
+//! spawned by `fork` and `lfork`. Bytes that do not decode are skipped one at
+//! a time, like the VM does. The decoded nodes are grouped in basic blocks,
+//! and the loops between blocks are reported with the cycles an iteration
+//! takes.
+//!
+//! Registers are unknown, so `zf` is only known after instructions with
+//! constant results, like `ld %0, r2`: a `zjmp` then always or never jumps.
+//! Likewise, only `st` and `sti` with constant offsets are known to overwrite
+//! the code. They are reported, but the code they write is not decoded.
+
+use super::disassembler::Code;
+use crate::{
+    spec::{op_spec, OpType, ParamType, VmConfig},
+    vm::{
+        decoder::Decode,
+        loader::{parse_champion, ChampionError},
+        types::{Instruction, Param},
+    },
+};
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Write as _},
+    ops::Range,
+};
+
+/// The control flow graph of a champion's code
+#[derive(Debug)]
+pub struct ControlFlow {
+    /// Basic blocks by start offset, only made of reachable code
+    pub blocks: BTreeMap<usize, Block>,
+    pub edges: Vec<Edge>,
+    pub loops: Vec<Loop>,
+    /// Stores overwriting the champion's code
+    pub stores: Vec<Store>,
+    pub code_size: usize,
+}
+
+/// Code executed in sequence: only its first node is entered from elsewhere
+/// and only its last node leads elsewhere
+#[derive(Debug)]
+pub struct Block {
+    pub start: usize,
+    pub nodes: Vec<Node>,
+}
+
+#[derive(Debug)]
+pub struct Node {
+    pub offset: usize,
+    pub kind: NodeKind,
+}
+
+#[derive(Debug)]
+pub enum NodeKind {
+    Instruction(Instruction),
+    /// An op code with invalid parameters, skipped once the op's cycles are
+    /// spent
+    InvalidParameters(OpType),
+    /// Not an op code, skipped right away
+    InvalidOpCode(u8),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edge {
+    /// The start of the block the edge leaves
+    pub from: usize,
+    pub to: Target,
+    pub kind: EdgeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Target {
+    /// The start of a block
+    Block(usize),
+    /// Outside of the champion's code, relative to its start
+    Outside(isize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    FallThrough,
+    /// A `zjmp`, taken when `zf` is set
+    Jump,
+    /// A process started by `fork` or `lfork`
+    Spawn,
+}
+
+/// A cycle of blocks, not counting spawned processes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loop {
+    /// The starts of the blocks in execution order, from the loop's entry
+    pub blocks: Vec<usize>,
+    /// The cycles an iteration takes
+    pub cycles: u32,
+}
+
+/// A `st` or `sti` writing to a known place in memory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Store {
+    /// The offset of the instruction
+    pub from: usize,
+    /// The offset of the 4 bytes written, relative to the champion's start
+    pub target: isize,
+}
+
+impl Block {
+    /// The offset following the block's last node
+    pub fn end(&self) -> usize {
+        self.nodes.last().map_or(self.start, Node::end)
+    }
+
+    pub fn cycles(&self) -> u32 {
+        self.nodes.iter().map(Node::cycles).sum()
+    }
+}
+
+impl Node {
+    pub fn size(&self) -> usize {
+        match &self.kind {
+            NodeKind::Instruction(instr) => instr.byte_size,
+            NodeKind::InvalidParameters(_) | NodeKind::InvalidOpCode(_) => 1,
+        }
+    }
+
+    pub fn end(&self) -> usize {
+        self.offset + self.size()
+    }
+
+    /// The cycles a process spends on the node
+    pub fn cycles(&self) -> u32 {
+        match &self.kind {
+            NodeKind::Instruction(Instruction { kind: op, .. })
+            | NodeKind::InvalidParameters(op) => op_spec(*op).cycles,
+            NodeKind::InvalidOpCode(_) => 1,
+        }
+    }
+}
+
+impl Store {
+    /// The bytes written, relative to the champion's start
+    pub fn range(&self) -> Range<isize> {
+        self.target..self.target + 4
+    }
+}
+
+/// Analyzes a champion, decoding registers valid in arenas using `config`
+pub fn analyze(program: &[u8], config: &VmConfig) -> Result<ControlFlow, ChampionError> {
+    let champion = parse_champion(program)?;
+    Ok(analyze_code(champion.code, config))
+}
+
+/// Analyzes a champion's code, without its header
+pub fn analyze_code(code: &[u8], config: &VmConfig) -> ControlFlow {
+    let code = Code(code);
+    let code_size = code.0.len();
+
+    // What is known of `zf` when entering each node, None when it is unknown.
+    // Processes start with it unset and forks inherit it
+    let mut nodes = BTreeMap::new();
+    let mut carries = BTreeMap::<usize, Option<bool>>::new();
+
+    let mut pending = if code_size > 0 {
+        vec![(0, Some(false))]
+    } else {
+        vec![]
+    };
+    while let Some((offset, mut carry)) = pending.pop() {
+        match carries.get(&offset) {
+            Some(known) if known.is_none() || *known == carry => continue,
+            Some(_) => carry = None,
+            None => (),
+        }
+        carries.insert(offset, carry);
+
+        let node = nodes
+            .entry(offset)
+            .or_insert_with(|| decode(&code, offset, config));
+        let carry = match &node.kind {
+            NodeKind::Instruction(instr) => carry_after(instr, carry),
+            NodeKind::InvalidParameters(_) | NodeKind::InvalidOpCode(_) => carry,
+        };
+        pending.extend(
+            successors(node, carries[&offset], code_size, config)
+                .into_iter()
+                .filter_map(|(target, kind)| match (target, kind) {
+                    // Jumping or not tells whether `zf` is set
+                    (Target::Block(next), EdgeKind::Jump) => Some((next, Some(true))),
+                    (Target::Block(next), EdgeKind::FallThrough) if is_zjmp(node) => {
+                        Some((next, Some(false)))
+                    }
+                    (Target::Block(next), _) => Some((next, carry)),
+                    (Target::Outside(_), _) => None,
+                }),
+        );
+    }
+
+    let successors = nodes
+        .iter()
+        .map(|(offset, node)| {
+            (
+                *offset,
+                successors(node, carries[offset], code_size, config),
+            )
+        })
+        .collect::<BTreeMap<_, _>>();
+    let stores = nodes
+        .values()
+        .filter_map(|node| store(node, config))
+        .collect();
+
+    let leaders = leaders(&successors);
+    let mut blocks = BTreeMap::new();
+    let mut edges = Vec::new();
+
+    for &start in &leaders {
+        let mut block_nodes = Vec::new();
+        let mut offset = start;
+        let exits = loop {
+            let next = &successors[&offset];
+            block_nodes.push(
+                nodes
+                    .remove(&offset)
+                    .expect("Nodes belong to a single block"),
+            );
+            match next.as_slice() {
+                [(Target::Block(next), EdgeKind::FallThrough)] if !leaders.contains(next) => {
+                    offset = *next
+                }
+                _ => break next,
+            }
+        };
+
+        edges.extend(exits.iter().map(|&(to, kind)| Edge {
+            from: start,
+            to,
+            kind,
+        }));
+        blocks.insert(
+            start,
+            Block {
+                start,
+                nodes: block_nodes,
+            },
+        );
+    }
+
+    let loops = find_loops(&blocks, &edges);
+
+    ControlFlow {
+        blocks,
+        edges,
+        loops,
+        stores,
+        code_size,
+    }
+}
+
+/// Where a process goes after a node, knowing `carry`, the state of `zf`
+fn successors(
+    node: &Node,
+    carry: Option<bool>,
+    code_size: usize,
+    config: &VmConfig,
+) -> Vec<(Target, EdgeKind)> {
+    let locate = |relative: isize| locate(node.offset, relative, code_size, config);
+    let fall_through = (locate(node.size() as isize), EdgeKind::FallThrough);
+
+    let instr = match &node.kind {
+        NodeKind::Instruction(instr) => instr,
+        NodeKind::InvalidParameters(_) | NodeKind::InvalidOpCode(_) => return vec![fall_through],
+    };
+    let offset = instr.params[0].value as isize;
+    let limited = offset % config.idx_mod as isize;
+
+    match instr.kind {
+        OpType::Zjmp => {
+            let jump = (locate(limited), EdgeKind::Jump);
+            match carry {
+                Some(true) => vec![jump],
+                Some(false) => vec![fall_through],
+                None => vec![fall_through, jump],
+            }
+        }
+        OpType::Fork => vec![fall_through, (locate(limited), EdgeKind::Spawn)],
+        OpType::Lfork => {
+            let long = offset % config.mem_size as isize;
+            vec![fall_through, (locate(long), EdgeKind::Spawn)]
+        }
+        _ => vec![fall_through],
+    }
+}
+
+fn is_zjmp(node: &Node) -> bool {
+    matches!(&node.kind, NodeKind::Instruction(instr) if matches!(instr.kind, OpType::Zjmp))
+}
+
+/// The store done by a node, if its target is known
+fn store(node: &Node, config: &VmConfig) -> Option<Store> {
+    let instr = match &node.kind {
+        NodeKind::Instruction(instr) => instr,
+        NodeKind::InvalidParameters(_) | NodeKind::InvalidOpCode(_) => return None,
+    };
+    let [_, second, third] = &instr.params;
+
+    let relative = match instr.kind {
+        OpType::St if second.kind == ParamType::Indirect => second.value,
+        OpType::Sti if second.kind == ParamType::Direct && third.kind == ParamType::Direct => {
+            second.value.wrapping_add(third.value)
+        }
+        _ => return None,
+    };
+
+    Some(Store {
+        from: node.offset,
+        target: node.offset as isize + relative as isize % config.idx_mod as isize,
+    })
+}
+
+/// What is known of `zf` after an instruction, knowing `carry` before it
+fn carry_after(instr: &Instruction, carry: Option<bool>) -> Option<bool> {
+    use OpType::*;
+
+    let [first, second, _] = &instr.params;
+    let is_zero = |param: &Param| param.kind == ParamType::Direct && param.value == 0;
+    let same_registers = first.kind == ParamType::Register
+        && second.kind == ParamType::Register
+        && first.value == second.value;
+
+    match instr.kind {
+        Ld | Lld if first.kind == ParamType::Direct => Some(first.value == 0),
+        And if is_zero(first) || is_zero(second) => Some(true),
+        Sub | Xor if same_registers => Some(true),
+        Ld | Lld | Add | Sub | And | Or | Xor | Lldi => None,
+        Live | St | Zjmp | Ldi | Sti | Fork | Lfork | Aff => carry,
+    }
+}
+
+fn decode(code: &Code<'_>, offset: usize, config: &VmConfig) -> Node {
+    let kind = match code.decode_op(offset) {
+        Ok(op) => match code.decode_instr(op, offset, config.reg_count) {
+            Ok(instr) => NodeKind::Instruction(instr),
+            Err(_) => NodeKind::InvalidParameters(op),
+        },
+        Err(_) => NodeKind::InvalidOpCode(code[offset]),
+    };
+
+    Node { offset, kind }
+}
+
+/// Where a process at `offset` ends up after moving by `relative` bytes
+fn locate(offset: usize, relative: isize, code_size: usize, config: &VmConfig) -> Target {
+    let at = offset as isize + relative;
+    let wrapped = at.rem_euclid(config.mem_size as isize) as usize;
+
+    if wrapped < code_size {
+        Target::Block(wrapped)
+    } else {
+        Target::Outside(at)
+    }
+}
+
+/// The nodes starting a block: the entry point, and every node that is not
+/// only entered by falling through from a node that cannot go anywhere else
+fn leaders(successors: &BTreeMap<usize, Vec<(Target, EdgeKind)>>) -> BTreeSet<usize> {
+    let mut entries = BTreeMap::<usize, Vec<bool>>::new();
+    for next in successors.values() {
+        let only_next = next.len() == 1;
+        for (target, kind) in next {
+            if let Target::Block(offset) = target {
+                let sequential = only_next && *kind == EdgeKind::FallThrough;
+                entries.entry(*offset).or_default().push(sequential);
+            }
+        }
+    }
+
+    successors
+        .keys()
+        .copied()
+        .filter(|offset| *offset == 0 || entries[offset] != [true])
+        .collect()
+}
+
+/// Every loop closed by an edge going back to a block being explored, in a
+/// depth first walk of the blocks
+fn find_loops(blocks: &BTreeMap<usize, Block>, edges: &[Edge]) -> Vec<Loop> {
+    let mut successors = BTreeMap::<usize, Vec<usize>>::new();
+    for edge in edges {
+        if let (Target::Block(to), EdgeKind::FallThrough | EdgeKind::Jump) = (edge.to, edge.kind) {
+            successors.entry(edge.from).or_default().push(to);
+        }
+    }
+
+    let mut walk = LoopWalk {
+        blocks,
+        successors,
+        visited: BTreeSet::new(),
+        path: Vec::new(),
+        loops: Vec::new(),
+    };
+    // Blocks only reached by spawned processes start walks of their own
+    for &start in blocks.keys() {
+        walk.visit(start);
+    }
+
+    walk.loops
+}
+
+struct LoopWalk<'a> {
+    blocks: &'a BTreeMap<usize, Block>,
+    successors: BTreeMap<usize, Vec<usize>>,
+    visited: BTreeSet<usize>,
+    path: Vec<usize>,
+    loops: Vec<Loop>,
+}
+
+impl LoopWalk<'_> {
+    fn visit(&mut self, block: usize) {
+        if !self.visited.insert(block) {
+            return;
+        }
+
+        self.path.push(block);
+        for next in self.successors.get(&block).cloned().unwrap_or_default() {
+            match self.path.iter().position(|on_path| *on_path == next) {
+                Some(entry) => {
+                    let blocks = self.path[entry..].to_vec();
+                    let cycles = blocks.iter().map(|start| self.blocks[start].cycles()).sum();
+                    self.loops.push(Loop { blocks, cycles });
+                }
+                None => self.visit(next),
+            }
+        }
+        self.path.pop();
+    }
+}
+
+impl ControlFlow {
+    /// Whether an instruction starting at `offset` can be executed
+    pub fn is_reachable(&self, offset: usize) -> bool {
+        self.nodes().any(|node| node.offset == offset)
+    }
+
+    /// Every reachable node, by block
+    pub fn nodes(&self) -> impl Iterator<Item = &Node> {
+        self.blocks.values().flat_map(|block| &block.nodes)
+    }
+
+    /// The ranges of code bytes that are never executed
+    pub fn unreachable(&self) -> Vec<Range<usize>> {
+        let mut covered = vec![false; self.code_size];
+        for node in self.nodes() {
+            let end = node.end().min(self.code_size);
+            covered[node.offset..end]
+                .iter_mut()
+                .for_each(|byte| *byte = true);
+        }
+
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for (offset, _) in covered.iter().enumerate().filter(|(_, covered)| !**covered) {
+            match ranges.last_mut() {
+                Some(range) if range.end == offset => range.end += 1,
+                _ => ranges.push(offset..offset + 1),
+            }
+        }
+        ranges
+    }
+
+    /// The offsets of the reachable nodes overwritten by a known store
+    pub fn overwritten(&self) -> Vec<usize> {
+        self.nodes()
+            .filter(|node| {
+                let (start, end) = (node.offset as isize, node.end() as isize);
+                self.stores.iter().any(|store| {
+                    let written = store.range();
+                    written.start < end && start < written.end
+                })
+            })
+            .map(|node| node.offset)
+            .collect()
+    }
+
+    /// The block containing the node starting at `offset`
+    fn block_of(&self, offset: usize) -> Option<usize> {
+        self.blocks
+            .values()
+            .find(|block| block.nodes.iter().any(|node| node.offset == offset))
+            .map(|block| block.start)
+    }
+
+    /// The graph in Graphviz's DOT language
+    pub fn to_dot(&self, name: &str) -> String {
+        let mut dot = format!("digraph \"{}\" {{\n", escape(name));
+        dot.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+
+        for block in self.blocks.values() {
+            let mut label = String::new();
+            for cycle in self.loops.iter().filter(|l| l.blocks[0] == block.start) {
+                let _ = write!(label, "loop of {} cycles\\l", cycle.cycles);
+            }
+            for node in &block.nodes {
+                let _ = write!(label, "{}: {}\\l", node.offset, escape(&node.to_string()));
+            }
+            let _ = writeln!(dot, "    b{} [label=\"{}\"];", block.start, label);
+        }
+
+        let outside = self
+            .edges
+            .iter()
+            .filter_map(|edge| match edge.to {
+                Target::Outside(offset) => Some(offset),
+                Target::Block(_) => None,
+            })
+            .collect::<BTreeSet<_>>();
+        for offset in &outside {
+            let _ = writeln!(
+                dot,
+                "    \"outside {0}\" [label=\"{0}\", shape=ellipse, style=dashed];",
+                offset
+            );
+        }
+
+        for edge in &self.edges {
+            let to = match edge.to {
+                Target::Block(start) => format!("b{}", start),
+                Target::Outside(offset) => format!("\"outside {}\"", offset),
+            };
+            let attributes = match edge.kind {
+                EdgeKind::FallThrough => "",
+                EdgeKind::Jump => " [label=\"zf\"]",
+                EdgeKind::Spawn => " [label=\"fork\", style=dashed]",
+            };
+            let _ = writeln!(dot, "    b{} -> {}{};", edge.from, to, attributes);
+        }
+
+        for store in &self.stores {
+            let written = store.range();
+            let targets = self
+                .nodes()
+                .filter(|node| {
+                    written.start < node.end() as isize && (node.offset as isize) < written.end
+                })
+                .filter_map(|node| self.block_of(node.offset))
+                .collect::<BTreeSet<_>>();
+            if let Some(from) = self.block_of(store.from) {
+                for to in targets {
+                    let _ = writeln!(
+                        dot,
+                        "    b{} -> b{} [label=\"writes\", style=dotted, color=red];",
+                        from, to
+                    );
+                }
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            NodeKind::Instruction(instr) => write!(f, "{}", instr),
+            NodeKind::InvalidParameters(op) => {
+                write!(f, "{} (invalid parameters)", op.to_string().to_lowercase())
+            }
+            NodeKind::InvalidOpCode(byte) => write!(f, ".code 0x{:02x}", byte),
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -1,63 +1,139 @@
-use super::{parser::ParsedLine, types::Op};
+use super::{
+    lexer::InputRange,
+    parser::ParsedLine,
+    span::{Origin, Span},
+    types::{Expr, Op},
+};
+
+use std::mem;
 
 #[derive(Debug)]
 pub struct Champion {
     pub name: String,
     pub comment: String,
     pub instructions: Vec<ParsedInstruction>,
+    /// Where each instruction comes from
+    pub origins: Vec<Origin>,
+    /// The `.name` directive, if any
+    pub name_span: Option<Span>,
+    /// The `.comment` directive, if any
+    pub comment_span: Option<Span>,
 }
 
 #[derive(Default)]
 pub struct ChampionBuilder {
-    name: Option<String>,
-    comment: Option<String>,
+    name: Option<(String, Span)>,
+    comment: Option<(String, Span)>,
     instructions: Vec<ParsedInstruction>,
+    origins: Vec<Origin>,
+    current_origin: Option<Origin>,
 }
 
 impl ChampionBuilder {
     fn with_name(&mut self, name: String) -> AssembleResult<&mut Self> {
-        if let Some(previous_name) = self.name.replace(name) {
-            Err(AssembleError::NameAlreadySet(previous_name))
-        } else {
-            Ok(self)
+        let span = self.current_span();
+        match self.name.replace((name, span.clone())) {
+            Some((previous_name, first)) => Err(AssembleError::NameAlreadySet {
+                name: previous_name,
+                at: span,
+                first,
+            }),
+            None => Ok(self),
         }
     }
 
     fn with_comment(&mut self, comment: String) -> AssembleResult<&mut Self> {
-        if let Some(previous_comment) = self.comment.replace(comment) {
-            Err(AssembleError::CommentAlreadySet(previous_comment))
-        } else {
-            Ok(self)
+        let span = self.current_span();
+        match self.comment.replace((comment, span.clone())) {
+            Some((previous_comment, first)) => Err(AssembleError::CommentAlreadySet {
+                comment: previous_comment,
+                at: span,
+                first,
+            }),
+            None => Ok(self),
         }
     }
 
+    /// The whole line being assembled
+    fn current_span(&self) -> Span {
+        let origin = self
+            .current_origin
+            .as_ref()
+            .expect("Lines are assembled with their origin");
+        origin.span.clone()
+    }
+
     fn add_instr(&mut self, instr_data: impl Into<ParsedInstruction>) -> &mut Self {
         self.instructions.push(instr_data.into());
+        self.origins.push(
+            self.current_origin
+                .clone()
+                .expect("Lines are assembled with their origin"),
+        );
         self
     }
 
-    pub fn assemble(&mut self, parsed_line: ParsedLine) -> AssembleResult<&mut Self> {
+    /// Assembles a line of the expanded source
+    pub fn assemble(
+        &mut self,
+        parsed_line: ParsedLine,
+        origin: &Origin,
+    ) -> AssembleResult<&mut Self> {
         use ParsedLine::*;
 
+        self.current_origin = Some(origin.clone());
+
         match parsed_line {
             ChampionName(name) => self.with_name(name),
             ChampionComment(comment) => self.with_comment(comment),
 
             Code(bytes) => Ok(self.add_instr(bytes)),
             Op(op) => Ok(self.add_instr(op)),
-            Label(label) => Ok(self.add_instr(label)),
-            LabelAndOp(label, op) => Ok(self.add_instr(label).add_instr(op)),
+            Label(label, at) => Ok(self.add_instr((label, at))),
+            LabelAndOp(label, at, op) => Ok(self.add_instr((label, at)).add_instr(op)),
+            Constant(name, value) => Ok(self.add_instr(ParsedInstruction::Constant(name, value))),
 
             Empty => Ok(self),
         }
     }
 
-    pub fn finish(self) -> AssembleResult<Champion> {
-        Ok(Champion {
-            name: self.name.ok_or(AssembleError::MissingName)?,
-            comment: self.comment.ok_or(AssembleError::MissingComment)?,
-            instructions: self.instructions,
-        })
+    pub fn finish(mut self) -> AssembleResult<Champion> {
+        if self.name.is_none() {
+            return Err(AssembleError::MissingName);
+        }
+        if self.comment.is_none() {
+            return Err(AssembleError::MissingComment);
+        }
+
+        Ok(self.champion())
+    }
+
+    /// Same as `finish` but leaves the missing header fields empty, returning
+    /// the errors alongside the champion
+    pub fn finish_lossy(mut self) -> (Champion, Vec<AssembleError>) {
+        let mut errors = Vec::new();
+        if self.name.is_none() {
+            errors.push(AssembleError::MissingName);
+        }
+        if self.comment.is_none() {
+            errors.push(AssembleError::MissingComment);
+        }
+
+        (self.champion(), errors)
+    }
+
+    fn champion(&mut self) -> Champion {
+        let (name, name_span) = self.name.take().unzip();
+        let (comment, comment_span) = self.comment.take().unzip();
+
+        Champion {
+            name: name.unwrap_or_default(),
+            comment: comment.unwrap_or_default(),
+            instructions: mem::take(&mut self.instructions),
+            origins: mem::take(&mut self.origins),
+            name_span,
+            comment_span,
+        }
     }
 }
 
@@ -65,19 +141,36 @@ type AssembleResult<T> = Result<T, AssembleError>;
 
 #[derive(Debug, derive_more::From)]
 pub enum ParsedInstruction {
-    Label(String),
+    /// With the range of its definition
+    Label(String, InputRange),
     Op(Op),
     RawCode(Vec<u8>),
+    Constant(String, Expr),
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum AssembleError {
-    #[error("Duplicate '.name' directive: the champion was already named '{0}'")]
-    NameAlreadySet(String),
-    #[error("Duplicate '.comment' directive: the champion already had a comment '{0}'")]
-    CommentAlreadySet(String),
+    #[error("Duplicate '.name' directive: the champion was already named '{name}'")]
+    NameAlreadySet { name: String, at: Span, first: Span },
+    #[error("Duplicate '.comment' directive: the champion already had a comment '{comment}'")]
+    CommentAlreadySet {
+        comment: String,
+        at: Span,
+        first: Span,
+    },
     #[error("The champion is missing a '.name' directive")]
     MissingName,
     #[error("The champion is missing a '.comment' directive")]
     MissingComment,
 }
+
+impl AssembleError {
+    /// Where the error is, None for errors about the whole champion
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            AssembleError::NameAlreadySet { at, .. }
+            | AssembleError::CommentAlreadySet { at, .. } => Some(at.clone()),
+            AssembleError::MissingName | AssembleError::MissingComment => None,
+        }
+    }
+}
@@ -1,34 +1,112 @@
 use super::{
     assembler::{Champion, ParsedInstruction},
+    diagnostics::Diagnostic,
+    lexer::InputRange,
+    span::{Origin, Span},
     types::*,
+    warnings::{CompileWarning, Linter, ParamUse},
 };
 use crate::spec::*;
 
 use std::{
     collections::{hash_map::Entry, HashMap},
+    fmt::Write as _,
     io::{Error as IOError, Seek, SeekFrom, Write},
 };
 
 type CompileResult<T> = Result<T, CompileError>;
 
-pub fn compile_champion(out: impl Write + Seek, mut champion: Champion) -> CompileResult<usize> {
-    let mut state = State::new(out)?;
+pub fn compile_champion(
+    out: impl Write + Seek,
+    champion: Champion,
+    config: &VmConfig,
+) -> CompileResult<usize> {
+    compile(out, champion, config, false, None).map(|(size, _, _)| size)
+}
+
+/// Same as `compile_champion` but also reports suspicious code
+pub fn compile_champion_with_warnings(
+    out: impl Write + Seek,
+    champion: Champion,
+    config: &VmConfig,
+) -> CompileResult<(usize, Vec<CompileWarning>)> {
+    compile(out, champion, config, false, None).map(|(size, _, warnings)| (size, warnings))
+}
+
+/// Same as `compile_champion` but keeps going after errors to report all of
+/// them. The listing and the warnings are only reported when there were no
+/// errors
+pub fn compile_champion_with_diagnostics(
+    out: impl Write + Seek,
+    champion: Champion,
+    config: &VmConfig,
+) -> Result<(usize, Listing, Vec<CompileWarning>), Vec<Diagnostic>> {
+    let mut diagnostics = Vec::new();
+
+    match compile(out, champion, config, true, Some(&mut diagnostics)) {
+        Ok((size, listing, warnings)) if diagnostics.is_empty() => {
+            Ok((size, listing.expect("The listing was requested"), warnings))
+        }
+        Ok(_) => Err(diagnostics),
+        Err(e) => {
+            diagnostics.push(Diagnostic::new(e, None));
+            Err(diagnostics)
+        }
+    }
+}
 
-    for instr in champion.instructions.drain(..) {
-        match instr {
-            ParsedInstruction::Op(op) => state.write_op(op)?,
-            ParsedInstruction::Label(label) => state.register_label(label)?,
-            ParsedInstruction::RawCode(bytes) => state.add_raw_code(&bytes)?,
+/// Same as `compile_champion` but also describes what was emitted for each
+/// source line
+pub fn compile_champion_with_listing(
+    out: impl Write + Seek,
+    champion: Champion,
+    config: &VmConfig,
+) -> CompileResult<(usize, Listing)> {
+    compile(out, champion, config, true, None)
+        .map(|(size, listing, _)| (size, listing.expect("The listing was requested")))
+}
+
+fn compile(
+    out: impl Write + Seek,
+    mut champion: Champion,
+    config: &VmConfig,
+    with_listing: bool,
+    diagnostics: Option<&mut Vec<Diagnostic>>,
+) -> CompileResult<(usize, Option<Listing>, Vec<CompileWarning>)> {
+    let mut state = State::new(out, config, with_listing, diagnostics)?;
+    let origins = std::mem::take(&mut champion.origins);
+    // The instruction that goes past the size limit
+    let mut too_long_at = None;
+
+    for (instr, origin) in champion.instructions.drain(..).zip(origins) {
+        state.start_line(&origin);
+        state.linter.instruction(&instr, origin.line(), config);
+        let result = match instr {
+            ParsedInstruction::Op(op) => state.write_op(op),
+            ParsedInstruction::Label(label, at) => state.register_label(label, at),
+            ParsedInstruction::RawCode(bytes) => state.add_raw_code(&bytes),
+            ParsedInstruction::Constant(name, value) => state.define_constant(name, value),
+        };
+        state.recover(result, Some(&origin))?;
+
+        if state.size > config.champ_max_size && too_long_at.is_none() {
+            too_long_at = Some(origin.span);
         }
     }
 
-    state.write_header(&champion)?;
-    state.resolve_labels()?;
+    let result = state.write_header(&champion);
+    state.recover(result, None)?;
+    state.resolve_placeholders()?;
 
-    if state.size > CHAMP_MAX_SIZE {
-        Err(CompileError::ProgramTooLong(state.size))
+    if let Some(at) = too_long_at {
+        Err(CompileError::ProgramTooLong(
+            state.size,
+            config.champ_max_size,
+            at,
+        ))
     } else {
-        Ok(state.size)
+        let warnings = std::mem::take(&mut state.linter).finish(config);
+        Ok((state.size, state.finish_listing(), warnings))
     }
 }
 
@@ -90,26 +168,125 @@ fn op_spec(op: &Op) -> OpSpec {
     crate::spec::op_spec(op_type)
 }
 
-struct State<W> {
+struct State<'d, W> {
     out: W,
     size: usize,
     label_positions: HashMap<String, usize>,
-    labels_to_fill: Vec<LabelPlaceholder>,
+    label_definitions: HashMap<String, Span>,
+    /// Constant definitions with where they come from
+    constants: HashMap<String, (Expr, Origin)>,
+    /// Arena parameters that can be used as constants unless redefined
+    builtin_constants: [(&'static str, i64); 3],
+    placeholders: Vec<Placeholder>,
     current_op_pos: usize,
+    current_origin: Option<Origin>,
+    listing: Option<ListingState>,
+    linter: Linter,
+    config: VmConfig,
+    /// How the operation being written uses each of its parameters, if they
+    /// are direct or indirect
+    param_uses: [(ParamUse, ParamUse); MAX_PARAMS],
+    param_idx: usize,
+    /// Where errors are collected when compiling past them
+    diagnostics: Option<&'d mut Vec<Diagnostic>>,
 }
 
-impl<W: Write + Seek> State<W> {
-    fn new(mut out: W) -> CompileResult<Self> {
+/// Keeps a copy of the emitted code so that the listing shows the bytes of
+/// resolved labels
+struct ListingState {
+    lines: Vec<ListingLine>,
+    code: Vec<u8>,
+}
+
+impl<'d, W: Write + Seek> State<'d, W> {
+    fn new(
+        mut out: W,
+        config: &VmConfig,
+        with_listing: bool,
+        diagnostics: Option<&'d mut Vec<Diagnostic>>,
+    ) -> CompileResult<Self> {
         out.seek(SeekFrom::Start(HEADER_SIZE as u64))?;
         Ok(Self {
             out,
             size: 0,
             label_positions: HashMap::new(),
-            labels_to_fill: Vec::new(),
+            label_definitions: HashMap::new(),
+            constants: HashMap::new(),
+            builtin_constants: [
+                ("MEM_SIZE", config.mem_size as i64),
+                ("IDX_MOD", config.idx_mod as i64),
+                ("CHAMP_MAX_SIZE", config.champ_max_size as i64),
+            ],
+            placeholders: Vec::new(),
             current_op_pos: 0,
+            current_origin: None,
+            listing: if with_listing {
+                Some(ListingState {
+                    lines: Vec::new(),
+                    code: Vec::new(),
+                })
+            } else {
+                None
+            },
+            linter: Linter::default(),
+            config: config.clone(),
+            param_uses: [(ParamUse::Value, ParamUse::Value); MAX_PARAMS],
+            param_idx: 0,
+            diagnostics,
         })
     }
 
+    /// Records the error of a step when collecting diagnostics, so that
+    /// compilation can go on. IO errors are always returned
+    fn recover(&mut self, result: CompileResult<()>, origin: Option<&Origin>) -> CompileResult<()> {
+        match (result, &mut self.diagnostics) {
+            (Err(CompileError::IOError(e)), _) => Err(CompileError::IOError(e)),
+            (Err(e), Some(diagnostics)) => {
+                diagnostics.push(Diagnostic::new(e, origin.map(|origin| origin.site.clone())));
+                Ok(())
+            }
+            (result, _) => result,
+        }
+    }
+
+    fn start_line(&mut self, origin: &Origin) {
+        let offset = self.size;
+        let line = origin.line();
+        self.current_origin = Some(origin.clone());
+
+        if let Some(listing) = &mut self.listing {
+            if listing.lines.last().map(|last| last.line) != Some(line) {
+                listing.lines.push(ListingLine {
+                    line,
+                    offset,
+                    bytes: Vec::new(),
+                    cycles: None,
+                    label_refs: Vec::new(),
+                });
+            }
+        }
+    }
+
+    fn finish_listing(&mut self) -> Option<Listing> {
+        let ListingState { mut lines, code } = self.listing.take()?;
+
+        // Each line spans up to the start of the next one
+        let mut boundaries = lines.iter().map(|line| line.offset).collect::<Vec<_>>();
+        boundaries.push(code.len());
+        for (idx, line) in lines.iter_mut().enumerate() {
+            line.bytes = code[line.offset..boundaries[idx + 1]].to_vec();
+        }
+
+        let mut symbols = self
+            .label_positions
+            .iter()
+            .map(|(label, offset)| (label.clone(), *offset))
+            .collect::<Vec<_>>();
+        symbols.sort_by(|(l1, o1), (l2, o2)| o1.cmp(o2).then_with(|| l1.cmp(l2)));
+
+        Some(Listing { lines, symbols })
+    }
+
     fn write_header(&mut self, champion: &Champion) -> CompileResult<()> {
         let header = Header::from_champion(champion, self.size as u32)?;
 
@@ -119,13 +296,18 @@ impl<W: Write + Seek> State<W> {
         Ok(())
     }
 
-    fn register_label(&mut self, label: String) -> CompileResult<()> {
+    fn register_label(&mut self, label: String, at: InputRange) -> CompileResult<()> {
+        let at = self.current_origin().locate(at);
+
         match self.label_positions.entry(label) {
+            // The first definition is kept, so that uses of the label are resolved
             Entry::Occupied(entry) => {
-                let (label, _) = entry.remove_entry();
-                Err(CompileError::DuplicateLabel(label))
+                let label = entry.key().clone();
+                let first = self.label_definitions[&label].clone();
+                Err(CompileError::DuplicateLabel { label, at, first })
             }
             Entry::Vacant(entry) => {
+                self.label_definitions.insert(entry.key().clone(), at);
                 entry.insert(self.size);
                 Ok(())
             }
@@ -133,34 +315,182 @@ impl<W: Write + Seek> State<W> {
     }
 
     fn add_raw_code(&mut self, bytes: &[u8]) -> CompileResult<()> {
-        self.write(&bytes)
+        self.write(bytes)
     }
 
-    fn resolve_labels(&mut self) -> CompileResult<()> {
-        for placeholder in &self.labels_to_fill {
-            let position = *self
-                .label_positions
-                .get(&placeholder.name)
-                .ok_or_else(|| CompileError::MissingLabel(placeholder.name.clone()))?;
-            self.out.seek(SeekFrom::Start(
-                (HEADER_SIZE + placeholder.write_pos) as u64,
-            ))?;
-            write_numeric(
-                &mut self.out,
-                ((position as isize) - (placeholder.op_pos as isize)) as u32,
-                placeholder.size,
-            )?;
+    fn define_constant(&mut self, name: String, value: Expr) -> CompileResult<()> {
+        let origin = self.current_origin().clone();
+
+        match self.constants.entry(name) {
+            // The first definition is kept, as for labels
+            Entry::Occupied(entry) => Err(CompileError::DuplicateConstant {
+                name: entry.key().clone(),
+                at: origin.span,
+                first: entry.get().1.span.clone(),
+            }),
+            Entry::Vacant(entry) => {
+                for label in expr_labels(&value) {
+                    self.linter.use_label(label);
+                }
+                entry.insert((value, origin));
+                Ok(())
+            }
+        }
+    }
+
+    fn current_origin(&self) -> &Origin {
+        self.current_origin
+            .as_ref()
+            .expect("Instructions are compiled after starting their line")
+    }
+
+    /// Fills the parameters that could only be computed once every label was
+    /// known
+    fn resolve_placeholders(&mut self) -> CompileResult<()> {
+        for placeholder in std::mem::take(&mut self.placeholders) {
+            let result = self.resolve_placeholder(&placeholder);
+            self.recover(result, Some(&placeholder.origin))?;
         }
 
         Ok(())
     }
 
+    fn resolve_placeholder(&mut self, placeholder: &Placeholder) -> CompileResult<()> {
+        let value = match &placeholder.value {
+            Deferred::Label(label, at) => {
+                let position = *self.label_positions.get(label).ok_or_else(|| {
+                    CompileError::MissingLabel(label.clone(), placeholder.origin.locate(at.clone()))
+                })?;
+                position as i64 - placeholder.op_pos as i64
+            }
+            Deferred::Expr(expr) => {
+                let value = self.evaluate(
+                    expr,
+                    placeholder.op_pos,
+                    &placeholder.origin,
+                    &mut Vec::new(),
+                )?;
+                if !fits(value, placeholder.size) {
+                    return Err(ExprError {
+                        kind: ExprErrorKind::DoesNotFit(value, placeholder.size),
+                        span: placeholder.origin.locate(expr.at.clone()),
+                    }
+                    .into());
+                }
+                value
+            }
+        };
+
+        self.linter.resolved(
+            value,
+            placeholder.param_use,
+            placeholder.origin.line(),
+            &self.config,
+        );
+
+        self.out.seek(SeekFrom::Start(
+            (HEADER_SIZE + placeholder.write_pos) as u64,
+        ))?;
+        write_numeric(&mut self.out, value as u32, placeholder.size)?;
+
+        if let Some(listing) = &mut self.listing {
+            let code_slot = &mut listing.code[placeholder.write_pos..][..placeholder.size];
+            write_numeric(code_slot, value as u32, placeholder.size)?;
+
+            let line = listing
+                .lines
+                .iter_mut()
+                .rev()
+                .find(|line| line.offset <= placeholder.op_pos);
+            if let Some(line) = line {
+                for label in placeholder.value.labels() {
+                    if let Some(position) = self.label_positions.get(label) {
+                        line.label_refs.push((String::from(label), *position));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Computes an expression appearing in the operation at `op_pos`.
+    /// Labels evaluate to their offset from that operation, including those
+    /// used in constant definitions
+    fn evaluate(
+        &self,
+        expr: &Expr,
+        op_pos: usize,
+        origin: &Origin,
+        constants_in_use: &mut Vec<String>,
+    ) -> Result<i64, ExprError> {
+        let error = |kind| ExprError {
+            kind,
+            span: origin.locate(expr.at.clone()),
+        };
+
+        match &expr.kind {
+            ExprKind::Number(n) => Ok(*n),
+            ExprKind::Label(label) => self
+                .label_positions
+                .get(label)
+                .map(|position| *position as i64 - op_pos as i64)
+                .ok_or_else(|| error(ExprErrorKind::MissingLabel(label.clone()))),
+            ExprKind::Constant(name) => {
+                if constants_in_use.contains(name) {
+                    return Err(error(ExprErrorKind::RecursiveConstant(name.clone())));
+                }
+
+                match self.constants.get(name) {
+                    Some((value, definition)) => {
+                        constants_in_use.push(name.clone());
+                        let result = self.evaluate(value, op_pos, definition, constants_in_use);
+                        constants_in_use.pop();
+                        result
+                    }
+                    None => self
+                        .builtin_constants
+                        .iter()
+                        .find(|(builtin, _)| builtin == name)
+                        .map(|(_, value)| *value)
+                        .ok_or_else(|| error(ExprErrorKind::UnknownConstant(name.clone()))),
+                }
+            }
+            ExprKind::Neg(operand) => self
+                .evaluate(operand, op_pos, origin, constants_in_use)?
+                .checked_neg()
+                .ok_or_else(|| error(ExprErrorKind::Overflow)),
+            ExprKind::Binary(op, lhs, rhs) => {
+                let lhs = self.evaluate(lhs, op_pos, origin, constants_in_use)?;
+                let rhs = self.evaluate(rhs, op_pos, origin, constants_in_use)?;
+
+                let result = match op {
+                    BinOp::Add => lhs.checked_add(rhs),
+                    BinOp::Sub => lhs.checked_sub(rhs),
+                    BinOp::Mul => lhs.checked_mul(rhs),
+                    BinOp::Div if rhs == 0 => return Err(error(ExprErrorKind::DivisionByZero)),
+                    BinOp::Div => lhs.checked_div(rhs),
+                };
+                result.ok_or_else(|| error(ExprErrorKind::Overflow))
+            }
+        }
+    }
+
     fn write(&mut self, buf: &[u8]) -> CompileResult<()> {
         self.out.write_all(buf)?;
         self.size += buf.len();
+        if let Some(listing) = &mut self.listing {
+            listing.code.extend_from_slice(buf);
+        }
         Ok(())
     }
 
+    fn write_numeric_param(&mut self, n: u32, write_size: usize) -> CompileResult<()> {
+        let mut bytes = [0; 4];
+        let size = write_numeric(&mut bytes[..], n, write_size)?;
+        self.write(&bytes[..size])
+    }
+
     fn write_op(&mut self, op: Op) -> CompileResult<()> {
         let OpSpec {
             code,
@@ -170,6 +500,13 @@ impl<W: Write + Seek> State<W> {
         } = op_spec(&op);
 
         self.current_op_pos = self.size;
+        for (idx, uses) in self.param_uses.iter_mut().enumerate() {
+            *uses = (ParamUse::of(&op, idx, false), ParamUse::of(&op, idx, true));
+        }
+        self.param_idx = 0;
+        if let Some(line) = self.listing.as_mut().and_then(|l| l.lines.last_mut()) {
+            line.cycles = Some(line.cycles.unwrap_or(0) + op_spec(&op).cycles);
+        }
 
         if has_pcb {
             self.write(&[code, pcb(&op)])?;
@@ -216,44 +553,71 @@ impl<W: Write + Seek> State<W> {
     }
 
     fn write_reg(&mut self, reg: Register) -> CompileResult<()> {
+        self.param_idx += 1;
         self.write(&[reg.0])
     }
 
+    /// How the current parameter is used by its operation, moving on to the
+    /// next one
+    fn next_param_use(&mut self, indirect: bool) -> ParamUse {
+        let (direct_use, indirect_use) = self.param_uses[self.param_idx];
+        self.param_idx += 1;
+        if indirect {
+            indirect_use
+        } else {
+            direct_use
+        }
+    }
+
+    fn write_literal(&mut self, n: i64, size: usize, param_use: ParamUse) -> CompileResult<()> {
+        let line = self.current_origin().line();
+        self.linter.literal(n, size, param_use, line, &self.config);
+        self.write_numeric_param(n as u32, size)
+    }
+
+    /// Reserves space for a parameter that will be filled by
+    /// `resolve_placeholders`
+    fn write_placeholder(
+        &mut self,
+        value: Deferred,
+        size: usize,
+        param_use: ParamUse,
+    ) -> CompileResult<()> {
+        for label in value.labels() {
+            self.linter.use_label(label);
+        }
+        self.placeholders.push(Placeholder {
+            write_pos: self.size,
+            op_pos: self.current_op_pos,
+            value,
+            size,
+            origin: self.current_origin().clone(),
+            param_use,
+        });
+        self.write(&[0; 4][..size])
+    }
+
     fn write_dir(&mut self, dir: Direct, dir_size: DirectSize) -> CompileResult<()> {
+        let param_use = self.next_param_use(false);
+        let size = dir_size as usize;
         match dir {
-            Direct::Label(label) => {
-                self.labels_to_fill.push(LabelPlaceholder {
-                    write_pos: self.size,
-                    op_pos: self.current_op_pos,
-                    name: label,
-                    size: dir_size as _,
-                });
-                self.write(match dir_size {
-                    DirectSize::TwoBytes => &[0; 2],
-                    DirectSize::FourBytes => &[0; 4],
-                })
-            }
-            Direct::Numeric(n) => {
-                self.size += write_numeric(&mut self.out, n as u32, dir_size as _)?;
-                Ok(())
+            Direct::Label(label, at) => {
+                self.write_placeholder(Deferred::Label(label, at), size, param_use)
             }
+            Direct::Numeric(n) => self.write_literal(n, size, param_use),
+            Direct::Expr(expr) => self.write_placeholder(Deferred::Expr(expr), size, param_use),
         }
     }
 
     fn write_ind(&mut self, ind: Indirect) -> CompileResult<()> {
+        let param_use = self.next_param_use(true);
         match ind {
-            Indirect::Label(label) => {
-                self.labels_to_fill.push(LabelPlaceholder {
-                    write_pos: self.size,
-                    op_pos: self.current_op_pos,
-                    name: label,
-                    size: IND_SIZE,
-                });
-                self.write(&[0, 0])
+            Indirect::Label(label, at) => {
+                self.write_placeholder(Deferred::Label(label, at), IND_SIZE, param_use)
             }
-            Indirect::Numeric(n) => {
-                self.size += write_numeric(&mut self.out, n as u32, IND_SIZE)?;
-                Ok(())
+            Indirect::Numeric(n) => self.write_literal(n, IND_SIZE, param_use),
+            Indirect::Expr(expr) => {
+                self.write_placeholder(Deferred::Expr(expr), IND_SIZE, param_use)
             }
         }
     }
@@ -288,6 +652,13 @@ impl<W: Write + Seek> State<W> {
     }
 }
 
+/// Whether a value can be written on `size` bytes, either as a signed or as an
+/// unsigned number
+pub(super) fn fits(value: i64, size: usize) -> bool {
+    let bits = size * 8;
+    value >= -(1 << (bits - 1)) && value < (1 << bits)
+}
+
 fn write_numeric(mut out: impl Write, n: u32, write_size: usize) -> CompileResult<usize> {
     let truncated = n << ((4 - write_size) * 8);
     let be_bytes = truncated.to_be_bytes();
@@ -297,12 +668,129 @@ fn write_numeric(mut out: impl Write, n: u32, write_size: usize) -> CompileResul
     Ok(bytes_to_write.len())
 }
 
+/// What the compiler emitted for each source line
+#[derive(Debug)]
+pub struct Listing {
+    pub lines: Vec<ListingLine>,
+    /// Every label with its code offset, sorted by offset
+    pub symbols: Vec<(String, usize)>,
+}
+
+#[derive(Debug)]
+pub struct ListingLine {
+    /// The source line, starting at 1
+    pub line: usize,
+    pub offset: usize,
+    pub bytes: Vec<u8>,
+    /// The cycle cost of the line's operation, if it has one
+    pub cycles: Option<u32>,
+    /// The labels referenced on the line with the offset they resolved to
+    pub label_refs: Vec<(String, usize)>,
+}
+
+impl Listing {
+    /// Formats the listing next to the source it was compiled from
+    pub fn render(&self, source: &str) -> String {
+        let source_lines = source.lines().collect::<Vec<_>>();
+        let bytes_width = self
+            .lines
+            .iter()
+            .map(|line| line.bytes.len() * 3)
+            .max()
+            .unwrap_or(0)
+            .max(5);
+
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "{:>5}  {:<6}  {:<bytes_width$}  {:>6}  SOURCE",
+            "LINE",
+            "OFFSET",
+            "BYTES",
+            "CYCLES",
+            bytes_width = bytes_width
+        );
+
+        for line in &self.lines {
+            let bytes = line
+                .bytes
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let cycles = line.cycles.map(|c| c.to_string()).unwrap_or_default();
+            let text = source_lines
+                .get(line.line - 1)
+                .map_or("", |text| text.trim_end());
+
+            let _ = write!(
+                out,
+                "{:>5}  0x{:04x}  {:<bytes_width$}  {:>6}  {}",
+                line.line,
+                line.offset,
+                bytes,
+                cycles,
+                text,
+                bytes_width = bytes_width
+            );
+            for (label, offset) in &line.label_refs {
+                let _ = write!(out, "  ; {} = 0x{:04x}", label, offset);
+            }
+            out.push('\n');
+        }
+
+        out.push_str("\nLABELS\n");
+        for (label, offset) in &self.symbols {
+            let _ = writeln!(out, "0x{:04x}  {}", offset, label);
+        }
+
+        out
+    }
+}
+
 #[derive(Debug)]
-struct LabelPlaceholder {
+struct Placeholder {
     write_pos: usize,
     op_pos: usize,
-    name: String,
+    value: Deferred,
     size: usize,
+    origin: Origin,
+    param_use: ParamUse,
+}
+
+#[derive(Debug)]
+enum Deferred {
+    Label(String, InputRange),
+    Expr(Expr),
+}
+
+impl Deferred {
+    /// The labels referenced directly by the parameter
+    fn labels(&self) -> Vec<&str> {
+        match self {
+            Deferred::Label(label, _) => vec![label],
+            Deferred::Expr(expr) => expr_labels(expr),
+        }
+    }
+}
+
+/// The labels referenced directly by an expression
+fn expr_labels(expr: &Expr) -> Vec<&str> {
+    fn collect<'a>(expr: &'a Expr, labels: &mut Vec<&'a str>) {
+        match &expr.kind {
+            ExprKind::Label(label) => labels.push(label),
+            ExprKind::Neg(operand) => collect(operand, labels),
+            ExprKind::Binary(_, lhs, rhs) => {
+                collect(lhs, labels);
+                collect(rhs, labels);
+            }
+            ExprKind::Number(_) | ExprKind::Constant(_) => (),
+        }
+    }
+
+    let mut labels = Vec::new();
+    collect(expr, &mut labels);
+    labels
 }
 
 const IND_SIZE: usize = 2;
@@ -313,14 +801,18 @@ impl Header {
         let name = champion.name.as_bytes();
         prog_name
             .get_mut(..name.len())
-            .ok_or_else(|| CompileError::ProgramNameTooLong(name.len()))?
+            .ok_or_else(|| {
+                CompileError::ProgramNameTooLong(name.len(), champion.name_span.clone())
+            })?
             .copy_from_slice(name);
 
         let mut prog_comment = [0; PROG_COMMENT_LENGTH + 1];
         let comment = champion.comment.as_bytes();
         prog_comment
             .get_mut(..comment.len())
-            .ok_or_else(|| CompileError::ProgramCommentTooLong(comment.len()))?
+            .ok_or_else(|| {
+                CompileError::ProgramCommentTooLong(comment.len(), champion.comment_span.clone())
+            })?
             .copy_from_slice(comment);
 
         Ok(Self {
@@ -356,23 +848,75 @@ pub enum CompileError {
         "The champion's name is too long: {0} bytes (maximum allowed is {})",
         PROG_COMMENT_LENGTH
     )]
-    ProgramNameTooLong(usize),
+    ProgramNameTooLong(usize, Option<Span>),
     #[error(
         "The champion's comment is too long: {0} bytes (maximum allowed is {})",
         PROG_COMMENT_LENGTH
     )]
-    ProgramCommentTooLong(usize),
+    ProgramCommentTooLong(usize, Option<Span>),
+    /// With the label's use
     #[error(
         "The label '{0}' is missing. It is referenced in a parameter but has never been declared"
     )]
-    MissingLabel(String),
-    #[error("The label '{0}' has been declared multiple times. A label can only be declared once")]
-    DuplicateLabel(String),
+    MissingLabel(String, Span),
+    #[error(
+        "The label '{label}' has been declared multiple times. A label can only be declared once"
+    )]
+    DuplicateLabel {
+        label: String,
+        at: Span,
+        first: Span,
+    },
+    /// With the instruction that goes past the limit
+    #[error("The champion's code is too big: {0} bytes (maximum allowed is {1})")]
+    ProgramTooLong(usize, usize, Span),
     #[error(
-        "The champion's code is too big: {0} bytes (maximum allowed is {})",
-        CHAMP_MAX_SIZE
+        "The constant '{name}' has been defined multiple times. A constant can only be defined once"
     )]
-    ProgramTooLong(usize),
+    DuplicateConstant { name: String, at: Span, first: Span },
+    #[error("Invalid expression on {}: {}", .0.span, .0.kind)]
+    ExprError(#[from] ExprError),
     #[error("Unexpected IO error: {0}")]
     IOError(#[from] IOError),
 }
+
+impl CompileError {
+    /// Where the error is, None for errors about the whole champion
+    pub fn span(&self) -> Option<Span> {
+        use CompileError::*;
+
+        match self {
+            ProgramNameTooLong(_, span) | ProgramCommentTooLong(_, span) => span.clone(),
+            MissingLabel(_, at)
+            | DuplicateLabel { at, .. }
+            | ProgramTooLong(_, _, at)
+            | DuplicateConstant { at, .. } => Some(at.clone()),
+            ExprError(e) => Some(e.span.clone()),
+            IOError(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("{kind} at [{}..{})", .span.columns.start, .span.columns.end)]
+pub struct ExprError {
+    pub kind: ExprErrorKind,
+    /// The expression, or the constant definition it failed in
+    pub span: Span,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExprErrorKind {
+    #[error("The constant '{0}' is not defined")]
+    UnknownConstant(String),
+    #[error("The label '{0}' is missing")]
+    MissingLabel(String),
+    #[error("The constant '{0}' is defined in terms of itself")]
+    RecursiveConstant(String),
+    #[error("Arithmetic overflow")]
+    Overflow,
+    #[error("Division by zero")]
+    DivisionByZero,
+    #[error("The value {0} does not fit in a {1} bytes parameter")]
+    DoesNotFit(i64, usize),
+}
@@ -0,0 +1,185 @@
+//! Links a compiled champion's code back to its source.
+//!
+//! Debug information is stored in a line based sidecar file:
+//! - a `corewa-rs debug info v1` header
+//! - an optional `file <path>` line naming the source file
+//! - `map <start> <end> <line> <column>` lines for each range of code
+//! - `label <offset> <name>` lines for each label
+
+use super::{
+    compiler::Listing,
+    lexer::{Term, Tokenizer},
+};
+use crate::vm::profiler::{sort_hot_spots, Counters, HotSpot};
+
+use std::{
+    collections::BTreeMap,
+    io::{self, BufRead, Write},
+    ops::Range,
+};
+
+const HEADER: &str = "corewa-rs debug info v1";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DebugInfo {
+    pub file: Option<String>,
+    /// Sorted by code offset, without overlaps
+    pub mappings: Vec<SourceMapping>,
+    pub labels: Vec<(String, usize)>,
+}
+
+/// A range of code generated by a single source line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceMapping {
+    pub code: Range<usize>,
+    /// Starting at 1
+    pub line: usize,
+    /// The byte offset of the operation in its line, starting at 0
+    pub column: usize,
+}
+
+impl DebugInfo {
+    /// Builds the debug information of a champion from its compilation
+    /// listing and the source it was compiled from
+    pub fn new(listing: &Listing, source: &str, file: Option<String>) -> Self {
+        let source_lines = source.lines().collect::<Vec<_>>();
+
+        let mappings = listing
+            .lines
+            .iter()
+            .filter(|line| !line.bytes.is_empty())
+            .map(|line| SourceMapping {
+                code: line.offset..line.offset + line.bytes.len(),
+                line: line.line,
+                column: source_lines
+                    .get(line.line - 1)
+                    .map_or(0, |text| code_column(text)),
+            })
+            .collect();
+
+        Self {
+            file,
+            mappings,
+            labels: listing.symbols.clone(),
+        }
+    }
+
+    /// The source location of the code at `offset`
+    pub fn locate(&self, offset: usize) -> Option<&SourceMapping> {
+        let idx = self
+            .mappings
+            .partition_point(|mapping| mapping.code.end <= offset);
+
+        self.mappings
+            .get(idx)
+            .filter(|mapping| mapping.code.contains(&offset))
+    }
+
+    /// Groups a player's profile by the source lines of its code, hottest
+    /// first. Offsets outside of the code are kept apart
+    pub fn hot_spots(&self, offsets: &BTreeMap<usize, Counters>) -> Vec<HotSpot> {
+        let mut by_code = BTreeMap::<usize, HotSpot>::new();
+        for (offset, counters) in offsets {
+            let (start, line) = match self.locate(*offset) {
+                Some(mapping) => (mapping.code.start, Some(mapping.line)),
+                None => (*offset, None),
+            };
+            by_code
+                .entry(start)
+                .or_insert_with(|| HotSpot {
+                    offset: start,
+                    line,
+                    counters: Counters::default(),
+                })
+                .counters
+                .add(counters);
+        }
+
+        let mut hot_spots = by_code.into_values().collect::<Vec<_>>();
+        sort_hot_spots(&mut hot_spots);
+        hot_spots
+    }
+
+    pub fn write(&self, mut out: impl Write) -> io::Result<()> {
+        writeln!(out, "{}", HEADER)?;
+        if let Some(file) = &self.file {
+            writeln!(out, "file {}", file)?;
+        }
+        for mapping in &self.mappings {
+            writeln!(
+                out,
+                "map {} {} {} {}",
+                mapping.code.start, mapping.code.end, mapping.line, mapping.column
+            )?;
+        }
+        for (label, offset) in &self.labels {
+            writeln!(out, "label {} {}", offset, label)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read(input: impl BufRead) -> Result<Self, DebugInfoError> {
+        let mut lines = input.lines();
+
+        let header = lines.next().transpose()?;
+        if header.as_deref() != Some(HEADER) {
+            return Err(DebugInfoError::InvalidHeader);
+        }
+
+        let mut debug_info = Self::default();
+
+        for (idx, line) in lines.enumerate() {
+            let line = line?;
+            // The header is on the first line
+            let line_no = idx + 2;
+            let invalid = || DebugInfoError::InvalidLine(line_no);
+
+            let (kind, rest) = line.split_once(' ').ok_or_else(invalid)?;
+            match kind {
+                "file" => debug_info.file = Some(String::from(rest)),
+                "map" => {
+                    let numbers = rest
+                        .split(' ')
+                        .map(str::parse)
+                        .collect::<Result<Vec<usize>, _>>()
+                        .map_err(|_| invalid())?;
+                    match numbers[..] {
+                        [start, end, line, column] => debug_info.mappings.push(SourceMapping {
+                            code: start..end,
+                            line,
+                            column,
+                        }),
+                        _ => return Err(invalid()),
+                    }
+                }
+                "label" => {
+                    let (offset, label) = rest.split_once(' ').ok_or_else(invalid)?;
+                    let offset = offset.parse().map_err(|_| invalid())?;
+                    debug_info.labels.push((String::from(label), offset));
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(debug_info)
+    }
+}
+
+/// Where the operation or directive of a line starts, after any label
+fn code_column(text: &str) -> usize {
+    Tokenizer::new(text)
+        .map_while(Result::ok)
+        .find(|token| token.term != Term::LabelDef)
+        .map_or(0, |token| token.range.start)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DebugInfoError {
+    #[error("IO error while reading debug info: {0}")]
+    IOError(#[from] io::Error),
+    #[error("This is not a debug info file")]
+    InvalidHeader,
+    #[error("Invalid debug info on line {0}")]
+    InvalidLine(usize),
+}
@@ -0,0 +1,153 @@
+//! Reporting every error of a champion at once, instead of stopping at the
+//! first one
+
+use super::{
+    assembler::{AssembleError, ChampionBuilder},
+    compiler::{compile_champion_with_diagnostics, CompileError, Listing},
+    loader::SourceLoader,
+    parser::parse_line,
+    preprocessor,
+    span::Span,
+    warnings::CompileWarning,
+    ReadError,
+};
+use crate::spec::VmConfig;
+
+use std::{fmt, io::Cursor, path::Path};
+
+/// An error with its location in the main source
+#[derive(Debug, thiserror::Error)]
+pub struct Diagnostic {
+    pub error: DiagnosticError,
+    /// The error's own location when it is in the main source, or the line
+    /// including the file it is in. None for errors about the whole champion
+    pub span: Option<Span>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DiagnosticError {
+    #[error(transparent)]
+    Read(#[from] ReadError),
+    #[error(transparent)]
+    Compile(#[from] CompileError),
+}
+
+impl DiagnosticError {
+    /// Where the error is, possibly in an included file
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            DiagnosticError::Read(e) => e.span(),
+            DiagnosticError::Compile(e) => e.span(),
+        }
+    }
+}
+
+impl Diagnostic {
+    /// Errors located in included files are shown on `site`, the main source
+    /// line they end up on
+    pub fn new(error: impl Into<DiagnosticError>, site: Option<Span>) -> Self {
+        let error = error.into();
+        let span = match error.span() {
+            Some(span) if span.file.is_none() => Some(span),
+            _ => site,
+        };
+
+        Self { error, span }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)?;
+
+        // Parse, preprocessing and expression errors already mention their line
+        let located = matches!(
+            self.error,
+            DiagnosticError::Read(
+                ReadError::ParseError(..)
+                    | ReadError::ExpandedParseError(..)
+                    | ReadError::PreprocessError(_)
+            ) | DiagnosticError::Compile(CompileError::ExprError(_))
+        );
+        match self.error.span().or_else(|| self.span.clone()) {
+            Some(span) if !located => write!(f, " ({})", span),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A champion compiled without errors
+#[derive(Debug)]
+pub struct Compiled {
+    /// The champion's name, from its `.name` directive
+    pub name: String,
+    pub byte_code: Vec<u8>,
+    /// What was emitted for each source line
+    pub listing: Listing,
+    pub warnings: Vec<CompileWarning>,
+}
+
+/// Compiles a champion, recovering from errors at line boundaries to report
+/// all of them. Returns the compiled champion when there were none, and the
+/// diagnostics sorted by line otherwise
+pub fn compile_with_diagnostics(
+    source: &str,
+    path: Option<&Path>,
+    loader: &impl SourceLoader,
+    config: &VmConfig,
+) -> Result<Compiled, Vec<Diagnostic>> {
+    let (lines, errors) = preprocessor::expand_lossy(source, path, loader, config);
+
+    let mut diagnostics = errors
+        .into_iter()
+        .map(|e| Diagnostic::new(ReadError::from(e), None))
+        .collect::<Vec<_>>();
+    let mut champ_builder = ChampionBuilder::default();
+
+    for line in lines {
+        let site = Some(line.origin.site.clone());
+
+        let parsed_line = match parse_line(&line.text) {
+            Ok(parsed_line) => parsed_line,
+            Err(e) => {
+                diagnostics.push(Diagnostic::new(ReadError::parse(e, &line), site));
+                continue;
+            }
+        };
+
+        if let Err(e) = champ_builder.assemble(parsed_line, &line.origin) {
+            diagnostics.push(Diagnostic::new(ReadError::from(e), site));
+        }
+    }
+
+    let (champion, errors) = champ_builder.finish_lossy();
+    diagnostics.extend(
+        errors
+            .into_iter()
+            .map(|e: AssembleError| Diagnostic::new(ReadError::from(e), None)),
+    );
+
+    let name = champion.name.clone();
+    let mut byte_code = Cursor::new(Vec::new());
+    let compiled = match compile_champion_with_diagnostics(&mut byte_code, champion, config) {
+        Ok((_, listing, warnings)) => Some((listing, warnings)),
+        Err(errors) => {
+            diagnostics.extend(errors);
+            None
+        }
+    };
+
+    match compiled {
+        Some((listing, warnings)) if diagnostics.is_empty() => Ok(Compiled {
+            name,
+            byte_code: byte_code.into_inner(),
+            listing,
+            warnings,
+        }),
+        _ => {
+            // Errors about the whole champion come first
+            diagnostics.sort_by_key(|diagnostic| diagnostic.span.as_ref().map(|span| span.line));
+            Err(diagnostics)
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use crate::{
+    spec::{OpType, VmConfig},
+    vm::{
+        decoder::{Decode, Read},
+        loader::{parse_champion, ChampionError},
+        types::Instruction,
+    },
+};
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+    fmt,
+    ops::Index,
+};
+
+/// The number of bytes per `.code` directive
+const RAW_CODE_LINE_WIDTH: usize = 16;
+
+/// A champion turned back into assembly source.
+/// Its `Display` implementation yields source code that assembles to the
+/// original bytecode
+#[derive(Debug)]
+pub struct Disassembly {
+    pub name: String,
+    pub comment: String,
+    pub items: Vec<Item>,
+    /// Synthesized labels by code offset
+    pub labels: BTreeMap<usize, String>,
+    pub code_size: usize,
+}
+
+#[derive(Debug)]
+pub struct Item {
+    pub offset: usize,
+    pub kind: ItemKind,
+}
+
+#[derive(Debug)]
+pub enum ItemKind {
+    /// A decoded instruction. Jumps and forks to the start of another item
+    /// reference it with a label
+    Instruction {
+        instr: Instruction,
+        target: Option<String>,
+    },
+    /// Bytes that do not decode to an instruction
+    RawCode(Vec<u8>),
+}
+
+/// Disassembles a champion, decoding registers valid in arenas using `config`
+pub fn disassemble(program: &[u8], config: &VmConfig) -> Result<Disassembly, DisassembleError> {
+    let champion = parse_champion(program)?;
+
+    for text in [&champion.name, &champion.comment] {
+        if text.contains(['"', '\n']) {
+            return Err(DisassembleError::UnrepresentableString(text.clone()));
+        }
+    }
+
+    let code = Code(champion.code);
+    let decoded = decode_all(&code, config);
+
+    // Every item start is a valid label position, and so is every byte of raw
+    // code since `.code` directives can be split anywhere
+    let label_positions = decoded
+        .iter()
+        .map(|(offset, _)| *offset)
+        .chain(std::iter::once(code.0.len()))
+        .collect::<BTreeSet<_>>();
+
+    let labels = decoded
+        .iter()
+        .filter_map(|(offset, instr)| jump_target(*offset, instr.as_ref()?))
+        .filter(|target| label_positions.contains(target))
+        .map(|target| (target, format!("l{}", target)))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut items: Vec<Item> = Vec::new();
+    for (offset, instr) in decoded {
+        match instr {
+            Some(instr) => {
+                let target = jump_target(offset, &instr).and_then(|target| labels.get(&target));
+                items.push(Item {
+                    offset,
+                    kind: ItemKind::Instruction {
+                        target: target.cloned(),
+                        instr,
+                    },
+                });
+            }
+            None => {
+                let byte = code.0[offset];
+                match items.last_mut() {
+                    Some(Item {
+                        offset: start,
+                        kind: ItemKind::RawCode(bytes),
+                    }) if !labels.contains_key(&offset)
+                        && offset == *start + bytes.len()
+                        && bytes.len() < RAW_CODE_LINE_WIDTH =>
+                    {
+                        bytes.push(byte)
+                    }
+                    _ => items.push(Item {
+                        offset,
+                        kind: ItemKind::RawCode(vec![byte]),
+                    }),
+                }
+            }
+        }
+    }
+
+    Ok(Disassembly {
+        name: champion.name,
+        comment: champion.comment,
+        items,
+        labels,
+        code_size: code.0.len(),
+    })
+}
+
+/// The offsets of the bytes that cannot be decoded when going through `code`
+/// linearly
+pub(super) fn undecodable_offsets(code: &[u8], config: &VmConfig) -> Vec<usize> {
+    decode_all(&Code(code), config)
+        .into_iter()
+        .filter(|(_, instr)| instr.is_none())
+        .map(|(offset, _)| offset)
+        .collect()
+}
+
+/// Decodes the code linearly. Undecodable bytes are yielded one by one with
+/// no instruction
+fn decode_all(code: &Code<'_>, config: &VmConfig) -> Vec<(usize, Option<Instruction>)> {
+    let mut decoded = Vec::new();
+    let mut offset = 0;
+
+    while offset < code.0.len() {
+        let instr = code
+            .decode_op(offset)
+            .ok()
+            .and_then(|op| code.decode_instr(op, offset, config.reg_count).ok())
+            .filter(|instr| offset + instr.byte_size <= code.0.len());
+
+        let size = instr.as_ref().map_or(1, |instr| instr.byte_size);
+        decoded.push((offset, instr));
+        offset += size;
+    }
+
+    decoded
+}
+
+fn jump_target(offset: usize, instr: &Instruction) -> Option<usize> {
+    match instr.kind {
+        OpType::Zjmp | OpType::Fork | OpType::Lfork => {
+            usize::try_from(offset as isize + instr.params[0].value as isize).ok()
+        }
+        _ => None,
+    }
+}
+
+/// A champion's code, reading as zeros past its end
+pub(super) struct Code<'a>(pub(super) &'a [u8]);
+
+impl Index<usize> for Code<'_> {
+    type Output = u8;
+
+    fn index(&self, idx: usize) -> &u8 {
+        self.0.get(idx).unwrap_or(&0)
+    }
+}
+
+impl Read for Code<'_> {
+    fn read_i32(&self, at: usize) -> i32 {
+        i32::from_be_bytes([self[at], self[at + 1], self[at + 2], self[at + 3]])
+    }
+
+    fn read_i16(&self, at: usize) -> i16 {
+        i16::from_be_bytes([self[at], self[at + 1]])
+    }
+}
+
+impl fmt::Display for ItemKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ItemKind::Instruction {
+                instr,
+                target: Some(label),
+            } => write!(f, "{} %:{}", instr.kind.to_string().to_lowercase(), label),
+            ItemKind::Instruction { instr, .. } => write!(f, "{}", instr),
+            ItemKind::RawCode(bytes) => {
+                write!(f, ".code")?;
+                for byte in bytes {
+                    write!(f, " 0x{:02x}", byte)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Disassembly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, ".name \"{}\"", self.name)?;
+        writeln!(f, ".comment \"{}\"", self.comment)?;
+        writeln!(f)?;
+
+        for item in &self.items {
+            if let Some(label) = self.labels.get(&item.offset) {
+                writeln!(f, "{}:", label)?;
+            }
+            writeln!(f, "\t{}", item.kind)?;
+        }
+
+        if let Some(label) = self.labels.get(&self.code_size) {
+            writeln!(f, "{}:", label)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DisassembleError {
+    #[error("Invalid champion: {0}")]
+    InvalidChampion(#[from] ChampionError),
+    #[error("'{0}' contains characters that cannot be written in a quoted string")]
+    UnrepresentableString(String),
+}
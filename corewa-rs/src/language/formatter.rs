@@ -0,0 +1,281 @@
+//! Canonical layout of champion sources.
+//!
+//! - Directives and constant assignments start at the first column
+//! - Operations are aligned in columns: labels, mnemonics, then operands
+//! - Operands are separated by `, ` and binary operators by spaces
+//! - Decimal numbers lose their `0d` prefix and leading zeros, hexadecimal
+//!   numbers are lowercased and lose their leading zeros
+//! - Comments are kept, trailing ones one space after the code
+//! - Consecutive blank lines are merged, leading and trailing ones removed
+//!
+//! Only whitespace and the spelling of numbers change, so a formatted source
+//! compiles to the same bytecode. Formatting a formatted source is a no-op.
+
+use super::lexer::{LexerError, Term, Token, Tokenizer};
+
+/// The minimum column of mnemonics, leaving room for short labels
+const MIN_MNEMONIC_COLUMN: usize = 8;
+/// The length of the longest mnemonic
+const MIN_MNEMONIC_WIDTH: usize = 5;
+
+/// Formats a champion's source, failing on lines that cannot be tokenized
+pub fn format(source: &str) -> Result<String, FormatError> {
+    let lines = source
+        .lines()
+        .enumerate()
+        .map(|(idx, text)| {
+            layout(text).map_err(|error| FormatError {
+                error,
+                line: idx + 1,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mnemonic_column = lines
+        .iter()
+        .filter_map(|line| match &line.kind {
+            LineKind::Code {
+                label: Some(label),
+                op: Some(_),
+            } => Some(label.len() + 1),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+        .max(MIN_MNEMONIC_COLUMN);
+    let operand_column = lines
+        .iter()
+        .filter_map(|line| match &line.kind {
+            LineKind::Code {
+                op: Some((mnemonic, _)),
+                ..
+            } => Some(mnemonic.len() + 1),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+        .max(MIN_MNEMONIC_WIDTH + 1);
+
+    let mut formatted = String::with_capacity(source.len());
+    let mut pending_blank = false;
+
+    for Line { kind, comment } in lines {
+        let code = match kind {
+            LineKind::Blank => {
+                pending_blank = !formatted.is_empty();
+                continue;
+            }
+            LineKind::Comment { indented: false } => String::new(),
+            LineKind::Comment { indented: true } => " ".repeat(mnemonic_column),
+            LineKind::TopLevel(code) => code,
+            LineKind::Code { label, op: None } => label.unwrap_or_default(),
+            LineKind::Code {
+                label,
+                op: Some((mnemonic, operands)),
+            } => {
+                let label = label.unwrap_or_default();
+                if operands.is_empty() {
+                    format!("{:<width$}{}", label, mnemonic, width = mnemonic_column)
+                } else {
+                    format!(
+                        "{:<label_width$}{:<mnemonic_width$}{}",
+                        label,
+                        mnemonic,
+                        operands,
+                        label_width = mnemonic_column,
+                        mnemonic_width = operand_column,
+                    )
+                }
+            }
+        };
+
+        if pending_blank {
+            formatted.push('\n');
+            pending_blank = false;
+        }
+
+        formatted.push_str(&code);
+        if let Some(comment) = comment {
+            if !code.trim().is_empty() {
+                formatted.push(' ');
+            }
+            formatted.push_str(&comment);
+        }
+        formatted.push('\n');
+    }
+
+    Ok(formatted)
+}
+
+struct Line {
+    kind: LineKind,
+    comment: Option<String>,
+}
+
+enum LineKind {
+    Blank,
+    /// A line with only a comment, which is kept at the first column or
+    /// indented like operations
+    Comment {
+        indented: bool,
+    },
+    /// Directives and constant assignments, at the first column
+    TopLevel(String),
+    /// Operations, macro calls and `.code` directives, with their label
+    Code {
+        label: Option<String>,
+        op: Option<(String, String)>,
+    },
+}
+
+fn layout(text: &str) -> Result<Line, LexerError> {
+    let mut tokens = Tokenizer::new(text).collect::<Result<Vec<_>, _>>()?;
+
+    let comment = match tokens.last() {
+        Some(token) if token.term == Term::Comment => {
+            let comment = text[token.range.clone()].trim_end();
+            tokens.pop();
+            Some(String::from(comment))
+        }
+        _ => None,
+    };
+
+    let kind = match tokens.first().map(|token| token.term) {
+        None if comment.is_some() => LineKind::Comment {
+            indented: text.starts_with(char::is_whitespace),
+        },
+        None => LineKind::Blank,
+        Some(Term::CodeCmd) => LineKind::Code {
+            label: None,
+            op: Some(op(text, &tokens)),
+        },
+        Some(Term::DefineCmd) if tokens.len() > 2 => LineKind::TopLevel(format!(
+            "{} {} {}",
+            token_text(text, &tokens[0]),
+            token_text(text, &tokens[1]),
+            join(text, &tokens[2..], false),
+        )),
+        Some(term) if is_directive(term) => {
+            let directive = token_text(text, &tokens[0]);
+            let code = match join(text, &tokens[1..], false) {
+                args if args.is_empty() => directive,
+                args => format!("{} {}", directive, args),
+            };
+            LineKind::TopLevel(code)
+        }
+        Some(Term::Ident) if tokens.get(1).map(|token| token.term) == Some(Term::Equals) => {
+            LineKind::TopLevel(join(text, &tokens, false))
+        }
+        Some(Term::LabelDef) => LineKind::Code {
+            label: Some(token_text(text, &tokens[0])),
+            op: Some(&tokens[1..])
+                .filter(|rest| !rest.is_empty())
+                .map(|rest| op(text, rest)),
+        },
+        Some(_) => LineKind::Code {
+            label: None,
+            op: Some(op(text, &tokens)),
+        },
+    };
+
+    Ok(Line { kind, comment })
+}
+
+fn is_directive(term: Term) -> bool {
+    use Term::*;
+
+    matches!(
+        term,
+        ChampionNameCmd
+            | ChampionCommentCmd
+            | DefineCmd
+            | MacroCmd
+            | EndMacroCmd
+            | ReptCmd
+            | EndReptCmd
+            | IncludeCmd
+    )
+}
+
+/// Splits an operation in its mnemonic and its operands
+fn op(text: &str, tokens: &[Token]) -> (String, String) {
+    match tokens[0].term {
+        Term::Ident => (
+            token_text(text, &tokens[0]),
+            join(text, &tokens[1..], false),
+        ),
+        // `.code` only takes numbers, so every minus sign is a negation
+        Term::CodeCmd => (token_text(text, &tokens[0]), join(text, &tokens[1..], true)),
+        // Not an operation, it is kept on a single column
+        _ => (join(text, tokens, false), String::new()),
+    }
+}
+
+/// Lays out a sequence of tokens on a line
+fn join(text: &str, tokens: &[Token], unary_minus_only: bool) -> String {
+    let mut joined = String::new();
+    let mut previous: Option<Term> = None;
+    let mut previous_is_unary = false;
+
+    for token in tokens {
+        let term = token.term;
+        let is_unary =
+            term == Term::Minus && (unary_minus_only || !previous.is_some_and(ends_operand));
+
+        let attached = match previous {
+            None => true,
+            Some(previous) => {
+                matches!(term, Term::ParamSeparator | Term::CloseParen)
+                    || matches!(previous, Term::DirectChar | Term::OpenParen)
+                    || previous_is_unary
+            }
+        };
+        if !attached {
+            joined.push(' ');
+        }
+        joined.push_str(&token_text(text, token));
+
+        previous = Some(term);
+        previous_is_unary = is_unary;
+    }
+
+    joined
+}
+
+/// Whether a token can be the left hand side of a binary operator
+fn ends_operand(term: Term) -> bool {
+    matches!(
+        term,
+        Term::Number { .. } | Term::Ident | Term::LabelUse | Term::CloseParen
+    )
+}
+
+fn token_text(text: &str, token: &Token) -> String {
+    let raw = &text[token.range.clone()];
+
+    match token.term {
+        Term::QuotedString => format!("\"{}\"", raw),
+        Term::Number { .. } => normalize_number(raw),
+        _ => String::from(raw),
+    }
+}
+
+fn normalize_number(raw: &str) -> String {
+    let normalized = if let Some(digits) = raw.strip_prefix("0x") {
+        i64::from_str_radix(digits, 16).map(|n| format!("0x{:x}", n))
+    } else {
+        let digits = raw.strip_prefix("0d").unwrap_or(raw);
+        digits.parse::<i64>().map(|n| n.to_string())
+    };
+
+    // Numbers the parser rejects are left for it to report
+    normalized.unwrap_or_else(|_| String::from(raw))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{error} on line {line}")]
+pub struct FormatError {
+    pub error: LexerError,
+    /// Starting at 1
+    pub line: usize,
+}
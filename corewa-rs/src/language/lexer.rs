@@ -2,7 +2,7 @@
 
 const IDENT_CHARS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_0123456789";
 
-type InputRange = ::std::ops::Range<usize>;
+pub type InputRange = ::std::ops::Range<usize>;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Token {
@@ -28,13 +28,19 @@ impl Iterator for Tokenizer<'_> {
             ':' => self.lex_label_use(idx),
             ',' => self.lex_single(Term::ParamSeparator, idx),
             '%' => self.lex_single(Term::DirectChar, idx),
+            '+' => self.lex_single(Term::Plus, idx),
+            '-' => self.lex_single(Term::Minus, idx),
+            '*' => self.lex_single(Term::Star, idx),
+            '/' => self.lex_single(Term::Slash, idx),
+            '(' => self.lex_single(Term::OpenParen, idx),
+            ')' => self.lex_single(Term::CloseParen, idx),
+            '=' => self.lex_single(Term::Equals, idx),
 
             '.' => self.lex_directive(idx),
             '"' => self.lex_quoted_string(idx),
             '#' => self.lex_comment(idx),
-            '-' => self.lex_negative_number(idx),
 
-            c if c.is_digit(10) => self.lex_number(idx),
+            c if c.is_ascii_digit() => self.lex_number(idx),
             c if IDENT_CHARS.contains(c) => self.lex_ident(idx),
 
             _ => {
@@ -57,7 +63,7 @@ impl Tokenizer<'_> {
     where
         F: Fn(&(usize, char)) -> bool,
     {
-        while self.chars.peek().map_or(false, &skipper) {
+        while self.chars.peek().is_some_and(&skipper) {
             self.chars.next();
         }
     }
@@ -80,10 +86,16 @@ impl Tokenizer<'_> {
     }
 
     fn lex_directive(&mut self, idx_start: usize) -> TokenResult {
-        const DIRECTIVES: [(&str, Term); 3] = [
+        const DIRECTIVES: [(&str, Term); 9] = [
             (".name", Term::ChampionNameCmd),
             (".comment", Term::ChampionCommentCmd),
             (".code", Term::CodeCmd),
+            (".define", Term::DefineCmd),
+            (".macro", Term::MacroCmd),
+            (".endm", Term::EndMacroCmd),
+            (".rept", Term::ReptCmd),
+            (".endr", Term::EndReptCmd),
+            (".include", Term::IncludeCmd),
         ];
 
         let current_str = &self.input[idx_start..];
@@ -128,15 +140,6 @@ impl Tokenizer<'_> {
         Ok(Term::Comment.at(idx_start..self.input.len()))
     }
 
-    fn lex_negative_number(&mut self, idx_start: usize) -> TokenResult {
-        self.chars.next(); // consume -
-
-        match self.chars.peek() {
-            Some((_, c)) if c.is_digit(10) => self.lex_number(idx_start),
-            _ => Err(LexerErrorKind::NoNumberAfterMinus.at(idx_start..idx_start + 1)),
-        }
-    }
-
     fn lex_number(&mut self, idx_start: usize) -> TokenResult {
         let (_, first_digit) = self.chars.next().expect("Empty input while lexing number");
 
@@ -188,6 +191,18 @@ pub enum Term {
     ChampionCommentCmd,
     #[display(fmt = "Code directive")]
     CodeCmd,
+    #[display(fmt = "Define directive")]
+    DefineCmd,
+    #[display(fmt = "Macro directive")]
+    MacroCmd,
+    #[display(fmt = "End of macro directive")]
+    EndMacroCmd,
+    #[display(fmt = "Repeat directive")]
+    ReptCmd,
+    #[display(fmt = "End of repeat directive")]
+    EndReptCmd,
+    #[display(fmt = "Include directive")]
+    IncludeCmd,
     #[display(fmt = "Quoted string")]
     QuotedString,
     #[display(fmt = "Comment")]
@@ -200,6 +215,20 @@ pub enum Term {
     ParamSeparator,
     #[display(fmt = "Direct character")]
     DirectChar,
+    #[display(fmt = "Plus sign")]
+    Plus,
+    #[display(fmt = "Minus sign")]
+    Minus,
+    #[display(fmt = "Multiplication sign")]
+    Star,
+    #[display(fmt = "Division sign")]
+    Slash,
+    #[display(fmt = "Opening parenthesis")]
+    OpenParen,
+    #[display(fmt = "Closing parenthesis")]
+    CloseParen,
+    #[display(fmt = "Equal sign")]
+    Equals,
     #[display(fmt = "Number")]
     Number { base: NumberBase },
     #[display(fmt = "Identifier")]
@@ -236,8 +265,6 @@ pub enum LexerErrorKind {
     InvalidDirective,
     #[error("Missing end quote for string")]
     UnclosedQuotedString,
-    #[error("Missing number after minus sign")]
-    NoNumberAfterMinus,
     #[error("Invalid number")]
     InvalidNumberAfterBase,
     #[error("Invalid number base: {0}")]
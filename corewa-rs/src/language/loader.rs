@@ -0,0 +1,83 @@
+//! Loading the files referenced by `.include` directives
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+/// Provides the source of included files
+pub trait SourceLoader {
+    /// Loads the source at `path`, already resolved relative to the
+    /// including file
+    fn load(&self, path: &Path) -> io::Result<String>;
+}
+
+/// Loads sources from the filesystem, relative to the working directory
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileLoader;
+
+impl SourceLoader for FileLoader {
+    fn load(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+}
+
+/// Rejects every include, for sources that must not reach the filesystem
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoLoader;
+
+impl SourceLoader for NoLoader {
+    fn load(&self, _path: &Path) -> io::Result<String> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Including files is not allowed here",
+        ))
+    }
+}
+
+/// Loads sources from a set of files kept in memory
+#[derive(Debug, Clone, Default)]
+pub struct MemoryLoader {
+    files: HashMap<PathBuf, String>,
+}
+
+impl MemoryLoader {
+    pub fn insert(&mut self, path: impl AsRef<Path>, source: impl Into<String>) {
+        self.files.insert(normalize(path.as_ref()), source.into());
+    }
+}
+
+impl SourceLoader for MemoryLoader {
+    fn load(&self, path: &Path) -> io::Result<String> {
+        self.files.get(&normalize(path)).cloned().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "No such file in the loaded sources",
+            )
+        })
+    }
+}
+
+/// Removes the `.` and `..` components of a path, without touching the
+/// filesystem
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
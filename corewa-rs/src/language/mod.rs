@@ -1,54 +1,143 @@
+#![allow(clippy::result_large_err)] // Errors carry their spans, and are only built once per line
+
+pub mod analysis;
 pub mod assembler;
 pub mod compiler;
+pub mod debug_info;
+pub mod diagnostics;
+pub mod disassembler;
+pub mod formatter;
 pub mod lexer;
+pub mod loader;
 pub mod parser;
+pub mod preprocessor;
+pub mod report;
+pub mod span;
 pub mod types;
+pub mod warnings;
 
 pub use parser::error_range;
 
 use assembler::{AssembleError, Champion, ChampionBuilder};
-use compiler::{compile_champion, CompileError};
+use compiler::{compile_champion, compile_champion_with_listing, CompileError, Listing};
+use loader::{NoLoader, SourceLoader};
 use parser::{parse_line, ParseError};
+use preprocessor::{ExpandedLine, Expansion, PreprocessError};
+use span::Span;
+
+use crate::spec::VmConfig;
 
-use std::io::{BufRead, BufReader, Cursor, Error as IOError, Read, Write};
+use std::{
+    io::{Cursor, Error as IOError, Read, Write},
+    path::Path,
+};
 
+/// Reads a champion without touching the filesystem: its includes are
+/// rejected. Use `read_champion_with_loader` and a `FileLoader` to allow them
 pub fn read_champion(input: impl Read) -> Result<Champion, ReadError> {
-    let mut reader = BufReader::new(input);
-    let mut buffer = String::with_capacity(128);
-    let mut line_no = 1;
+    read_champion_with_loader(input, None, &NoLoader)
+}
+
+/// Same as `read_champion` but loads included files with `loader`, relative
+/// to the champion's `path` when it is known
+pub fn read_champion_with_loader(
+    mut input: impl Read,
+    path: Option<&Path>,
+    loader: &impl SourceLoader,
+) -> Result<Champion, ReadError> {
+    let mut source = String::new();
+    input.read_to_string(&mut source)?;
 
     let mut champ_builder = ChampionBuilder::default();
 
-    while reader.read_line(&mut buffer)? > 0 {
-        let parsed_line = parse_line(&buffer).map_err(|e| ReadError::ParseError(e, line_no))?;
-        champ_builder.assemble(parsed_line)?;
-        line_no += 1;
-        buffer.clear();
+    for expanded_line in preprocessor::expand(&source, path, loader, &VmConfig::default())? {
+        let parsed_line =
+            parse_line(&expanded_line.text).map_err(|e| ReadError::parse(e, &expanded_line))?;
+        champ_builder.assemble(parsed_line, &expanded_line.origin)?;
     }
 
     Ok(champ_builder.finish()?)
 }
 
-pub fn write_champion(mut output: impl Write, champion: Champion) -> Result<usize, WriteError> {
+pub fn write_champion(output: impl Write, champion: Champion) -> Result<usize, WriteError> {
+    write_champion_with_config(output, champion, &VmConfig::default())
+}
+
+/// Same as `write_champion` but checks the champion's size against custom
+/// arena parameters
+pub fn write_champion_with_config(
+    mut output: impl Write,
+    champion: Champion,
+    config: &VmConfig,
+) -> Result<usize, WriteError> {
     let mut seek_vec = Cursor::new(Vec::with_capacity(8192));
 
-    compile_champion(&mut seek_vec, champion)?;
+    compile_champion(&mut seek_vec, champion, config)?;
 
     let data = seek_vec.get_ref();
     output.write_all(data)?;
     Ok(data.len())
 }
 
+/// Same as `write_champion_with_config` but also describes what was emitted
+/// for each source line
+pub fn write_champion_with_listing(
+    mut output: impl Write,
+    champion: Champion,
+    config: &VmConfig,
+) -> Result<(usize, Listing), WriteError> {
+    let mut seek_vec = Cursor::new(Vec::with_capacity(8192));
+
+    let (_, listing) = compile_champion_with_listing(&mut seek_vec, champion, config)?;
+
+    let data = seek_vec.get_ref();
+    output.write_all(data)?;
+    Ok((data.len(), listing))
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum ReadError {
     #[error("IO error while reading champion: {0}")]
     IOError(#[from] IOError),
-    #[error("Parse error on line {1}: {0}")]
-    ParseError(ParseError, usize),
+    /// In the main source or in an included file
+    #[error("Parse error on {1}: {0}")]
+    ParseError(ParseError, Span),
+    /// In the expansion of a macro or a repeated block, reported on its call
+    /// site
+    #[error("Parse error on {1} {2}: {0}")]
+    ExpandedParseError(ParseError, Span, Expansion),
+    #[error("Preprocessing error: {0}")]
+    PreprocessError(#[from] PreprocessError),
     #[error("Error assembling champion: {0}")]
     AssembleError(#[from] AssembleError),
 }
 
+impl ReadError {
+    /// Locates a parse error of an expanded line
+    pub fn parse(error: ParseError, line: &ExpandedLine) -> Self {
+        let span = line.origin.locate(error_range(&error, &line.text));
+
+        match &line.expansion {
+            Some(expansion) if expansion.name.is_some() => {
+                ReadError::ExpandedParseError(error, span, expansion.clone())
+            }
+            _ => ReadError::ParseError(error, span),
+        }
+    }
+
+    /// Where the error is, None for errors about the whole champion
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ReadError::ParseError(_, span) | ReadError::ExpandedParseError(_, span, _) => {
+                Some(span.clone())
+            }
+            ReadError::PreprocessError(e) => Some(e.span.clone()),
+            ReadError::AssembleError(e) => e.span(),
+            ReadError::IOError(_) => None,
+        }
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum WriteError {
     #[error("IO error while writing champion: {0}")]
@@ -56,3 +145,13 @@ pub enum WriteError {
     #[error("Error compiling champion: {0}")]
     CompileError(#[from] CompileError),
 }
+
+impl WriteError {
+    /// Where the error is, None for errors about the whole champion
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            WriteError::CompileError(e) => e.span(),
+            WriteError::IOError(_) => None,
+        }
+    }
+}
@@ -1,7 +1,7 @@
 mod combinator;
 
 use super::{
-    lexer::{LexerError, NumberBase, Term, Token, TokenResult, Tokenizer},
+    lexer::{InputRange, LexerError, NumberBase, Term, Token, TokenResult, Tokenizer},
     types::*,
 };
 use combinator::*;
@@ -12,8 +12,9 @@ pub enum ParsedLine {
     ChampionComment(String),
     Code(Vec<u8>),
     Op(Op),
-    Label(String),
-    LabelAndOp(String, Op),
+    Label(String, InputRange),
+    LabelAndOp(String, InputRange, Op),
+    Constant(String, Expr),
     Empty,
 }
 
@@ -29,21 +30,33 @@ pub fn parse_line(input: &str) -> Result<ParsedLine, ParseError> {
         Term::ChampionNameCmd => champion_name(&mut tokens).map(ParsedLine::ChampionName),
         Term::ChampionCommentCmd => champion_comment(&mut tokens).map(ParsedLine::ChampionComment),
         Term::CodeCmd => code(&mut tokens).map(ParsedLine::Code),
+        Term::DefineCmd => {
+            define(&mut tokens).map(|(name, value)| ParsedLine::Constant(name, value))
+        }
         Term::LabelDef => {
-            let label = label(&mut tokens)?;
+            let (label, at) = label(&mut tokens)?;
 
             let parsed = match tokens.peek() {
                 None
                 | Some(Ok(Token {
                     term: Term::Comment,
                     ..
-                })) => ParsedLine::Label(label),
-                _ => ParsedLine::LabelAndOp(label, op(&mut tokens)?),
+                })) => ParsedLine::Label(label, at),
+                _ => ParsedLine::LabelAndOp(label, at, op(&mut tokens)?),
             };
 
             Ok(parsed)
         }
-        Term::Ident => op(&mut tokens).map(ParsedLine::Op),
+        Term::Ident => {
+            let mut lookahead = tokens.clone();
+            lookahead.tokens.next();
+
+            if lookahead.peek_term() == Some(Term::Equals) {
+                assignment(&mut tokens).map(|(name, value)| ParsedLine::Constant(name, value))
+            } else {
+                op(&mut tokens).map(ParsedLine::Op)
+            }
+        }
         Term::Comment => return Ok(ParsedLine::Empty),
         _ => return Err(ParseError::Unexpected(first_tok)),
     }?;
@@ -74,16 +87,49 @@ fn champion_comment(input: &mut TokenStream<'_>) -> ParseResult<String> {
 
 fn code(input: &mut TokenStream<'_>) -> ParseResult<Vec<u8>> {
     input.next(Term::CodeCmd)?;
-    let numbers = number.many().parse(input)?;
+    let numbers = signed_number.many().parse(input)?;
     // TODO: Better enforce numeric limit invariants
     let as_bytes = numbers.into_iter().map(|x| x as u8).collect();
     Ok(as_bytes)
 }
 
-fn label(input: &mut TokenStream<'_>) -> ParseResult<String> {
+/// `.define NAME expression`
+fn define(input: &mut TokenStream<'_>) -> ParseResult<(String, Expr)> {
+    input.next(Term::DefineCmd)?;
+    let name = constant_name(input)?;
+    Ok((name, expression(input)?))
+}
+
+/// `NAME = expression`
+fn assignment(input: &mut TokenStream<'_>) -> ParseResult<(String, Expr)> {
+    let name = constant_name(input)?;
+    input.next(Term::Equals)?;
+    Ok((name, expression(input)?))
+}
+
+fn constant_name(input: &mut TokenStream<'_>) -> ParseResult<String> {
+    let (tok, name) = input.next_with_token(Term::Ident)?;
+
+    if is_register_name(name) {
+        Err(ParseError::InvalidConstantName(String::from(name), tok))
+    } else {
+        Ok(String::from(name))
+    }
+}
+
+/// Registers take precedence over constants in parameters so constants
+/// cannot be named like them
+fn is_register_name(ident: &str) -> bool {
+    ident
+        .strip_prefix('r')
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// The label with the range of its definition, colon included
+fn label(input: &mut TokenStream<'_>) -> ParseResult<(String, InputRange)> {
     input
-        .next(Term::LabelDef)
-        .map(|label_str| String::from(&label_str[..label_str.len() - 1]))
+        .next_with_token(Term::LabelDef)
+        .map(|(tok, label_str)| (String::from(&label_str[..label_str.len() - 1]), tok.range))
 }
 
 fn label_param(input: &mut TokenStream<'_>) -> ParseResult<String> {
@@ -112,17 +158,12 @@ fn number(input: &mut TokenStream<'_>) -> ParseResult<i64> {
             range,
         } => {
             let mut number_as_str = &input.input[range];
-            let negative = number_as_str.starts_with('-');
-            if negative {
-                number_as_str = &number_as_str[1..];
-            }
             if number_as_str.starts_with("0d") || number_as_str.starts_with("0x") {
                 number_as_str = &number_as_str[2..];
             }
 
             i64::from_str_radix(number_as_str, base.radix())
                 .map_err(|e| ParseError::ParseIntError(e, tok))
-                .map(|x| if negative { -x } else { x })
         }
         _ => Err(expected_either((
             ParseError::ExpectedButGot(
@@ -141,6 +182,115 @@ fn number(input: &mut TokenStream<'_>) -> ParseResult<i64> {
     }
 }
 
+fn signed_number(input: &mut TokenStream<'_>) -> ParseResult<i64> {
+    if input.peek_term() == Some(Term::Minus) {
+        input.next(Term::Minus)?;
+        number(input).map(|x| -x)
+    } else {
+        number(input)
+    }
+}
+
+/// Sums and differences, the lowest precedence level of expressions
+fn expression(input: &mut TokenStream<'_>) -> ParseResult<Expr> {
+    binary_chain(input, product, |term| match term {
+        Term::Plus => Some(BinOp::Add),
+        Term::Minus => Some(BinOp::Sub),
+        _ => None,
+    })
+}
+
+fn product(input: &mut TokenStream<'_>) -> ParseResult<Expr> {
+    binary_chain(input, unary, |term| match term {
+        Term::Star => Some(BinOp::Mul),
+        Term::Slash => Some(BinOp::Div),
+        _ => None,
+    })
+}
+
+/// Left associative sequence of operands separated by operators of a same
+/// precedence level
+fn binary_chain(
+    input: &mut TokenStream<'_>,
+    operand: fn(&mut TokenStream<'_>) -> ParseResult<Expr>,
+    operator: fn(Term) -> Option<BinOp>,
+) -> ParseResult<Expr> {
+    let mut lhs = operand(input)?;
+
+    while let Some(op) = input.peek_term().and_then(operator) {
+        input.tokens.next();
+        let rhs = operand(input)?;
+        lhs = Expr {
+            at: lhs.at.start..rhs.at.end,
+            kind: ExprKind::Binary(op, Box::new(lhs), Box::new(rhs)),
+        };
+    }
+
+    Ok(lhs)
+}
+
+fn unary(input: &mut TokenStream<'_>) -> ParseResult<Expr> {
+    if input.peek_term() != Some(Term::Minus) {
+        return primary(input);
+    }
+
+    let (minus, _) = input.next_with_token(Term::Minus)?;
+    let operand = unary(input)?;
+    let at = minus.range.start..operand.at.end;
+
+    let kind = match operand.kind {
+        // Negative literals
+        ExprKind::Number(n) => ExprKind::Number(-n),
+        _ => ExprKind::Neg(Box::new(operand)),
+    };
+
+    Ok(Expr { kind, at })
+}
+
+fn primary(input: &mut TokenStream<'_>) -> ParseResult<Expr> {
+    let token = match input.peek() {
+        None => return Err(ParseError::ExpectedOperandButGotEof),
+        Some(tok_result) => tok_result.clone()?,
+    };
+    let at = token.range.clone();
+
+    let kind = match token.term {
+        Term::Number { .. } => ExprKind::Number(number(input)?),
+        Term::LabelUse => ExprKind::Label(label_param(input)?),
+        Term::Ident if !is_register_name(&input.input[token.range.clone()]) => {
+            ExprKind::Constant(String::from(input.next(Term::Ident)?))
+        }
+        Term::OpenParen => {
+            input.next(Term::OpenParen)?;
+            let inner = expression(input)?;
+            let (close, _) = input.next_with_token(Term::CloseParen)?;
+
+            return Ok(Expr {
+                kind: inner.kind,
+                at: at.start..close.range.end,
+            });
+        }
+        _ => return Err(ParseError::ExpectedOperand(token)),
+    };
+
+    Ok(Expr { kind, at })
+}
+
+/// Keeps the simplest forms of operands for bare numbers and labels
+fn operand<T: From<(String, InputRange)> + From<i64> + From<Expr>>(expr: Expr) -> T {
+    match expr {
+        Expr {
+            kind: ExprKind::Number(n),
+            ..
+        } => T::from(n),
+        Expr {
+            kind: ExprKind::Label(label),
+            at,
+        } => T::from((label, at)),
+        expr => T::from(expr),
+    }
+}
+
 fn register(input: &mut TokenStream<'_>) -> ParseResult<Register> {
     let (tok, reg_str) = input.next_with_token(Term::Ident)?;
     let mut chars = reg_str.chars();
@@ -161,19 +311,11 @@ fn register(input: &mut TokenStream<'_>) -> ParseResult<Register> {
 
 fn direct(input: &mut TokenStream<'_>) -> ParseResult<Direct> {
     input.next(Term::DirectChar)?;
-    label_param
-        .map(Direct::Label)
-        .or(number.map(Direct::Numeric))
-        .map_err(expected_either)
-        .parse(input)
+    expression(input).map(operand)
 }
 
 fn indirect(input: &mut TokenStream<'_>) -> ParseResult<Indirect> {
-    label_param
-        .map(Indirect::Label)
-        .or(number.map(Indirect::Numeric))
-        .map_err(expected_either)
-        .parse(input)
+    expression(input).map(operand)
 }
 
 fn reg_dir(input: &mut TokenStream<'_>) -> ParseResult<RegDir> {
@@ -261,6 +403,13 @@ impl TokenStream<'_> {
         self.tokens.peek()
     }
 
+    fn peek_term(&mut self) -> Option<Term> {
+        match self.peek() {
+            Some(Ok(token)) => Some(token.term),
+            _ => None,
+        }
+    }
+
     fn next(&mut self, term: Term) -> ParseResult<&str> {
         self.next_with_token(term).map(|(_, s)| s)
     }
@@ -295,6 +444,9 @@ pub enum ParseError {
     ParseIntError(std::num::ParseIntError, Token),
     RegisterParseIntError(std::num::ParseIntError, Token),
     InvalidOpMnemonic(String, Token),
+    ExpectedOperand(Token),
+    ExpectedOperandButGotEof,
+    InvalidConstantName(String, Token),
 }
 
 fn expected_either((e1, e2): (ParseError, ParseError)) -> ParseError {
@@ -355,28 +507,48 @@ impl fmt::Display for ParseError {
             ParseIntError(err, _) => write!(f, "Invalid number: {}", err),
             RegisterParseIntError(err, _) => write!(f, "Invalid register number: {}", err),
             InvalidOpMnemonic(mnemonic, _) => write!(f, "'{}' is not a valid operation", mnemonic),
+            ExpectedOperand(token) => write!(
+                f,
+                "Expected a number, a label, a constant or a parenthesized expression but got '{}'",
+                token.term
+            ),
+            ExpectedOperandButGotEof => write!(f, "Expected an operand before the end of the line"),
+            InvalidConstantName(name, _) => {
+                write!(
+                    f,
+                    "'{}' is a register name and cannot name a constant",
+                    name
+                )
+            }
         }
     }
 }
 
-pub fn error_range(err: &ParseError) -> (usize, Option<usize>) {
+/// The byte range of an error in the line that was parsed. Errors about a
+/// missing token point right after the end of the line's code
+pub fn error_range(err: &ParseError, line: &str) -> InputRange {
     use ParseError::*;
 
     match err {
-        LexerError(err) => (err.at.start, Some(err.at.end)),
-        ExpectedButGotEof(_) => (0, None),
+        LexerError(err) => err.at.clone(),
+        ExpectedButGotEof(_) | ExpectedOperandButGotEof => {
+            let end = line.trim_end().len();
+            end..end
+        }
         ExpectedOneOf(errors) => {
-            let ranges = errors.iter().map(error_range).collect::<Vec<_>>();
+            let ranges = errors
+                .iter()
+                .map(|err| error_range(err, line))
+                .collect::<Vec<_>>();
 
-            let min_start = ranges
+            let start = ranges
                 .iter()
-                .map(|(start, _)| *start)
+                .map(|range| range.start)
                 .min()
                 .expect("ExpectedOneOf has been given an empty sequence");
+            let end = ranges.iter().map(|range| range.end).max().unwrap_or(start);
 
-            let max_end = ranges.iter().flat_map(|(_, opt_end)| *opt_end).max();
-
-            (min_start, max_end)
+            start..end
         }
         RemainingInput(token)
         | Unexpected(token)
@@ -386,6 +558,8 @@ pub fn error_range(err: &ParseError) -> (usize, Option<usize>) {
         | MissingRegisterPrefix(token)
         | ParseIntError(_, token)
         | RegisterParseIntError(_, token)
-        | InvalidOpMnemonic(_, token) => (token.range.start, Some(token.range.end)),
+        | InvalidOpMnemonic(_, token)
+        | ExpectedOperand(token)
+        | InvalidConstantName(_, token) => token.range.clone(),
     }
 }
@@ -0,0 +1,706 @@
+//! Macro expansion and file inclusion, applied to the source before it is
+//! parsed.
+//!
+//! - `.include "path"` inserts the lines of another file, resolved relative
+//!   to the including one
+//! - `.macro name param1, param2` ... `.endm` defines a macro. Each call
+//!   (`name arg1, arg2`) expands to its body, with every identifier naming a
+//!   parameter replaced by the matching argument
+//! - `.rept count` ... `.endr` repeats the enclosed lines
+//!
+//! Labels declared inside a macro or a repeated block are renamed for each
+//! expansion so that they do not clash with each other. Expansions are given
+//! a budget of lines sized from the largest champion the arena accepts, so
+//! that nested blocks cannot multiply into billions of lines.
+
+use super::{
+    lexer::{Term, Token, Tokenizer},
+    loader::{self, SourceLoader},
+    span::{Origin, Span},
+};
+use crate::spec::VmConfig;
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, io,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+/// Nested expansions deeper than this are considered infinitely recursive
+const MAX_EXPANSION_DEPTH: usize = 64;
+const MAX_REPEAT_COUNT: usize = 4096;
+/// Every byte of code takes at least one line, so expansions can only produce
+/// this many lines per byte a champion can hold, leaving room for labels,
+/// comments and blank lines
+const EXPANDED_LINES_PER_BYTE: usize = 8;
+
+/// A line of the expanded source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpandedLine {
+    pub text: String,
+    /// The line of the main source, starting at 1. Expanded and included
+    /// lines are numbered after the call site of their outermost expansion or
+    /// inclusion
+    pub line: usize,
+    pub expansion: Option<Expansion>,
+    /// Where errors in the text are reported
+    pub origin: Origin,
+}
+
+/// Where an expanded line comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expansion {
+    /// The expanded macro, or `.rept` for repeated blocks. None for lines
+    /// that were only included
+    pub name: Option<String>,
+    /// The line where the text was written, starting at 1
+    pub body_line: usize,
+    /// The included file where the text was written, if not the main source
+    pub file: Option<PathBuf>,
+}
+
+/// Expands the source of a champion. Its included files are resolved relative
+/// to `path`, or to the root of `loader` when it is unknown. The lines produced
+/// by expansions are limited according to `config.champ_max_size`
+pub fn expand(
+    source: &str,
+    path: Option<&Path>,
+    loader: &impl SourceLoader,
+    config: &VmConfig,
+) -> Result<Vec<ExpandedLine>, PreprocessError> {
+    let (expanded, errors) = expand_lossy(source, path, loader, config);
+
+    match errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(expanded),
+    }
+}
+
+/// Expands the source of a champion like `expand`, skipping the directives
+/// and calls that fail, along with their blocks, to report all the errors.
+/// Errors that are repeated by several expansions are only reported once.
+/// Expanding stops at the first expansion that is too deep or too large
+pub fn expand_lossy(
+    source: &str,
+    path: Option<&Path>,
+    loader: &impl SourceLoader,
+    config: &VmConfig,
+) -> (Vec<ExpandedLine>, Vec<PreprocessError>) {
+    let lines = source_lines(source, None);
+    let main_path = path.map(loader::normalize);
+
+    let mut expander = Expander {
+        loader,
+        macros: HashMap::new(),
+        expansion_count: 0,
+        line_budget: config.champ_max_size * EXPANDED_LINES_PER_BYTE,
+        include_stack: main_path.iter().cloned().collect(),
+        main_path,
+        errors: Vec::new(),
+    };
+    let mut expanded = Vec::with_capacity(lines.len());
+    if let Err(error) = expander.expand_block(&lines, None, 0, &mut expanded) {
+        expander.report(error);
+    }
+
+    (expanded, expander.errors)
+}
+
+#[derive(Debug, Clone)]
+struct SourceLine {
+    text: String,
+    line: usize,
+    /// None for the main source
+    file: Option<Rc<Path>>,
+}
+
+fn source_lines(source: &str, file: Option<Rc<Path>>) -> Vec<SourceLine> {
+    source
+        .lines()
+        .enumerate()
+        .map(|(idx, text)| SourceLine {
+            text: String::from(text),
+            line: idx + 1,
+            file: file.clone(),
+        })
+        .collect()
+}
+
+#[derive(Clone)]
+struct Macro {
+    params: Vec<String>,
+    body: Vec<SourceLine>,
+}
+
+impl SourceLine {
+    fn span(&self) -> Span {
+        Span::line(self.file.as_deref(), self.line, &self.text)
+    }
+}
+
+/// The expansion or inclusion lines are being produced for
+struct Site {
+    /// In the main source
+    line: Span,
+    /// None for included files
+    name: Option<String>,
+    /// The call site of the outermost expansion, None for included files
+    call: Option<Span>,
+}
+
+impl Site {
+    fn in_expansion(site: Option<&Site>) -> bool {
+        site.is_some_and(|site| site.name.is_some())
+    }
+}
+
+struct Expander<'l> {
+    loader: &'l dyn SourceLoader,
+    macros: HashMap<String, Macro>,
+    expansion_count: usize,
+    /// The number of lines expansions can still instantiate
+    line_budget: usize,
+    main_path: Option<PathBuf>,
+    /// The files being included, to detect cycles
+    include_stack: Vec<PathBuf>,
+    /// The errors of the directives and calls that were skipped
+    errors: Vec<PreprocessError>,
+}
+
+impl Expander<'_> {
+    /// Only returns the errors that end the whole expansion, the others are
+    /// reported and their line skipped
+    fn expand_block(
+        &mut self,
+        lines: &[SourceLine],
+        site: Option<&Site>,
+        depth: usize,
+        out: &mut Vec<ExpandedLine>,
+    ) -> Result<(), PreprocessError> {
+        let mut idx = 0;
+
+        while idx < lines.len() {
+            match self.expand_line(lines, &mut idx, site, depth, out) {
+                Err(error) if error.kind.is_fatal() => return Err(error),
+                Err(error) => self.report(error),
+                Ok(()) => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Expands the line at `idx`, moving `idx` past it and the block it opens
+    fn expand_line(
+        &mut self,
+        lines: &[SourceLine],
+        idx: &mut usize,
+        site: Option<&Site>,
+        depth: usize,
+        out: &mut Vec<ExpandedLine>,
+    ) -> Result<(), PreprocessError> {
+        let source_line = &lines[*idx];
+        *idx += 1;
+
+        // Lines that cannot be tokenized are left for the parser to report
+        let tokens = match significant_tokens(&source_line.text) {
+            Some(tokens) => tokens,
+            None => {
+                emit(out, source_line.text.clone(), source_line, site);
+                return Ok(());
+            }
+        };
+        let error = |kind: PreprocessErrorKind| kind.at(source_line);
+
+        match tokens.first().map(|token| token.term) {
+            Some(Term::MacroCmd) => {
+                // The body is skipped even when the definition is invalid
+                let body_start = *idx;
+                let body_len = lines[body_start..]
+                    .iter()
+                    .position(|line| first_term(line) == Some(Term::EndMacroCmd));
+                if let Some(body_len) = body_len {
+                    *idx += body_len + 1;
+                }
+
+                if Site::in_expansion(site) {
+                    return Err(error(PreprocessErrorKind::NestedDefinition));
+                }
+                let (name, params) = definition(&source_line.text, &tokens)
+                    .ok_or_else(|| error(PreprocessErrorKind::InvalidDefinition))?;
+                let body_len = body_len
+                    .ok_or_else(|| error(PreprocessErrorKind::UnterminatedMacro(name.clone())))?;
+                let body = lines[body_start..body_start + body_len].to_vec();
+
+                if let Some(nested) = body
+                    .iter()
+                    .find(|line| first_term(line) == Some(Term::MacroCmd))
+                {
+                    return Err(PreprocessErrorKind::NestedDefinition.at(nested));
+                }
+                if self.macros.contains_key(&name) {
+                    return Err(error(PreprocessErrorKind::DuplicateMacro(name)));
+                }
+                self.macros.insert(name, Macro { params, body });
+            }
+            Some(Term::ReptCmd) => {
+                // The body is skipped even when the count is invalid
+                let body_start = *idx;
+                let body_len = matching_endr(&lines[body_start..]);
+                if let Some(body_len) = body_len {
+                    *idx += body_len + 1;
+                }
+
+                let count = repeat_count(&source_line.text, &tokens)
+                    .ok_or_else(|| error(PreprocessErrorKind::InvalidRepeatCount))?;
+                let body_len =
+                    body_len.ok_or_else(|| error(PreprocessErrorKind::UnterminatedRept))?;
+                let body = &lines[body_start..body_start + body_len];
+
+                for _ in 0..count {
+                    self.expand_body(
+                        ".rept",
+                        &HashMap::new(),
+                        body,
+                        source_line,
+                        site,
+                        depth,
+                        out,
+                    )?;
+                }
+            }
+            Some(Term::IncludeCmd) => {
+                if Site::in_expansion(site) {
+                    return Err(error(PreprocessErrorKind::IncludeInExpansion));
+                }
+
+                let included = match tokens[..] {
+                    [_, ref path] if path.term == Term::QuotedString => {
+                        &source_line.text[path.range.clone()]
+                    }
+                    _ => return Err(error(PreprocessErrorKind::InvalidInclude)),
+                };
+                let including = source_line.file.as_deref().or(self.main_path.as_deref());
+                let path = loader::normalize(
+                    &including
+                        .and_then(Path::parent)
+                        .unwrap_or_else(|| Path::new(""))
+                        .join(included),
+                );
+
+                if self.include_stack.contains(&path) {
+                    return Err(error(PreprocessErrorKind::IncludeCycle(path)));
+                }
+                let source = self
+                    .loader
+                    .load(&path)
+                    .map_err(|e| error(PreprocessErrorKind::IncludeFailed(path.clone(), e)))?;
+
+                let lines = source_lines(&source, Some(Rc::from(path.as_path())));
+                let include_site = Site {
+                    line: site.map_or_else(|| source_line.span(), |site| site.line.clone()),
+                    name: None,
+                    call: None,
+                };
+                self.include_stack.push(path);
+                self.expand_block(&lines, Some(&include_site), depth, out)?;
+                self.include_stack.pop();
+            }
+            Some(Term::EndMacroCmd) => {
+                return Err(error(PreprocessErrorKind::UnexpectedEnd(".endm")))
+            }
+            Some(Term::EndReptCmd) => {
+                return Err(error(PreprocessErrorKind::UnexpectedEnd(".endr")))
+            }
+            _ => match self.macro_call(&source_line.text, &tokens) {
+                Some(call) => {
+                    let Macro { params, body } = self.macros[call.name].clone();
+                    if params.len() != call.args.len() {
+                        return Err(error(PreprocessErrorKind::ArgumentCount {
+                            name: String::from(call.name),
+                            expected: params.len(),
+                            got: call.args.len(),
+                        }));
+                    }
+
+                    if let Some(label) = call.label {
+                        emit(out, String::from(label), source_line, site);
+                    }
+
+                    let substitutions = params
+                        .iter()
+                        .map(String::as_str)
+                        .zip(call.args)
+                        .collect::<HashMap<_, _>>();
+                    self.expand_body(
+                        call.name,
+                        &substitutions,
+                        &body,
+                        source_line,
+                        site,
+                        depth,
+                        out,
+                    )?;
+                }
+                None => emit(out, source_line.text.clone(), source_line, site),
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Records an error unless an expansion already reported it on the same
+    /// line
+    fn report(&mut self, error: PreprocessError) {
+        let is_new = !self.errors.iter().any(|reported| {
+            reported.span.file == error.span.file && reported.span.line == error.span.line
+        });
+        if is_new {
+            self.errors.push(error);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn expand_body(
+        &mut self,
+        name: &str,
+        substitutions: &HashMap<&str, &str>,
+        body: &[SourceLine],
+        call_line: &SourceLine,
+        site: Option<&Site>,
+        depth: usize,
+        out: &mut Vec<ExpandedLine>,
+    ) -> Result<(), PreprocessError> {
+        if depth >= MAX_EXPANSION_DEPTH {
+            return Err(PreprocessErrorKind::TooDeep(String::from(name)).at(call_line));
+        }
+        // Empty bodies cost a line too, or nesting them would be free
+        self.line_budget = self
+            .line_budget
+            .checked_sub(body.len().max(1))
+            .ok_or_else(|| PreprocessErrorKind::TooLarge(String::from(name)).at(call_line))?;
+
+        self.expansion_count += 1;
+        let suffix = format!("__{}", self.expansion_count);
+
+        let local_labels = body
+            .iter()
+            .filter_map(|line| significant_tokens(&line.text).map(|tokens| (line, tokens)))
+            .flat_map(|(line, tokens)| {
+                tokens
+                    .into_iter()
+                    .filter(|token| token.term == Term::LabelDef)
+                    .map(move |token| {
+                        String::from(&line.text[token.range.start..token.range.end - 1])
+                    })
+            })
+            .collect::<HashSet<_>>();
+
+        let instantiated = body
+            .iter()
+            .map(|line| SourceLine {
+                text: instantiate(&line.text, substitutions, &local_labels, &suffix),
+                line: line.line,
+                file: line.file.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        let inner_site = Site {
+            line: site.map_or_else(|| call_line.span(), |site| site.line.clone()),
+            name: Some(String::from(name)),
+            call: Some(
+                site.and_then(|site| site.call.clone())
+                    .unwrap_or_else(|| call_line.span()),
+            ),
+        };
+        self.expand_block(&instantiated, Some(&inner_site), depth + 1, out)
+    }
+
+    /// Recognizes `name arg1, arg2` and `label: name arg1, arg2` lines calling
+    /// a known macro
+    fn macro_call<'a>(&self, text: &'a str, tokens: &[Token]) -> Option<MacroCall<'a>> {
+        let (label, rest) = match tokens {
+            // The indentation is kept so that the label keeps its columns
+            [first, rest @ ..] if first.term == Term::LabelDef => {
+                (Some(&text[..first.range.end]), rest)
+            }
+            _ => (None, tokens),
+        };
+
+        let (name, args) = match rest {
+            [name, args @ ..] if name.term == Term::Ident => (&text[name.range.clone()], args),
+            _ => return None,
+        };
+        if !self.macros.contains_key(name) {
+            return None;
+        }
+
+        let args = if args.is_empty() {
+            Vec::new()
+        } else {
+            args.split(|token| token.term == Term::ParamSeparator)
+                .map(|arg| match arg {
+                    [] => "",
+                    [first, .., last] => &text[first.range.start..last.range.end],
+                    [single] => &text[single.range.clone()],
+                })
+                .collect()
+        };
+
+        Some(MacroCall { label, name, args })
+    }
+}
+
+struct MacroCall<'a> {
+    label: Option<&'a str>,
+    name: &'a str,
+    args: Vec<&'a str>,
+}
+
+fn emit(out: &mut Vec<ExpandedLine>, text: String, source_line: &SourceLine, site: Option<&Site>) {
+    let span = Span::line(source_line.file.as_deref(), source_line.line, &text);
+
+    out.push(match site {
+        Some(site) => ExpandedLine {
+            line: site.line.line,
+            expansion: Some(Expansion {
+                name: site.name.clone(),
+                body_line: source_line.line,
+                file: source_line.file.as_deref().map(Path::to_path_buf),
+            }),
+            origin: Origin {
+                exact: site.call.is_none(),
+                span: site.call.clone().unwrap_or(span),
+                site: site.line.clone(),
+            },
+            text,
+        },
+        None => ExpandedLine {
+            line: source_line.line,
+            expansion: None,
+            origin: Origin {
+                span: span.clone(),
+                exact: true,
+                site: span,
+            },
+            text,
+        },
+    })
+}
+
+/// The tokens of a line up to its comment, if it can be tokenized
+fn significant_tokens(text: &str) -> Option<Vec<Token>> {
+    Tokenizer::new(text)
+        .take_while(|token| {
+            !matches!(
+                token,
+                Ok(Token {
+                    term: Term::Comment,
+                    ..
+                })
+            )
+        })
+        .collect::<Result<_, _>>()
+        .ok()
+}
+
+fn first_term(line: &SourceLine) -> Option<Term> {
+    Tokenizer::new(&line.text)
+        .next()
+        .and_then(Result::ok)
+        .map(|token| token.term)
+}
+
+/// Parses `.macro name param1, param2, ...`
+fn definition(text: &str, tokens: &[Token]) -> Option<(String, Vec<String>)> {
+    let (name, params) = match tokens {
+        [_, name, params @ ..] if name.term == Term::Ident => (&text[name.range.clone()], params),
+        _ => return None,
+    };
+
+    let mut names = Vec::new();
+    for (idx, token) in params.iter().enumerate() {
+        let expected = if idx % 2 == 0 {
+            Term::Ident
+        } else {
+            Term::ParamSeparator
+        };
+        if token.term != expected {
+            return None;
+        }
+        if expected == Term::Ident {
+            names.push(String::from(&text[token.range.clone()]));
+        }
+    }
+
+    // Missing parameter after a trailing separator
+    if params.len() % 2 == 0 && !params.is_empty() {
+        return None;
+    }
+
+    let unique_names = names.iter().collect::<HashSet<_>>();
+    if unique_names.len() != names.len() {
+        return None;
+    }
+
+    Some((String::from(name), names))
+}
+
+/// Parses `.rept count`
+fn repeat_count(text: &str, tokens: &[Token]) -> Option<usize> {
+    let number = match tokens {
+        [_, number] => number,
+        _ => return None,
+    };
+
+    let digits = &text[number.range.clone()];
+    let count = match number.term {
+        Term::Number { base } => {
+            let digits = digits
+                .strip_prefix("0x")
+                .or_else(|| digits.strip_prefix("0d"))
+                .unwrap_or(digits);
+            usize::from_str_radix(digits, base.radix()).ok()?
+        }
+        _ => return None,
+    };
+
+    Some(count).filter(|count| *count <= MAX_REPEAT_COUNT)
+}
+
+/// The length of a `.rept` body, accounting for nested blocks
+fn matching_endr(lines: &[SourceLine]) -> Option<usize> {
+    let mut nesting = 0;
+
+    for (idx, line) in lines.iter().enumerate() {
+        match first_term(line) {
+            Some(Term::ReptCmd) => nesting += 1,
+            Some(Term::EndReptCmd) if nesting == 0 => return Some(idx),
+            Some(Term::EndReptCmd) => nesting -= 1,
+            _ => (),
+        }
+    }
+
+    None
+}
+
+/// Substitutes the parameters of a line and renames its local labels
+fn instantiate(
+    text: &str,
+    substitutions: &HashMap<&str, &str>,
+    local_labels: &HashSet<String>,
+    suffix: &str,
+) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut copied = 0;
+
+    for token in Tokenizer::new(text).map_while(Result::ok) {
+        let token_text = &text[token.range.clone()];
+
+        let replacement = match token.term {
+            Term::Ident => substitutions.get(token_text).map(|arg| String::from(*arg)),
+            Term::LabelDef => {
+                let label = &token_text[..token_text.len() - 1];
+                local_labels
+                    .contains(label)
+                    .then(|| format!("{}{}:", label, suffix))
+            }
+            Term::LabelUse => {
+                let label = &token_text[1..];
+                local_labels
+                    .contains(label)
+                    .then(|| format!(":{}{}", label, suffix))
+            }
+            _ => None,
+        };
+
+        if let Some(replacement) = replacement {
+            out.push_str(&text[copied..token.range.start]);
+            out.push_str(&replacement);
+            copied = token.range.end;
+        }
+    }
+
+    out.push_str(&text[copied..]);
+    out
+}
+
+impl fmt::Display for Expansion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let location = match &self.file {
+            Some(file) => format!("line {} of {}", self.body_line, file.display()),
+            None => format!("line {}", self.body_line),
+        };
+
+        match &self.name {
+            Some(name) => write!(f, "in the expansion of '{}' ({})", name, location),
+            None => write!(f, "({})", location),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("{kind} on {span}")]
+pub struct PreprocessError {
+    pub kind: PreprocessErrorKind,
+    /// The whole line of the directive or call
+    pub span: Span,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PreprocessErrorKind {
+    #[error("Invalid macro definition, expected '.macro name param1, param2, ...'")]
+    InvalidDefinition,
+    #[error("Macros cannot be defined inside other macros or repeated blocks")]
+    NestedDefinition,
+    #[error("The macro '{0}' has been defined multiple times. A macro can only be defined once")]
+    DuplicateMacro(String),
+    #[error("The macro '{0}' is missing its '.endm' directive")]
+    UnterminatedMacro(String),
+    #[error("This '.rept' directive is missing its '.endr' directive")]
+    UnterminatedRept,
+    #[error("'{0}' does not close any block")]
+    UnexpectedEnd(&'static str),
+    #[error(
+        "Invalid repeat count, expected '.rept' followed by a number up to {}",
+        MAX_REPEAT_COUNT
+    )]
+    InvalidRepeatCount,
+    #[error("The macro '{name}' takes {expected} arguments but {got} were given")]
+    ArgumentCount {
+        name: String,
+        expected: usize,
+        got: usize,
+    },
+    #[error("The expansion of '{0}' is nested too deeply, it is probably recursive")]
+    TooDeep(String),
+    #[error("The expansion of '{0}' produces too many lines to fit in a champion")]
+    TooLarge(String),
+    #[error("Invalid include, expected '.include' followed by a quoted path")]
+    InvalidInclude,
+    #[error("Files cannot be included inside macros or repeated blocks")]
+    IncludeInExpansion,
+    #[error("'{}' ends up including itself", .0.display())]
+    IncludeCycle(PathBuf),
+    #[error("Could not include '{}': {}", .0.display(), .1)]
+    IncludeFailed(PathBuf, io::Error),
+}
+
+impl PreprocessErrorKind {
+    /// Whether the error ends the expansion, since skipping the failing line
+    /// would only lead to the same error again
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            PreprocessErrorKind::TooDeep(_) | PreprocessErrorKind::TooLarge(_)
+        )
+    }
+
+    fn at(self, line: &SourceLine) -> PreprocessError {
+        PreprocessError {
+            kind: self,
+            span: line.span(),
+        }
+    }
+}
@@ -0,0 +1,242 @@
+//! Rendering errors like rustc does, with the source lines they point to:
+//!
+//! ```text
+//! error: The label 'dup' has been declared multiple times. A label can only be declared once
+//!  --> champion.s:9:1
+//!   |
+//! 8 | dup:
+//!   | ---- first declared here
+//! 9 | dup:
+//!   | ^^^^
+//! ```
+
+use super::{
+    assembler::AssembleError,
+    compiler::CompileError,
+    diagnostics::{Diagnostic, DiagnosticError},
+    loader::SourceLoader,
+    span::Span,
+    ReadError, WriteError,
+};
+
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+/// An error ready to be rendered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub message: String,
+    /// Where the error is, None for errors about the whole champion
+    pub span: Option<Span>,
+    /// Other locations explaining the error
+    pub labels: Vec<(Span, String)>,
+    pub notes: Vec<String>,
+}
+
+impl Report {
+    fn new(message: impl ToString, span: Option<Span>) -> Self {
+        Self {
+            message: message.to_string(),
+            span,
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    fn label(mut self, span: &Span, label: &str) -> Self {
+        self.labels.push((span.clone(), String::from(label)));
+        self
+    }
+
+    fn note(mut self, note: impl ToString) -> Self {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    /// Renders the report with snippets of the main `source`, found at `path`
+    /// if it is not stdin, and of the included files provided by `loader`
+    pub fn render(&self, source: &str, path: Option<&Path>, loader: &impl SourceLoader) -> String {
+        let mut out = format!("error: {}\n", self.message);
+
+        let mut annotations = self
+            .span
+            .iter()
+            .map(|span| (span, '^', ""))
+            .chain(
+                self.labels
+                    .iter()
+                    .map(|(span, label)| (span, '-', label.as_str())),
+            )
+            .collect::<Vec<_>>();
+        annotations.sort_by_key(|(span, ..)| (span.file.clone(), span.line));
+
+        let gutter = annotations
+            .iter()
+            .map(|(span, ..)| span.line.to_string().len())
+            .max()
+            .unwrap_or(0);
+
+        // The file of the error comes first, then the files of its labels
+        let mut files = Vec::<Option<PathBuf>>::new();
+        let first_file = self.span.as_ref().map(|span| span.file.clone());
+        for file in first_file
+            .into_iter()
+            .chain(annotations.iter().map(|(span, ..)| span.file.clone()))
+        {
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+
+        for (idx, file) in files.iter().enumerate() {
+            let in_file = annotations
+                .iter()
+                .filter(|(span, ..)| &span.file == file)
+                .collect::<Vec<_>>();
+            let text = match file {
+                Some(file) => loader.load(file).ok(),
+                None => Some(String::from(source)),
+            };
+            let name = file.as_deref().or(path).map_or_else(
+                || String::from("<stdin>"),
+                |path| path.display().to_string(),
+            );
+
+            // The error is pointed at first, labels in other files at their first line
+            let (first, arrow) = match (&self.span, idx) {
+                (Some(span), 0) => (span, "-->"),
+                (None, 0) => (in_file[0].0, "-->"),
+                _ => (in_file[0].0, ":::"),
+            };
+            let _ = writeln!(
+                out,
+                "{:gutter$}{} {}:{}:{}",
+                "",
+                arrow,
+                name,
+                first.line,
+                first.columns.start + 1,
+                gutter = gutter
+            );
+            let _ = writeln!(out, "{:gutter$} |", "", gutter = gutter);
+
+            let lines = text.as_deref().map(|text| text.lines().collect::<Vec<_>>());
+            let mut previous = None;
+            for (span, marker, label) in in_file {
+                let line = match lines.as_ref().and_then(|lines| lines.get(span.line - 1)) {
+                    Some(line) => *line,
+                    None => continue,
+                };
+                let start = span.columns.start.min(line.len());
+                let end = span.columns.end.min(line.len()).max(start);
+
+                // Lines with several annotations are only shown once
+                if previous != Some(span.line) {
+                    if previous.is_some_and(|previous| span.line > previous + 1) {
+                        out.push_str("...\n");
+                    }
+                    let _ = writeln!(
+                        out,
+                        "{:>gutter$} | {}",
+                        span.line,
+                        expand_tabs(line),
+                        gutter = gutter
+                    );
+                }
+                previous = Some(span.line);
+                let underline = marker
+                    .to_string()
+                    .repeat(display_width(&line[start..end]).max(1));
+                let _ = writeln!(
+                    out,
+                    "{:gutter$} | {:indent$}{} {}",
+                    "",
+                    "",
+                    underline,
+                    label,
+                    indent = display_width(&line[..start]),
+                    gutter = gutter
+                );
+            }
+        }
+
+        for note in &self.notes {
+            let _ = writeln!(out, "{:gutter$} = note: {}", "", note, gutter = gutter);
+        }
+
+        // Labels are optional, so underlines may end with a space
+        out.lines()
+            .map(|line| format!("{}\n", line.trim_end()))
+            .collect()
+    }
+}
+
+/// Tabs are shown as 4 spaces so that underlines line up
+fn expand_tabs(text: &str) -> String {
+    text.replace('\t', "    ")
+}
+
+fn display_width(text: &str) -> usize {
+    text.chars().map(|c| if c == '\t' { 4 } else { 1 }).sum()
+}
+
+impl From<&ReadError> for Report {
+    fn from(error: &ReadError) -> Self {
+        match error {
+            ReadError::ParseError(e, span) => Report::new(e, Some(span.clone())),
+            ReadError::ExpandedParseError(e, span, expansion) => {
+                Report::new(e, Some(span.clone())).note(expansion)
+            }
+            ReadError::PreprocessError(e) => Report::new(&e.kind, Some(e.span.clone())),
+            ReadError::AssembleError(e) => Report::from(e),
+            ReadError::IOError(_) => Report::new(error, None),
+        }
+    }
+}
+
+impl From<&AssembleError> for Report {
+    fn from(error: &AssembleError) -> Self {
+        let report = Report::new(error, error.span());
+
+        match error {
+            AssembleError::NameAlreadySet { first, .. } => report.label(first, "first set here"),
+            AssembleError::CommentAlreadySet { first, .. } => report.label(first, "first set here"),
+            AssembleError::MissingName | AssembleError::MissingComment => report,
+        }
+    }
+}
+
+impl From<&CompileError> for Report {
+    fn from(error: &CompileError) -> Self {
+        match error {
+            CompileError::ExprError(e) => Report::new(&e.kind, Some(e.span.clone())),
+            CompileError::DuplicateLabel { first, .. } => {
+                Report::new(error, error.span()).label(first, "first declared here")
+            }
+            CompileError::DuplicateConstant { first, .. } => {
+                Report::new(error, error.span()).label(first, "first defined here")
+            }
+            _ => Report::new(error, error.span()),
+        }
+    }
+}
+
+impl From<&WriteError> for Report {
+    fn from(error: &WriteError) -> Self {
+        match error {
+            WriteError::CompileError(e) => Report::from(e),
+            WriteError::IOError(_) => Report::new(error, None),
+        }
+    }
+}
+
+impl From<&Diagnostic> for Report {
+    fn from(diagnostic: &Diagnostic) -> Self {
+        match &diagnostic.error {
+            DiagnosticError::Read(e) => Report::from(e),
+            DiagnosticError::Compile(e) => Report::from(e),
+        }
+    }
+}
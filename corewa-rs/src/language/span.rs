@@ -0,0 +1,75 @@
+//! Locations in the sources of a champion, used to report errors
+
+use super::lexer::InputRange;
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// A byte range in a line of a champion's sources
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// The included file the span is in, None for the main source
+    pub file: Option<PathBuf>,
+    /// Starting at 1
+    pub line: usize,
+    pub columns: InputRange,
+}
+
+impl Span {
+    /// The text of a line, without its surrounding whitespace
+    pub fn line(file: Option<&Path>, line: usize, text: &str) -> Self {
+        let end = text.trim_end().len();
+        let start = end - text[..end].trim_start().len();
+
+        Self {
+            file: file.map(Path::to_path_buf),
+            line,
+            columns: start..end,
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}", self.line)?;
+        if let Some(file) = &self.file {
+            write!(f, " of {}", file.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Where a line of the expanded source comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Origin {
+    /// The line where the text was written, or the call site of its outermost
+    /// expansion
+    pub span: Span,
+    /// Whether columns in the text match `span`. Expanded text differs from
+    /// its call site, so its errors are reported on the whole call site
+    pub exact: bool,
+    /// The line of the main source the text ends up on: the line itself, or
+    /// the call site or inclusion it comes from
+    pub site: Span,
+}
+
+impl Origin {
+    /// The location of a byte range of the text
+    pub fn locate(&self, columns: InputRange) -> Span {
+        if self.exact {
+            Span {
+                columns,
+                ..self.span.clone()
+            }
+        } else {
+            self.span.clone()
+        }
+    }
+
+    /// The main source line, starting at 1
+    pub fn line(&self) -> usize {
+        self.site.line
+    }
+}
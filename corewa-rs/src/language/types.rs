@@ -1,6 +1,7 @@
 use derive_more::From;
 use enum_dispatch::enum_dispatch;
 
+use super::lexer::InputRange;
 use crate::spec::{DIR_PARAM_CODE, IND_PARAM_CODE, REG_PARAM_CODE};
 
 #[derive(Debug, PartialEq, Eq)]
@@ -28,13 +29,43 @@ pub struct Register(pub u8);
 
 #[derive(Debug, PartialEq, Eq, From)]
 pub enum Direct {
-    Label(String),
+    /// With the range of its use
+    Label(String, InputRange),
     Numeric(i64),
+    Expr(Expr),
 }
 #[derive(Debug, PartialEq, Eq, From)]
 pub enum Indirect {
-    Label(String),
+    /// With the range of its use
+    Label(String, InputRange),
     Numeric(i64),
+    Expr(Expr),
+}
+
+/// An operand computed at compile time.
+/// Bare numbers and labels are parsed as `Numeric` and `Label` operands
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub at: InputRange,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExprKind {
+    Number(i64),
+    /// Evaluates to the label's offset from the operation, like label operands
+    Label(String),
+    Constant(String),
+    Neg(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
 }
 
 #[enum_dispatch(ToParamCode)]
@@ -0,0 +1,224 @@
+//! Detection of code that compiles but is almost certainly a bug
+
+use super::{
+    assembler::ParsedInstruction,
+    compiler::fits,
+    disassembler::undecodable_offsets,
+    types::{AnyParam, DirInd, Direct, Op},
+};
+use crate::spec::VmConfig;
+
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{kind} (line {line})")]
+pub struct CompileWarning {
+    pub kind: CompileWarningKind,
+    /// The source line, starting at 1
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CompileWarningKind {
+    #[error("The label '{0}' is never used")]
+    UnusedLabel(String),
+    #[error("This code is unreachable: the previous 'zjmp' always jumps")]
+    UnreachableCode,
+    #[error("The byte 0x{0:02x} of this '.code' directive is not a valid instruction")]
+    InvalidOpCode(u8),
+    #[error("The offset {0} will be taken modulo IDX_MOD ({1})")]
+    OffsetTruncated(i64, usize),
+    #[error("'live' reports player {0}, which is not a valid player id")]
+    InvalidPlayerId(i64),
+    #[error("The value {0} does not fit in a {1} bytes parameter and will be truncated")]
+    ValueTruncated(i64, usize),
+}
+
+/// How an operation uses one of its numeric parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ParamUse {
+    Value,
+    /// An offset reduced modulo IDX_MOD
+    Offset,
+    /// The player reported by `live`
+    PlayerId,
+}
+
+impl ParamUse {
+    /// The use of the numeric parameter at `idx` in `op`, which is indirect or
+    /// direct
+    pub(super) fn of(op: &Op, idx: usize, indirect: bool) -> Self {
+        use Op::*;
+
+        match op {
+            // Long operations are not restricted by IDX_MOD
+            Lld(..) | Lldi(..) | Lfork(..) => ParamUse::Value,
+            _ if indirect => ParamUse::Offset,
+            Live(..) => ParamUse::PlayerId,
+            Zjmp(..) | Fork(..) => ParamUse::Offset,
+            Ldi(..) if idx < 2 => ParamUse::Offset,
+            Sti(..) if idx > 0 => ParamUse::Offset,
+            _ => ParamUse::Value,
+        }
+    }
+}
+
+/// Collects warnings while the compiler goes through a champion
+#[derive(Default)]
+pub(super) struct Linter {
+    warnings: Vec<CompileWarning>,
+    /// Whether the carry flag is known to be set before the current
+    /// instruction, regardless of the path taken to get there
+    carry_set: bool,
+    unreachable: bool,
+    label_lines: HashMap<String, usize>,
+    used_labels: HashSet<String>,
+    /// The labels declared since the last instruction
+    pending_labels: Vec<String>,
+    /// The labels pointing to the current instruction
+    instruction_labels: Vec<String>,
+    /// Player ids reported by `live` operations, with the labels pointing
+    /// to them
+    lives: Vec<(i64, Vec<String>, usize)>,
+}
+
+impl Linter {
+    fn warn(&mut self, kind: CompileWarningKind, line: usize) {
+        self.warnings.push(CompileWarning { kind, line });
+    }
+
+    /// Looks at an instruction before it is compiled
+    pub(super) fn instruction(
+        &mut self,
+        instr: &ParsedInstruction,
+        line: usize,
+        config: &VmConfig,
+    ) {
+        match instr {
+            ParsedInstruction::Label(label, _) => {
+                // Labels can be jumped to from anywhere
+                self.unreachable = false;
+                self.carry_set = false;
+                self.label_lines.entry(label.clone()).or_insert(line);
+                self.pending_labels.push(label.clone());
+                return;
+            }
+            ParsedInstruction::Constant(..) => return,
+            ParsedInstruction::RawCode(bytes) => {
+                if let Some(offset) = undecodable_offsets(bytes, config).first() {
+                    self.warn(CompileWarningKind::InvalidOpCode(bytes[*offset]), line);
+                }
+                self.check_reachable(line);
+                self.carry_set = false;
+            }
+            ParsedInstruction::Op(op) => {
+                self.check_reachable(line);
+                if let Op::Zjmp(_) = op {
+                    self.unreachable = self.carry_set;
+                }
+                self.carry_set = sets_carry(op).unwrap_or(self.carry_set);
+            }
+        }
+
+        self.instruction_labels = std::mem::take(&mut self.pending_labels);
+    }
+
+    fn check_reachable(&mut self, line: usize) {
+        if self.unreachable {
+            self.warn(CompileWarningKind::UnreachableCode, line);
+            // Only the start of unreachable regions is reported
+            self.unreachable = false;
+        }
+    }
+
+    pub(super) fn use_label(&mut self, label: &str) {
+        self.used_labels.insert(String::from(label));
+    }
+
+    /// Checks the value written for a parameter of the current instruction
+    pub(super) fn literal(
+        &mut self,
+        value: i64,
+        size: usize,
+        param_use: ParamUse,
+        line: usize,
+        config: &VmConfig,
+    ) {
+        if !fits(value, size) {
+            self.warn(CompileWarningKind::ValueTruncated(value, size), line);
+        }
+
+        if param_use == ParamUse::PlayerId {
+            let labels = self.instruction_labels.clone();
+            self.lives.push((value, labels, line));
+        } else {
+            self.resolved(value, param_use, line, config);
+        }
+    }
+
+    /// Checks the value computed for a label or expression parameter.
+    /// Expressions that do not fit their parameter fail to compile instead
+    pub(super) fn resolved(
+        &mut self,
+        value: i64,
+        param_use: ParamUse,
+        line: usize,
+        config: &VmConfig,
+    ) {
+        if param_use == ParamUse::Offset && value.unsigned_abs() >= config.idx_mod as u64 {
+            self.warn(
+                CompileWarningKind::OffsetTruncated(value, config.idx_mod),
+                line,
+            );
+        }
+    }
+
+    pub(super) fn finish(mut self, config: &VmConfig) -> Vec<CompileWarning> {
+        let mut unused = self
+            .label_lines
+            .iter()
+            .filter(|(label, _)| !self.used_labels.contains(*label))
+            .map(|(label, line)| CompileWarning {
+                kind: CompileWarningKind::UnusedLabel(label.clone()),
+                line: *line,
+            })
+            .collect::<Vec<_>>();
+        self.warnings.append(&mut unused);
+
+        // The player id of a labeled `live` is usually written at runtime
+        // from r1, so only the others are checked
+        for (value, labels, line) in std::mem::take(&mut self.lives) {
+            let patched = labels.iter().any(|label| self.used_labels.contains(label));
+            let max_id = config.max_players as u64;
+            if !patched && !(1..=max_id).contains(&value.unsigned_abs()) {
+                self.warn(CompileWarningKind::InvalidPlayerId(value), line);
+            }
+        }
+
+        self.warnings.sort_by_key(|warning| warning.line);
+        self.warnings
+    }
+}
+
+/// Whether an operation leaves the carry flag set, if it is known at compile
+/// time. None for operations that do not change it
+fn sets_carry(op: &Op) -> Option<bool> {
+    use Op::*;
+
+    let is_zero = |param: &AnyParam| matches!(param, AnyParam::Dir(Direct::Numeric(0)));
+    let known_zero = match op {
+        Ld(DirInd::Dir(Direct::Numeric(n)), _) | Lld(DirInd::Dir(Direct::Numeric(n)), _) => {
+            return Some(*n == 0)
+        }
+        And(a, b, _) => is_zero(a) || is_zero(b),
+        Sub(a, b, _) => a == b,
+        Xor(AnyParam::Reg(a), AnyParam::Reg(b), _) => a == b,
+        Ld(..) | Lld(..) | Add(..) | Or(..) | Xor(..) | Lldi(..) => false,
+        Live(..) | St(..) | Zjmp(..) | Ldi(..) | Sti(..) | Fork(..) | Lfork(..) | Aff(..) => {
+            return None
+        }
+    };
+
+    // Unknown results leave the carry unknown, which is treated as unset
+    Some(known_zero)
+}
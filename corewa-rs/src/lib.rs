@@ -2,4 +2,5 @@
 
 pub mod language;
 pub mod spec;
+pub mod tournament;
 pub mod vm;
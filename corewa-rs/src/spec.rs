@@ -6,7 +6,7 @@ pub const PROG_COMMENT_LENGTH: usize = 2048;
 pub type ProgName = [u8; PROG_NAME_LENGTH + 1];
 pub type ProgComment = [u8; PROG_COMMENT_LENGTH + 1];
 
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct Header {
     pub magic: u32,
     pub prog_name: ProgName,
@@ -34,6 +34,81 @@ pub const MAX_CHECKS: u32 = 10;
 pub const REG_COUNT: usize = 16;
 pub const MAX_PARAMS: usize = 3;
 
+/// The arena parameters of a match.
+/// The default preset uses the standard values defined above
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VmConfig {
+    pub mem_size: usize,
+    pub idx_mod: usize,
+    pub champ_max_size: usize,
+    pub max_players: usize,
+
+    pub check_interval: u32,
+    pub cycle_delta: u32,
+    pub nbr_live: u32,
+    pub max_checks: u32,
+
+    pub reg_count: usize,
+}
+
+impl Default for VmConfig {
+    fn default() -> Self {
+        Self {
+            mem_size: MEM_SIZE,
+            idx_mod: IDX_MOD,
+            champ_max_size: CHAMP_MAX_SIZE,
+            max_players: MAX_PLAYERS,
+
+            check_interval: CHECK_INTERVAL,
+            cycle_delta: CYCLE_DELTA,
+            nbr_live: NBR_LIVE,
+            max_checks: MAX_CHECKS,
+
+            reg_count: REG_COUNT,
+        }
+    }
+}
+
+impl VmConfig {
+    pub fn validate(&self) -> Result<(), InvalidConfig> {
+        if self.mem_size == 0 {
+            return Err(InvalidConfig::EmptyMemory);
+        }
+        if self.idx_mod == 0 || self.idx_mod > self.mem_size {
+            return Err(InvalidConfig::IdxMod(self.idx_mod, self.mem_size));
+        }
+        if self.champ_max_size > self.mem_size {
+            return Err(InvalidConfig::ChampMaxSize(
+                self.champ_max_size,
+                self.mem_size,
+            ));
+        }
+        if self.max_players == 0 {
+            return Err(InvalidConfig::NoPlayers);
+        }
+        // Register numbers are encoded on a single byte
+        if self.reg_count == 0 || self.reg_count > usize::from(u8::MAX) {
+            return Err(InvalidConfig::RegCount(self.reg_count));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum InvalidConfig {
+    #[error("The memory size must be greater than 0")]
+    EmptyMemory,
+    #[error("The index modulo ({0}) must be between 1 and the memory size ({1})")]
+    IdxMod(usize, usize),
+    #[error("The champion maximum size ({0}) cannot exceed the memory size ({1})")]
+    ChampMaxSize(usize, usize),
+    #[error("At least one player must be allowed")]
+    NoPlayers,
+    #[error("The register count ({0}) must be between 1 and 255")]
+    RegCount(usize),
+}
+
 #[derive(Debug)]
 pub struct OpSpec {
     pub code: u8,
@@ -50,7 +125,7 @@ pub enum DirectSize {
     FourBytes = 4,
 }
 
-#[derive(Debug, Clone, Copy, derive_more::Display)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, derive_more::Display)]
 pub enum OpType {
     Live = 1,
     Ld,
@@ -70,8 +145,9 @@ pub enum OpType {
     Aff,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub enum ParamType {
+    #[default]
     Register,
     Direct,
     Indirect,
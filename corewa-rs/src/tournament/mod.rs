@@ -0,0 +1,149 @@
+pub mod ranking;
+pub mod schedule;
+
+use crate::{
+    spec::VmConfig,
+    vm::{
+        loader::{read_players, LoadError},
+        outcome::MatchOutcome,
+        VirtualMachine,
+    },
+};
+use ranking::{rank, Standing};
+use schedule::{round_robin, Lineup};
+
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+/// A champion taking part in a tournament
+#[derive(Debug, Clone)]
+pub struct Contender {
+    pub name: String,
+    pub champion: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TournamentConfig {
+    pub vm: VmConfig,
+    /// Lineups go from duels up to this many players
+    pub max_players: usize,
+    /// Matches still running after this many cycles are stopped
+    pub cycle_limit: Option<u32>,
+    pub threads: usize,
+}
+
+impl Default for TournamentConfig {
+    fn default() -> Self {
+        Self {
+            vm: VmConfig::default(),
+            max_players: 2,
+            cycle_limit: None,
+            threads: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchResult {
+    pub lineup: Lineup,
+    pub outcome: MatchOutcome,
+}
+
+#[derive(Debug, Clone)]
+pub struct Tournament {
+    /// Every match played, in schedule order
+    pub matches: Vec<MatchResult>,
+    /// Best rated first
+    pub standings: Vec<Standing>,
+}
+
+/// Plays every lineup of the round robin across the configured number of
+/// threads. Results do not depend on the number of threads
+pub fn play(
+    contenders: &[Contender],
+    config: &TournamentConfig,
+) -> Result<Tournament, TournamentError> {
+    if contenders.len() < 2 {
+        return Err(TournamentError::NotEnoughContenders(contenders.len()));
+    }
+    if !(2..=config.vm.max_players).contains(&config.max_players) {
+        return Err(TournamentError::InvalidLineupSize(
+            config.max_players,
+            config.vm.max_players,
+        ));
+    }
+
+    for contender in contenders {
+        read_players(&[(1, contender.champion.clone())], &[], &config.vm)
+            .map_err(|err| TournamentError::InvalidContender(contender.name.clone(), err))?;
+    }
+
+    let lineups = round_robin(contenders.len(), 2, config.max_players);
+    let next_lineup = AtomicUsize::new(0);
+
+    let mut played = thread::scope(|scope| {
+        let workers = (0..config.threads.max(1))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut played = Vec::new();
+                    loop {
+                        let idx = next_lineup.fetch_add(1, Ordering::Relaxed);
+                        let Some(lineup) = lineups.get(idx) else {
+                            break played;
+                        };
+                        played.push((idx, play_match(contenders, lineup, config)));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("A tournament worker panicked"))
+            .collect::<Vec<_>>()
+    });
+
+    played.sort_by_key(|(idx, _)| *idx);
+    let matches = played
+        .into_iter()
+        .zip(lineups)
+        .map(|((_, outcome), lineup)| MatchResult { lineup, outcome })
+        .collect::<Vec<_>>();
+
+    let standings = rank(contenders.len(), &matches);
+
+    Ok(Tournament { matches, standings })
+}
+
+fn play_match(
+    contenders: &[Contender],
+    lineup: &[usize],
+    config: &TournamentConfig,
+) -> MatchOutcome {
+    let players = lineup
+        .iter()
+        .enumerate()
+        .map(|(position, &contender)| (position as i32 + 1, contenders[contender].champion.clone()))
+        .collect::<Vec<_>>();
+
+    let mut vm = VirtualMachine::with_config(config.vm.clone());
+    vm.load_players(&players)
+        .expect("Contenders are validated before the tournament starts");
+
+    match config.cycle_limit {
+        Some(limit) => vm.run_until(limit),
+        None => vm.run_to_completion(),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TournamentError {
+    #[error("A tournament needs at least 2 contenders, got {0}")]
+    NotEnoughContenders(usize),
+    #[error("Invalid number of players per match: {0} (must be between 2 and {1})")]
+    InvalidLineupSize(usize, usize),
+    #[error("Invalid contender '{0}': {1}")]
+    InvalidContender(String, LoadError),
+}
@@ -0,0 +1,123 @@
+use super::MatchResult;
+
+use std::cmp::Ordering;
+
+pub const INITIAL_RATING: f64 = 1500.0;
+const K_FACTOR: f64 = 32.0;
+
+/// The record of a contender over a whole tournament
+#[derive(Debug, Clone, PartialEq)]
+pub struct Standing {
+    pub contender: usize,
+    pub played: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub ties: u32,
+    pub rating: f64,
+}
+
+/// A contender wins a match when it was reported alive more recently than
+/// everybody else. Contenders sharing the most recent live report all get a
+/// tie, whoever the load order tie break picked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    Win,
+    Tie,
+    Loss,
+}
+
+impl MatchResult {
+    /// The placement of each contender of the lineup, in lineup order
+    pub fn placements(&self) -> Vec<Placement> {
+        let last_lives = self.last_lives();
+        let latest = last_lives.iter().copied().max().unwrap_or(0);
+        let contenders = last_lives.iter().filter(|&&cycle| cycle == latest).count();
+
+        last_lives
+            .iter()
+            .map(|&cycle| {
+                if cycle < latest {
+                    Placement::Loss
+                } else if contenders > 1 {
+                    Placement::Tie
+                } else {
+                    Placement::Win
+                }
+            })
+            .collect()
+    }
+
+    fn last_lives(&self) -> Vec<u32> {
+        self.outcome
+            .last_lives
+            .iter()
+            .map(|(_, cycle)| *cycle)
+            .collect()
+    }
+}
+
+/// Builds the standings of every contender, best rated first.
+/// Ratings are Elo ratings where a match of N players counts as the N - 1
+/// duels of each player against the others, decided by their last live
+/// reports. Matches are rated in order
+pub fn rank(contender_count: usize, matches: &[MatchResult]) -> Vec<Standing> {
+    let mut standings = (0..contender_count)
+        .map(|contender| Standing {
+            contender,
+            played: 0,
+            wins: 0,
+            losses: 0,
+            ties: 0,
+            rating: INITIAL_RATING,
+        })
+        .collect::<Vec<_>>();
+
+    for result in matches {
+        for (&contender, placement) in result.lineup.iter().zip(result.placements()) {
+            let standing = &mut standings[contender];
+            standing.played += 1;
+            match placement {
+                Placement::Win => standing.wins += 1,
+                Placement::Tie => standing.ties += 1,
+                Placement::Loss => standing.losses += 1,
+            }
+        }
+
+        let last_lives = result.last_lives();
+        let duel_weight = K_FACTOR / (result.lineup.len().max(2) - 1) as f64;
+        let deltas = result
+            .lineup
+            .iter()
+            .zip(&last_lives)
+            .map(|(&contender, &cycle)| {
+                let rating = standings[contender].rating;
+                result
+                    .lineup
+                    .iter()
+                    .zip(&last_lives)
+                    .filter(|(&opponent, _)| opponent != contender)
+                    .map(|(&opponent, &opponent_cycle)| {
+                        let score = match cycle.cmp(&opponent_cycle) {
+                            Ordering::Greater => 1.0,
+                            Ordering::Equal => 0.5,
+                            Ordering::Less => 0.0,
+                        };
+                        let expected = expected_score(rating, standings[opponent].rating);
+                        duel_weight * (score - expected)
+                    })
+                    .sum::<f64>()
+            })
+            .collect::<Vec<_>>();
+
+        for (&contender, delta) in result.lineup.iter().zip(deltas) {
+            standings[contender].rating += delta;
+        }
+    }
+
+    standings.sort_by(|a, b| b.rating.total_cmp(&a.rating));
+    standings
+}
+
+fn expected_score(rating: f64, opponent_rating: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent_rating - rating) / 400.0))
+}
@@ -0,0 +1,64 @@
+/// A match between some contenders, identified by their index.
+/// Contenders are loaded in the arena in the order of the lineup
+pub type Lineup = Vec<usize>;
+
+/// Every lineup of `min_players` to `max_players` distinct contenders among
+/// `contender_count`, in every start position order
+pub fn round_robin(contender_count: usize, min_players: usize, max_players: usize) -> Vec<Lineup> {
+    let mut lineups = Vec::new();
+
+    for size in min_players..=max_players.min(contender_count) {
+        for combination in combinations(contender_count, size) {
+            lineups.extend(permutations(&combination));
+        }
+    }
+
+    lineups
+}
+
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    fn extend(
+        start: usize,
+        n: usize,
+        k: usize,
+        current: &mut Vec<usize>,
+        out: &mut Vec<Vec<usize>>,
+    ) {
+        if current.len() == k {
+            out.push(current.clone());
+            return;
+        }
+        for i in start..n {
+            current.push(i);
+            extend(i + 1, n, k, current, out);
+            current.pop();
+        }
+    }
+
+    let mut out = Vec::new();
+    extend(0, n, k, &mut Vec::with_capacity(k), &mut out);
+    out
+}
+
+/// Heap's algorithm
+fn permutations(items: &[usize]) -> Vec<Vec<usize>> {
+    let mut items = items.to_vec();
+    let mut counters = vec![0; items.len()];
+    let mut out = vec![items.clone()];
+
+    let mut i = 0;
+    while i < items.len() {
+        if counters[i] < i {
+            let swap_with = if i % 2 == 0 { 0 } else { counters[i] };
+            items.swap(swap_with, i);
+            out.push(items.clone());
+            counters[i] += 1;
+            i = 0;
+        } else {
+            counters[i] = 0;
+            i += 1;
+        }
+    }
+
+    out
+}
@@ -0,0 +1,486 @@
+//! Breakpoints and watchpoints on top of the virtual machine.
+//!
+//! The debugger does not live inside the virtual machine: it inspects the VM
+//! around each cycle instead. Executions and memory writes are found in the
+//! events recorded during the cycle, which is why `step` enables the events
+//! for the duration of the cycle when a breakpoint needs them. Engines that
+//! tick the VM themselves (e.g. replays) can call `before_tick` and
+//! `after_tick` around their own ticks, with the events enabled.
+
+use super::{
+    events::EventKind,
+    process::{Process, ProcessState},
+    types::{Pid, PlayerId},
+    VirtualMachine,
+};
+use crate::spec::OpType;
+
+use fxhash::FxHashSet as HashSet;
+use std::{collections::BTreeMap, fmt, ops::Range};
+
+pub type BreakpointId = usize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// A process is about to read the instruction at an address
+    Address(usize),
+    /// An instruction of this kind is executed
+    OpCode(OpType),
+    /// The process executes an instruction
+    Pid(Pid),
+    /// A process of the player executes an instruction
+    Player(PlayerId),
+    /// Memory in `range` is written, by a process of `by` if any.
+    /// `range` may extend past the end of the memory, in which case it wraps
+    /// around
+    Watch {
+        range: Range<usize>,
+        by: Option<PlayerId>,
+    },
+    /// A register of a process, or of any process if `pid` is `None`, starts
+    /// meeting a condition. Registers are numbered from 1.
+    /// Forked processes do not break as they start with their parent's
+    /// registers
+    Register {
+        pid: Option<Pid>,
+        register: usize,
+        comparison: Comparison,
+        value: i32,
+    },
+    /// Processes are checked for lives
+    LiveCheck,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+}
+
+impl Comparison {
+    pub fn holds(self, lhs: i32, rhs: i32) -> bool {
+        match self {
+            Comparison::Equal => lhs == rhs,
+            Comparison::NotEqual => lhs != rhs,
+            Comparison::Less => lhs < rhs,
+            Comparison::Greater => lhs > rhs,
+        }
+    }
+}
+
+/// A breakpoint being triggered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hit {
+    pub breakpoint: BreakpointId,
+    /// The cycle count right after the cycle that triggered the breakpoint
+    pub cycle: u32,
+    pub cause: Cause,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cause {
+    Reached {
+        pid: Pid,
+        player_id: PlayerId,
+        pc: usize,
+    },
+    Executed {
+        pid: Pid,
+        player_id: PlayerId,
+        pc: usize,
+        op: OpType,
+    },
+    Written {
+        pid: Pid,
+        owner: PlayerId,
+        at: usize,
+        size: usize,
+    },
+    Register {
+        pid: Pid,
+        player_id: PlayerId,
+        register: usize,
+        value: i32,
+    },
+    LiveCheck {
+        check_interval: u32,
+    },
+}
+
+/// Why running stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stop {
+    /// At least one breakpoint was hit during the last cycle
+    Break(Vec<Hit>),
+    /// Processes were checked for lives, see `Debugger::run_until_live_check`
+    LiveCheck,
+    CycleLimit,
+    MatchOver,
+}
+
+#[derive(Debug, Default)]
+pub struct Debugger {
+    breakpoints: BTreeMap<BreakpointId, Breakpoint>,
+    next_id: BreakpointId,
+    /// The register breakpoints whose condition held for a process before
+    /// the cycle, so that they only break when the condition starts holding
+    met_conditions: HashSet<(BreakpointId, Pid)>,
+    /// The processes alive before the cycle
+    pids_before: HashSet<Pid>,
+    /// The first event and the cycle count of the cycle being debugged
+    first_event: usize,
+    cycle_before: u32,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, breakpoint: Breakpoint) -> BreakpointId {
+        self.next_id += 1;
+        self.breakpoints.insert(self.next_id, breakpoint);
+        self.next_id
+    }
+
+    pub fn remove(&mut self, id: BreakpointId) -> Option<Breakpoint> {
+        self.breakpoints.remove(&id)
+    }
+
+    pub fn clear(&mut self) {
+        self.breakpoints.clear()
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = (BreakpointId, &Breakpoint)> {
+        self.breakpoints
+            .iter()
+            .map(|(id, breakpoint)| (*id, breakpoint))
+    }
+
+    /// Whether some breakpoints can only be detected through the events
+    pub fn needs_events(&self) -> bool {
+        self.breakpoints.values().any(|breakpoint| {
+            matches!(
+                breakpoint,
+                Breakpoint::OpCode(_)
+                    | Breakpoint::Pid(_)
+                    | Breakpoint::Player(_)
+                    | Breakpoint::Watch { .. }
+            )
+        })
+    }
+
+    /// Runs a single cycle and returns the breakpoints it hit
+    pub fn step(&mut self, vm: &mut VirtualMachine) -> Vec<Hit> {
+        let events_enabled = vm.events.is_enabled();
+        if !events_enabled && self.needs_events() {
+            vm.events.enable();
+        }
+
+        self.before_tick(vm);
+        vm.tick();
+        let hits = self.after_tick(vm);
+
+        if !events_enabled {
+            vm.events.disable();
+        }
+        hits
+    }
+
+    /// Runs the match until a breakpoint is hit, until it is over or until
+    /// the cycle count reaches `cycle_limit`
+    pub fn run(&mut self, vm: &mut VirtualMachine, cycle_limit: u32) -> Stop {
+        self.run_while(vm, cycle_limit, |_| false)
+    }
+
+    /// Like `run`, but also stops after the next live check
+    pub fn run_until_live_check(&mut self, vm: &mut VirtualMachine, cycle_limit: u32) -> Stop {
+        self.run_while(vm, cycle_limit, |vm| vm.last_live_check == vm.cycles)
+    }
+
+    fn run_while(
+        &mut self,
+        vm: &mut VirtualMachine,
+        cycle_limit: u32,
+        should_stop: impl Fn(&VirtualMachine) -> bool,
+    ) -> Stop {
+        loop {
+            if vm.is_over() {
+                return Stop::MatchOver;
+            }
+            if vm.cycles >= cycle_limit {
+                return Stop::CycleLimit;
+            }
+
+            let hits = self.step(vm);
+            if !hits.is_empty() {
+                return Stop::Break(hits);
+            }
+            if should_stop(vm) {
+                return Stop::LiveCheck;
+            }
+        }
+    }
+
+    /// Prepares the inspection of the next cycle
+    pub fn before_tick(&mut self, vm: &VirtualMachine) {
+        self.first_event = vm.events.pending().len();
+        self.cycle_before = vm.cycles;
+
+        self.pids_before = vm.processes.iter().map(|process| process.pid).collect();
+        self.met_conditions.clear();
+        for (id, breakpoint) in &self.breakpoints {
+            if let Breakpoint::Register { .. } = breakpoint {
+                for process in &vm.processes {
+                    if register_condition(breakpoint, process).is_some() {
+                        self.met_conditions.insert((*id, process.pid));
+                    }
+                }
+            }
+        }
+    }
+
+    /// The breakpoints hit during the cycle that just ran, since the last
+    /// call to `before_tick`
+    pub fn after_tick(&mut self, vm: &VirtualMachine) -> Vec<Hit> {
+        // Matches that are over do not run anymore
+        if vm.cycles == self.cycle_before {
+            return Vec::new();
+        }
+
+        let cycle = vm.cycles;
+        let events = vm.events.pending().get(self.first_event..).unwrap_or(&[]);
+        let mem_size = vm.config.mem_size;
+
+        let mut hits = Vec::new();
+        let mut hit = |breakpoint, cause| {
+            hits.push(Hit {
+                breakpoint,
+                cycle,
+                cause,
+            })
+        };
+
+        for (id, breakpoint) in &self.breakpoints {
+            let id = *id;
+            match breakpoint {
+                Breakpoint::Address(addr) => {
+                    for process in &vm.processes {
+                        let is_idle = matches!(process.state, ProcessState::Idle);
+                        if is_idle && process.pc.addr() == addr % mem_size {
+                            hit(
+                                id,
+                                Cause::Reached {
+                                    pid: process.pid,
+                                    player_id: process.player_id,
+                                    pc: process.pc.addr(),
+                                },
+                            );
+                        }
+                    }
+                }
+                Breakpoint::OpCode(_) | Breakpoint::Pid(_) | Breakpoint::Player(_) => {
+                    for event in events {
+                        if let EventKind::InstructionExecuted {
+                            pid,
+                            player_id,
+                            pc,
+                            instr,
+                        } = &event.kind
+                        {
+                            let matches = match breakpoint {
+                                Breakpoint::OpCode(op) => *op == instr.kind,
+                                Breakpoint::Pid(expected) => expected == pid,
+                                Breakpoint::Player(expected) => expected == player_id,
+                                _ => false,
+                            };
+                            if matches {
+                                hit(
+                                    id,
+                                    Cause::Executed {
+                                        pid: *pid,
+                                        player_id: *player_id,
+                                        pc: *pc,
+                                        op: instr.kind,
+                                    },
+                                );
+                            }
+                        }
+                    }
+                }
+                Breakpoint::Watch { range, by } => {
+                    for event in events {
+                        if let EventKind::MemoryWritten {
+                            pid,
+                            owner,
+                            at,
+                            size,
+                        } = &event.kind
+                        {
+                            let by_anyone = by.is_none_or(|by| by == *owner);
+                            if by_anyone && overlaps(range, *at, *size, mem_size) {
+                                hit(
+                                    id,
+                                    Cause::Written {
+                                        pid: *pid,
+                                        owner: *owner,
+                                        at: *at,
+                                        size: *size,
+                                    },
+                                );
+                            }
+                        }
+                    }
+                }
+                Breakpoint::Register { register, .. } => {
+                    for process in &vm.processes {
+                        let was_met = !self.pids_before.contains(&process.pid)
+                            || self.met_conditions.contains(&(id, process.pid));
+                        if let Some(value) = register_condition(breakpoint, process) {
+                            if !was_met {
+                                hit(
+                                    id,
+                                    Cause::Register {
+                                        pid: process.pid,
+                                        player_id: process.player_id,
+                                        register: *register,
+                                        value,
+                                    },
+                                );
+                            }
+                        }
+                    }
+                }
+                Breakpoint::LiveCheck => {
+                    if vm.last_live_check == vm.cycles {
+                        hit(
+                            id,
+                            Cause::LiveCheck {
+                                check_interval: vm.check_interval,
+                            },
+                        );
+                    }
+                }
+            }
+        }
+
+        hits
+    }
+}
+
+/// The value of the register when the process meets the condition of a
+/// register breakpoint
+fn register_condition(breakpoint: &Breakpoint, process: &Process) -> Option<i32> {
+    match breakpoint {
+        Breakpoint::Register {
+            pid,
+            register,
+            comparison,
+            value,
+        } => {
+            if pid.is_some_and(|pid| pid != process.pid) {
+                return None;
+            }
+            let actual = *process.registers.get(register.checked_sub(1)?)?;
+            Some(actual).filter(|actual| comparison.holds(*actual, *value))
+        }
+        _ => None,
+    }
+}
+
+/// Whether the `size` bytes written at `at` overlap with `range`, both
+/// wrapping around the memory
+fn overlaps(range: &Range<usize>, at: usize, size: usize, mem_size: usize) -> bool {
+    let len = range.end.saturating_sub(range.start);
+    if len == 0 || size == 0 {
+        return false;
+    }
+    if len >= mem_size {
+        return true;
+    }
+
+    // Offsets of both starts from the start of the range
+    let start = range.start % mem_size;
+    let written = (at % mem_size + mem_size - start) % mem_size;
+    written < len || written + size > mem_size
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            Comparison::Equal => "==",
+            Comparison::NotEqual => "!=",
+            Comparison::Less => "<",
+            Comparison::Greater => ">",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Breakpoint::Address(addr) => write!(f, "pc at {}", addr),
+            Breakpoint::OpCode(op) => write!(f, "op {}", op.to_string().to_lowercase()),
+            Breakpoint::Pid(pid) => write!(f, "process #{}", pid),
+            Breakpoint::Player(player_id) => write!(f, "player {}", player_id),
+            Breakpoint::Watch { range, by } => {
+                write!(f, "writes to {}..{}", range.start, range.end)?;
+                match by {
+                    Some(player_id) => write!(f, " by player {}", player_id),
+                    None => Ok(()),
+                }
+            }
+            Breakpoint::Register {
+                pid,
+                register,
+                comparison,
+                value,
+            } => {
+                write!(f, "r{} {} {}", register, comparison, value)?;
+                match pid {
+                    Some(pid) => write!(f, " in process #{}", pid),
+                    None => Ok(()),
+                }
+            }
+            Breakpoint::LiveCheck => write!(f, "live check"),
+        }
+    }
+}
+
+impl fmt::Display for Hit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] Breakpoint {}: ", self.cycle, self.breakpoint)?;
+        match &self.cause {
+            Cause::Reached { pid, pc, .. } => write!(f, "#{} reached {}", pid, pc),
+            Cause::Executed { pid, pc, op, .. } => write!(
+                f,
+                "#{} executed '{}' at {}",
+                pid,
+                op.to_string().to_lowercase(),
+                pc
+            ),
+            Cause::Written {
+                pid,
+                owner,
+                at,
+                size,
+            } => write!(
+                f,
+                "#{} (player {}) wrote {} bytes at {}",
+                pid, owner, size, at
+            ),
+            Cause::Register {
+                pid,
+                register,
+                value,
+                ..
+            } => write!(f, "#{} has r{} = {}", pid, register, value),
+            Cause::LiveCheck { check_interval } => {
+                write!(f, "live check, next in {} cycles", check_interval)
+            }
+        }
+    }
+}
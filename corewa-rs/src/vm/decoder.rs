@@ -13,7 +13,12 @@ pub trait Decode: Read {
         op_from_code(op_code).ok_or(InvalidOpCode(op_code))
     }
 
-    fn decode_instr(&self, op: OpType, addr: usize) -> Result<Instruction, InstrDecodeError> {
+    fn decode_instr(
+        &self,
+        op: OpType,
+        addr: usize,
+        reg_count: usize,
+    ) -> Result<Instruction, InstrDecodeError> {
         // Decode the operation's parameter types and start counting the
         // instruction's total byte size:
         // If the op has a pcb, decode it and start counting at 2 bytes:
@@ -39,8 +44,12 @@ pub trait Decode: Read {
 
         for idx in 0..op_spec.param_count {
             let param_type = param_types[idx];
-            let (param, param_byte_size) =
-                self.decode_param(param_type, addr + instr_byte_size, &op_spec.dir_size)?;
+            let (param, param_byte_size) = self.decode_param(
+                param_type,
+                addr + instr_byte_size,
+                &op_spec.dir_size,
+                reg_count,
+            )?;
             instr_byte_size += param_byte_size;
             params[idx] = param;
         }
@@ -57,15 +66,17 @@ pub trait Decode: Read {
         kind: ParamType,
         addr: usize,
         dir_size: &DirectSize,
+        reg_count: usize,
     ) -> Result<(Param, usize), InstrDecodeError> {
         use ParamType::*;
 
         let (value, size) = match (&kind, dir_size) {
             (Register, _) => {
                 let reg = self[addr];
-                match reg as usize {
-                    1..=REG_COUNT => (i32::from(reg), 1),
-                    _ => return Err(InstrDecodeError::InvalidRegNumber(reg)),
+                if (1..=reg_count).contains(&usize::from(reg)) {
+                    (i32::from(reg), 1)
+                } else {
+                    return Err(InstrDecodeError::InvalidRegNumber(reg));
                 }
             }
             (Direct, DirectSize::FourBytes) => (self.read_i32(addr), 4),
@@ -80,12 +91,6 @@ impl<T: Read> Decode for T {}
 
 type ParamTypes = [ParamType; MAX_PARAMS];
 
-impl Default for ParamType {
-    fn default() -> Self {
-        ParamType::Register
-    }
-}
-
 fn params_from_unambiguous_masks(masks: [u8; MAX_PARAMS]) -> ParamTypes {
     fn to_param_type(mask: u8) -> ParamType {
         match mask {
@@ -154,7 +159,7 @@ fn read_type_and_mask_flag(param_code: u8) -> Option<(ParamType, u8)> {
     }
 }
 
-fn op_from_code(code: u8) -> Option<OpType> {
+pub fn op_from_code(code: u8) -> Option<OpType> {
     let op_type = match code {
         1 => OpType::Live,
         2 => OpType::Ld,
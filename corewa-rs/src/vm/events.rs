@@ -0,0 +1,130 @@
+use super::types::*;
+
+use std::fmt;
+
+/// Something that happened in the virtual machine during a given cycle
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub cycle: u32,
+    pub kind: EventKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum EventKind {
+    /// A process executed a decoded instruction located at `pc`
+    InstructionExecuted {
+        pid: Pid,
+        player_id: PlayerId,
+        pc: usize,
+        instr: Instruction,
+    },
+    /// `size` bytes of memory were written starting at `at`
+    MemoryWritten {
+        pid: Pid,
+        owner: PlayerId,
+        at: usize,
+        size: usize,
+    },
+    ProcessForked {
+        parent: Pid,
+        child: Pid,
+        player_id: PlayerId,
+        pc: usize,
+    },
+    ProcessKilled {
+        pid: Pid,
+        player_id: PlayerId,
+    },
+    /// A process reported `player_id` as being alive
+    LiveReported {
+        pid: Pid,
+        player_id: PlayerId,
+    },
+    LiveCheck {
+        killed: usize,
+        check_interval: u32,
+    },
+    /// A process displayed a character with `aff`
+    Aff {
+        pid: Pid,
+        player_id: PlayerId,
+        chr: char,
+    },
+}
+
+/// Buffers events until they get drained.
+/// Recording is disabled by default in which case events are not even built
+#[derive(Debug, Default)]
+pub struct EventLog(Option<Vec<Event>>);
+
+impl EventLog {
+    pub fn enable(&mut self) {
+        self.0.get_or_insert_with(Vec::new);
+    }
+
+    pub fn disable(&mut self) {
+        self.0 = None;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.0.is_some()
+    }
+
+    #[inline]
+    pub fn record(&mut self, cycle: u32, make_kind: impl FnOnce() -> EventKind) {
+        if let Some(events) = &mut self.0 {
+            events.push(Event {
+                cycle,
+                kind: make_kind(),
+            })
+        }
+    }
+
+    /// The events recorded since the last drain
+    pub fn pending(&self) -> &[Event] {
+        self.0.as_deref().unwrap_or(&[])
+    }
+
+    pub fn drain(&mut self) -> impl Iterator<Item = Event> + '_ {
+        self.0.iter_mut().flat_map(|events| events.drain(..))
+    }
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use EventKind::*;
+
+        match self {
+            InstructionExecuted { pid, pc, instr, .. } => {
+                write!(f, "#{} executed '{}' at {}", pid, instr, pc)
+            }
+            MemoryWritten { pid, at, size, .. } => {
+                write!(f, "#{} wrote {} bytes at {}", pid, size, at)
+            }
+            ProcessForked {
+                parent, child, pc, ..
+            } => {
+                write!(f, "#{} forked #{} at {}", parent, child, pc)
+            }
+            ProcessKilled { pid, .. } => write!(f, "#{} was killed", pid),
+            LiveReported { pid, player_id } => {
+                write!(f, "#{} reported player {} alive", pid, player_id)
+            }
+            LiveCheck {
+                killed,
+                check_interval,
+            } => write!(
+                f,
+                "Live check killed {} processes, next in {} cycles",
+                killed, check_interval
+            ),
+            Aff { pid, chr, .. } => write!(f, "#{} displayed {:?}", pid, chr),
+        }
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.cycle, self.kind)
+    }
+}
@@ -1,16 +1,20 @@
-use super::{process::Process, types::*, PidPool};
-use crate::spec::{ParamType, MEM_SIZE};
+use super::events::EventKind;
+use super::{events::EventLog, memory::Memory, process::Process, types::*, PidPool};
+use crate::spec::{ParamType, VmConfig};
 
-use fxhash::FxHashSet as HashSet;
+use fxhash::{FxHashMap as HashMap, FxHashSet as HashSet};
 
 pub struct ExecutionContext<'a> {
-    pub memory: &'a mut super::memory::Memory<MEM_SIZE>,
+    pub config: &'a VmConfig,
+    pub memory: &'a mut Memory,
     pub process: &'a mut Process,
     pub forks: &'a mut Vec<Process>,
     pub cycle: u32,
     pub live_count: &'a mut u32,
     pub pid_pool: &'a mut PidPool,
     pub live_ids: &'a mut HashSet<PlayerId>,
+    pub outputs: &'a mut HashMap<PlayerId, String>,
+    pub events: &'a mut EventLog,
 }
 
 impl ExecutionContext<'_> {
@@ -21,7 +25,10 @@ impl ExecutionContext<'_> {
             Register => self.process.registers[param.value as usize - 1],
             Direct => param.value,
             Indirect => {
-                let at = self.process.pc.offset(param.value as isize, offset_type);
+                let at = self
+                    .process
+                    .pc
+                    .offset(param.value as isize, offset_type, self.config);
                 self.memory.read_i32(at)
             }
         }
@@ -36,4 +43,29 @@ impl ExecutionContext<'_> {
         debug_assert_eq!(param.kind, ParamType::Register);
         self.process.registers[param.value as usize - 1] = value;
     }
+
+    pub fn write_i32(&mut self, value: i32, at: usize) {
+        let (pid, owner) = (self.process.pid, self.process.player_id);
+
+        self.memory.write_i32(value, owner, at);
+        self.events.record(self.cycle, || EventKind::MemoryWritten {
+            pid,
+            owner,
+            at,
+            size: std::mem::size_of::<i32>(),
+        });
+    }
+
+    pub fn fork(&mut self, pc: usize) {
+        let child = Process::fork(self.pid_pool.get(), pc.into(), self);
+        let (parent, child_pid, player_id) = (self.process.pid, child.pid, child.player_id);
+
+        self.forks.push(child);
+        self.events.record(self.cycle, || EventKind::ProcessForked {
+            parent,
+            child: child_pid,
+            player_id,
+            pc,
+        });
+    }
 }
@@ -1,4 +1,4 @@
-use super::{execution_context::ExecutionContext, process::Process, types::*};
+use super::{events::EventKind, execution_context::ExecutionContext, types::*, MAX_OUTPUT_LEN};
 use crate::spec::ParamType;
 
 pub fn exec_live(instr: &Instruction, ctx: &mut ExecutionContext<'_>) {
@@ -7,6 +7,12 @@ pub fn exec_live(instr: &Instruction, ctx: &mut ExecutionContext<'_>) {
     *ctx.live_count += 1;
     ctx.process.last_live_cycle = ctx.cycle;
     ctx.live_ids.insert(player_id_p.value);
+
+    let pid = ctx.process.pid;
+    ctx.events.record(ctx.cycle, || EventKind::LiveReported {
+        pid,
+        player_id: player_id_p.value,
+    });
 }
 
 pub fn exec_ld(instr: &Instruction, ctx: &mut ExecutionContext<'_>) {
@@ -24,13 +30,13 @@ pub fn exec_st(instr: &Instruction, ctx: &mut ExecutionContext<'_>) {
     let value_to_store = ctx.get_reg(src_p);
     match dst_p.kind {
         ParamType::Register => ctx.set_reg(dst_p, value_to_store),
-        ParamType::Indirect => ctx.memory.write_i32(
-            value_to_store,
-            ctx.process.player_id,
-            ctx.process
+        ParamType::Indirect => {
+            let at = ctx
+                .process
                 .pc
-                .offset(dst_p.value as isize, OffsetType::Limited),
-        ),
+                .offset(dst_p.value as isize, OffsetType::Limited, ctx.config);
+            ctx.write_i32(value_to_store, at)
+        }
         _ => unreachable!("St Param #2 invariant broken"),
     }
 }
@@ -40,7 +46,7 @@ pub fn exec_add(instr: &Instruction, ctx: &mut ExecutionContext<'_>) {
 
     let lhs = ctx.get_reg(lhs_p);
     let rhs = ctx.get_reg(rhs_p);
-    let result = lhs + rhs;
+    let result = lhs.wrapping_add(rhs);
     ctx.set_reg(dst_p, result);
 
     ctx.process.zf = result == 0;
@@ -51,7 +57,7 @@ pub fn exec_sub(instr: &Instruction, ctx: &mut ExecutionContext<'_>) {
 
     let lhs = ctx.get_reg(lhs_p);
     let rhs = ctx.get_reg(rhs_p);
-    let result = lhs - rhs;
+    let result = lhs.wrapping_sub(rhs);
     ctx.set_reg(dst_p, result);
 
     ctx.process.zf = result == 0;
@@ -96,13 +102,15 @@ pub fn exec_zjmp(instr: &Instruction, ctx: &mut ExecutionContext<'_>) {
     if !ctx.process.zf {
         return;
     }
-    let jumped_offet = ctx
-        .process
-        .pc
-        .offset(offset_p.value as isize, OffsetType::Limited);
+    let jumped_offet =
+        ctx.process
+            .pc
+            .offset(offset_p.value as isize, OffsetType::Limited, ctx.config);
     ctx.process.pc = jumped_offet.into();
     // Negating the instruction jump
-    ctx.process.pc.advance(-(instr.byte_size as isize))
+    ctx.process
+        .pc
+        .advance(-(instr.byte_size as isize), ctx.config)
 }
 
 pub fn exec_ldi(instr: &Instruction, ctx: &mut ExecutionContext<'_>) {
@@ -110,10 +118,10 @@ pub fn exec_ldi(instr: &Instruction, ctx: &mut ExecutionContext<'_>) {
 
     let lhs = ctx.get_param(lhs_p, OffsetType::Limited);
     let rhs = ctx.get_param(rhs_p, OffsetType::Limited);
-    let addr = (lhs + rhs) as isize;
+    let addr = lhs.wrapping_add(rhs) as isize;
     let value = ctx
         .memory
-        .read_i32(ctx.process.pc.offset(addr, OffsetType::Limited));
+        .read_i32(ctx.process.pc.offset(addr, OffsetType::Limited, ctx.config));
     ctx.set_reg(dst_p, value)
 }
 
@@ -123,12 +131,12 @@ pub fn exec_sti(instr: &Instruction, ctx: &mut ExecutionContext<'_>) {
     let value = ctx.get_reg(src_p);
     let lhs = ctx.get_param(lhs_p, OffsetType::Limited);
     let rhs = ctx.get_param(rhs_p, OffsetType::Limited);
-    let offset = lhs + rhs;
-    ctx.memory.write_i32(
-        value,
-        ctx.process.player_id,
-        ctx.process.pc.offset(offset as isize, OffsetType::Limited),
-    );
+    let offset = lhs.wrapping_add(rhs);
+    let at = ctx
+        .process
+        .pc
+        .offset(offset as isize, OffsetType::Limited, ctx.config);
+    ctx.write_i32(value, at);
 }
 
 pub fn exec_fork(instr: &Instruction, ctx: &mut ExecutionContext<'_>) {
@@ -137,9 +145,8 @@ pub fn exec_fork(instr: &Instruction, ctx: &mut ExecutionContext<'_>) {
     let forked_pc = ctx
         .process
         .pc
-        .offset(offset_p.value as isize, OffsetType::Limited);
-    let child_process = Process::fork(ctx.pid_pool.get(), forked_pc.into(), ctx);
-    ctx.forks.push(child_process);
+        .offset(offset_p.value as isize, OffsetType::Limited, ctx.config);
+    ctx.fork(forked_pc);
 }
 
 pub fn exec_lld(instr: &Instruction, ctx: &mut ExecutionContext<'_>) {
@@ -156,10 +163,10 @@ pub fn exec_lldi(instr: &Instruction, ctx: &mut ExecutionContext<'_>) {
 
     let lhs = ctx.get_param(lhs_p, OffsetType::Long);
     let rhs = ctx.get_param(rhs_p, OffsetType::Long);
-    let addr = (lhs + rhs) as isize;
+    let addr = lhs.wrapping_add(rhs) as isize;
     let value = ctx
         .memory
-        .read_i32(ctx.process.pc.offset(addr, OffsetType::Long));
+        .read_i32(ctx.process.pc.offset(addr, OffsetType::Long, ctx.config));
     ctx.set_reg(dst_p, value);
 
     ctx.process.zf = value == 0;
@@ -171,9 +178,25 @@ pub fn exec_lfork(instr: &Instruction, ctx: &mut ExecutionContext<'_>) {
     let forked_pc = ctx
         .process
         .pc
-        .offset(offset_p.value as isize, OffsetType::Long);
-    let child_process = Process::fork(ctx.pid_pool.get(), forked_pc.into(), ctx);
-    ctx.forks.push(child_process);
+        .offset(offset_p.value as isize, OffsetType::Long, ctx.config);
+    ctx.fork(forked_pc);
 }
 
-pub fn exec_aff(_instr: &Instruction, _ctx: &mut ExecutionContext<'_>) {}
+pub fn exec_aff(instr: &Instruction, ctx: &mut ExecutionContext<'_>) {
+    let [chr_p, _, _] = &instr.params;
+
+    let chr = char::from(ctx.get_reg(chr_p).rem_euclid(256) as u8);
+    let (pid, player_id) = (ctx.process.pid, ctx.process.player_id);
+    let output = ctx.outputs.entry(player_id).or_default();
+    // Characters take at least a byte, so only long outputs need counting
+    if output.len() >= MAX_OUTPUT_LEN && output.chars().count() >= MAX_OUTPUT_LEN {
+        output.remove(0);
+    }
+    output.push(chr);
+
+    ctx.events.record(ctx.cycle, || EventKind::Aff {
+        pid,
+        player_id,
+        chr,
+    });
+}
@@ -0,0 +1,161 @@
+use super::types::{Player, PlayerId};
+use crate::spec::*;
+
+use byteorder::{BigEndian, ReadBytesExt};
+use fxhash::FxHashSet as HashSet;
+use std::{
+    ffi::CStr,
+    io::{Cursor, Read},
+};
+
+/// A champion whose header has been validated, ready to be loaded in memory
+pub struct LoadedChampion<'a> {
+    pub name: String,
+    pub comment: String,
+    pub code: &'a [u8],
+}
+
+/// Validates every player before anything gets loaded in the arena so that a
+/// failure leaves the virtual machine untouched. The players `loaded` in the
+/// arena already count towards the limit and keep their ids
+pub fn read_players<'a>(
+    players: &'a [(PlayerId, Vec<u8>)],
+    loaded: &[Player],
+    config: &VmConfig,
+) -> Result<Vec<LoadedChampion<'a>>, LoadError> {
+    let player_count = loaded.len() + players.len();
+    if player_count > config.max_players {
+        return Err(LoadError::TooManyPlayers(player_count, config.max_players));
+    }
+
+    let mut seen_ids = loaded
+        .iter()
+        .map(|player| player.id)
+        .collect::<HashSet<_>>();
+    for (player_id, _) in players {
+        if !seen_ids.insert(*player_id) {
+            return Err(LoadError::DuplicatePlayerId(*player_id));
+        }
+    }
+
+    players
+        .iter()
+        .map(|(player_id, program)| {
+            read_champion(program, config)
+                .map_err(|err| LoadError::InvalidChampion(*player_id, err))
+        })
+        .collect()
+}
+
+fn read_champion<'a>(
+    program: &'a [u8],
+    config: &VmConfig,
+) -> Result<LoadedChampion<'a>, ChampionError> {
+    let champion = parse_champion(program)?;
+
+    if champion.code.len() > config.champ_max_size {
+        return Err(ChampionError::TooBig(
+            champion.code.len(),
+            config.champ_max_size,
+        ));
+    }
+
+    Ok(champion)
+}
+
+/// Validates a champion's header regardless of any arena limit
+pub fn parse_champion(program: &[u8]) -> Result<LoadedChampion<'_>, ChampionError> {
+    let header_bytes = program
+        .get(..HEADER_SIZE)
+        .ok_or(ChampionError::TruncatedHeader(program.len()))?;
+    let header = Header::from_bytes(header_bytes);
+
+    let magic = header.magic;
+    if magic != COREWAR_MAGIC {
+        return Err(ChampionError::InvalidMagic(magic));
+    }
+
+    let code = &program[HEADER_SIZE..];
+    let prog_size = header.prog_size;
+    if prog_size as usize != code.len() {
+        return Err(ChampionError::SizeMismatch(prog_size, code.len()));
+    }
+
+    let name = c_string(&header.prog_name).ok_or(ChampionError::InvalidName)?;
+    let comment = c_string(&header.prog_comment).ok_or(ChampionError::InvalidComment)?;
+
+    Ok(LoadedChampion {
+        name,
+        comment,
+        code,
+    })
+}
+
+fn c_string(bytes: &[u8]) -> Option<String> {
+    CStr::from_bytes_until_nul(bytes)
+        .ok()?
+        .to_str()
+        .ok()
+        .map(String::from)
+}
+
+impl Header {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let mut reader = Cursor::new(bytes);
+
+        // The header's size is checked beforehand so none of the reads can fail
+        let magic = reader
+            .read_u32::<BigEndian>()
+            .expect("Failed to read Magic");
+
+        let mut prog_name = [0; PROG_NAME_LENGTH + 1];
+        reader
+            .read_exact(&mut prog_name)
+            .expect("Failed to read program name");
+
+        let prog_size = reader
+            .read_u32::<BigEndian>()
+            .expect("Failed to read program size");
+
+        let mut prog_comment = [0; PROG_COMMENT_LENGTH + 1];
+        reader
+            .read_exact(&mut prog_comment)
+            .expect("Failed to read program comment");
+
+        Self {
+            magic,
+            prog_name,
+            prog_size,
+            prog_comment,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LoadError {
+    #[error("Too many players: {0} (maximum allowed is {1})")]
+    TooManyPlayers(usize, usize),
+    #[error("The player id {0} is used by more than one player")]
+    DuplicatePlayerId(PlayerId),
+    #[error("Invalid champion for player {0}: {1}")]
+    InvalidChampion(PlayerId, ChampionError),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ChampionError {
+    #[error(
+        "The file is too small to contain a header: {0} bytes (a header is {} bytes)",
+        HEADER_SIZE
+    )]
+    TruncatedHeader(usize),
+    #[error("Invalid magic number: 0x{0:08X} (expected 0x{:08X})", COREWAR_MAGIC)]
+    InvalidMagic(u32),
+    #[error("The header announces {0} bytes of code but the champion has {1}")]
+    SizeMismatch(u32, usize),
+    #[error("The champion's code is too big: {0} bytes (maximum allowed is {1})")]
+    TooBig(usize, usize),
+    #[error("The champion's name is not a valid UTF-8 null-terminated string")]
+    InvalidName,
+    #[error("The champion's comment is not a valid UTF-8 null-terminated string")]
+    InvalidComment,
+}
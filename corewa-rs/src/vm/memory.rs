@@ -6,25 +6,30 @@ use crate::spec::*;
 use byteorder::{BigEndian, ByteOrder};
 use std::mem;
 
-pub struct Memory<const LEN: usize> {
-    pub values: WrappingArray<u8, LEN>,
-    pub ages: WrappingArray<u16, LEN>,
-    pub owners: WrappingArray<PlayerId, LEN>,
+#[derive(Clone)]
+pub struct Memory {
+    pub values: WrappingArray<u8>,
+    pub ages: WrappingArray<u16>,
+    pub owners: WrappingArray<PlayerId>,
 }
 
-impl Default for Memory<MEM_SIZE> {
+impl Default for Memory {
     fn default() -> Self {
+        Self::new(MEM_SIZE)
+    }
+}
+
+impl Memory {
+    pub fn new(size: usize) -> Self {
         Self {
-            values: [0; MEM_SIZE].into(),
-            ages: [1024; MEM_SIZE].into(),
-            owners: [0; MEM_SIZE].into(),
+            values: WrappingArray::filled(0, size),
+            ages: WrappingArray::filled(1024, size),
+            owners: WrappingArray::filled(0, size),
         }
     }
-}
 
-impl<const LEN: usize> Memory<LEN> {
     pub fn size(&self) -> usize {
-        MEM_SIZE
+        self.values.len()
     }
 
     pub fn tick(&mut self) {
@@ -42,7 +47,7 @@ impl<const LEN: usize> Memory<LEN> {
     }
 
     pub fn read_i32(&self, addr: usize) -> i32 {
-        if addr > MEM_SIZE - mem::size_of::<i32>() {
+        if addr + mem::size_of::<i32>() > self.size() {
             i32::from_be_bytes([
                 self[addr + 0],
                 self[addr + 1],
@@ -55,7 +60,7 @@ impl<const LEN: usize> Memory<LEN> {
     }
 
     pub fn read_i16(&self, addr: usize) -> i16 {
-        if addr > MEM_SIZE - mem::size_of::<i16>() {
+        if addr + mem::size_of::<i16>() > self.size() {
             i16::from_be_bytes([self[addr + 0], self[addr + 1]])
         } else {
             BigEndian::read_i16(&self.values.inner()[addr..addr + 2])
@@ -69,7 +74,7 @@ impl<const LEN: usize> Memory<LEN> {
     }
 }
 
-impl<const LEN: usize> std::ops::Index<usize> for Memory<LEN> {
+impl std::ops::Index<usize> for Memory {
     type Output = u8;
 
     fn index(&self, index: usize) -> &u8 {
@@ -77,7 +82,7 @@ impl<const LEN: usize> std::ops::Index<usize> for Memory<LEN> {
     }
 }
 
-impl<const LEN: usize> super::decoder::Read for Memory<LEN> {
+impl super::decoder::Read for Memory {
     fn read_i16(&self, at: usize) -> i16 {
         self.read_i16(at)
     }
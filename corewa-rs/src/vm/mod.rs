@@ -1,6 +1,14 @@
+pub mod debugger;
 pub mod decoder;
+pub mod events;
+pub mod loader;
 pub mod memory;
+pub mod outcome;
 pub mod process;
+pub mod profiler;
+pub mod replay;
+pub mod snapshot;
+pub mod stats;
 pub mod types;
 
 mod execution_context;
@@ -10,23 +18,35 @@ mod wrapping_array;
 
 use crate::spec::*;
 use decoder::Decode;
+use events::{Event, EventKind, EventLog};
 use execution_context::ExecutionContext;
+use loader::LoadError;
 use memory::Memory;
+use outcome::{MatchOutcome, Termination};
 use process::{Process, ProcessState};
+use profiler::{Profile, Profiler};
+use snapshot::Snapshot;
+use stats::{Stats, StatsCollector};
 use types::*;
 
-use std::ffi::CStr;
-
 use fxhash::{FxHashMap as HashMap, FxHashSet as HashSet};
 
+/// The number of characters kept in each player's `aff` output, older ones
+/// are dropped so that a looping `aff` cannot grow it forever
+pub const MAX_OUTPUT_LEN: usize = 4096;
+
 pub struct VirtualMachine {
+    pub config: VmConfig,
     pub players: Vec<Player>,
 
-    pub memory: Memory<MEM_SIZE>,
+    pub memory: Memory,
     pub processes: Vec<Process>,
     pub pid_pool: PidPool,
 
     pub last_lives: HashMap<PlayerId, u32>,
+    /// The last `MAX_OUTPUT_LEN` characters displayed by each player's
+    /// processes with `aff`
+    pub outputs: HashMap<PlayerId, String>,
 
     pub cycles: u32,
     pub last_live_check: u32,
@@ -34,38 +54,144 @@ pub struct VirtualMachine {
     pub live_count_since_last_check: u32,
     pub checks_without_cycle_decrement: u32,
 
-    pub process_count_per_cells: [u32; MEM_SIZE],
+    pub process_count_per_cells: Vec<u32>,
     pub process_count_by_player_id: HashMap<PlayerId, u32>,
+    /// Lives reported for each player since the start of the match
+    pub live_count_by_player_id: HashMap<PlayerId, u32>,
+
+    pub events: EventLog,
+    pub profiler: Profiler,
+    pub stats: StatsCollector,
 }
 
 impl VirtualMachine {
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
+        Self::with_config(VmConfig::default())
+    }
+
+    /// Creates a virtual machine playing by custom arena parameters.
+    /// The configuration is expected to have been checked with
+    /// `VmConfig::validate`
+    pub fn with_config(config: VmConfig) -> Self {
         Self {
-            players: Vec::with_capacity(MAX_PLAYERS),
+            players: Vec::with_capacity(config.max_players),
 
-            memory: Memory::default(),
+            memory: Memory::new(config.mem_size),
             processes: Vec::with_capacity(65536),
             pid_pool: PidPool::default(),
 
-            last_lives: HashMap::with_capacity_and_hasher(MAX_PLAYERS, Default::default()),
+            last_lives: HashMap::with_capacity_and_hasher(config.max_players, Default::default()),
+            outputs: HashMap::with_capacity_and_hasher(config.max_players, Default::default()),
 
             cycles: 0,
             last_live_check: 0,
-            check_interval: CHECK_INTERVAL,
+            check_interval: config.check_interval,
             live_count_since_last_check: 0,
             checks_without_cycle_decrement: 0,
 
-            process_count_per_cells: [0; MEM_SIZE],
+            process_count_per_cells: vec![0; config.mem_size],
             process_count_by_player_id: HashMap::with_capacity_and_hasher(
-                MAX_PLAYERS,
+                config.max_players,
                 Default::default(),
             ),
+            live_count_by_player_id: HashMap::with_capacity_and_hasher(
+                config.max_players,
+                Default::default(),
+            ),
+
+            events: EventLog::default(),
+            profiler: Profiler::default(),
+            stats: StatsCollector::default(),
+
+            config,
         }
     }
 
+    /// Starts recording the events happening in the virtual machine.
+    /// They can then be retrieved with `drain_events`
+    pub fn enable_events(&mut self) {
+        self.events.enable()
+    }
+
+    /// Takes every event recorded since the last call
+    pub fn drain_events(&mut self) -> impl Iterator<Item = Event> + '_ {
+        self.events.drain()
+    }
+
+    /// Starts counting where processes spend their cycles.
+    /// The counts can then be read with `profile`
+    pub fn enable_profiling(&mut self) {
+        self.profiler.enable()
+    }
+
+    /// What was counted since profiling was enabled
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profiler.profile()
+    }
+
+    /// Starts sampling territory statistics every `interval` cycles.
+    /// The samples can then be read with `stats`
+    pub fn enable_stats(&mut self, interval: u32) {
+        self.stats.enable(interval)
+    }
+
+    /// What was sampled since the statistics were enabled
+    pub fn stats(&self) -> Option<&Stats> {
+        self.stats.stats()
+    }
+
+    /// Captures the whole state of the match, along with the profile so that
+    /// cycles run again after a restore are not counted twice.
+    /// The configuration, the recorded events and the statistics are left out
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            players: self.players.clone(),
+            memory: self.memory.clone(),
+            processes: self.processes.clone(),
+            pid_pool: self.pid_pool.clone(),
+            last_lives: self.last_lives.clone(),
+            outputs: self.outputs.clone(),
+            cycles: self.cycles,
+            last_live_check: self.last_live_check,
+            check_interval: self.check_interval,
+            live_count_since_last_check: self.live_count_since_last_check,
+            checks_without_cycle_decrement: self.checks_without_cycle_decrement,
+            process_count_per_cells: self.process_count_per_cells.clone(),
+            process_count_by_player_id: self.process_count_by_player_id.clone(),
+            live_count_by_player_id: self.live_count_by_player_id.clone(),
+            profile: self.profiler.profile().cloned(),
+        }
+    }
+
+    /// Puts the match back in the state captured by `snapshot`.
+    /// The snapshot must come from a virtual machine with the same
+    /// configuration. The profile is rolled back and statistics sampled after
+    /// the snapshot are dropped
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        let snapshot = snapshot.clone();
+
+        self.players = snapshot.players;
+        self.memory = snapshot.memory;
+        self.processes = snapshot.processes;
+        self.pid_pool = snapshot.pid_pool;
+        self.last_lives = snapshot.last_lives;
+        self.outputs = snapshot.outputs;
+        self.cycles = snapshot.cycles;
+        self.last_live_check = snapshot.last_live_check;
+        self.check_interval = snapshot.check_interval;
+        self.live_count_since_last_check = snapshot.live_count_since_last_check;
+        self.checks_without_cycle_decrement = snapshot.checks_without_cycle_decrement;
+        self.process_count_per_cells = snapshot.process_count_per_cells;
+        self.process_count_by_player_id = snapshot.process_count_by_player_id;
+        self.live_count_by_player_id = snapshot.live_count_by_player_id;
+
+        self.profiler.rewind(snapshot.profile);
+        self.stats.rewind(self.cycles);
+    }
+
     pub fn tick(&mut self) {
-        if self.processes.is_empty() {
+        if self.is_over() {
             return;
         }
 
@@ -79,43 +205,109 @@ impl VirtualMachine {
         if should_live_check {
             self.live_check()
         }
+
+        if self.stats.is_due(self.cycles) || self.is_over() {
+            self.stats.sample(
+                self.cycles,
+                self.check_interval,
+                &self.memory,
+                &self.players,
+                &self.process_count_by_player_id,
+                &self.live_count_by_player_id,
+            );
+        }
+    }
+
+    /// A match is over once every process has been killed
+    pub fn is_over(&self) -> bool {
+        self.processes.is_empty()
+    }
+
+    /// The outcome of the match if it is over
+    pub fn outcome(&self) -> Option<MatchOutcome> {
+        if self.is_over() {
+            Some(self.outcome_with(Termination::AllProcessesDead))
+        } else {
+            None
+        }
     }
 
-    pub fn load_players(&mut self, players: &[(PlayerId, Vec<u8>)]) {
-        let player_spacing = MEM_SIZE / players.len().max(1);
-        for (i, (player_id, program)) in players.iter().enumerate() {
-            let header_bytes = &program[..HEADER_SIZE];
-            let header = Header::from_bytes(header_bytes);
+    /// Runs the match until every process has been killed
+    pub fn run_to_completion(&mut self) -> MatchOutcome {
+        while !self.is_over() {
+            self.tick();
+        }
 
+        self.outcome_with(Termination::AllProcessesDead)
+    }
+
+    /// Runs the match until every process has been killed or until the cycle
+    /// count reaches `cycle_limit`, whichever comes first
+    pub fn run_until(&mut self, cycle_limit: u32) -> MatchOutcome {
+        while !self.is_over() && self.cycles < cycle_limit {
+            self.tick();
+        }
+
+        let termination = if self.is_over() {
+            Termination::AllProcessesDead
+        } else {
+            Termination::CycleLimit
+        };
+
+        self.outcome_with(termination)
+    }
+
+    fn outcome_with(&self, termination: Termination) -> MatchOutcome {
+        let last_lives = self
+            .players
+            .iter()
+            .map(|player| {
+                let last_live = self.last_lives.get(&player.id).copied().unwrap_or(0);
+                (player.id, last_live)
+            })
+            .collect();
+
+        MatchOutcome::decide(last_lives, self.cycles, termination)
+    }
+
+    /// Loads every player's champion in the arena.
+    /// Nothing is loaded if any of the champions is invalid
+    pub fn load_players(&mut self, players: &[(PlayerId, Vec<u8>)]) -> Result<(), LoadError> {
+        let champions = loader::read_players(players, &self.players, &self.config)?;
+
+        let player_spacing = self.config.mem_size / players.len().max(1);
+        for (i, ((player_id, _), champion)) in players.iter().zip(champions).enumerate() {
+            let origin = i * player_spacing;
             self.players.push(Player {
                 id: *player_id,
-                name: header
-                    .name()
-                    .to_owned()
-                    .into_string()
-                    .expect("Invalid UTF8 in program name"),
-                comment: header
-                    .comment()
-                    .to_owned()
-                    .into_string()
-                    .expect("Invalid UTF8 in program comment"),
-                size: program.len() - HEADER_SIZE,
+                name: champion.name,
+                comment: champion.comment,
+                size: champion.code.len(),
+                origin,
             });
 
-            let champion = &program[HEADER_SIZE..];
-            self.load_champion(champion, *player_id, i * player_spacing);
+            self.load_champion(champion.code, *player_id, origin);
         }
+
+        Ok(())
     }
 
     fn load_champion(&mut self, champion: &[u8], player_id: PlayerId, at: usize) {
         self.memory.write(at, champion, player_id);
 
-        let mut starting_process = Process::new(self.pid_pool.get(), player_id, at.into());
+        let mut starting_process = Process::new(
+            self.pid_pool.get(),
+            player_id,
+            at.into(),
+            self.config.reg_count,
+        );
         starting_process.registers[0] = player_id;
         self.processes.push(starting_process);
         self.last_lives.insert(player_id, 0);
+        self.outputs.insert(player_id, String::new());
         self.process_count_per_cells[at] += 1;
         self.process_count_by_player_id.insert(player_id, 1);
+        self.live_count_by_player_id.insert(player_id, 0);
     }
 
     fn run_processes(&mut self) {
@@ -129,9 +321,21 @@ impl VirtualMachine {
                     if let Ok(op) = self.memory.decode_op(process.pc.addr()) {
                         let exec_at = self.cycles + op_spec(op).cycles - 1;
                         process.state = ProcessState::Executing { exec_at, op };
+                        self.profiler.record(
+                            process.pid,
+                            process.player_id,
+                            process.pc.addr(),
+                            |counters| counters.wait_cycles += 1,
+                        );
                     } else {
                         let pc_start = process.pc.addr();
-                        process.pc.advance(1);
+                        self.profiler.record(
+                            process.pid,
+                            process.player_id,
+                            pc_start,
+                            |counters| counters.invalid_op_codes += 1,
+                        );
+                        process.pc.advance(1, &self.config);
                         self.process_count_per_cells[pc_start] -= 1;
                         self.process_count_per_cells[process.pc.addr()] += 1;
                     }
@@ -139,9 +343,26 @@ impl VirtualMachine {
                 // Execute
                 ProcessState::Executing { exec_at, op } if exec_at == self.cycles => {
                     let pc_start = process.pc.addr();
-                    match self.memory.decode_instr(op, pc_start) {
+                    match self
+                        .memory
+                        .decode_instr(op, pc_start, self.config.reg_count)
+                    {
                         Ok(instr) => {
+                            self.profiler.record(
+                                process.pid,
+                                process.player_id,
+                                pc_start,
+                                |counters| counters.executions += 1,
+                            );
+                            self.events
+                                .record(self.cycles, || EventKind::InstructionExecuted {
+                                    pid: process.pid,
+                                    player_id: process.player_id,
+                                    pc: pc_start,
+                                    instr: instr.clone(),
+                                });
                             let execution_context = ExecutionContext {
+                                config: &self.config,
                                 memory: &mut self.memory,
                                 process,
                                 forks: &mut forks,
@@ -149,19 +370,40 @@ impl VirtualMachine {
                                 live_count: &mut self.live_count_since_last_check,
                                 pid_pool: &mut self.pid_pool,
                                 live_ids: &mut lives,
+                                outputs: &mut self.outputs,
+                                events: &mut self.events,
                             };
                             execute_instr(&instr, execution_context);
+
+                            if let OpType::Live = instr.kind {
+                                let player_id = instr.params[0].value;
+                                if let Some(count) =
+                                    self.live_count_by_player_id.get_mut(&player_id)
+                                {
+                                    *count += 1;
+                                }
+                            }
                         }
                         Err(_e) => {
-                            process.pc.advance(1);
+                            self.profiler.record(
+                                process.pid,
+                                process.player_id,
+                                pc_start,
+                                |counters| counters.decode_failures += 1,
+                            );
+                            process.pc.advance(1, &self.config);
                         }
                     };
                     process.state = ProcessState::Idle;
                     self.process_count_per_cells[pc_start] -= 1;
                     self.process_count_per_cells[process.pc.addr()] += 1;
                 }
-
-                _ => (),
+                ProcessState::Executing { .. } => self.profiler.record(
+                    process.pid,
+                    process.player_id,
+                    process.pc.addr(),
+                    |counters| counters.wait_cycles += 1,
+                ),
             };
         }
 
@@ -184,8 +426,11 @@ impl VirtualMachine {
     fn live_check(&mut self) {
         let count_per_cells = &mut self.process_count_per_cells;
         let count_by_player_id = &mut self.process_count_by_player_id;
+        let events = &mut self.events;
 
+        let cycle = self.cycles;
         let last_live_check = self.last_live_check;
+        let process_count = self.processes.len();
         self.processes.retain(|process| {
             let killed = process.last_live_cycle <= last_live_check;
             if killed {
@@ -193,24 +438,41 @@ impl VirtualMachine {
                 if let Some(count) = count_by_player_id.get_mut(&process.player_id) {
                     *count -= 1;
                 }
+                events.record(cycle, || EventKind::ProcessKilled {
+                    pid: process.pid,
+                    player_id: process.player_id,
+                });
             }
             !killed
         });
+        let killed = process_count - self.processes.len();
+
+        let config = &self.config;
+        let previous_check_interval = self.check_interval;
 
-        if self.live_count_since_last_check >= NBR_LIVE {
-            self.check_interval = self.check_interval.saturating_sub(CYCLE_DELTA);
+        if self.live_count_since_last_check >= config.nbr_live {
+            self.check_interval = self.check_interval.saturating_sub(config.cycle_delta);
             self.checks_without_cycle_decrement = 0;
         } else {
             self.checks_without_cycle_decrement += 1;
         }
 
-        if self.checks_without_cycle_decrement >= MAX_CHECKS {
-            self.check_interval = self.check_interval.saturating_sub(CYCLE_DELTA);
+        if self.checks_without_cycle_decrement >= config.max_checks {
+            self.check_interval = self.check_interval.saturating_sub(config.cycle_delta);
             self.checks_without_cycle_decrement = 0;
         }
 
         self.live_count_since_last_check = 0;
         self.last_live_check = self.cycles;
+
+        let check_interval = self.check_interval;
+        if check_interval != previous_check_interval {
+            self.stats.record_check_interval(cycle, check_interval);
+        }
+        self.events.record(cycle, || EventKind::LiveCheck {
+            killed,
+            check_interval,
+        });
     }
 }
 
@@ -237,56 +499,11 @@ fn execute_instr(instr: &Instruction, mut ctx: ExecutionContext<'_>) {
         Aff => exec_aff,
     };
 
-    exec(&instr, &mut ctx);
-    ctx.process.pc.advance(instr.byte_size as isize);
-}
-
-impl Header {
-    fn from_bytes(bytes: &[u8]) -> Self {
-        use byteorder::{BigEndian, ReadBytesExt};
-        use std::io::{Cursor, Read};
-
-        let mut reader = Cursor::new(bytes);
-
-        let magic = reader
-            .read_u32::<BigEndian>()
-            .expect("Failed to read Magic");
-
-        let mut prog_name = [0; PROG_NAME_LENGTH + 1];
-        reader
-            .read_exact(&mut prog_name)
-            .expect("Failed to read program name");
-
-        let prog_size = reader
-            .read_u32::<BigEndian>()
-            .expect("Failed to read program size");
-
-        let mut prog_comment = [0; PROG_COMMENT_LENGTH + 1];
-        reader
-            .read_exact(&mut prog_comment)
-            .expect("Failed to read program name");
-
-        Self {
-            magic,
-            prog_name,
-            prog_size,
-            prog_comment,
-        }
-    }
-
-    fn name(&self) -> &CStr {
-        let idx = self.prog_name.iter().position(|&b| b == 0).unwrap();
-        CStr::from_bytes_with_nul(&self.prog_name[..idx + 1]).expect("Invalid program name")
-    }
-
-    fn comment(&self) -> &CStr {
-        let idx = self.prog_comment.iter().position(|&b| b == 0).unwrap();
-
-        CStr::from_bytes_with_nul(&self.prog_comment[..idx + 1]).expect("Invalid program comment")
-    }
+    exec(instr, &mut ctx);
+    ctx.process.pc.advance(instr.byte_size as isize, ctx.config);
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct PidPool(Pid);
 
 impl PidPool {
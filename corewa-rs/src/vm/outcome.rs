@@ -0,0 +1,79 @@
+use super::types::PlayerId;
+
+/// The result of a finished (or interrupted) match
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchOutcome {
+    /// The winning player, `None` only when no player was loaded
+    pub winner: Option<PlayerId>,
+    pub tie_break: TieBreak,
+    pub final_cycle: u32,
+    /// The cycle at which each player was last reported alive, in load order.
+    /// A player that was never reported alive has a value of `0`
+    pub last_lives: Vec<(PlayerId, u32)>,
+    pub termination: Termination,
+}
+
+/// How the winner was picked among the players
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TieBreak {
+    /// A single player was reported alive more recently than all the others
+    LastLive,
+    /// Several players share the most recent live report (which includes the
+    /// case where nobody was ever reported alive): the one that was loaded
+    /// last among them wins
+    LoadOrder,
+}
+
+/// Why the match stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    /// Every process has been killed by a live check
+    AllProcessesDead,
+    /// The match was stopped after reaching a cycle limit while some
+    /// processes were still running
+    CycleLimit,
+}
+
+impl MatchOutcome {
+    /// Picks the winner from each player's last live report.
+    /// `last_lives` must be in the players' load order
+    pub fn decide(
+        last_lives: Vec<(PlayerId, u32)>,
+        final_cycle: u32,
+        termination: Termination,
+    ) -> Self {
+        // `max_by_key` yields the last maximum element which implements the
+        // load order tie break
+        let latest = last_lives.iter().max_by_key(|(_, cycle)| *cycle).copied();
+
+        let tie_break = match latest {
+            Some((_, latest_cycle)) => {
+                let contenders = last_lives
+                    .iter()
+                    .filter(|(_, cycle)| *cycle == latest_cycle)
+                    .count();
+                if contenders > 1 {
+                    TieBreak::LoadOrder
+                } else {
+                    TieBreak::LastLive
+                }
+            }
+            None => TieBreak::LoadOrder,
+        };
+
+        Self {
+            winner: latest.map(|(player_id, _)| player_id),
+            tie_break,
+            final_cycle,
+            last_lives,
+            termination,
+        }
+    }
+
+    pub fn last_live(&self, player_id: PlayerId) -> Option<u32> {
+        self.last_lives
+            .iter()
+            .find(|(id, _)| *id == player_id)
+            .map(|(_, cycle)| *cycle)
+    }
+}
@@ -5,7 +5,7 @@ use super::{
 };
 use crate::spec::OpType;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Process {
     pub pid: Pid,
     pub player_id: PlayerId,
@@ -23,12 +23,12 @@ pub enum ProcessState {
 }
 
 impl Process {
-    pub fn new(pid: Pid, player_id: PlayerId, pc: ProgramCounter) -> Self {
+    pub fn new(pid: Pid, player_id: PlayerId, pc: ProgramCounter, reg_count: usize) -> Self {
         Self {
             pid,
             player_id,
             pc,
-            registers: Registers::default(),
+            registers: vec![0; reg_count],
             zf: false,
             state: ProcessState::Idle,
             last_live_cycle: 0,
@@ -40,7 +40,7 @@ impl Process {
             pid,
             player_id: ctx.process.player_id,
             pc,
-            registers: ctx.process.registers,
+            registers: ctx.process.registers.clone(),
             zf: ctx.process.zf,
             state: ProcessState::Idle,
             last_live_cycle: 0,
@@ -0,0 +1,149 @@
+//! Counts where processes spend their cycles, to find the hot spots of
+//! champions
+
+use super::types::{Pid, Player, PlayerId};
+
+use fxhash::FxHashMap as HashMap;
+use std::{cmp::Reverse, collections::BTreeMap};
+
+/// What happened at a place in memory, or to a process
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counters {
+    /// Instructions executed
+    pub executions: u64,
+    /// Cycles spent waiting for instructions to execute, from the cycle their
+    /// op code is read
+    pub wait_cycles: u64,
+    /// Bytes skipped as they are not op codes
+    pub invalid_op_codes: u64,
+    /// Instructions skipped as their parameters could not be decoded
+    pub decode_failures: u64,
+}
+
+impl Counters {
+    /// The cycles spent by processes, as each cycle is counted once
+    pub fn cycles(&self) -> u64 {
+        self.executions + self.wait_cycles + self.invalid_op_codes + self.decode_failures
+    }
+
+    pub fn add(&mut self, other: &Counters) {
+        self.executions += other.executions;
+        self.wait_cycles += other.wait_cycles;
+        self.invalid_op_codes += other.invalid_op_codes;
+        self.decode_failures += other.decode_failures;
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    /// By the player owning the processes and by memory address
+    pub addresses: HashMap<(PlayerId, usize), Counters>,
+    /// By process, along with the process' player
+    pub processes: BTreeMap<Pid, (PlayerId, Counters)>,
+}
+
+/// Code where a player's processes spent cycles
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HotSpot {
+    /// From the start of the player's code
+    pub offset: usize,
+    /// The source line of the code, when debug info is available
+    pub line: Option<usize>,
+    pub counters: Counters,
+}
+
+impl Profile {
+    /// The counters of a player's processes by offset from the start of its
+    /// code, wrapping around the memory
+    pub fn offsets(&self, player: &Player, mem_size: usize) -> BTreeMap<usize, Counters> {
+        let mut offsets = BTreeMap::<usize, Counters>::new();
+        for ((player_id, addr), counters) in &self.addresses {
+            if *player_id == player.id {
+                let offset = (addr + mem_size - player.origin % mem_size) % mem_size;
+                offsets.entry(offset).or_default().add(counters);
+            }
+        }
+        offsets
+    }
+
+    /// The offsets where a player's processes spent the most cycles first
+    pub fn hot_spots(&self, player: &Player, mem_size: usize) -> Vec<HotSpot> {
+        let mut hot_spots = self
+            .offsets(player, mem_size)
+            .into_iter()
+            .map(|(offset, counters)| HotSpot {
+                offset,
+                line: None,
+                counters,
+            })
+            .collect::<Vec<_>>();
+        sort_hot_spots(&mut hot_spots);
+        hot_spots
+    }
+
+    /// The counters of all of a player's processes
+    pub fn total(&self, player_id: PlayerId) -> Counters {
+        let mut total = Counters::default();
+        for (_, counters) in self.processes.values().filter(|(id, _)| *id == player_id) {
+            total.add(counters);
+        }
+        total
+    }
+}
+
+/// Sorts hot spots by decreasing cycles, then by offset
+pub fn sort_hot_spots(hot_spots: &mut [HotSpot]) {
+    hot_spots.sort_by_key(|spot| (Reverse(spot.counters.cycles()), spot.offset));
+}
+
+/// Profiles the processes' execution.
+/// Profiling is disabled by default in which case nothing is counted
+#[derive(Debug, Default)]
+pub struct Profiler(Option<Profile>);
+
+impl Profiler {
+    pub fn enable(&mut self) {
+        self.0.get_or_insert_with(Profile::default);
+    }
+
+    pub fn disable(&mut self) {
+        self.0 = None;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.0.is_some()
+    }
+
+    pub fn profile(&self) -> Option<&Profile> {
+        self.0.as_ref()
+    }
+
+    /// Goes back to the counts of a snapshot, or to none if profiling was
+    /// disabled when it was taken. Nothing happens while profiling is disabled
+    pub fn rewind(&mut self, profile: Option<Profile>) {
+        if let Some(current) = &mut self.0 {
+            *current = profile.unwrap_or_default();
+        }
+    }
+
+    /// Counts something a process did at `addr`
+    #[inline]
+    pub fn record(
+        &mut self,
+        pid: Pid,
+        player_id: PlayerId,
+        addr: usize,
+        count: impl Fn(&mut Counters),
+    ) {
+        if let Some(profile) = &mut self.0 {
+            count(profile.addresses.entry((player_id, addr)).or_default());
+            count(
+                &mut profile
+                    .processes
+                    .entry(pid)
+                    .or_insert_with(|| (player_id, Counters::default()))
+                    .1,
+            );
+        }
+    }
+}
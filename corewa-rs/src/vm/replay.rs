@@ -0,0 +1,422 @@
+//! Match recordings.
+//!
+//! The virtual machine is deterministic: a match is entirely defined by its
+//! arena parameters and its players. A replay therefore stores those inputs
+//! along with periodic checksums of the VM state which are used to detect
+//! diverging playbacks (e.g. recordings made with a different VM version).
+//! Every few checkpoints, a snapshot of the whole VM state is stored as well
+//! so that seeking only plays the cycles since the closest snapshot, instead
+//! of the whole match from its start. While playing back, more snapshots are
+//! kept in memory so that seeking backwards stays cheap.
+//!
+//! Layout (big endian):
+//! - magic `CWRP` and format version on 1 byte
+//! - arena parameters
+//! - player count on 1 byte, then for each player its id and its champion's
+//!   bytecode prefixed by its size
+//! - checkpoint interval
+//! - a sequence of records: a checkpoint (tag `1`, cycle, checksum), a
+//!   snapshot (tag `3`, state written by `Snapshot::write`, starting with its
+//!   cycle) following every `SNAPSHOT_CHECKPOINTS`th checkpoint, and the end of
+//!   the match (tag `2`, final cycle)
+//!
+//! Checksums are the 64 bits FNV-1a hash of the state encoded in big endian,
+//! see `state_checksum`, so that they do not depend on the host.
+
+use super::{
+    events::Event, loader::LoadError, snapshot::Snapshot, types::PlayerId, VirtualMachine,
+};
+use crate::spec::VmConfig;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Write};
+
+const REPLAY_MAGIC: &[u8; 4] = b"CWRP";
+const REPLAY_VERSION: u8 = 2;
+
+const CHECKPOINT_TAG: u8 = 1;
+const END_TAG: u8 = 2;
+const SNAPSHOT_TAG: u8 = 3;
+
+/// The number of checkpoints between the snapshots stored in a replay
+const SNAPSHOT_CHECKPOINTS: u32 = 8;
+
+pub const DEFAULT_CHECKPOINT_INTERVAL: u32 = 1000;
+
+/// How often the playback keeps a snapshot of the VM to seek back from
+const SNAPSHOT_INTERVAL: u32 = 256;
+
+/// Writes a replay while a match is being played
+pub struct ReplayRecorder<W> {
+    out: W,
+    checkpoint_interval: u32,
+    last_recorded_cycle: Option<u32>,
+}
+
+impl<W: Write> ReplayRecorder<W> {
+    pub fn new(
+        mut out: W,
+        config: &VmConfig,
+        players: &[(PlayerId, Vec<u8>)],
+        checkpoint_interval: u32,
+    ) -> Result<Self, ReplayError> {
+        // The player count is written on a single byte
+        if players.len() > config.max_players.min(usize::from(u8::MAX)) {
+            let error = LoadError::TooManyPlayers(players.len(), config.max_players);
+            return Err(ReplayError::LoadError(error));
+        }
+
+        out.write_all(REPLAY_MAGIC)?;
+        out.write_u8(REPLAY_VERSION)?;
+        write_config(&mut out, config)?;
+
+        out.write_u8(players.len() as u8)?;
+        for (player_id, champion) in players {
+            out.write_i32::<BigEndian>(*player_id)?;
+            out.write_u32::<BigEndian>(champion.len() as u32)?;
+            out.write_all(champion)?;
+        }
+
+        out.write_u32::<BigEndian>(checkpoint_interval)?;
+
+        Ok(Self {
+            out,
+            checkpoint_interval: checkpoint_interval.max(1),
+            last_recorded_cycle: None,
+        })
+    }
+
+    /// Meant to be called after every tick of the recorded virtual machine.
+    /// Cycles that were already recorded (e.g. after rewinding the match) are
+    /// skipped
+    pub fn record(&mut self, vm: &VirtualMachine) -> io::Result<()> {
+        let cycle = vm.cycles;
+        let is_due = cycle.is_multiple_of(self.checkpoint_interval);
+        let is_new = self.last_recorded_cycle.is_none_or(|last| cycle > last);
+
+        if is_due && is_new {
+            self.out.write_u8(CHECKPOINT_TAG)?;
+            self.out.write_u32::<BigEndian>(cycle)?;
+            self.out.write_u64::<BigEndian>(state_checksum(vm))?;
+            self.last_recorded_cycle = Some(cycle);
+
+            let snapshot_interval = self
+                .checkpoint_interval
+                .saturating_mul(SNAPSHOT_CHECKPOINTS);
+            if cycle.is_multiple_of(snapshot_interval) {
+                self.out.write_u8(SNAPSHOT_TAG)?;
+                vm.snapshot().write(&mut self.out)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(mut self, vm: &VirtualMachine) -> io::Result<W> {
+        self.out.write_u8(END_TAG)?;
+        self.out.write_u32::<BigEndian>(vm.cycles)?;
+        self.out.flush()?;
+
+        Ok(self.out)
+    }
+}
+
+/// A recorded match that can be played back from any cycle
+pub struct Replay {
+    config: VmConfig,
+    players: Vec<(PlayerId, Vec<u8>)>,
+    checkpoints: Vec<(u32, u64)>,
+    final_cycle: u32,
+    checkpoint_interval: u32,
+    vm: VirtualMachine,
+    /// Sorted by cycle: the first cycle, the snapshots stored in the replay
+    /// and those of the cycles played so far, every `SNAPSHOT_INTERVAL` cycles
+    snapshots: Vec<Snapshot>,
+    events_enabled: bool,
+}
+
+impl Replay {
+    pub fn read(mut input: impl Read) -> Result<Self, ReplayError> {
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != REPLAY_MAGIC {
+            return Err(ReplayError::InvalidMagic);
+        }
+
+        let version = input.read_u8()?;
+        if version != REPLAY_VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+
+        let config = read_config(&mut input)?;
+        config.validate().map_err(ReplayError::InvalidConfig)?;
+
+        let player_count = input.read_u8()?;
+        let players = (0..player_count)
+            .map(|_| {
+                let player_id = input.read_i32::<BigEndian>()?;
+                let size = input.read_u32::<BigEndian>()?;
+                let mut champion = Vec::new();
+                input
+                    .by_ref()
+                    .take(size.into())
+                    .read_to_end(&mut champion)?;
+                if champion.len() != size as usize {
+                    return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+                }
+                Ok((player_id, champion))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let checkpoint_interval = input.read_u32::<BigEndian>()?;
+
+        let mut checkpoints = Vec::new();
+        let mut stored_snapshots = Vec::new();
+        let final_cycle = loop {
+            // A recording that was interrupted ends at its last checkpoint
+            let tag = match input.read_u8() {
+                Ok(tag) => tag,
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    break checkpoints.last().map_or(0, |(cycle, _)| *cycle);
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            match tag {
+                CHECKPOINT_TAG => {
+                    let cycle = input.read_u32::<BigEndian>()?;
+                    let checksum = input.read_u64::<BigEndian>()?;
+                    checkpoints.push((cycle, checksum));
+                }
+                SNAPSHOT_TAG => stored_snapshots.push(Snapshot::read(&mut input, &config)?),
+                END_TAG => break input.read_u32::<BigEndian>()?,
+                _ => return Err(ReplayError::InvalidRecord(tag)),
+            }
+        };
+
+        let mut vm = VirtualMachine::with_config(config.clone());
+        vm.load_players(&players)?;
+        let mut snapshots = vec![vm.snapshot()];
+        snapshots.extend(stored_snapshots);
+        if !snapshots.is_sorted_by_key(|snapshot| snapshot.cycles) {
+            return Err(ReplayError::UnorderedSnapshots);
+        }
+
+        Ok(Self {
+            config,
+            players,
+            checkpoints,
+            final_cycle,
+            checkpoint_interval,
+            vm,
+            snapshots,
+            events_enabled: false,
+        })
+    }
+
+    pub fn config(&self) -> &VmConfig {
+        &self.config
+    }
+
+    pub fn players(&self) -> &[(PlayerId, Vec<u8>)] {
+        &self.players
+    }
+
+    pub fn final_cycle(&self) -> u32 {
+        self.final_cycle
+    }
+
+    pub fn checkpoint_interval(&self) -> u32 {
+        self.checkpoint_interval
+    }
+
+    /// The virtual machine in the state of the last cycle sought
+    pub fn vm(&self) -> &VirtualMachine {
+        &self.vm
+    }
+
+    /// Records the events of the played back cycles.
+    /// Seeking backwards emits no events since those cycles were already
+    /// played
+    pub fn enable_events(&mut self) {
+        self.events_enabled = true;
+        self.vm.enable_events();
+    }
+
+    pub fn drain_events(&mut self) -> impl Iterator<Item = Event> + '_ {
+        self.vm.drain_events()
+    }
+
+    /// Samples territory statistics of the played back cycles, see
+    /// `VirtualMachine::enable_stats`
+    pub fn enable_stats(&mut self, interval: u32) {
+        self.vm.enable_stats(interval);
+    }
+
+    /// Moves the playback to `cycle` (capped to the final cycle).
+    /// Seeking backwards, or forwards past a snapshot, restores the closest
+    /// snapshot before `cycle` and plays the match from there. The cycles
+    /// skipped forwards emit no events and are not sampled for statistics
+    pub fn seek(&mut self, cycle: u32) -> Result<&VirtualMachine, ReplayError> {
+        let cycle = cycle.min(self.final_cycle);
+
+        // There is always a snapshot of the first cycle
+        let closest = self
+            .snapshots
+            .partition_point(|snapshot| snapshot.cycles <= cycle)
+            - 1;
+        let backwards = cycle < self.vm.cycles;
+        if backwards || self.snapshots[closest].cycles > self.vm.cycles {
+            self.vm.restore(&self.snapshots[closest]);
+            self.check_restored()?;
+        }
+
+        if backwards {
+            self.vm.events.disable();
+            let played = self.play_until(cycle);
+            if self.events_enabled {
+                self.vm.events.enable();
+            }
+            played?;
+        } else {
+            self.play_until(cycle)?;
+        }
+
+        Ok(&self.vm)
+    }
+
+    /// Compares a restored snapshot with the checkpoint of its cycle, if any,
+    /// since stored snapshots can be tampered with too
+    fn check_restored(&self) -> Result<(), ReplayError> {
+        let cycle = self.vm.cycles;
+        match self
+            .checkpoints
+            .binary_search_by_key(&cycle, |(checkpoint_cycle, _)| *checkpoint_cycle)
+        {
+            Ok(idx) if self.checkpoints[idx].1 != state_checksum(&self.vm) => {
+                Err(ReplayError::Desync(cycle))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn play_until(&mut self, cycle: u32) -> Result<(), ReplayError> {
+        let first_checkpoint = self
+            .checkpoints
+            .partition_point(|(checkpoint_cycle, _)| *checkpoint_cycle <= self.vm.cycles);
+        let mut checkpoints = self.checkpoints[first_checkpoint..].iter().peekable();
+
+        while self.vm.cycles < cycle && !self.vm.is_over() {
+            self.vm.tick();
+
+            if let Some((checkpoint_cycle, checksum)) = checkpoints.peek() {
+                if *checkpoint_cycle == self.vm.cycles {
+                    if *checksum != state_checksum(&self.vm) {
+                        return Err(ReplayError::Desync(self.vm.cycles));
+                    }
+                    checkpoints.next();
+                }
+            }
+
+            if self.vm.cycles.is_multiple_of(SNAPSHOT_INTERVAL) {
+                let idx = self
+                    .snapshots
+                    .partition_point(|snapshot| snapshot.cycles < self.vm.cycles);
+                let is_new = self
+                    .snapshots
+                    .get(idx)
+                    .is_none_or(|snapshot| snapshot.cycles != self.vm.cycles);
+                if is_new {
+                    self.snapshots.insert(idx, self.vm.snapshot());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A fingerprint of the parts of the VM state that matter for a match: the
+/// 64 bits FNV-1a hash of their big endian encoding
+pub fn state_checksum(vm: &VirtualMachine) -> u64 {
+    let mut hasher = Fnv1a::default();
+
+    hasher.write(&vm.cycles.to_be_bytes());
+    hasher.write(&vm.check_interval.to_be_bytes());
+    hasher.write(vm.memory.values.inner());
+    for owner in vm.memory.owners.inner() {
+        hasher.write(&owner.to_be_bytes());
+    }
+    for process in &vm.processes {
+        hasher.write(&process.pid.to_be_bytes());
+        hasher.write(&(process.pc.addr() as u32).to_be_bytes());
+        hasher.write(&[process.zf as u8]);
+        for register in &process.registers {
+            hasher.write(&register.to_be_bytes());
+        }
+    }
+
+    hasher.0
+}
+
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+fn write_config(mut out: impl Write, config: &VmConfig) -> io::Result<()> {
+    out.write_u32::<BigEndian>(config.mem_size as u32)?;
+    out.write_u32::<BigEndian>(config.idx_mod as u32)?;
+    out.write_u32::<BigEndian>(config.champ_max_size as u32)?;
+    out.write_u32::<BigEndian>(config.max_players as u32)?;
+    out.write_u32::<BigEndian>(config.check_interval)?;
+    out.write_u32::<BigEndian>(config.cycle_delta)?;
+    out.write_u32::<BigEndian>(config.nbr_live)?;
+    out.write_u32::<BigEndian>(config.max_checks)?;
+    out.write_u32::<BigEndian>(config.reg_count as u32)
+}
+
+fn read_config(mut input: impl Read) -> io::Result<VmConfig> {
+    Ok(VmConfig {
+        mem_size: input.read_u32::<BigEndian>()? as usize,
+        idx_mod: input.read_u32::<BigEndian>()? as usize,
+        champ_max_size: input.read_u32::<BigEndian>()? as usize,
+        max_players: input.read_u32::<BigEndian>()? as usize,
+        check_interval: input.read_u32::<BigEndian>()?,
+        cycle_delta: input.read_u32::<BigEndian>()?,
+        nbr_live: input.read_u32::<BigEndian>()?,
+        max_checks: input.read_u32::<BigEndian>()?,
+        reg_count: input.read_u32::<BigEndian>()? as usize,
+    })
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReplayError {
+    #[error("IO error while reading or writing replay: {0}")]
+    IOError(#[from] io::Error),
+    #[error("This is not a replay file")]
+    InvalidMagic,
+    #[error("Unsupported replay version: {0}")]
+    UnsupportedVersion(u8),
+    #[error("Invalid record tag: {0}")]
+    InvalidRecord(u8),
+    #[error("Invalid arena parameters: {0}")]
+    InvalidConfig(crate::spec::InvalidConfig),
+    #[error("Invalid recorded players: {0}")]
+    LoadError(#[from] LoadError),
+    #[error("The snapshots of the replay are not sorted by cycle")]
+    UnorderedSnapshots,
+    #[error("The playback diverged from the recording at cycle {0}")]
+    Desync(u32),
+}
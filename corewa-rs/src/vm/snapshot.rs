@@ -0,0 +1,333 @@
+use super::{
+    decoder::op_from_code,
+    memory::Memory,
+    process::{Process, ProcessState},
+    profiler::Profile,
+    types::*,
+    PidPool, VirtualMachine,
+};
+use crate::spec::{op_spec, VmConfig};
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use fxhash::FxHashMap as HashMap;
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Write},
+};
+
+/// The state of a match at a given cycle, see `VirtualMachine::snapshot`
+#[derive(Clone)]
+pub struct Snapshot {
+    pub players: Vec<Player>,
+    pub memory: Memory,
+    pub processes: Vec<Process>,
+    pub pid_pool: PidPool,
+    pub last_lives: HashMap<PlayerId, u32>,
+    pub outputs: HashMap<PlayerId, String>,
+    pub cycles: u32,
+    pub last_live_check: u32,
+    pub check_interval: u32,
+    pub live_count_since_last_check: u32,
+    pub checks_without_cycle_decrement: u32,
+    pub process_count_per_cells: Vec<u32>,
+    pub process_count_by_player_id: HashMap<PlayerId, u32>,
+    pub live_count_by_player_id: HashMap<PlayerId, u32>,
+    /// None when profiling was disabled
+    pub profile: Option<Profile>,
+}
+
+impl Snapshot {
+    /// Writes the state in big endian, as stored in replays.
+    /// The profile is left out
+    pub fn write(&self, mut out: impl Write) -> io::Result<()> {
+        out.write_u32::<BigEndian>(self.cycles)?;
+        out.write_u32::<BigEndian>(self.last_live_check)?;
+        out.write_u32::<BigEndian>(self.check_interval)?;
+        out.write_u32::<BigEndian>(self.live_count_since_last_check)?;
+        out.write_u32::<BigEndian>(self.checks_without_cycle_decrement)?;
+        out.write_u32::<BigEndian>(self.pid_pool.0)?;
+
+        out.write_u32::<BigEndian>(self.players.len() as u32)?;
+        for player in &self.players {
+            out.write_i32::<BigEndian>(player.id)?;
+            write_string(&mut out, &player.name)?;
+            write_string(&mut out, &player.comment)?;
+            out.write_u32::<BigEndian>(player.size as u32)?;
+            out.write_u32::<BigEndian>(player.origin as u32)?;
+        }
+
+        // The memory and the registers are sized by the configuration
+        out.write_all(self.memory.values.inner())?;
+        for age in self.memory.ages.inner() {
+            out.write_u16::<BigEndian>(*age)?;
+        }
+        for owner in self.memory.owners.inner() {
+            out.write_i32::<BigEndian>(*owner)?;
+        }
+        for count in &self.process_count_per_cells {
+            out.write_u32::<BigEndian>(*count)?;
+        }
+
+        out.write_u32::<BigEndian>(self.processes.len() as u32)?;
+        for process in &self.processes {
+            out.write_u32::<BigEndian>(process.pid)?;
+            out.write_i32::<BigEndian>(process.player_id)?;
+            out.write_u32::<BigEndian>(process.pc.addr() as u32)?;
+            out.write_u8(process.zf as u8)?;
+            out.write_u32::<BigEndian>(process.last_live_cycle)?;
+            for register in &process.registers {
+                out.write_i32::<BigEndian>(*register)?;
+            }
+            match &process.state {
+                ProcessState::Idle => out.write_u8(0)?,
+                ProcessState::Executing { op, exec_at } => {
+                    out.write_u8(op_spec(*op).code)?;
+                    out.write_u32::<BigEndian>(*exec_at)?;
+                }
+            }
+        }
+
+        write_counts(&mut out, &self.last_lives)?;
+        write_counts(&mut out, &self.process_count_by_player_id)?;
+        write_counts(&mut out, &self.live_count_by_player_id)?;
+
+        let mut outputs = self.outputs.iter().collect::<Vec<_>>();
+        outputs.sort_by_key(|(player_id, _)| **player_id);
+        out.write_u32::<BigEndian>(outputs.len() as u32)?;
+        for (player_id, output) in outputs {
+            out.write_i32::<BigEndian>(*player_id)?;
+            write_string(&mut out, output)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a state written by `write` for a virtual machine configured with
+    /// `config`
+    pub fn read(mut input: impl Read, config: &VmConfig) -> io::Result<Self> {
+        let cycles = input.read_u32::<BigEndian>()?;
+        let last_live_check = input.read_u32::<BigEndian>()?;
+        let check_interval = input.read_u32::<BigEndian>()?;
+        let live_count_since_last_check = input.read_u32::<BigEndian>()?;
+        let checks_without_cycle_decrement = input.read_u32::<BigEndian>()?;
+        let pid_pool = PidPool(input.read_u32::<BigEndian>()?);
+
+        let player_count = input.read_u32::<BigEndian>()?;
+        if player_count as usize > config.max_players {
+            return Err(invalid_data("Too many players"));
+        }
+        let players = (0..player_count)
+            .map(|_| {
+                Ok(Player {
+                    id: input.read_i32::<BigEndian>()?,
+                    name: read_string(&mut input)?,
+                    comment: read_string(&mut input)?,
+                    size: input.read_u32::<BigEndian>()? as usize,
+                    origin: input.read_u32::<BigEndian>()? as usize,
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut memory = Memory::new(config.mem_size);
+        input.read_exact(memory.values.inner_mut())?;
+        for age in memory.ages.inner_mut() {
+            *age = input.read_u16::<BigEndian>()?;
+        }
+        for owner in memory.owners.inner_mut() {
+            *owner = input.read_i32::<BigEndian>()?;
+        }
+        let process_count_per_cells = (0..config.mem_size)
+            .map(|_| input.read_u32::<BigEndian>())
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let process_count = input.read_u32::<BigEndian>()?;
+        let processes = (0..process_count)
+            .map(|_| {
+                let pid = input.read_u32::<BigEndian>()?;
+                let player_id = input.read_i32::<BigEndian>()?;
+                let pc = input.read_u32::<BigEndian>()? as usize;
+                if pc >= config.mem_size {
+                    return Err(invalid_data("Program counter out of the memory"));
+                }
+                let zf = input.read_u8()? != 0;
+                let last_live_cycle = input.read_u32::<BigEndian>()?;
+                let registers = (0..config.reg_count)
+                    .map(|_| input.read_i32::<BigEndian>())
+                    .collect::<io::Result<Vec<_>>>()?;
+                let state = match input.read_u8()? {
+                    0 => ProcessState::Idle,
+                    code => ProcessState::Executing {
+                        op: op_from_code(code).ok_or_else(|| invalid_data("Invalid op code"))?,
+                        exec_at: input.read_u32::<BigEndian>()?,
+                    },
+                };
+
+                Ok(Process {
+                    pid,
+                    player_id,
+                    pc: pc.into(),
+                    registers,
+                    zf,
+                    state,
+                    last_live_cycle,
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let last_lives = read_counts(&mut input)?;
+        let process_count_by_player_id = read_counts(&mut input)?;
+        let live_count_by_player_id = read_counts(&mut input)?;
+
+        let output_count = input.read_u32::<BigEndian>()?;
+        let outputs = (0..output_count)
+            .map(|_| Ok((input.read_i32::<BigEndian>()?, read_string(&mut input)?)))
+            .collect::<io::Result<HashMap<_, _>>>()?;
+
+        Ok(Self {
+            players,
+            memory,
+            processes,
+            pid_pool,
+            last_lives,
+            outputs,
+            cycles,
+            last_live_check,
+            check_interval,
+            live_count_since_last_check,
+            checks_without_cycle_decrement,
+            process_count_per_cells,
+            process_count_by_player_id,
+            live_count_by_player_id,
+            profile: None,
+        })
+    }
+}
+
+fn write_string(mut out: impl Write, text: &str) -> io::Result<()> {
+    out.write_u32::<BigEndian>(text.len() as u32)?;
+    out.write_all(text.as_bytes())
+}
+
+fn read_string(mut input: impl Read) -> io::Result<String> {
+    let len = input.read_u32::<BigEndian>()?;
+    let mut bytes = Vec::new();
+    input.take(len.into()).read_to_end(&mut bytes)?;
+    if bytes.len() != len as usize {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+    }
+    String::from_utf8(bytes).map_err(|_| invalid_data("Invalid UTF-8 string"))
+}
+
+/// Sorted by player so that equal states are written identically
+fn write_counts(mut out: impl Write, counts: &HashMap<PlayerId, u32>) -> io::Result<()> {
+    let mut counts = counts.iter().collect::<Vec<_>>();
+    counts.sort_by_key(|(player_id, _)| **player_id);
+
+    out.write_u32::<BigEndian>(counts.len() as u32)?;
+    for (player_id, count) in counts {
+        out.write_i32::<BigEndian>(*player_id)?;
+        out.write_u32::<BigEndian>(*count)?;
+    }
+    Ok(())
+}
+
+fn read_counts(mut input: impl Read) -> io::Result<HashMap<PlayerId, u32>> {
+    let len = input.read_u32::<BigEndian>()?;
+    (0..len)
+        .map(|_| {
+            Ok((
+                input.read_i32::<BigEndian>()?,
+                input.read_u32::<BigEndian>()?,
+            ))
+        })
+        .collect()
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub const DEFAULT_HISTORY_INTERVAL: u32 = 64;
+pub const DEFAULT_HISTORY_CAPACITY: usize = 256;
+
+/// A ring buffer of snapshots taken every `interval` cycles.
+/// Stepping back restores the closest older snapshot and runs the match again
+/// up to the requested cycle
+pub struct History {
+    snapshots: VecDeque<Snapshot>,
+    interval: u32,
+    capacity: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_INTERVAL, DEFAULT_HISTORY_CAPACITY)
+    }
+}
+
+impl History {
+    pub fn new(interval: u32, capacity: usize) -> Self {
+        Self {
+            snapshots: VecDeque::with_capacity(capacity),
+            interval: interval.max(1),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear()
+    }
+
+    /// The oldest cycle that can be stepped back to
+    pub fn oldest_cycle(&self) -> Option<u32> {
+        self.snapshots.front().map(|snapshot| snapshot.cycles)
+    }
+
+    /// Meant to be called before the first tick and after every tick
+    pub fn record(&mut self, vm: &VirtualMachine) {
+        let is_due = vm.cycles.is_multiple_of(self.interval);
+        let is_new = self
+            .snapshots
+            .back()
+            .is_none_or(|last| last.cycles < vm.cycles);
+
+        if is_due && is_new {
+            if self.snapshots.len() == self.capacity {
+                self.snapshots.pop_front();
+            }
+            self.snapshots.push_back(vm.snapshot());
+        }
+    }
+
+    /// Rewinds the match by `cycles` cycles, or as far as the history goes.
+    /// Returns the cycle the match was rewound to, if any snapshot was available
+    pub fn step_back(&mut self, vm: &mut VirtualMachine, cycles: u32) -> Option<u32> {
+        let target = vm.cycles.saturating_sub(cycles);
+
+        // Snapshots past the target are about to be recorded again
+        while self
+            .snapshots
+            .back()
+            .is_some_and(|snapshot| snapshot.cycles > target)
+            && self.snapshots.len() > 1
+        {
+            self.snapshots.pop_back();
+        }
+
+        let snapshot = self.snapshots.back()?;
+        vm.restore(snapshot);
+
+        // The cycles in between were already seen, their events are not
+        // emitted again
+        let events_enabled = vm.events.is_enabled();
+        vm.events.disable();
+        while vm.cycles < target && !vm.is_over() {
+            vm.tick();
+        }
+        if events_enabled {
+            vm.events.enable();
+        }
+
+        Some(vm.cycles)
+    }
+}
@@ -0,0 +1,239 @@
+//! Territory statistics: how the arena is shared between the players over the
+//! course of a match, sampled every few cycles
+
+use super::{
+    memory::Memory,
+    types::{Player, PlayerId},
+};
+
+use fxhash::FxHashMap as HashMap;
+use std::io::{self, Write};
+
+pub const DEFAULT_SAMPLE_INTERVAL: u32 = 100;
+
+/// The state of a player when a sample was taken
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlayerSample {
+    pub player_id: PlayerId,
+    /// Memory cells last written by the player
+    pub owned_cells: usize,
+    /// Bytes of each opponent's code that the player wrote over, in the
+    /// opponents' load order
+    pub overwritten: Vec<(PlayerId, usize)>,
+    /// Lives reported for the player since the previous sample
+    pub live_reports: u32,
+    /// Lives reported for the player since the start of the match
+    pub total_live_reports: u32,
+    pub processes: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Sample {
+    pub cycle: u32,
+    pub check_interval: u32,
+    /// In the players' load order
+    pub players: Vec<PlayerSample>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckIntervalChange {
+    pub cycle: u32,
+    pub check_interval: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Stats {
+    /// The number of cycles between samples
+    pub interval: u32,
+    pub samples: Vec<Sample>,
+    pub check_interval_changes: Vec<CheckIntervalChange>,
+}
+
+impl Stats {
+    fn new(interval: u32) -> Self {
+        Self {
+            interval: interval.max(1),
+            samples: Vec::new(),
+            check_interval_changes: Vec::new(),
+        }
+    }
+
+    /// A value of a player across every sample
+    pub fn series<T>(&self, player_id: PlayerId, value: impl Fn(&PlayerSample) -> T) -> Vec<T> {
+        self.samples
+            .iter()
+            .filter_map(|sample| {
+                sample
+                    .players
+                    .iter()
+                    .find(|player| player.player_id == player_id)
+                    .map(&value)
+            })
+            .collect()
+    }
+
+    /// One row per sample and player, with a column of overwritten bytes for
+    /// each player
+    pub fn write_csv(&self, mut out: impl Write) -> io::Result<()> {
+        let player_ids = self.player_ids();
+
+        write!(
+            out,
+            "cycle,check_interval,player_id,owned_cells,processes,live_reports"
+        )?;
+        for player_id in &player_ids {
+            write!(out, ",overwritten_{}", player_id)?;
+        }
+        writeln!(out)?;
+
+        for sample in &self.samples {
+            for player in &sample.players {
+                write!(
+                    out,
+                    "{},{},{},{},{},{}",
+                    sample.cycle,
+                    sample.check_interval,
+                    player.player_id,
+                    player.owned_cells,
+                    player.processes,
+                    player.live_reports
+                )?;
+                for player_id in &player_ids {
+                    let overwritten = player
+                        .overwritten
+                        .iter()
+                        .find(|(id, _)| id == player_id)
+                        .map_or(0, |(_, bytes)| *bytes);
+                    write!(out, ",{}", overwritten)?;
+                }
+                writeln!(out)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn player_ids(&self) -> Vec<PlayerId> {
+        self.samples.first().map_or_else(Vec::new, |sample| {
+            sample
+                .players
+                .iter()
+                .map(|player| player.player_id)
+                .collect()
+        })
+    }
+}
+
+/// Samples territory statistics every `interval` cycles.
+/// Collecting is disabled by default in which case nothing is recorded
+#[derive(Debug, Default)]
+pub struct StatsCollector(Option<Stats>);
+
+impl StatsCollector {
+    pub fn enable(&mut self, interval: u32) {
+        self.0.get_or_insert_with(|| Stats::new(interval));
+    }
+
+    pub fn disable(&mut self) {
+        self.0 = None;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.0.is_some()
+    }
+
+    pub fn stats(&self) -> Option<&Stats> {
+        self.0.as_ref()
+    }
+
+    pub fn record_check_interval(&mut self, cycle: u32, check_interval: u32) {
+        if let Some(stats) = &mut self.0 {
+            stats.check_interval_changes.push(CheckIntervalChange {
+                cycle,
+                check_interval,
+            });
+        }
+    }
+
+    pub fn is_due(&self, cycle: u32) -> bool {
+        self.0
+            .as_ref()
+            .is_some_and(|stats| cycle.is_multiple_of(stats.interval))
+    }
+
+    pub fn sample(
+        &mut self,
+        cycle: u32,
+        check_interval: u32,
+        memory: &Memory,
+        players: &[Player],
+        process_count_by_player_id: &HashMap<PlayerId, u32>,
+        live_count_by_player_id: &HashMap<PlayerId, u32>,
+    ) {
+        let stats = match &mut self.0 {
+            Some(stats) => stats,
+            None => return,
+        };
+
+        let mut owned_cells = HashMap::<PlayerId, usize>::default();
+        for owner in memory.owners.inner() {
+            *owned_cells.entry(*owner).or_default() += 1;
+        }
+
+        let players = players
+            .iter()
+            .map(|player| {
+                let overwritten = players
+                    .iter()
+                    .filter(|opponent| opponent.id != player.id)
+                    .map(|opponent| {
+                        let bytes = (opponent.origin..opponent.origin + opponent.size)
+                            .filter(|addr| memory.owners[*addr] == player.id)
+                            .count();
+                        (opponent.id, bytes)
+                    })
+                    .collect();
+
+                let total_live_reports = live_count_by_player_id
+                    .get(&player.id)
+                    .copied()
+                    .unwrap_or(0);
+                let previous_live_reports = stats.samples.last().map_or(0, |sample| {
+                    sample
+                        .players
+                        .iter()
+                        .find(|previous| previous.player_id == player.id)
+                        .map_or(0, |previous| previous.total_live_reports)
+                });
+
+                PlayerSample {
+                    player_id: player.id,
+                    owned_cells: owned_cells.get(&player.id).copied().unwrap_or(0),
+                    overwritten,
+                    live_reports: total_live_reports - previous_live_reports,
+                    total_live_reports,
+                    processes: process_count_by_player_id
+                        .get(&player.id)
+                        .copied()
+                        .unwrap_or(0),
+                }
+            })
+            .collect();
+
+        stats.samples.push(Sample {
+            cycle,
+            check_interval,
+            players,
+        });
+    }
+
+    /// Forgets what was recorded after `cycle`, for when the match is rewound
+    pub fn rewind(&mut self, cycle: u32) {
+        if let Some(stats) = &mut self.0 {
+            stats.samples.retain(|sample| sample.cycle <= cycle);
+            stats
+                .check_interval_changes
+                .retain(|change| change.cycle <= cycle);
+        }
+    }
+}
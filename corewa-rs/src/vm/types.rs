@@ -1,22 +1,32 @@
-use crate::spec::{op_spec, OpType, ParamType, MAX_PARAMS, REG_COUNT};
+use crate::spec::{op_spec, OpType, ParamType, MAX_PARAMS};
 use std::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Player {
     pub id: PlayerId,
     pub name: String,
     pub comment: String,
     pub size: usize,
+    /// Where the champion's code was loaded in memory
+    pub origin: usize,
 }
 
-#[derive(Debug)]
+impl Player {
+    /// The offset of `addr` in the player's code, if it lies inside it
+    pub fn code_offset(&self, addr: usize, mem_size: usize) -> Option<usize> {
+        let offset = (addr % mem_size + mem_size - self.origin % mem_size) % mem_size;
+        Some(offset).filter(|offset| *offset < self.size)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Instruction {
     pub kind: OpType,
     pub params: [Param; MAX_PARAMS],
     pub byte_size: usize,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Param {
     pub kind: ParamType,
     pub value: i32,
@@ -30,7 +40,7 @@ pub enum OffsetType {
 
 pub type Register = i32;
 pub type Pid = u32;
-pub type Registers = [Register; REG_COUNT];
+pub type Registers = Vec<Register>;
 pub type PlayerId = i32;
 
 impl fmt::Display for Param {
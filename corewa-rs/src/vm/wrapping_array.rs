@@ -1,31 +1,52 @@
-#[derive(derive_more::From)]
-pub struct WrappingArray<T, const LEN: usize>([T; LEN]);
+#[derive(Clone)]
+pub struct WrappingArray<T>(Box<[T]>);
 
-impl<T, const LEN: usize> WrappingArray<T, LEN> {
+impl<T: Clone> WrappingArray<T> {
+    pub fn filled(value: T, len: usize) -> Self {
+        Self(vec![value; len].into_boxed_slice())
+    }
+}
+
+impl<T> WrappingArray<T> {
     pub fn as_ptr(&self) -> *const T {
         self.0.as_ptr()
     }
 
-    pub fn inner(&self) -> &[T; LEN] {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn inner(&self) -> &[T] {
         &self.0
     }
 
-    pub fn inner_mut(&mut self) -> &mut [T; LEN] {
+    pub fn inner_mut(&mut self) -> &mut [T] {
         &mut self.0
     }
 }
 
-impl<T, const LEN: usize> std::ops::Index<usize> for WrappingArray<T, LEN> {
+impl<T> From<Vec<T>> for WrappingArray<T> {
+    fn from(values: Vec<T>) -> Self {
+        Self(values.into_boxed_slice())
+    }
+}
+
+impl<T> std::ops::Index<usize> for WrappingArray<T> {
     type Output = T;
 
     fn index(&self, index: usize) -> &T {
-        self.0.index(index % LEN)
+        self.0.index(index % self.0.len())
     }
 }
 
-impl<T, const LEN: usize> std::ops::IndexMut<usize> for WrappingArray<T, LEN> {
+impl<T> std::ops::IndexMut<usize> for WrappingArray<T> {
     fn index_mut(&mut self, index: usize) -> &mut T {
-        self.0.index_mut(index % LEN)
+        let len = self.0.len();
+        self.0.index_mut(index % len)
     }
 }
 
@@ -33,29 +54,25 @@ impl<T, const LEN: usize> std::ops::IndexMut<usize> for WrappingArray<T, LEN> {
 mod test {
     use super::WrappingArray;
 
-    fn usize_indexing_wraps_correctly<const N: usize>() {
-        let array: WrappingArray<_, N> = {
-            let mut array = [0; N];
-            (0..N).for_each(|x| array[x] = x);
-            array.into()
-        };
+    fn usize_indexing_wraps_correctly(n: usize) {
+        let array: WrappingArray<_> = (0..n).collect::<Vec<_>>().into();
 
         for offset in 0..256 {
-            for idx in 0..N {
-                assert_eq!(array[offset * N + idx], idx)
+            for idx in 0..n {
+                assert_eq!(array[offset * n + idx], idx)
             }
         }
     }
 
     #[test]
     fn usize_indexing_wraps_correctly_for_many_sizes() {
-        usize_indexing_wraps_correctly::<0>();
-        usize_indexing_wraps_correctly::<10>();
-        usize_indexing_wraps_correctly::<20>();
-        usize_indexing_wraps_correctly::<32>();
-        usize_indexing_wraps_correctly::<64>();
-        usize_indexing_wraps_correctly::<128>();
-        usize_indexing_wraps_correctly::<256>();
-        usize_indexing_wraps_correctly::<4096>();
+        usize_indexing_wraps_correctly(0);
+        usize_indexing_wraps_correctly(10);
+        usize_indexing_wraps_correctly(20);
+        usize_indexing_wraps_correctly(32);
+        usize_indexing_wraps_correctly(64);
+        usize_indexing_wraps_correctly(128);
+        usize_indexing_wraps_correctly(256);
+        usize_indexing_wraps_correctly(4096);
     }
 }
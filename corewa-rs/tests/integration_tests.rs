@@ -1,3 +1,5 @@
+#![allow(clippy::result_large_err)] // Champion errors carry their spans
+
 macro_rules! assert_matches {
     ($expression:expr, $($pattern:tt)+) => {
         match $expression {
@@ -7,5 +9,16 @@ macro_rules! assert_matches {
     }
 }
 
+/// Compiles a champion's source into its bytecode, header included
+fn compile(source: &str) -> Vec<u8> {
+    use corewa_rs::language::{read_champion, write_champion};
+
+    let champion = read_champion(source.as_bytes()).expect("Failed to read champion");
+    let mut byte_code = Vec::new();
+    write_champion(&mut byte_code, champion).expect("Failed to write champion");
+    byte_code
+}
+
 mod language;
+mod tournament;
 mod vm;
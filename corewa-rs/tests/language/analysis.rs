@@ -0,0 +1,164 @@
+use super::champion_source;
+use crate::compile;
+
+use corewa_rs::{
+    language::analysis::{analyze, analyze_code, ControlFlow, Edge, EdgeKind, Loop, Store, Target},
+    spec::VmConfig,
+};
+
+use std::ops::Range;
+
+fn flow(code: &str) -> ControlFlow {
+    let byte_code = compile(&champion_source(code));
+
+    analyze(&byte_code, &VmConfig::default()).expect("Failed to analyze champion")
+}
+
+fn block_starts(flow: &ControlFlow) -> Vec<usize> {
+    flow.blocks.keys().copied().collect()
+}
+
+fn edge(from: usize, to: Target, kind: EdgeKind) -> Edge {
+    Edge { from, to, kind }
+}
+
+#[test]
+fn blocks_split_at_branches() {
+    let flow = flow(
+        "
+start:  ld 4, r2
+        zjmp %:end
+        live %1
+end:    fork %:start
+",
+    );
+
+    assert_eq!(block_starts(&flow), [0, 8, 13]);
+    assert_eq!(flow.blocks[&0].nodes.len(), 2);
+    assert_eq!(
+        flow.edges,
+        [
+            edge(0, Target::Block(8), EdgeKind::FallThrough),
+            edge(0, Target::Block(13), EdgeKind::Jump),
+            edge(8, Target::Block(13), EdgeKind::FallThrough),
+            edge(13, Target::Outside(16), EdgeKind::FallThrough),
+            edge(13, Target::Block(0), EdgeKind::Spawn),
+        ]
+    );
+    // Spawned processes do not loop
+    assert!(flow.loops.is_empty());
+    assert!(flow.unreachable().is_empty());
+}
+
+#[test]
+fn loops_cost_their_cycles() {
+    let flow = flow(
+        "
+loop:   live %1
+        ld 0, r3
+        zjmp %:loop
+        live %2
+",
+    );
+
+    assert_eq!(block_starts(&flow), [0, 13]);
+    assert_eq!(
+        flow.loops,
+        [Loop {
+            blocks: vec![0],
+            cycles: 10 + 5 + 20,
+        }]
+    );
+}
+
+#[test]
+fn known_carries_decide_jumps() {
+    let flow = flow(
+        "
+        ld %0, r2
+        zjmp %:end
+        .code 1 2 3
+end:    live %1
+        ld %1, r2
+        zjmp %:end
+",
+    );
+
+    // Both `zjmp`s have a single way out
+    assert_eq!(
+        flow.edges,
+        [
+            edge(0, Target::Block(13), EdgeKind::Jump),
+            edge(13, Target::Outside(28), EdgeKind::FallThrough),
+        ]
+    );
+    assert_eq!(flow.unreachable(), vec![Range { start: 10, end: 13 }]);
+    assert!(flow.is_reachable(13));
+    assert!(!flow.is_reachable(10));
+}
+
+#[test]
+fn undecodable_bytes_are_skipped() {
+    let flow = analyze_code(&[0xff, 0x01, 0x00, 0x00, 0x00, 0x01], &VmConfig::default());
+
+    assert_eq!(block_starts(&flow), [0]);
+    assert_eq!(flow.blocks[&0].nodes.len(), 2);
+    assert_eq!(flow.blocks[&0].cycles(), 1 + 10);
+}
+
+#[test]
+fn known_stores_are_reported() {
+    let flow = flow(
+        "
+        sti r1, %:target, %1
+        st r1, -4
+        sti r1, r2, %0
+target: live %0
+",
+    );
+
+    assert_eq!(
+        flow.stores,
+        [
+            Store {
+                from: 0,
+                target: 19
+            },
+            Store { from: 7, target: 3 },
+        ]
+    );
+    assert_eq!(flow.overwritten(), [0, 18]);
+}
+
+#[test]
+fn empty_champions_have_no_blocks() {
+    let flow = analyze_code(&[], &VmConfig::default());
+    assert!(flow.blocks.is_empty());
+    assert!(flow.edges.is_empty());
+}
+
+#[test]
+fn dot_export() {
+    let dot = flow(
+        "
+        sti r1, %:loop, %1
+        and r1, %0, r1
+loop:   live %1
+        zjmp %:loop
+",
+    )
+    .to_dot("zork");
+
+    assert_eq!(
+        dot,
+        r#"digraph "zork" {
+    node [shape=box, fontname="monospace"];
+    b0 [label="0: sti r1, %15, %1\l7: and r1, %0, r1\l"];
+    b15 [label="loop of 30 cycles\l15: live %1\l20: zjmp %-5\l"];
+    b0 -> b15;
+    b15 -> b15 [label="zf"];
+    b0 -> b15 [label="writes", style=dotted, color=red];
+}
+"#
+    );
+}
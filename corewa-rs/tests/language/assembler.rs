@@ -33,7 +33,7 @@ fn missing_comment() {
 fn duplicate_name() {
     assert_matches!(
         read_sample!("duplicate_name"),
-        Err(AssembleError(NameAlreadySet(_)))
+        Err(AssembleError(NameAlreadySet { at, first, .. })) if at.line == 6 && first.line == 1
     );
 }
 
@@ -41,6 +41,6 @@ fn duplicate_name() {
 fn duplicate_comment() {
     assert_matches!(
         read_sample!("duplicate_comment"),
-        Err(AssembleError(CommentAlreadySet(_)))
+        Err(AssembleError(CommentAlreadySet { at, first, .. })) if at.line == 6 && first.line == 2
     );
 }
@@ -0,0 +1,172 @@
+use corewa_rs::{
+    language::{
+        debug_info::{DebugInfo, DebugInfoError, SourceMapping},
+        read_champion, write_champion_with_listing,
+    },
+    spec::VmConfig,
+    vm::{
+        profiler::{Counters, HotSpot},
+        VirtualMachine,
+    },
+};
+
+const SOURCE: &str = r#".name "mapped"
+.comment ""
+
+start:
+	sti r1, %:live, %1
+live:	live %1
+	.code 1 2
+	zjmp %:start
+"#;
+
+fn debug_info(source: &str) -> (Vec<u8>, DebugInfo) {
+    let champion = read_champion(source.as_bytes()).expect("Failed to read champion");
+    let mut byte_code = Vec::new();
+    let (_, listing) = write_champion_with_listing(&mut byte_code, champion, &VmConfig::default())
+        .expect("Failed to write champion");
+
+    let debug_info = DebugInfo::new(&listing, source, Some(String::from("mapped.s")));
+    (byte_code, debug_info)
+}
+
+#[test]
+fn mappings() {
+    let (_, debug_info) = debug_info(SOURCE);
+
+    assert_eq!(
+        debug_info.mappings,
+        [
+            SourceMapping {
+                code: 0..7,
+                line: 5,
+                column: 1
+            },
+            SourceMapping {
+                code: 7..12,
+                line: 6,
+                column: 6
+            },
+            SourceMapping {
+                code: 12..14,
+                line: 7,
+                column: 1
+            },
+            SourceMapping {
+                code: 14..17,
+                line: 8,
+                column: 1
+            },
+        ]
+    );
+    assert_eq!(
+        debug_info.labels,
+        [(String::from("start"), 0), (String::from("live"), 7)]
+    );
+}
+
+#[test]
+fn locate() {
+    let (_, debug_info) = debug_info(SOURCE);
+
+    let line_at = |offset| debug_info.locate(offset).map(|mapping| mapping.line);
+    assert_eq!(line_at(0), Some(5));
+    assert_eq!(line_at(6), Some(5));
+    assert_eq!(line_at(7), Some(6));
+    assert_eq!(line_at(13), Some(7));
+    assert_eq!(line_at(16), Some(8));
+    assert_eq!(line_at(17), None);
+}
+
+#[test]
+fn sidecar_round_trip() {
+    let (_, debug_info) = debug_info(SOURCE);
+
+    let mut sidecar = Vec::new();
+    debug_info
+        .write(&mut sidecar)
+        .expect("Failed to write debug info");
+
+    let read_back = DebugInfo::read(&sidecar[..]).expect("Failed to read debug info");
+    assert_eq!(read_back, debug_info);
+}
+
+#[test]
+fn invalid_sidecar() {
+    assert_matches!(
+        DebugInfo::read(&b"not debug info\n"[..]),
+        Err(DebugInfoError::InvalidHeader)
+    );
+    assert_matches!(
+        DebugInfo::read(&b"corewa-rs debug info v1\nmap 0 7 5\n"[..]),
+        Err(DebugInfoError::InvalidLine(2))
+    );
+}
+
+#[test]
+fn process_code_offsets() {
+    let (byte_code, debug_info) = debug_info(SOURCE);
+
+    let mut vm = VirtualMachine::new();
+    vm.load_players(&[(1, byte_code.clone()), (2, byte_code)])
+        .expect("Failed to load players");
+
+    let mem_size = vm.config.mem_size;
+    let second = &vm.players[1];
+    assert_eq!(second.origin, mem_size / 2);
+
+    let process = vm
+        .processes
+        .iter()
+        .find(|process| process.player_id == second.id)
+        .expect("No process for the second player");
+    let offset = second.code_offset(process.pc.addr(), mem_size);
+    assert_eq!(offset, Some(0));
+    assert_eq!(debug_info.locate(0).map(|mapping| mapping.line), Some(5));
+
+    assert_eq!(second.code_offset(mem_size / 2 + 17, mem_size), None);
+    assert_eq!(second.code_offset(0, mem_size), None);
+}
+
+#[test]
+fn hot_spots_by_line() {
+    let (_, debug_info) = debug_info(SOURCE);
+
+    let invalid = Counters {
+        invalid_op_codes: 1,
+        ..Counters::default()
+    };
+    let live = Counters {
+        executions: 1,
+        wait_cycles: 9,
+        ..Counters::default()
+    };
+    let offsets = vec![(7, live), (12, invalid), (13, invalid), (100, invalid)]
+        .into_iter()
+        .collect();
+
+    // Both bytes of `.code 1 2` are counted on its line
+    assert_eq!(
+        debug_info.hot_spots(&offsets),
+        [
+            HotSpot {
+                offset: 7,
+                line: Some(6),
+                counters: live
+            },
+            HotSpot {
+                offset: 12,
+                line: Some(7),
+                counters: Counters {
+                    invalid_op_codes: 2,
+                    ..Counters::default()
+                }
+            },
+            HotSpot {
+                offset: 100,
+                line: None,
+                counters: invalid
+            },
+        ]
+    );
+}
@@ -0,0 +1,211 @@
+use corewa_rs::{
+    language::{
+        assembler::AssembleError,
+        compiler::CompileError,
+        diagnostics::{compile_with_diagnostics, Diagnostic, DiagnosticError},
+        loader::MemoryLoader,
+        preprocessor::PreprocessErrorKind,
+        read_champion,
+        span::Span,
+        write_champion, ReadError,
+    },
+    spec::VmConfig,
+};
+
+use std::ops::Range;
+
+fn diagnose(source: &str) -> Result<Vec<u8>, Vec<Diagnostic>> {
+    compile_with_diagnostics(source, None, &MemoryLoader::default(), &VmConfig::default())
+        .map(|compiled| compiled.byte_code)
+}
+
+fn locations(diagnostics: &[Diagnostic]) -> Vec<Option<(usize, Range<usize>)>> {
+    diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let span = diagnostic.span.as_ref()?;
+            Some((span.line, span.columns.clone()))
+        })
+        .collect()
+}
+
+#[test]
+fn valid_champions_are_compiled() {
+    let source = ".name \"valid\"\n.comment \"\"\nstart: live %1\nzjmp %:start";
+
+    let champion = read_champion(source.as_bytes()).expect("Failed to read champion");
+    let mut expected = Vec::new();
+    write_champion(&mut expected, champion).expect("Failed to write champion");
+
+    assert_eq!(diagnose(source).expect("Failed to compile"), expected);
+}
+
+#[test]
+fn every_error_is_reported() {
+    let source = r#".name "errors"
+.comment ""
+live %:nowhere
+lol r1
+    ld %(1 / 0), r1
+.name "again"
+ld 1,
+dup:
+dup:
+"#;
+    let diagnostics = diagnose(source).expect_err("Expected errors");
+
+    assert_eq!(
+        locations(&diagnostics),
+        [
+            Some((3, 6..14)),
+            Some((4, 0..3)),
+            Some((5, 8..15)),
+            Some((6, 0..13)),
+            Some((7, 5..5)),
+            Some((9, 0..4)),
+        ]
+    );
+    assert_matches!(
+        diagnostics[0].error,
+        DiagnosticError::Compile(CompileError::MissingLabel(..))
+    );
+    assert_matches!(
+        diagnostics[1].error,
+        DiagnosticError::Read(ReadError::ParseError(_, Span { line: 4, .. }))
+    );
+    assert_matches!(
+        diagnostics[2].error,
+        DiagnosticError::Compile(CompileError::ExprError(_))
+    );
+    assert_matches!(
+        diagnostics[3].error,
+        DiagnosticError::Read(ReadError::AssembleError(AssembleError::NameAlreadySet {
+            first: Span { line: 1, .. },
+            ..
+        }))
+    );
+    assert_matches!(
+        diagnostics[5].error,
+        DiagnosticError::Compile(CompileError::DuplicateLabel {
+            first: Span { line: 8, .. },
+            ..
+        })
+    );
+    assert_eq!(
+        diagnostics[0].to_string(),
+        "The label 'nowhere' is missing. It is referenced in a parameter but has never been declared (line 3)"
+    );
+}
+
+#[test]
+fn errors_about_the_whole_champion_come_first() {
+    let diagnostics = diagnose("live %:nowhere").expect_err("Expected errors");
+
+    assert_eq!(locations(&diagnostics), [None, None, Some((1, 6..14))]);
+    assert_matches!(
+        diagnostics[0].error,
+        DiagnosticError::Read(ReadError::AssembleError(AssembleError::MissingName))
+    );
+    assert_matches!(
+        diagnostics[1].error,
+        DiagnosticError::Read(ReadError::AssembleError(AssembleError::MissingComment))
+    );
+}
+
+#[test]
+fn expanded_lines_are_reported_whole() {
+    let source = ".name \"\"\n.comment \"\"\n.macro m\nld %(1 / 0), r1\nlol\n.endm\nm\n";
+    let diagnostics = diagnose(source).expect_err("Expected errors");
+
+    assert_eq!(locations(&diagnostics), [Some((7, 0..1)), Some((7, 0..1))]);
+    assert_matches!(
+        diagnostics[0].error,
+        DiagnosticError::Read(ReadError::ExpandedParseError(..))
+    );
+}
+
+#[test]
+fn preprocessing_errors_are_collected() {
+    let source = "\
+.name \"\"
+.comment \"\"
+.endr
+.rept 99999
+live %1
+.endr
+.include \"missing.s\"
+ld %1 r1
+";
+    let diagnostics = diagnose(source).expect_err("Expected errors");
+
+    assert_eq!(
+        locations(&diagnostics),
+        [
+            Some((3, 0..5)),
+            Some((4, 0..11)),
+            Some((7, 0..20)),
+            Some((8, 6..8))
+        ]
+    );
+    assert_matches!(
+        &diagnostics[0].error,
+        DiagnosticError::Read(ReadError::PreprocessError(e)) if matches!(e.kind, PreprocessErrorKind::UnexpectedEnd(_))
+    );
+    assert_matches!(
+        &diagnostics[1].error,
+        DiagnosticError::Read(ReadError::PreprocessError(e)) if matches!(e.kind, PreprocessErrorKind::InvalidRepeatCount)
+    );
+    assert_matches!(
+        &diagnostics[2].error,
+        DiagnosticError::Read(ReadError::PreprocessError(e)) if matches!(e.kind, PreprocessErrorKind::IncludeFailed(..))
+    );
+}
+
+#[test]
+fn preprocessing_errors_of_expansions_are_reported_once() {
+    let source = "\
+.name \"\"
+.comment \"\"
+.rept 3
+.include \"body.s\"
+.endr
+";
+    let diagnostics = diagnose(source).expect_err("Expected errors");
+
+    assert_eq!(locations(&diagnostics), [Some((4, 0..17))]);
+    assert_matches!(
+        &diagnostics[0].error,
+        DiagnosticError::Read(ReadError::PreprocessError(e)) if matches!(e.kind, PreprocessErrorKind::IncludeInExpansion)
+    );
+}
+
+#[test]
+fn duplicate_labels_keep_their_first_definition() {
+    let source = ".name \"\"\n.comment \"\"\nloop: live %1\nloop: zjmp %:loop\n";
+    let diagnostics = diagnose(source).expect_err("Expected errors");
+
+    assert_eq!(locations(&diagnostics), [Some((4, 0..5))]);
+}
+
+#[test]
+fn duplicate_constants_keep_their_first_definition() {
+    let source = ".name \"\"\n.comment \"\"\n.define X 1\n.define X 2\n.define X 3\nlive %X\n";
+    let diagnostics = diagnose(source).expect_err("Expected errors");
+
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.span.as_ref().map(|span| span.line))
+            .collect::<Vec<_>>(),
+        [Some(4), Some(5)]
+    );
+    for diagnostic in &diagnostics {
+        assert_matches!(
+            diagnostic.error,
+            DiagnosticError::Compile(CompileError::DuplicateConstant {
+                first: Span { line: 3, .. },
+                ..
+            })
+        );
+    }
+}
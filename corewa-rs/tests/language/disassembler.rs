@@ -0,0 +1,125 @@
+use corewa_rs::{
+    language::{
+        disassembler::{disassemble, DisassembleError},
+        read_champion, write_champion,
+    },
+    spec::VmConfig,
+};
+
+macro_rules! assert_round_trip {
+    ($($name:ident),*) => {
+        $(
+            let original = &include_bytes!(concat!("../vm/samples/", stringify!($name), ".cor"))[..];
+            let source = disassemble(original, &VmConfig::default())
+                .expect(concat!("Failed to disassemble ", stringify!($name)))
+                .to_string();
+
+            let champion = read_champion(source.as_bytes())
+                .expect(concat!("Failed to reassemble ", stringify!($name)));
+            let mut reassembled = Vec::new();
+            write_champion(&mut reassembled, champion).expect("Failed to write champion");
+
+            assert!(reassembled == original, "{} did not round trip", stringify!($name));
+        )*
+    };
+}
+
+fn assemble(source: &str) -> Vec<u8> {
+    let champion = read_champion(source.as_bytes()).expect("Failed to read champion");
+    let mut byte_code = Vec::new();
+    write_champion(&mut byte_code, champion).expect("Failed to write champion");
+    byte_code
+}
+
+#[test]
+fn samples_round_trip() {
+    assert_round_trip!(
+        zork,
+        bigzork,
+        kappa,
+        thunder,
+        sweepmaster,
+        skynet,
+        mise_a_jour_windows95
+    );
+}
+
+#[test]
+fn jump_targets_get_labels() {
+    let byte_code = assemble(
+        r#"
+.name "loop"
+.comment ""
+
+start:	live %1
+		fork %:start
+		zjmp %:start
+		zjmp %2
+		lfork %:end
+end:
+"#,
+    );
+
+    let source = disassemble(&byte_code, &VmConfig::default())
+        .expect("Failed to disassemble")
+        .to_string();
+
+    assert!(source.contains("l0:\n\tlive %1\n\tfork %:l0\n\tzjmp %:l0\n"));
+    // Lands in the middle of the next instruction
+    assert!(source.contains("\tzjmp %2\n"));
+    assert!(source.ends_with("\tlfork %:l17\nl17:\n"));
+}
+
+#[test]
+fn undecodable_bytes_become_code() {
+    let byte_code = assemble(
+        r#"
+.name "raw"
+.comment ""
+
+.code 0 255 2
+live %1
+.code 11
+"#,
+    );
+
+    let source = disassemble(&byte_code, &VmConfig::default())
+        .expect("Failed to disassemble")
+        .to_string();
+
+    assert!(source.contains("\t.code 0x00 0xff 0x02\n\tlive %1\n\t.code 0x0b\n"));
+}
+
+#[test]
+fn registers_depend_on_config() {
+    let byte_code = assemble(
+        r#"
+.name "registers"
+.comment ""
+
+.code 16 64 20
+"#,
+    );
+
+    let source = disassemble(&byte_code, &VmConfig::default())
+        .expect("Failed to disassemble")
+        .to_string();
+    assert!(source.contains("\t.code 0x10 0x40 0x14\n"));
+
+    let config = VmConfig {
+        reg_count: 32,
+        ..VmConfig::default()
+    };
+    let source = disassemble(&byte_code, &config)
+        .expect("Failed to disassemble")
+        .to_string();
+    assert!(source.contains("\taff r20\n"));
+}
+
+#[test]
+fn invalid_champion() {
+    assert_matches!(
+        disassemble(&[0; 16], &VmConfig::default()),
+        Err(DisassembleError::InvalidChampion(_))
+    );
+}
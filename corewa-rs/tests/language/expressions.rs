@@ -0,0 +1,131 @@
+use super::{champion_source, code_section};
+
+use corewa_rs::language::{
+    compiler::{CompileError, ExprErrorKind},
+    read_champion, WriteError,
+};
+
+fn compile(code: &str) -> Result<Vec<u8>, WriteError> {
+    let champion =
+        read_champion(champion_source(code).as_bytes()).expect("Failed to read champion");
+    code_section(champion)
+}
+
+fn compile_ok(code: &str) -> Vec<u8> {
+    compile(code).expect("Failed to compile")
+}
+
+#[test]
+fn arithmetic() {
+    assert_eq!(
+        compile_ok("live %(1 + 2 * 3 - 8 / 4)"),
+        compile_ok("live %5")
+    );
+    assert_eq!(compile_ok("ld -(2 * 3), r1"), compile_ok("ld -6, r1"));
+}
+
+#[test]
+fn labels() {
+    // Labels are relative to the operation using them
+    let code = "
+start:
+    live %:end - :start + 4
+end:
+    zjmp %(:start - 2) * 2
+";
+    assert_eq!(
+        compile_ok(code),
+        [0x01, 0x00, 0x00, 0x00, 0x09, 0x09, 0xff, 0xf2]
+    );
+}
+
+#[test]
+fn constants() {
+    let code = "
+.define SIZE 4
+TWICE = SIZE * 2
+LENGTH = :end - :start
+start:
+    live %TWICE
+    ld LENGTH, r1
+end:
+";
+    assert_eq!(
+        compile_ok(code),
+        [0x01, 0x00, 0x00, 0x00, 0x08, 0x02, 0xd0, 0x00, 0x0a, 0x01]
+    );
+}
+
+#[test]
+fn constants_can_be_defined_after_their_use() {
+    assert_eq!(compile_ok("live %SIZE\nSIZE = 7"), compile_ok("live %7"));
+}
+
+#[test]
+fn builtin_constants() {
+    assert_eq!(compile_ok("ld -IDX_MOD, r1"), compile_ok("ld -512, r1"));
+    assert_eq!(
+        compile_ok("IDX_MOD = 2\nld IDX_MOD, r1"),
+        compile_ok("ld 2, r1")
+    );
+}
+
+fn expr_error(code: &str) -> (ExprErrorKind, usize, std::ops::Range<usize>) {
+    match compile(code) {
+        Err(WriteError::CompileError(CompileError::ExprError(e))) => {
+            (e.kind, e.span.line, e.span.columns)
+        }
+        result => panic!("Expected an expression error but got {:?}", result),
+    }
+}
+
+#[test]
+fn unknown_constant() {
+    let (kind, line, at) = expr_error("live %1\nlive %(SIZE * 2)");
+    assert_matches!(kind, ExprErrorKind::UnknownConstant(name) if name == "SIZE");
+    assert_eq!((line, at), (4, 7..11));
+}
+
+#[test]
+fn errors_in_constant_definitions_point_to_them() {
+    let (kind, line, at) = expr_error("live %SIZE\nSIZE = :nowhere");
+    assert_matches!(kind, ExprErrorKind::MissingLabel(label) if label == "nowhere");
+    assert_eq!((line, at), (4, 7..15));
+}
+
+#[test]
+fn recursive_constant() {
+    let (kind, ..) = expr_error("A = B + 1\nB = A\nlive %A");
+    assert_matches!(kind, ExprErrorKind::RecursiveConstant(name) if name == "A");
+}
+
+#[test]
+fn division_by_zero() {
+    let (kind, _, at) = expr_error("live %(4 / (2 - 2))");
+    assert_matches!(kind, ExprErrorKind::DivisionByZero);
+    assert_eq!(at, 6..19);
+}
+
+#[test]
+fn overflow() {
+    let (kind, ..) = expr_error("live %0x7fffffffffffffff + 1");
+    assert_matches!(kind, ExprErrorKind::Overflow);
+}
+
+#[test]
+fn value_too_big_for_parameter() {
+    assert!(compile("zjmp %0x7fff + 0x8000").is_ok());
+    let (kind, ..) = expr_error("zjmp %0x8000 * 2");
+    assert_matches!(kind, ExprErrorKind::DoesNotFit(0x10000, 2));
+    let (kind, ..) = expr_error("ld -0x4000 * 2 - 1, r1");
+    assert_matches!(kind, ExprErrorKind::DoesNotFit(-0x8001, 2));
+}
+
+#[test]
+fn duplicate_constant() {
+    assert_matches!(
+        compile("A = 1\n.define A 2"),
+        Err(WriteError::CompileError(CompileError::DuplicateConstant { name, at, first }))
+            if name == "A" && at.line == 4 && first.line == 3
+    );
+}
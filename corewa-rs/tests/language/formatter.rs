@@ -0,0 +1,135 @@
+use crate::compile;
+
+use corewa_rs::language::{
+    formatter::{format, FormatError},
+    lexer::LexerErrorKind,
+};
+
+fn format_ok(source: &str) -> String {
+    let formatted = format(source).expect("Failed to format");
+    assert_eq!(
+        format(&formatted).as_ref(),
+        Ok(&formatted),
+        "Not idempotent"
+    );
+    formatted
+}
+
+const ZORK: &str = include_str!("samples/zork.s");
+
+const MESSY: &str = "
+
+# Constants
+.define   SIZE  (0d4*0x0A )
+STEP=SIZE/2-1
+
+.name    \"messy\"    # trailing
+.comment \"spaces  inside  are kept\"
+
+.macro   load value,reg
+ ld %value ,reg
+.endm
+
+
+
+start:load 0042,r2
+  load - 3,r3
+ a_long_label:   sti r1,%:start,%-(STEP+1)
+    add r1 , r2,r3   #sum
+   # Indented comment
+        zjmp %:a_long_label
+   .code 0x0B -1   0d2
+end:
+
+
+";
+
+#[test]
+fn canonical_layout() {
+    assert_eq!(
+        format_ok(ZORK),
+        "\
+.name \"zork\"
+.comment \"I'M ALIIIIVE\"
+
+l2:     sti   r1, %:live, %1
+        and   r1, %0, r1
+
+live:   live  %1
+        zjmp  %:live
+"
+    );
+
+    assert_eq!(
+        format_ok(MESSY),
+        "\
+# Constants
+.define SIZE (4 * 0xa)
+STEP = SIZE / 2 - 1
+
+.name \"messy\" # trailing
+.comment \"spaces  inside  are kept\"
+
+.macro load value, reg
+              ld    %value, reg
+.endm
+
+start:        load  42, r2
+              load  -3, r3
+a_long_label: sti   r1, %:start, %-(STEP + 1)
+              add   r1, r2, r3 #sum
+              # Indented comment
+              zjmp  %:a_long_label
+              .code 0xb -1 2
+end:
+"
+    );
+}
+
+#[test]
+fn formatted_sources_compile_the_same() {
+    for source in &[ZORK, MESSY] {
+        assert_eq!(compile(&format_ok(source)), compile(source));
+    }
+}
+
+#[test]
+fn lines_without_code() {
+    assert_eq!(format_ok(""), "");
+    assert_eq!(format_ok("\n\n\n"), "");
+    assert_eq!(format_ok("#a   \n\n\n  #b"), "#a\n\n        #b\n");
+    assert_eq!(format_ok("label:#here"), "label: #here\n");
+}
+
+#[test]
+fn numbers() {
+    assert_eq!(format_ok("ld 0d007, r1"), "        ld    7, r1\n");
+    assert_eq!(format_ok("ld 0x00fF, r1"), "        ld    0xff, r1\n");
+    // Left for the parser to report
+    assert_eq!(
+        format_ok("ld 99999999999999999999, r1"),
+        "        ld    99999999999999999999, r1\n"
+    );
+}
+
+#[test]
+fn minus_signs() {
+    assert_eq!(format_ok("ld -1, r1"), "        ld    -1, r1\n");
+    assert_eq!(format_ok("ld 1-1, r1"), "        ld    1 - 1, r1\n");
+    assert_eq!(
+        format_ok("ld (1)-(-1), r1"),
+        "        ld    (1) - (-1), r1\n"
+    );
+    assert_eq!(format_ok("ld :l-1, r1"), "        ld    :l - 1, r1\n");
+    assert_eq!(format_ok(".code 1 -1"), "        .code 1 -1\n");
+}
+
+#[test]
+fn lexer_errors() {
+    match format(".name \"ok\"\nld \"unterminated, r1") {
+        Err(FormatError { error, line: 2 }) => {
+            assert_eq!(error.kind, LexerErrorKind::UnclosedQuotedString)
+        }
+        other => panic!("Expected a lexer error on line 2, got {:?}", other),
+    }
+}
@@ -0,0 +1,134 @@
+use super::{champion_source, code_section};
+
+use corewa_rs::language::{
+    loader::{FileLoader, MemoryLoader},
+    preprocessor::PreprocessErrorKind,
+    read_champion, read_champion_with_loader, ReadError,
+};
+
+use std::path::{Path, PathBuf};
+
+fn read(main: &str, path: Option<&str>, files: &[(&str, &str)]) -> Result<Vec<u8>, ReadError> {
+    let mut loader = MemoryLoader::default();
+    for (path, source) in files {
+        loader.insert(path, *source);
+    }
+
+    let source = champion_source(main);
+    let champion = read_champion_with_loader(source.as_bytes(), path.map(Path::new), &loader)?;
+    Ok(code_section(champion).expect("Failed to write champion"))
+}
+
+fn compile_ok(main: &str, files: &[(&str, &str)]) -> Vec<u8> {
+    read(main, None, files).expect("Failed to read champion")
+}
+
+#[test]
+fn included_lines_are_inserted() {
+    let files = [
+        ("lib/alive.s", ".include \"../lib/./loop.s\"\n    live %1"),
+        ("lib/loop.s", ".macro spin\nloop: zjmp %:loop\n.endm"),
+    ];
+    assert_eq!(
+        compile_ok(
+            ".include \"lib/alive.s\"\nstart: spin\nzjmp %:start",
+            &files
+        ),
+        compile_ok("live %1\nstart: zjmp %0\nzjmp %-3", &[])
+    );
+}
+
+#[test]
+fn paths_are_relative_to_the_main_file() {
+    let files = [("champions/lib.s", "live %1")];
+    assert_eq!(
+        read(".include \"lib.s\"", Some("champions/main.s"), &files).expect("Failed to read"),
+        compile_ok("live %1", &[])
+    );
+}
+
+#[test]
+fn cycles() {
+    let files = [("a.s", ".include \"b.s\""), ("b.s", "\n.include \"a.s\"")];
+    match read(".include \"a.s\"", None, &files) {
+        Err(ReadError::PreprocessError(e)) => {
+            assert_eq!(e.span.line, 2);
+            assert_eq!(e.span.file, Some(PathBuf::from("b.s")));
+            assert!(
+                matches!(e.kind, PreprocessErrorKind::IncludeCycle(path) if path == Path::new("a.s"))
+            );
+        }
+        other => panic!("Expected a cycle, got {:?}", other),
+    }
+
+    // The main file can be part of a cycle too
+    let files = [("dir/lib.s", ".include \"main.s\"")];
+    assert_matches!(
+        read(".include \"lib.s\"", Some("dir/main.s"), &files),
+        Err(ReadError::PreprocessError(e)) if matches!(e.kind, PreprocessErrorKind::IncludeCycle(_))
+    );
+}
+
+#[test]
+fn errors() {
+    assert_matches!(
+        read(".include \"missing.s\"", None, &[]),
+        Err(ReadError::PreprocessError(e)) if matches!(e.kind, PreprocessErrorKind::IncludeFailed(..)) && e.span.line == 3
+    );
+    assert_matches!(
+        read(".include 42", None, &[]),
+        Err(ReadError::PreprocessError(e)) if matches!(e.kind, PreprocessErrorKind::InvalidInclude)
+    );
+    assert_matches!(
+        read(".rept 2\n.include \"lib.s\"\n.endr", None, &[("lib.s", "")]),
+        Err(ReadError::PreprocessError(e)) if matches!(e.kind, PreprocessErrorKind::IncludeInExpansion)
+    );
+}
+
+#[test]
+fn errors_report_the_included_file() {
+    let files = [("lib.s", "live %1\nlol %1")];
+    let err = read("\n.include \"lib.s\"", None, &files).expect_err("Expected an error");
+
+    match &err {
+        ReadError::ParseError(_, span) => {
+            assert_eq!(span.file, Some(PathBuf::from("lib.s")));
+            assert_eq!((span.line, span.columns.clone()), (2, 0..3));
+        }
+        other => panic!("Expected a parse error, got {:?}", other),
+    }
+    assert!(err
+        .to_string()
+        .starts_with("Parse error on line 2 of lib.s: "));
+}
+
+#[test]
+fn file_loader() {
+    let dir = std::env::temp_dir().join(format!("corewa-rs-includes-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("lib")).expect("Failed to create directory");
+    std::fs::write(dir.join("lib/alive.s"), "live %1").expect("Failed to write file");
+
+    let source = ".name \"includes\"\n.comment \"\"\n.include \"lib/alive.s\"";
+    let champion =
+        read_champion_with_loader(source.as_bytes(), Some(&dir.join("main.s")), &FileLoader);
+    std::fs::remove_dir_all(&dir).expect("Failed to clean up");
+
+    assert_eq!(
+        champion
+            .expect("Failed to read champion")
+            .instructions
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn read_champion_rejects_includes() {
+    // The file exists relative to the working directory of the tests
+    let source = ".name \"includes\"\n.comment \"\"\n.include \"tests/language/samples/zork.s\"";
+
+    assert_matches!(
+        read_champion(source.as_bytes()),
+        Err(ReadError::PreprocessError(e)) if matches!(e.kind, PreprocessErrorKind::IncludeFailed(..))
+    );
+}
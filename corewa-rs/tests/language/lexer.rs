@@ -110,15 +110,61 @@ fn unclosed_quoted_string() {
 #[test]
 fn dangling_minus() {
     assert_eq!(
-        tokens("fork %-"),
+        tokens_ok("fork %-"),
+        [Ident.at(0..4), DirectChar.at(5..6), Minus.at(6..7)]
+    )
+}
+
+#[test]
+fn expression() {
+    assert_eq!(
+        tokens_ok("%(SIZE*2)-:a/4+-1"),
         [
-            Ok(Ident.at(0..4)),
-            Ok(DirectChar.at(5..6)),
-            Err(NoNumberAfterMinus.at(6..7))
+            DirectChar.at(0..1),
+            OpenParen.at(1..2),
+            Ident.at(2..6),
+            Star.at(6..7),
+            Number { base: Decimal }.at(7..8),
+            CloseParen.at(8..9),
+            Minus.at(9..10),
+            LabelUse.at(10..12),
+            Slash.at(12..13),
+            Number { base: Decimal }.at(13..14),
+            Plus.at(14..15),
+            Minus.at(15..16),
+            Number { base: Decimal }.at(16..17),
         ]
     )
 }
 
+#[test]
+fn constant_definitions() {
+    assert_eq!(
+        tokens_ok(".define SIZE 4"),
+        [
+            DefineCmd.at(0..7),
+            Ident.at(8..12),
+            Number { base: Decimal }.at(13..14)
+        ]
+    );
+    assert_eq!(
+        tokens_ok("SIZE = 4"),
+        [
+            Ident.at(0..4),
+            Equals.at(5..6),
+            Number { base: Decimal }.at(7..8)
+        ]
+    );
+}
+
+#[test]
+fn include_directive() {
+    assert_eq!(
+        tokens_ok(r#".include "lib.s""#),
+        [IncludeCmd.at(0..8), QuotedString.at(10..15)]
+    )
+}
+
 #[test]
 fn empty_label_def() {
     assert_eq!(tokens(":"), [Err(EmptyLabel.at(0..1))])
@@ -147,3 +193,19 @@ fn no_match() {
         ]
     )
 }
+
+#[test]
+fn macro_directives() {
+    assert_eq!(
+        tokens_ok(".macro m a\n.endm .rept 2 .endr"),
+        [
+            MacroCmd.at(0..6),
+            Ident.at(7..8),
+            Ident.at(9..10),
+            EndMacroCmd.at(11..16),
+            ReptCmd.at(17..22),
+            Number { base: Decimal }.at(23..24),
+            EndReptCmd.at(25..30),
+        ]
+    )
+}
@@ -0,0 +1,94 @@
+use corewa_rs::{
+    language::{
+        compiler::CompileError, read_champion, write_champion, write_champion_with_listing,
+        WriteError,
+    },
+    spec::VmConfig,
+};
+
+const SOURCE: &str = r#".name "listed"
+.comment ""
+
+start:
+	sti r1, %:live, %1
+live:	live %1
+	.code 1 2
+	zjmp %:start
+"#;
+
+#[test]
+fn lines_offsets_and_bytes() {
+    let champion = read_champion(SOURCE.as_bytes()).expect("Failed to read champion");
+    let (_, listing) = write_champion_with_listing(Vec::new(), champion, &VmConfig::default())
+        .expect("Failed to write champion");
+
+    let lines = listing
+        .lines
+        .iter()
+        .map(|line| (line.line, line.offset, line.bytes.len(), line.cycles))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            (4, 0, 0, None),
+            (5, 0, 7, Some(25)),
+            (6, 7, 5, Some(10)),
+            (7, 12, 2, None),
+            (8, 14, 3, Some(20)),
+        ]
+    );
+
+    // Resolved labels are shown in the bytes
+    assert_eq!(
+        listing.lines[1].bytes,
+        [0x0b, 0x68, 0x01, 0x00, 0x07, 0x00, 0x01]
+    );
+    assert_eq!(listing.lines[1].label_refs, [(String::from("live"), 7)]);
+    assert_eq!(listing.lines[4].bytes, [0x09, 0xff, 0xf2]);
+    assert_eq!(listing.lines[4].label_refs, [(String::from("start"), 0)]);
+
+    assert_eq!(
+        listing.symbols,
+        [(String::from("start"), 0), (String::from("live"), 7)]
+    );
+}
+
+#[test]
+fn listing_does_not_change_the_output() {
+    let champion = read_champion(SOURCE.as_bytes()).expect("Failed to read champion");
+    let mut expected = Vec::new();
+    write_champion(&mut expected, champion).expect("Failed to write champion");
+
+    let champion = read_champion(SOURCE.as_bytes()).expect("Failed to read champion");
+    let mut byte_code = Vec::new();
+    write_champion_with_listing(&mut byte_code, champion, &VmConfig::default())
+        .expect("Failed to write champion");
+
+    assert_eq!(byte_code, expected);
+}
+
+#[test]
+fn render() {
+    let champion = read_champion(SOURCE.as_bytes()).expect("Failed to read champion");
+    let (_, listing) = write_champion_with_listing(Vec::new(), champion, &VmConfig::default())
+        .expect("Failed to write champion");
+
+    let rendered = listing.render(SOURCE);
+    assert!(rendered
+        .contains("0x0000  0b 68 01 00 07 00 01       25  \tsti r1, %:live, %1  ; live = 0x0007"));
+    assert!(rendered.ends_with("LABELS\n0x0000  start\n0x0007  live\n"));
+}
+
+#[test]
+fn listing_uses_the_config() {
+    let config = VmConfig {
+        champ_max_size: 8,
+        ..VmConfig::default()
+    };
+
+    let champion = read_champion(SOURCE.as_bytes()).expect("Failed to read champion");
+    assert_matches!(
+        write_champion_with_listing(Vec::new(), champion, &config),
+        Err(WriteError::CompileError(CompileError::ProgramTooLong(..)))
+    );
+}
@@ -0,0 +1,203 @@
+use super::{champion_source, code_section};
+
+use corewa_rs::{
+    language::{
+        loader::MemoryLoader,
+        preprocessor::{self, Expansion, PreprocessErrorKind},
+        read_champion, ReadError,
+    },
+    spec::VmConfig,
+};
+
+fn read(code: &str) -> Result<Vec<u8>, ReadError> {
+    let champion = read_champion(champion_source(code).as_bytes())?;
+    Ok(code_section(champion).expect("Failed to write champion"))
+}
+
+fn compile_ok(code: &str) -> Vec<u8> {
+    read(code).expect("Failed to read champion")
+}
+
+fn macro_error(code: &str) -> (PreprocessErrorKind, usize) {
+    match read(code) {
+        Err(ReadError::PreprocessError(e)) => (e.kind, e.span.line),
+        other => panic!("Expected a macro error, got {:?}", other),
+    }
+}
+
+#[test]
+fn parameters() {
+    let code = "
+.macro load value, reg
+    ld %value, reg
+.endm
+    load 42, r2
+    load (1 + 2), r3 # comment
+";
+    assert_eq!(compile_ok(code), compile_ok("ld %42, r2\nld %(1 + 2), r3"));
+}
+
+#[test]
+fn calls_can_be_labeled_and_nested() {
+    let code = "
+.macro alive
+    live %1
+.endm
+.macro twice_alive
+    alive
+    alive
+.endm
+start: twice_alive
+    zjmp %:start
+";
+    assert_eq!(
+        compile_ok(code),
+        compile_ok("start: live %1\nlive %1\nzjmp %:start")
+    );
+}
+
+#[test]
+fn local_labels_are_unique_per_expansion() {
+    let code = "
+.macro spin
+loop:
+    zjmp %:loop
+.endm
+    spin
+    spin
+loop:
+    zjmp %:loop
+";
+    assert_eq!(compile_ok(code), compile_ok("zjmp %0\nzjmp %0\nzjmp %0"));
+}
+
+#[test]
+fn repetitions() {
+    let code = "
+.rept 3
+    live %1
+.rept 0x2
+    zjmp %:next
+next:
+.endr
+.endr
+";
+    assert_eq!(
+        compile_ok(code),
+        compile_ok("live %1\nzjmp %3\nzjmp %3\n".repeat(3).as_str())
+    );
+}
+
+#[test]
+fn expansion_lines() {
+    let source = ".macro m\n  live %1\n  .rept 2\n    ld 1, r1\n  .endr\n.endm\nm\n";
+    let lines = preprocessor::expand(source, None, &MemoryLoader::default(), &VmConfig::default())
+        .expect("Failed to expand");
+
+    let origins = lines
+        .iter()
+        .map(|line| (line.text.trim(), line.line, line.expansion.clone()))
+        .collect::<Vec<_>>();
+    let expansion = |name: &str, body_line| {
+        Some(Expansion {
+            name: Some(String::from(name)),
+            body_line,
+            file: None,
+        })
+    };
+    assert_eq!(
+        origins,
+        [
+            ("live %1", 7, expansion("m", 2)),
+            ("ld 1, r1", 7, expansion(".rept", 4)),
+            ("ld 1, r1", 7, expansion(".rept", 4)),
+        ]
+    );
+}
+
+#[test]
+fn parse_errors_point_to_the_call_site_and_body() {
+    let code = "
+.macro broken
+    live %1
+    lol %1
+.endm
+    broken
+";
+    match read(code) {
+        Err(ReadError::ExpandedParseError(_, span, expansion)) => {
+            // The whole call site, as the expanded text is not in the source
+            assert_eq!((span.line, span.columns), (8, 4..10));
+            assert_eq!(
+                expansion,
+                Expansion {
+                    name: Some(String::from("broken")),
+                    body_line: 6,
+                    file: None,
+                }
+            );
+        }
+        other => panic!("Expected a parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn errors() {
+    assert_matches!(
+        macro_error(".macro m a\n.endm\nm 1, 2"),
+        (
+            PreprocessErrorKind::ArgumentCount {
+                expected: 1,
+                got: 2,
+                ..
+            },
+            5
+        )
+    );
+    assert_matches!(
+        macro_error(".macro m\n.endm\n.macro m\n.endm"),
+        (PreprocessErrorKind::DuplicateMacro(_), 5)
+    );
+    assert_matches!(
+        macro_error(".macro m\nlive %1"),
+        (PreprocessErrorKind::UnterminatedMacro(_), 3)
+    );
+    assert_matches!(
+        macro_error(".macro m\n.macro n\n.endm"),
+        (PreprocessErrorKind::NestedDefinition, 4)
+    );
+    assert_matches!(
+        macro_error(".macro 1 a"),
+        (PreprocessErrorKind::InvalidDefinition, 3)
+    );
+    assert_matches!(
+        macro_error(".macro m a,"),
+        (PreprocessErrorKind::InvalidDefinition, 3)
+    );
+    assert_matches!(
+        macro_error(".rept :x\n.endr"),
+        (PreprocessErrorKind::InvalidRepeatCount, 3)
+    );
+    assert_matches!(
+        macro_error(".rept 2\n.rept 2\n.endr"),
+        (PreprocessErrorKind::UnterminatedRept, 3)
+    );
+    assert_matches!(
+        macro_error(".endm"),
+        (PreprocessErrorKind::UnexpectedEnd(".endm"), 3)
+    );
+    assert_matches!(
+        macro_error(".macro m\nm\n.endm\nm"),
+        (PreprocessErrorKind::TooDeep(_), 4)
+    );
+}
+
+#[test]
+fn nested_repeats_are_limited() {
+    let code = ".rept 4096\n.rept 4096\n.rept 4096\nlive %1\n.endr\n.endr\n.endr";
+    assert_matches!(macro_error(code), (PreprocessErrorKind::TooLarge(_), _));
+
+    // Empty bodies still count
+    let code = ".rept 4096\n.rept 4096\n.rept 4096\n.endr\n.endr\n.endr";
+    assert_matches!(macro_error(code), (PreprocessErrorKind::TooLarge(_), _));
+}
@@ -4,6 +4,34 @@ macro_rules! sample {
     };
 }
 
+use corewa_rs::{
+    language::{assembler::Champion, write_champion, WriteError},
+    spec::HEADER_SIZE,
+};
+
+/// Gives a champion's code the header directives it needs to compile
+fn champion_source(code: &str) -> String {
+    format!(".name \"test\"\n.comment \"\"\n{}", code)
+}
+
+/// Writes a champion and keeps its code section
+fn code_section(champion: Champion) -> Result<Vec<u8>, WriteError> {
+    let mut byte_code = Vec::new();
+    write_champion(&mut byte_code, champion)?;
+    Ok(byte_code.split_off(HEADER_SIZE))
+}
+
+mod analysis;
 mod assembler;
+mod debug_info;
+mod diagnostics;
+mod disassembler;
+mod expressions;
+mod formatter;
+mod includes;
 mod lexer;
+mod listing;
+mod macros;
 mod parser;
+mod report;
+mod warnings;
@@ -1,5 +1,5 @@
 use corewa_rs::language::{
-    lexer::Term::*,
+    lexer::{NumberBase, Term::*},
     parser::{
         parse_line,
         ParseError::{self, *},
@@ -54,9 +54,9 @@ mod op {
             test_ops!($($rest)*);
         }};
 
-        ($input:expr => $expected:expr) => {
+        ($input:expr => $expected:expr) => {{
             parse_test($input, Op($expected));
-        }
+        }}
     }
 
     #[test]
@@ -69,8 +69,8 @@ mod op {
     #[test]
     fn ld() {
         test_ops!(
-            "ld   %:here,  r6"  => Ld(Direct::Label("here".into()).into(), Register(6)),
-            "ld   :there,  r12" => Ld(Indirect::Label("there".into()).into(), Register(12)),
+            "ld   %:here,  r6"  => Ld(Direct::Label("here".into(), 6..11).into(), Register(6)),
+            "ld   :there,  r12" => Ld(Indirect::Label("there".into(), 5..11).into(), Register(12)),
         )
     }
 
@@ -78,7 +78,7 @@ mod op {
     fn st() {
         test_ops!(
             "st   r7,  r1"    => St(Register(7), Register(1).into()),
-            "st   r3,  :nice" => St(Register(3), Indirect::Label("nice".into()).into()),
+            "st   r3,  :nice" => St(Register(3), Indirect::Label("nice".into(), 10..15).into()),
         )
     }
 
@@ -103,9 +103,9 @@ mod op {
             "and r3, %1337, r7"    => And(Register(3).into(), Direct::Numeric(1337).into(), Register(7)),
             "and r14, 13, r11"     => And(Register(14).into(), Indirect::Numeric(13).into(), Register(11)),
             "and %42, 13, r14"     => And(Direct::Numeric(42).into(), Indirect::Numeric(13).into(), Register(14)),
-            "and %1337, %:foo, r8" => And(Direct::Numeric(1337).into(), Direct::Label("foo".into()).into(), Register(8)),
-            "and %:bar,:baz,r5"    => And(Direct::Label("bar".into()).into(), Indirect::Label("baz".into()).into(), Register(5)),
-            "and :bar,r1,r8"       => And(Indirect::Label("bar".into()).into(), Register(1).into(), Register(8)),
+            "and %1337, %:foo, r8" => And(Direct::Numeric(1337).into(), Direct::Label("foo".into(), 12..16).into(), Register(8)),
+            "and %:bar,:baz,r5"    => And(Direct::Label("bar".into(), 5..9).into(), Indirect::Label("baz".into(), 10..14).into(), Register(5)),
+            "and :bar,r1,r8"       => And(Indirect::Label("bar".into(), 4..8).into(), Register(1).into(), Register(8)),
             "and 7,%1337,r11"      => And(Indirect::Numeric(7).into(), Direct::Numeric(1337).into(), Register(11)),
             "and 42,666 ,r12"      => And(Indirect::Numeric(42).into(), Indirect::Numeric(666).into(), Register(12)),
         )
@@ -118,9 +118,9 @@ mod op {
             "or r3, %1337, r7"    => Or(Register(3).into(), Direct::Numeric(1337).into(), Register(7)),
             "or r14, 13, r11"     => Or(Register(14).into(), Indirect::Numeric(13).into(), Register(11)),
             "or %42, 13, r14"     => Or(Direct::Numeric(42).into(), Indirect::Numeric(13).into(), Register(14)),
-            "or %1337, %:foo, r8" => Or(Direct::Numeric(1337).into(), Direct::Label("foo".into()).into(), Register(8)),
-            "or %:bar,:baz,r5"    => Or(Direct::Label("bar".into()).into(), Indirect::Label("baz".into()).into(), Register(5)),
-            "or :bar,r1,r8"       => Or(Indirect::Label("bar".into()).into(), Register(1).into(), Register(8)),
+            "or %1337, %:foo, r8" => Or(Direct::Numeric(1337).into(), Direct::Label("foo".into(), 11..15).into(), Register(8)),
+            "or %:bar,:baz,r5"    => Or(Direct::Label("bar".into(), 4..8).into(), Indirect::Label("baz".into(), 9..13).into(), Register(5)),
+            "or :bar,r1,r8"       => Or(Indirect::Label("bar".into(), 3..7).into(), Register(1).into(), Register(8)),
             "or 7,%1337,r11"      => Or(Indirect::Numeric(7).into(), Direct::Numeric(1337).into(), Register(11)),
             "or 42,666 ,r12"      => Or(Indirect::Numeric(42).into(), Indirect::Numeric(666).into(), Register(12)),
         )
@@ -133,9 +133,9 @@ mod op {
             "xor r3, %1337, r7"    => Xor(Register(3).into(), Direct::Numeric(1337).into(), Register(7)),
             "xor r14, 13, r11"     => Xor(Register(14).into(), Indirect::Numeric(13).into(), Register(11)),
             "xor %42, 13, r14"     => Xor(Direct::Numeric(42).into(), Indirect::Numeric(13).into(), Register(14)),
-            "xor %1337, %:foo, r8" => Xor(Direct::Numeric(1337).into(), Direct::Label("foo".into()).into(), Register(8)),
-            "xor %:bar,:baz,r5"    => Xor(Direct::Label("bar".into()).into(), Indirect::Label("baz".into()).into(), Register(5)),
-            "xor :bar,r1,r8"       => Xor(Indirect::Label("bar".into()).into(), Register(1).into(), Register(8)),
+            "xor %1337, %:foo, r8" => Xor(Direct::Numeric(1337).into(), Direct::Label("foo".into(), 12..16).into(), Register(8)),
+            "xor %:bar,:baz,r5"    => Xor(Direct::Label("bar".into(), 5..9).into(), Indirect::Label("baz".into(), 10..14).into(), Register(5)),
+            "xor :bar,r1,r8"       => Xor(Indirect::Label("bar".into(), 4..8).into(), Register(1).into(), Register(8)),
             "xor 7,%1337,r11"      => Xor(Indirect::Numeric(7).into(), Direct::Numeric(1337).into(), Register(11)),
             "xor 42,666 ,r12"      => Xor(Indirect::Numeric(42).into(), Indirect::Numeric(666).into(), Register(12)),
         )
@@ -155,7 +155,7 @@ mod op {
             "ldi r2, %1, r4" => Ldi(Register(2).into(), Direct::Numeric(1).into(), Register(4)),
             "ldi %-1, r2, r5" => Ldi(Direct::Numeric(-1).into(), Register(2).into(), Register(5)),
             "ldi %42, %42, r6" => Ldi(Direct::Numeric(42).into(), Direct::Numeric(42).into(), Register(6)),
-            "ldi :foo, r2, r7" => Ldi(Indirect::Label("foo".into()).into(), Register(2).into(), Register(7)),
+            "ldi :foo, r2, r7" => Ldi(Indirect::Label("foo".into(), 4..8).into(), Register(2).into(), Register(7)),
             "ldi 6, %1337, r8" => Ldi(Indirect::Numeric(6).into(), Direct::Numeric(1337).into(), Register(8)),
         )
     }
@@ -167,7 +167,7 @@ mod op {
             "sti r4, r2, %1" => Sti(Register(4), Register(2).into(), Direct::Numeric(1).into()),
             "sti r5, %-1, r2" => Sti(Register(5), Direct::Numeric(-1).into(), Register(2).into()),
             "sti r6, %42, %42" => Sti(Register(6), Direct::Numeric(42).into(), Direct::Numeric(42).into()),
-            "sti r7, :foo, r2" => Sti(Register(7), Indirect::Label("foo".into()).into(), Register(2).into()),
+            "sti r7, :foo, r2" => Sti(Register(7), Indirect::Label("foo".into(), 8..12).into(), Register(2).into()),
             "sti r8, 6, %1337" => Sti(Register(8), Indirect::Numeric(6).into(), Direct::Numeric(1337).into()),
         )
     }
@@ -175,15 +175,15 @@ mod op {
     #[test]
     fn fork() {
         test_ops!(
-            "fork %:start" => Fork(Direct::Label("start".into()))
+            "fork %:start" => Fork(Direct::Label("start".into(), 6..12))
         )
     }
 
     #[test]
     fn lld() {
         test_ops!(
-            "lld   %:here,  r6"  => Lld(Direct::Label("here".into()).into(), Register(6)),
-            "lld   :there,  r12" => Lld(Indirect::Label("there".into()).into(), Register(12)),
+            "lld   %:here,  r6"  => Lld(Direct::Label("here".into(), 7..12).into(), Register(6)),
+            "lld   :there,  r12" => Lld(Indirect::Label("there".into(), 6..12).into(), Register(12)),
         )
     }
 
@@ -194,7 +194,7 @@ mod op {
             "lldi r2, %1, r4" => Lldi(Register(2).into(), Direct::Numeric(1).into(), Register(4)),
             "lldi %-1, r2, r5" => Lldi(Direct::Numeric(-1).into(), Register(2).into(), Register(5)),
             "lldi %42, %42, r6" => Lldi(Direct::Numeric(42).into(), Direct::Numeric(42).into(), Register(6)),
-            "lldi :foo, r2, r7" => Lldi(Indirect::Label("foo".into()).into(), Register(2).into(), Register(7)),
+            "lldi :foo, r2, r7" => Lldi(Indirect::Label("foo".into(), 5..9).into(), Register(2).into(), Register(7)),
             "lldi 6, %1337, r8" => Lldi(Indirect::Numeric(6).into(), Direct::Numeric(1337).into(), Register(8)),
         )
     }
@@ -216,7 +216,7 @@ mod op {
 
 #[test]
 fn label() {
-    parse_test("loop:", Label("loop".into()))
+    parse_test("loop:", Label("loop".into(), 0..5))
 }
 
 #[test]
@@ -225,6 +225,7 @@ fn label_and_op() {
         "loop: xor r2, r2, r2",
         LabelAndOp(
             "loop".into(),
+            0..5,
             Xor(Register(2).into(), Register(2).into(), Register(2)),
         ),
     )
@@ -278,3 +279,136 @@ fn invalid_op_mnemonic() {
         InvalidOpMnemonic("wat".into(), Ident.at(6..9)),
     )
 }
+
+mod expression {
+    use super::*;
+
+    /// Writes an expression fully parenthesized to check precedence
+    fn sexp(expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Number(n) => n.to_string(),
+            ExprKind::Label(label) => format!(":{}", label),
+            ExprKind::Constant(name) => name.clone(),
+            ExprKind::Neg(operand) => format!("(- {})", sexp(operand)),
+            ExprKind::Binary(op, lhs, rhs) => {
+                let op = match op {
+                    BinOp::Add => "+",
+                    BinOp::Sub => "-",
+                    BinOp::Mul => "*",
+                    BinOp::Div => "/",
+                };
+                format!("({} {} {})", op, sexp(lhs), sexp(rhs))
+            }
+        }
+    }
+
+    fn direct_expr(input: &str) -> Expr {
+        match parse_ok(input) {
+            Op(Live(Direct::Expr(expr))) => expr,
+            parsed => panic!("Not an expression operand: {:?}", parsed),
+        }
+    }
+
+    #[test]
+    fn spans() {
+        assert_eq!(
+            direct_expr("live %(SIZE * 2)"),
+            Expr {
+                kind: ExprKind::Binary(
+                    BinOp::Mul,
+                    Box::new(Expr {
+                        kind: ExprKind::Constant("SIZE".into()),
+                        at: 7..11
+                    }),
+                    Box::new(Expr {
+                        kind: ExprKind::Number(2),
+                        at: 14..15
+                    }),
+                ),
+                at: 6..16
+            }
+        )
+    }
+
+    #[test]
+    fn precedence() {
+        let cases = [
+            ("live %:loop - :start + 4", "(+ (- :loop :start) 4)"),
+            ("live %1 + 2 * 3", "(+ 1 (* 2 3))"),
+            ("live %(1 + 2) * 3", "(* (+ 1 2) 3)"),
+            ("live %8 / 4 / 2", "(/ (/ 8 4) 2)"),
+            ("live %-IDX_MOD", "(- IDX_MOD)"),
+            ("live %2 - -1", "(- 2 -1)"),
+            ("live %-(A * 2)", "(- (* A 2))"),
+        ];
+
+        for (input, expected) in cases.iter() {
+            assert_eq!(sexp(&direct_expr(input)), *expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn bare_operands() {
+        parse_test("live %(42)", Op(Live(42.into())));
+        parse_test("live %-(42)", Op(Live((-42).into())));
+        parse_test(
+            "ld (:here), r1",
+            Op(Ld(
+                Indirect::Label("here".into(), 3..10).into(),
+                Register(1),
+            )),
+        );
+    }
+
+    #[test]
+    fn indirect() {
+        match parse_ok("ld SIZE * 2, r1") {
+            Op(Ld(DirInd::Ind(Indirect::Expr(expr)), Register(1))) => {
+                assert_eq!(sexp(&expr), "(* SIZE 2)")
+            }
+            parsed => panic!("Not an indirect expression: {:?}", parsed),
+        }
+    }
+
+    #[test]
+    fn constant_definitions() {
+        parse_test(
+            ".define SIZE 4",
+            Constant(
+                "SIZE".into(),
+                Expr {
+                    kind: ExprKind::Number(4),
+                    at: 13..14,
+                },
+            ),
+        );
+
+        match parse_ok("SIZE = :end - :start # comment") {
+            Constant(name, expr) => {
+                assert_eq!(name, "SIZE");
+                assert_eq!(sexp(&expr), "(- :end :start)");
+            }
+            parsed => panic!("Not a constant definition: {:?}", parsed),
+        }
+    }
+
+    #[test]
+    fn errors() {
+        parse_expect_err("live %(1 + 2", ExpectedButGotEof(CloseParen));
+        parse_expect_err("live %-", ExpectedOperandButGotEof);
+        parse_expect_err("live %1 +", ExpectedOperandButGotEof);
+        parse_expect_err("live %*", ExpectedOperand(Star.at(6..7)));
+        parse_expect_err("live %1 + r2", ExpectedOperand(Ident.at(10..12)));
+        parse_expect_err("r1 = 2", InvalidConstantName("r1".into(), Ident.at(0..2)));
+        parse_expect_err(
+            ".define 4",
+            ExpectedButGot(
+                Ident,
+                Number {
+                    base: NumberBase::Decimal,
+                }
+                .at(8..9),
+            ),
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use corewa_rs::{
+    language::{
+        compiler::CompileError, loader::MemoryLoader, read_champion_with_loader, report::Report,
+        span::Span, write_champion, ReadError, WriteError,
+    },
+    spec::CHAMP_MAX_SIZE,
+};
+
+use std::path::{Path, PathBuf};
+
+const HEADER: &str = ".name \"report\"\n.comment \"\"\n";
+
+fn compile_err(code: &str, loader: &MemoryLoader) -> Report {
+    let source = format!("{}{}", HEADER, code);
+    match read_champion_with_loader(source.as_bytes(), None, loader) {
+        Ok(champion) => {
+            let e = write_champion(Vec::new(), champion).expect_err("Expected an error");
+            Report::from(&e)
+        }
+        Err(e) => Report::from(&e),
+    }
+}
+
+fn render(code: &str, loader: &MemoryLoader) -> String {
+    let source = format!("{}{}", HEADER, code);
+    compile_err(code, loader).render(&source, Some(Path::new("champion.s")), loader)
+}
+
+fn write_err(code: &str) -> CompileError {
+    let source = format!("{}{}", HEADER, code);
+    let champion = read_champion_with_loader(source.as_bytes(), None, &MemoryLoader::default())
+        .expect("Failed to read champion");
+    match write_champion(Vec::new(), champion) {
+        Err(WriteError::CompileError(e)) => e,
+        other => panic!("Expected a compile error, got {:?}", other),
+    }
+}
+
+fn span(line: usize, columns: std::ops::Range<usize>) -> Span {
+    Span {
+        file: None,
+        line,
+        columns,
+    }
+}
+
+#[test]
+fn missing_labels_point_to_their_use() {
+    assert_matches!(
+        write_err("live %1\n  ld %:nowhere, r1"),
+        CompileError::MissingLabel(label, at) if label == "nowhere" && at == span(4, 6..14)
+    );
+}
+
+#[test]
+fn duplicate_labels_point_to_both_definitions() {
+    assert_matches!(
+        write_err("dup: live %1\n  dup:"),
+        CompileError::DuplicateLabel { label, at, first }
+            if label == "dup" && at == span(4, 2..6) && first == span(3, 0..4)
+    );
+}
+
+#[test]
+fn overflowing_instructions_are_located() {
+    // 5 bytes each
+    let code = "live %1\n".repeat(CHAMP_MAX_SIZE);
+    assert_matches!(
+        write_err(&code),
+        CompileError::ProgramTooLong(_, _, at) if at == span(3 + CHAMP_MAX_SIZE / 5, 0..7)
+    );
+}
+
+#[test]
+fn unexpected_ends_point_past_the_line() {
+    let source = format!("{}ld %1,   ", HEADER);
+    assert_matches!(
+        read_champion_with_loader(source.as_bytes(), None, &MemoryLoader::default()),
+        Err(ReadError::ParseError(_, at)) if at == span(3, 6..6)
+    );
+}
+
+#[test]
+fn errors_are_rendered_with_their_source() {
+    assert_eq!(
+        render(
+            "dup:\tlive %1\ndup:\tlive %:nowhere",
+            &MemoryLoader::default()
+        ),
+        "\
+error: The label 'dup' has been declared multiple times. A label can only be declared once
+ --> champion.s:4:1
+  |
+3 | dup:    live %1
+  | ---- first declared here
+4 | dup:    live %:nowhere
+  | ^^^^
+"
+    );
+}
+
+#[test]
+fn empty_spans_have_a_caret() {
+    assert_eq!(
+        render("ld %1,", &MemoryLoader::default()),
+        "\
+error: Expected 'Identifier' before the end of the line
+ --> champion.s:3:7
+  |
+3 | ld %1,
+  |       ^
+"
+    );
+}
+
+#[test]
+fn included_files_are_rendered() {
+    let mut loader = MemoryLoader::default();
+    loader.insert("lib.s", "live %1\nlive %:nowhere");
+
+    let report = compile_err(".include \"lib.s\"", &loader);
+    assert_eq!(
+        report.span.as_ref().and_then(|span| span.file.clone()),
+        Some(PathBuf::from("lib.s"))
+    );
+    assert_eq!(
+        report.render("", None, &loader),
+        "\
+error: The label 'nowhere' is missing. It is referenced in a parameter but has never been declared
+ --> lib.s:2:7
+  |
+2 | live %:nowhere
+  |       ^^^^^^^^
+"
+    );
+}
+
+#[test]
+fn expansions_are_noted() {
+    let rendered = render(".macro m\nlol\n.endm\n  m", &MemoryLoader::default());
+    assert!(rendered.contains(" --> champion.s:6:3\n"));
+    assert!(rendered.contains("6 |   m\n  |   ^\n"));
+    assert!(rendered.ends_with("  = note: in the expansion of 'm' (line 4)\n"));
+}
+
+#[test]
+fn errors_about_the_whole_champion_have_no_snippet() {
+    let report = Report::from(
+        &read_champion_with_loader(&b"live %1"[..], None, &MemoryLoader::default())
+            .expect_err("Expected an error"),
+    );
+    assert_eq!(
+        report.render("live %1", None, &MemoryLoader::default()),
+        "error: The champion is missing a '.name' directive\n"
+    );
+}
@@ -0,0 +1,210 @@
+use super::champion_source;
+
+use corewa_rs::{
+    language::{
+        compiler::compile_champion_with_warnings,
+        read_champion,
+        warnings::{CompileWarning, CompileWarningKind::*},
+    },
+    spec::VmConfig,
+};
+
+use std::io::Cursor;
+
+fn warnings(code: &str) -> Vec<CompileWarning> {
+    let champion =
+        read_champion(champion_source(code).as_bytes()).expect("Failed to read champion");
+
+    let (_, warnings) =
+        compile_champion_with_warnings(Cursor::new(Vec::new()), champion, &VmConfig::default())
+            .expect("Failed to compile champion");
+    warnings
+}
+
+fn kinds(code: &str) -> Vec<(usize, String)> {
+    warnings(code)
+        .into_iter()
+        .map(|warning| (warning.line - 3, format!("{:?}", warning.kind)))
+        .collect()
+}
+
+#[test]
+fn clean_code() {
+    let code = "
+start:  sti r1, %:live, %1
+        and r1, %0, r1
+live:   live %1
+        zjmp %:start
+";
+    assert_eq!(warnings(code), []);
+}
+
+#[test]
+fn unused_labels() {
+    let code = "
+unused:
+used:   live %1
+        zjmp %:used
+in_constant:
+SIZE = :in_constant
+";
+    assert_eq!(
+        warnings(code),
+        [CompileWarning {
+            kind: UnusedLabel(String::from("unused")),
+            line: 4
+        }]
+    );
+}
+
+#[test]
+fn unreachable_code() {
+    let code = "
+loop:   ld %0, r2
+        zjmp %:loop
+        live %1
+        live %1
+        zjmp %:loop
+next:   live %1
+        xor r2, r2, r3
+        zjmp %:next
+        aff r1
+";
+    assert_eq!(
+        warnings(code),
+        [
+            CompileWarning {
+                kind: UnreachableCode,
+                line: 6
+            },
+            CompileWarning {
+                kind: UnreachableCode,
+                line: 12
+            },
+        ]
+    );
+
+    // The carry is unknown after a label or an unknown result
+    assert_eq!(warnings("ld %0, r2\nl: zjmp %:l\nlive %1"), []);
+    assert_eq!(warnings("ld %1, r2\nl: zjmp %:l\nlive %1"), []);
+    assert_eq!(
+        warnings("ld %0, r2\nadd r1, r2, r3\nl: zjmp %:l\nlive %1"),
+        []
+    );
+}
+
+#[test]
+fn invalid_op_codes() {
+    assert_eq!(
+        warnings(".code 0x01 0 0 0 1 0x2a"),
+        [CompileWarning {
+            kind: InvalidOpCode(0x2a),
+            line: 3
+        }]
+    );
+    assert_eq!(warnings(".code 0x09 0xff 0xfd"), []);
+}
+
+#[test]
+fn op_codes_depend_on_register_count() {
+    let code = ".code 16 64 20";
+    assert_eq!(
+        warnings(code),
+        [CompileWarning {
+            kind: InvalidOpCode(0x10),
+            line: 3
+        }]
+    );
+
+    let champion =
+        read_champion(champion_source(code).as_bytes()).expect("Failed to read champion");
+    let config = VmConfig {
+        reg_count: 32,
+        ..VmConfig::default()
+    };
+    let (_, warnings) = compile_champion_with_warnings(Cursor::new(Vec::new()), champion, &config)
+        .expect("Failed to compile champion");
+    assert_eq!(warnings, []);
+}
+
+#[test]
+fn truncated_offsets() {
+    let code = "
+        ld 512, r1
+        ld %512, r1
+        lld 512, r1
+        zjmp %-600
+        lfork %600
+        ldi %600, r2, r1
+        sti r1, r2, %600
+        sti r1, %600, r2
+        and 4, %600, r1
+        fork %:far
+        .code 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0
+        .rept 16
+        .code 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0
+        .endr
+far:
+";
+    let truncated = kinds(code)
+        .into_iter()
+        .filter(|(_, kind)| kind.starts_with("OffsetTruncated"))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        truncated,
+        [
+            (1, String::from("OffsetTruncated(512, 512)")),
+            (4, String::from("OffsetTruncated(-600, 512)")),
+            (6, String::from("OffsetTruncated(600, 512)")),
+            (7, String::from("OffsetTruncated(600, 512)")),
+            (8, String::from("OffsetTruncated(600, 512)")),
+            (10, String::from("OffsetTruncated(547, 512)")),
+        ]
+    );
+}
+
+#[test]
+fn player_ids() {
+    let code = "
+        live %1
+        live %-4
+        live %0
+        live %5
+patched: live %0
+        sti r1, %:patched, %1
+";
+    assert_eq!(
+        warnings(code),
+        [
+            CompileWarning {
+                kind: InvalidPlayerId(0),
+                line: 6
+            },
+            CompileWarning {
+                kind: InvalidPlayerId(5),
+                line: 7
+            },
+        ]
+    );
+}
+
+#[test]
+fn truncated_values() {
+    assert_eq!(
+        warnings("ld %5000000000, r1\nst r1, 70000"),
+        [
+            CompileWarning {
+                kind: ValueTruncated(5_000_000_000, 4),
+                line: 3
+            },
+            CompileWarning {
+                kind: ValueTruncated(70000, 2),
+                line: 4
+            },
+            CompileWarning {
+                kind: OffsetTruncated(70000, 512),
+                line: 4
+            },
+        ]
+    );
+}
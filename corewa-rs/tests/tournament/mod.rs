@@ -0,0 +1,100 @@
+use corewa_rs::tournament::{
+    play,
+    ranking::{Placement, INITIAL_RATING},
+    schedule::round_robin,
+    Contender, TournamentConfig, TournamentError,
+};
+
+macro_rules! contender {
+    ($name:ident) => {
+        Contender {
+            name: String::from(stringify!($name)),
+            champion: include_bytes!(concat!("../vm/samples/", stringify!($name), ".cor")).to_vec(),
+        }
+    };
+}
+
+#[test]
+fn round_robin_covers_every_start_position() {
+    // 3 duels and 1 three-player match, in every order
+    assert_eq!(round_robin(3, 2, 3).len(), 3 * 2 + 6);
+    // C(5, 2) * 2! + C(5, 3) * 3! + C(5, 4) * 4!
+    assert_eq!(round_robin(5, 2, 4).len(), 20 + 60 + 120);
+
+    let duels = round_robin(3, 2, 2);
+    assert!(duels.contains(&vec![0, 2]));
+    assert!(duels.contains(&vec![2, 0]));
+}
+
+#[test]
+fn standings() {
+    let contenders = [contender!(zork), contender!(bigzork), contender!(kappa)];
+    let config = TournamentConfig {
+        threads: 4,
+        ..TournamentConfig::default()
+    };
+
+    let tournament = play(&contenders, &config).expect("Failed to play tournament");
+    assert_eq!(tournament.matches.len(), 6);
+
+    for standing in &tournament.standings {
+        assert_eq!(standing.played, 4);
+        assert_eq!(
+            standing.wins + standing.losses + standing.ties,
+            standing.played
+        );
+    }
+
+    let ratings_sum: f64 = tournament.standings.iter().map(|s| s.rating).sum();
+    assert!((ratings_sum - 3.0 * INITIAL_RATING).abs() < 1e-6);
+
+    let ratings = tournament
+        .standings
+        .windows(2)
+        .all(|pair| pair[0].rating >= pair[1].rating);
+    assert!(ratings);
+}
+
+#[test]
+fn results_do_not_depend_on_threads() {
+    let contenders = [contender!(zork), contender!(bigzork), contender!(thunder)];
+
+    let sequential = play(&contenders, &TournamentConfig::default()).expect("Failed to play");
+    let parallel = play(
+        &contenders,
+        &TournamentConfig {
+            threads: 3,
+            ..TournamentConfig::default()
+        },
+    )
+    .expect("Failed to play");
+
+    assert_eq!(sequential.standings, parallel.standings);
+}
+
+#[test]
+fn mirror_matches_are_ties() {
+    let contenders = [contender!(zork), contender!(zork)];
+
+    let tournament = play(&contenders, &TournamentConfig::default()).expect("Failed to play");
+    for result in &tournament.matches {
+        assert_eq!(result.placements(), [Placement::Tie, Placement::Tie]);
+    }
+}
+
+#[test]
+fn invalid_contenders_are_rejected() {
+    let broken = Contender {
+        name: String::from("broken"),
+        champion: vec![0; 16],
+    };
+
+    assert_matches!(
+        play(&[contender!(zork), broken], &TournamentConfig::default()),
+        Err(TournamentError::InvalidContender(name, _)) if name == "broken"
+    );
+    assert_matches!(
+        play(&[contender!(zork)], &TournamentConfig::default()),
+        Err(TournamentError::NotEnoughContenders(1))
+    );
+}
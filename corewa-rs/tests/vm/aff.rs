@@ -0,0 +1,54 @@
+use crate::compile;
+use corewa_rs::vm::{VirtualMachine, MAX_OUTPUT_LEN};
+
+const TALKER: &str = r#"
+.name "talker"
+.comment "Says hi"
+
+ld  %72, r2
+aff r2
+ld  %361, r2 # 361 % 256 == 'i'
+aff r2
+ld  %-191, r2 # -191 wraps to 'A'
+aff r2
+"#;
+
+#[test]
+fn aff_displays_register_value_modulo_256() {
+    let mut vm = VirtualMachine::new();
+    vm.load_players(&[(1, compile(TALKER)), (2, sample!(zork).to_vec())])
+        .expect("Failed to load players");
+
+    vm.run_until(100);
+
+    assert_eq!(vm.outputs[&1], "HiA");
+    assert_eq!(vm.outputs[&2], "");
+}
+
+#[test]
+fn aff_output_keeps_the_last_characters() {
+    let looper = r#"
+.name "looper"
+.comment "Never stops talking"
+
+        ld      %200, r2 # 'È' takes two bytes
+        ld      %65, r3
+        ld      %0, r4
+        aff     r2
+loop:
+        .rept 64
+        aff     r3
+        .endr
+        live    %1
+        zjmp    %:loop
+"#;
+    let mut vm = VirtualMachine::new();
+    vm.load_players(&[(1, compile(looper))])
+        .expect("Failed to load players");
+
+    vm.run_until(20_000);
+
+    let output = &vm.outputs[&1];
+    assert_eq!(output.chars().count(), MAX_OUTPUT_LEN);
+    assert!(output.chars().all(|chr| chr == 'A'));
+}
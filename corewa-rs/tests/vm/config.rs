@@ -0,0 +1,128 @@
+use corewa_rs::{
+    language::{self, compiler::CompileError, span::Span, WriteError},
+    spec::{OpType, VmConfig},
+    vm::{
+        decoder::{Decode, InstrDecodeError},
+        memory::Memory,
+        VirtualMachine,
+    },
+};
+
+#[test]
+fn default_config_is_valid() {
+    assert!(VmConfig::default().validate().is_ok());
+}
+
+#[test]
+fn invalid_configs() {
+    let default = VmConfig::default();
+
+    let configs = [
+        VmConfig {
+            mem_size: 0,
+            ..default.clone()
+        },
+        VmConfig {
+            idx_mod: 0,
+            ..default.clone()
+        },
+        VmConfig {
+            idx_mod: default.mem_size + 1,
+            ..default.clone()
+        },
+        VmConfig {
+            champ_max_size: default.mem_size + 1,
+            ..default.clone()
+        },
+        VmConfig {
+            max_players: 0,
+            ..default.clone()
+        },
+        VmConfig {
+            reg_count: 256,
+            ..default
+        },
+    ];
+
+    for config in &configs {
+        assert!(config.validate().is_err(), "{:?}", config);
+    }
+}
+
+#[test]
+fn custom_memory_size() {
+    let config = VmConfig {
+        mem_size: 2048,
+        idx_mod: 256,
+        ..VmConfig::default()
+    };
+    let mut vm = VirtualMachine::with_config(config);
+    vm.load_players(&[(1, sample!(zork).to_vec()), (2, sample!(zork).to_vec())])
+        .expect("Failed to load players");
+
+    assert_eq!(vm.memory.size(), 2048);
+    assert_eq!(vm.processes[1].pc.addr(), 1024);
+    assert_eq!(vm.run_until(5000).final_cycle, 5000);
+}
+
+#[test]
+fn custom_live_checks() {
+    let config = VmConfig {
+        check_interval: 500,
+        cycle_delta: 100,
+        ..VmConfig::default()
+    };
+    let mut vm = VirtualMachine::with_config(config);
+    vm.load_players(&[(1, sample!(zork).to_vec())])
+        .expect("Failed to load players");
+
+    let outcome = vm.run_to_completion();
+    assert_eq!(outcome.winner, Some(1));
+    assert!(outcome.final_cycle < 57_955);
+}
+
+#[test]
+fn memory_reads_wrap_around_custom_size() {
+    let mut memory = Memory::new(10);
+    memory.write_i32(0x0102_0304, 1, 8);
+
+    assert_eq!(memory.read_i32(8), 0x0102_0304);
+    assert_eq!(memory[0], 0x03);
+    assert_eq!(memory.read_i16(9), 0x0203);
+}
+
+#[test]
+fn decoder_validates_custom_register_count() {
+    let mut memory = Memory::default();
+    // aff r3
+    memory.write(0, &[0x10, 0x40, 0x03], 1);
+
+    assert!(memory.decode_instr(OpType::Aff, 0, 16).is_ok());
+    assert!(matches!(
+        memory.decode_instr(OpType::Aff, 0, 2),
+        Err(InstrDecodeError::InvalidRegNumber(3))
+    ));
+}
+
+#[test]
+fn compiler_checks_custom_size_limit() {
+    let source = ".name \"tiny\"\n.comment \"\"\nlive %1\nlive %1\n";
+    let config = VmConfig {
+        champ_max_size: 8,
+        ..VmConfig::default()
+    };
+
+    let champion = language::read_champion(source.as_bytes()).unwrap();
+    let result = language::write_champion_with_config(Vec::new(), champion, &config);
+    assert!(matches!(
+        result,
+        Err(WriteError::CompileError(CompileError::ProgramTooLong(
+            10,
+            8,
+            Span { line: 4, .. }
+        )))
+    ));
+
+    let champion = language::read_champion(source.as_bytes()).unwrap();
+    assert!(language::write_champion(Vec::new(), champion).is_ok());
+}
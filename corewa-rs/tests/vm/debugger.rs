@@ -0,0 +1,213 @@
+use crate::compile;
+use corewa_rs::{
+    spec::OpType,
+    vm::{
+        debugger::{Breakpoint, Cause, Comparison, Debugger, Hit, Stop},
+        replay::{Replay, ReplayRecorder},
+        VirtualMachine,
+    },
+};
+
+// `ld` is executed during cycle 4, `st` during cycle 9 and writes 4 bytes
+// at 107
+const PROGRAM: &str = r#"
+.name "debugged"
+.comment ""
+
+        ld %5, r2
+        st r2, 100
+loop:   live %1
+        fork %:loop
+"#;
+
+fn vm() -> VirtualMachine {
+    let mut vm = VirtualMachine::new();
+    vm.load_players(&[(1, compile(PROGRAM))])
+        .expect("Failed to load players");
+    vm
+}
+
+fn run_to_break(breakpoint: Breakpoint) -> (VirtualMachine, Stop) {
+    let mut vm = vm();
+    let mut debugger = Debugger::new();
+    debugger.add(breakpoint);
+    let stop = debugger.run(&mut vm, 1000);
+    (vm, stop)
+}
+
+fn hit(cycle: u32, cause: Cause) -> Stop {
+    Stop::Break(vec![Hit {
+        breakpoint: 1,
+        cycle,
+        cause,
+    }])
+}
+
+#[test]
+fn address_breakpoints() {
+    let (vm, stop) = run_to_break(Breakpoint::Address(7));
+    assert_eq!(vm.cycles, 5);
+    assert_eq!(
+        stop,
+        hit(
+            5,
+            Cause::Reached {
+                pid: 0,
+                player_id: 1,
+                pc: 7
+            }
+        )
+    );
+}
+
+#[test]
+fn execution_breakpoints() {
+    let executed = |cycle, pc, op| {
+        hit(
+            cycle,
+            Cause::Executed {
+                pid: 0,
+                player_id: 1,
+                pc,
+                op,
+            },
+        )
+    };
+
+    assert_eq!(
+        run_to_break(Breakpoint::OpCode(OpType::St)).1,
+        executed(10, 7, OpType::St)
+    );
+    assert_eq!(
+        run_to_break(Breakpoint::Pid(0)).1,
+        executed(5, 0, OpType::Ld)
+    );
+    assert_eq!(
+        run_to_break(Breakpoint::Player(1)).1,
+        executed(5, 0, OpType::Ld)
+    );
+    assert_eq!(run_to_break(Breakpoint::Player(2)).1, Stop::CycleLimit);
+}
+
+#[test]
+fn watchpoints() {
+    let written = hit(
+        10,
+        Cause::Written {
+            pid: 0,
+            owner: 1,
+            at: 107,
+            size: 4,
+        },
+    );
+
+    let watch = |range, by| Breakpoint::Watch { range, by };
+    assert_eq!(run_to_break(watch(110..120, Some(1))).1, written);
+    assert_eq!(run_to_break(watch(100..108, None)).1, written);
+    // Wrapping around the memory
+    assert_eq!(run_to_break(watch(4200..4204, None)).1, written);
+
+    assert_eq!(run_to_break(watch(111..120, None)).1, Stop::CycleLimit);
+    assert_eq!(run_to_break(watch(100..120, Some(2))).1, Stop::CycleLimit);
+}
+
+#[test]
+fn register_breakpoints_break_once() {
+    let mut vm = vm();
+    let mut debugger = Debugger::new();
+    let id = debugger.add(Breakpoint::Register {
+        pid: None,
+        register: 2,
+        comparison: Comparison::Equal,
+        value: 5,
+    });
+
+    assert_eq!(
+        debugger.run(&mut vm, 1000),
+        Stop::Break(vec![Hit {
+            breakpoint: id,
+            cycle: 5,
+            cause: Cause::Register {
+                pid: 0,
+                player_id: 1,
+                register: 2,
+                value: 5
+            }
+        }])
+    );
+    // Forked processes start with the value
+    assert_eq!(debugger.run(&mut vm, 1000), Stop::CycleLimit);
+}
+
+#[test]
+fn run_until_live_check() {
+    let mut vm = vm();
+    let mut debugger = Debugger::new();
+
+    assert_eq!(
+        debugger.run_until_live_check(&mut vm, 5000),
+        Stop::LiveCheck
+    );
+    assert_eq!(vm.cycles, vm.config.check_interval);
+    assert_eq!(
+        debugger.run_until_live_check(&mut vm, 5000),
+        Stop::LiveCheck
+    );
+    assert_eq!(vm.cycles, 2 * vm.config.check_interval);
+}
+
+#[test]
+fn removed_breakpoints_do_not_break() {
+    let mut vm = vm();
+    let mut debugger = Debugger::new();
+    let id = debugger.add(Breakpoint::OpCode(OpType::Live));
+    assert_eq!(debugger.remove(id), Some(Breakpoint::OpCode(OpType::Live)));
+
+    assert_eq!(debugger.run(&mut vm, 100), Stop::CycleLimit);
+    assert_eq!(debugger.breakpoints().count(), 0);
+}
+
+#[test]
+fn events_are_left_as_they_were() {
+    let mut vm = vm();
+    let mut debugger = Debugger::new();
+    debugger.add(Breakpoint::Watch {
+        range: 0..4096,
+        by: None,
+    });
+
+    debugger.run(&mut vm, 100);
+    assert!(!vm.events.is_enabled());
+    assert_eq!(vm.drain_events().count(), 0);
+
+    vm.enable_events();
+    debugger.run(&mut vm, 100);
+    assert!(vm.events.is_enabled());
+    assert!(vm.drain_events().count() > 0);
+}
+
+#[test]
+fn ticks_driven_elsewhere() {
+    let players = [(1, compile(PROGRAM))];
+    let mut vm = VirtualMachine::new();
+    vm.load_players(&players).expect("Failed to load players");
+    let mut recorder = ReplayRecorder::new(Vec::new(), &vm.config, &players, 100)
+        .expect("Failed to start recording");
+    vm.run_until(50);
+    recorder.record(&vm).expect("Failed to record");
+    let recording = recorder.finish(&vm).expect("Failed to finish recording");
+
+    let mut replay = Replay::read(&recording[..]).expect("Failed to read replay");
+    replay.enable_events();
+    let mut debugger = Debugger::new();
+    debugger.add(Breakpoint::OpCode(OpType::St));
+
+    let mut hits = Vec::new();
+    while hits.is_empty() {
+        debugger.before_tick(replay.vm());
+        let next_cycle = replay.vm().cycles + 1;
+        replay.seek(next_cycle).expect("Failed to seek");
+        hits = debugger.after_tick(replay.vm());
+    }
+    assert_eq!(replay.vm().cycles, 10);
+}
@@ -0,0 +1,115 @@
+use crate::compile;
+use corewa_rs::vm::{events::EventKind, VirtualMachine};
+
+const EVENTFUL: &str = r#"
+.name "eventful"
+.comment ""
+
+st   r1, -100
+live %1
+fork %50
+aff  r1
+"#;
+
+fn vm_with_events() -> VirtualMachine {
+    let mut vm = VirtualMachine::new();
+    vm.load_players(&[(1, compile(EVENTFUL))])
+        .expect("Failed to load players");
+    vm.enable_events();
+    vm
+}
+
+#[test]
+fn events_are_disabled_by_default() {
+    let mut vm = VirtualMachine::new();
+    vm.load_players(&[(1, compile(EVENTFUL))])
+        .expect("Failed to load players");
+
+    vm.run_until(2000);
+    assert_eq!(vm.drain_events().count(), 0);
+}
+
+#[test]
+fn instruction_events() {
+    let mut vm = vm_with_events();
+    vm.run_until(1000);
+
+    let events = vm.drain_events().collect::<Vec<_>>();
+
+    let executed = events
+        .iter()
+        .filter_map(|event| match &event.kind {
+            EventKind::InstructionExecuted { pid: 0, pc, .. } => Some(*pc),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(executed, [0, 5, 10, 13]);
+
+    assert!(events.iter().any(|event| matches!(
+        event.kind,
+        EventKind::MemoryWritten {
+            pid: 0,
+            owner: 1,
+            at: 3996,
+            size: 4
+        }
+    )));
+    assert!(events.iter().any(|event| matches!(
+        event.kind,
+        EventKind::LiveReported {
+            pid: 0,
+            player_id: 1
+        }
+    )));
+    assert!(events.iter().any(|event| matches!(
+        event.kind,
+        EventKind::ProcessForked {
+            parent: 0,
+            child: 1,
+            player_id: 1,
+            pc: 60
+        }
+    )));
+    assert!(events.iter().any(|event| matches!(
+        event.kind,
+        EventKind::Aff {
+            pid: 0,
+            player_id: 1,
+            chr: '\u{1}'
+        }
+    )));
+
+    // Events are only drained once
+    assert_eq!(vm.drain_events().count(), 0);
+}
+
+#[test]
+fn live_check_events() {
+    let mut vm = vm_with_events();
+    vm.run_until(1537);
+
+    let events = vm.drain_events().collect::<Vec<_>>();
+
+    let killed = events
+        .iter()
+        .filter(|event| matches!(event.kind, EventKind::ProcessKilled { .. }))
+        .collect::<Vec<_>>();
+    assert_eq!(killed.len(), 1);
+    assert!(matches!(
+        killed[0].kind,
+        EventKind::ProcessKilled {
+            pid: 1,
+            player_id: 1
+        }
+    ));
+
+    let last = events.last().expect("No events recorded");
+    assert_eq!(last.cycle, 1536);
+    assert!(matches!(
+        last.kind,
+        EventKind::LiveCheck {
+            killed: 1,
+            check_interval: 1536
+        }
+    ));
+}
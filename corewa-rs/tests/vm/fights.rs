@@ -2,13 +2,9 @@ use corewa_rs::vm::VirtualMachine;
 
 fn fight_cycles(players: &[(i32, Vec<u8>)]) -> u32 {
     let mut vm = VirtualMachine::new();
-    vm.load_players(players);
+    vm.load_players(players).expect("Failed to load players");
 
-    while !vm.processes.is_empty() {
-        vm.tick();
-    }
-
-    vm.cycles
+    vm.run_to_completion().final_cycle
 }
 
 macro_rules! test_single {
@@ -0,0 +1,157 @@
+use corewa_rs::{
+    spec::{HEADER_SIZE, PROG_NAME_LENGTH},
+    vm::{
+        loader::{ChampionError, LoadError},
+        VirtualMachine,
+    },
+};
+
+fn load(players: &[(i32, Vec<u8>)]) -> Result<VirtualMachine, LoadError> {
+    let mut vm = VirtualMachine::new();
+    vm.load_players(players)?;
+    Ok(vm)
+}
+
+fn load_single(champion: Vec<u8>) -> Result<VirtualMachine, LoadError> {
+    load(&[(1, champion)])
+}
+
+fn with_prog_size(mut champion: Vec<u8>, size: u32) -> Vec<u8> {
+    let size_at = 4 + PROG_NAME_LENGTH + 1;
+    champion[size_at..size_at + 4].copy_from_slice(&size.to_be_bytes());
+    champion
+}
+
+#[test]
+fn valid_champions() {
+    let vm = load(&[(1, sample!(zork).to_vec()), (2, sample!(kappa).to_vec())]).unwrap();
+
+    assert_eq!(vm.players.len(), 2);
+    assert_eq!(vm.players[0].name, "zork");
+    assert_eq!(vm.processes.len(), 2);
+}
+
+#[test]
+fn truncated_header() {
+    let champion = sample!(zork)[..HEADER_SIZE - 1].to_vec();
+
+    assert!(matches!(
+        load_single(champion),
+        Err(LoadError::InvalidChampion(
+            1,
+            ChampionError::TruncatedHeader(_)
+        ))
+    ));
+}
+
+#[test]
+fn invalid_magic() {
+    let mut champion = sample!(zork).to_vec();
+    champion[0] = 0xFF;
+
+    assert!(matches!(
+        load_single(champion),
+        Err(LoadError::InvalidChampion(
+            1,
+            ChampionError::InvalidMagic(_)
+        ))
+    ));
+}
+
+#[test]
+fn size_mismatch() {
+    let mut champion = sample!(zork).to_vec();
+    champion.push(0);
+
+    assert!(matches!(
+        load_single(champion),
+        Err(LoadError::InvalidChampion(
+            1,
+            ChampionError::SizeMismatch(_, _)
+        ))
+    ));
+}
+
+#[test]
+fn champion_too_big() {
+    let mut champion = sample!(zork)[..HEADER_SIZE].to_vec();
+    champion.resize(HEADER_SIZE + 1000, 0);
+    let champion = with_prog_size(champion, 1000);
+
+    assert!(matches!(
+        load_single(champion),
+        Err(LoadError::InvalidChampion(
+            1,
+            ChampionError::TooBig(1000, 682)
+        ))
+    ));
+}
+
+#[test]
+fn invalid_name() {
+    let mut champion = sample!(zork).to_vec();
+    champion[4] = 0xFF;
+
+    assert!(matches!(
+        load_single(champion),
+        Err(LoadError::InvalidChampion(1, ChampionError::InvalidName))
+    ));
+
+    let mut champion = sample!(zork).to_vec();
+    champion[4..4 + PROG_NAME_LENGTH + 1].fill(b'a');
+
+    assert!(matches!(
+        load_single(champion),
+        Err(LoadError::InvalidChampion(1, ChampionError::InvalidName))
+    ));
+}
+
+#[test]
+fn too_many_players() {
+    let players = (1..=5)
+        .map(|id| (id, sample!(zork).to_vec()))
+        .collect::<Vec<_>>();
+
+    assert!(matches!(
+        load(&players),
+        Err(LoadError::TooManyPlayers(5, 4))
+    ));
+}
+
+#[test]
+fn duplicate_player_ids() {
+    let players = [(1, sample!(zork).to_vec()), (1, sample!(kappa).to_vec())];
+
+    assert!(matches!(
+        load(&players),
+        Err(LoadError::DuplicatePlayerId(1))
+    ));
+}
+
+#[test]
+fn limits_count_the_loaded_players() {
+    let mut vm = load(&[(1, sample!(zork).to_vec()), (2, sample!(kappa).to_vec())]).unwrap();
+
+    assert!(matches!(
+        vm.load_players(&[(1, sample!(zork).to_vec())]),
+        Err(LoadError::DuplicatePlayerId(1))
+    ));
+    let players = (3..=5)
+        .map(|id| (id, sample!(zork).to_vec()))
+        .collect::<Vec<_>>();
+    assert!(matches!(
+        vm.load_players(&players),
+        Err(LoadError::TooManyPlayers(5, 4))
+    ));
+    assert_eq!(vm.players.len(), 2);
+}
+
+#[test]
+fn nothing_is_loaded_on_error() {
+    let mut vm = VirtualMachine::new();
+    let players = [(1, sample!(zork).to_vec()), (2, vec![0; 10])];
+
+    assert!(vm.load_players(&players).is_err());
+    assert!(vm.players.is_empty());
+    assert!(vm.processes.is_empty());
+}
@@ -4,4 +4,14 @@ macro_rules! sample {
     };
 }
 
+mod aff;
+mod config;
+mod debugger;
+mod events;
 mod fights;
+mod loader;
+mod outcome;
+mod profiler;
+mod replay;
+mod snapshot;
+mod stats;
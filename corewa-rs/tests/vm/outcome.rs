@@ -0,0 +1,83 @@
+use corewa_rs::vm::{
+    outcome::{MatchOutcome, Termination, TieBreak},
+    VirtualMachine,
+};
+
+fn fight(players: &[(i32, Vec<u8>)]) -> MatchOutcome {
+    let mut vm = VirtualMachine::new();
+    vm.load_players(players).expect("Failed to load players");
+
+    vm.run_to_completion()
+}
+
+#[test]
+fn single_player_wins() {
+    let outcome = fight(&[(1, sample!(zork).to_vec())]);
+
+    assert_eq!(outcome.winner, Some(1));
+    assert_eq!(outcome.tie_break, TieBreak::LastLive);
+    assert_eq!(outcome.termination, Termination::AllProcessesDead);
+    assert_eq!(outcome.final_cycle, 57_955);
+}
+
+#[test]
+fn last_player_reported_alive_wins() {
+    let outcome = fight(&[
+        (1, sample!(kappa).to_vec()),
+        (2, sample!(thunder).to_vec()),
+        (3, sample!(sweepmaster).to_vec()),
+        (4, sample!(skynet).to_vec()),
+    ]);
+
+    assert_eq!(outcome.winner, Some(4));
+    assert_eq!(outcome.tie_break, TieBreak::LastLive);
+    assert_eq!(
+        outcome.last_lives,
+        [(1, 8959), (2, 24344), (3, 839), (4, 24365)]
+    );
+    assert_eq!(outcome.final_cycle, 24367);
+}
+
+#[test]
+fn winner_does_not_depend_on_load_order() {
+    let outcome = fight(&[(1, sample!(zork).to_vec()), (2, sample!(bigzork).to_vec())]);
+    assert_eq!(outcome.winner, Some(2));
+
+    let outcome = fight(&[(1, sample!(bigzork).to_vec()), (2, sample!(zork).to_vec())]);
+    assert_eq!(outcome.winner, Some(1));
+}
+
+#[test]
+fn simultaneous_lives_are_broken_by_load_order() {
+    let outcome = fight(&[(1, sample!(zork).to_vec()), (2, sample!(zork).to_vec())]);
+
+    assert_eq!(outcome.last_live(1), outcome.last_live(2));
+    assert_eq!(outcome.winner, Some(2));
+    assert_eq!(outcome.tie_break, TieBreak::LoadOrder);
+}
+
+#[test]
+fn no_players() {
+    let outcome = fight(&[]);
+
+    assert_eq!(outcome.winner, None);
+    assert_eq!(outcome.final_cycle, 0);
+}
+
+#[test]
+fn cycle_limit() {
+    let mut vm = VirtualMachine::new();
+    vm.load_players(&[(1, sample!(zork).to_vec())])
+        .expect("Failed to load players");
+
+    assert_eq!(vm.outcome(), None);
+
+    let outcome = vm.run_until(1000);
+    assert_eq!(outcome.termination, Termination::CycleLimit);
+    assert_eq!(outcome.final_cycle, 1000);
+    assert_eq!(outcome.winner, Some(1));
+
+    let outcome = vm.run_to_completion();
+    assert_eq!(outcome.termination, Termination::AllProcessesDead);
+    assert_eq!(vm.outcome(), Some(outcome));
+}
@@ -0,0 +1,97 @@
+use crate::compile;
+use corewa_rs::vm::{profiler::Counters, snapshot::History, VirtualMachine};
+
+const LOOPING: &str = r#"
+.name "looping"
+.comment ""
+
+        ld %0, r2
+loop:   live %1
+        .code 255
+        zjmp %:loop
+"#;
+
+fn profiled_vm() -> VirtualMachine {
+    let mut vm = VirtualMachine::new();
+    vm.load_players(&[(1, compile(LOOPING))])
+        .expect("Failed to load players");
+    vm.enable_profiling();
+    vm
+}
+
+#[test]
+fn profiling_is_disabled_by_default() {
+    let mut vm = VirtualMachine::new();
+    vm.load_players(&[(1, compile(LOOPING))])
+        .expect("Failed to load players");
+
+    vm.run_until(100);
+    assert!(vm.profile().is_none());
+}
+
+#[test]
+fn cycles_are_counted_where_they_are_spent() {
+    let mut vm = profiled_vm();
+    // `ld` (5 cycles) then 2 rounds of `live` (10), the invalid byte (1) and
+    // `zjmp` (20)
+    vm.run_until(67);
+
+    let profile = vm.profile().unwrap();
+    let offsets = profile.offsets(&vm.players[0], vm.config.mem_size);
+    assert_eq!(
+        offsets[&7],
+        Counters {
+            executions: 2,
+            wait_cycles: 18,
+            ..Counters::default()
+        }
+    );
+    assert_eq!(
+        offsets[&12],
+        Counters {
+            invalid_op_codes: 2,
+            ..Counters::default()
+        }
+    );
+    assert_eq!(
+        offsets[&13],
+        Counters {
+            executions: 2,
+            wait_cycles: 38,
+            ..Counters::default()
+        }
+    );
+
+    let total = profile.total(1);
+    assert_eq!(total.cycles(), 67);
+    assert_eq!(total.cycles(), offsets.values().map(Counters::cycles).sum());
+
+    let hot_spots = profile.hot_spots(&vm.players[0], vm.config.mem_size);
+    assert_eq!(
+        hot_spots.iter().map(|spot| spot.offset).collect::<Vec<_>>(),
+        [13, 7, 0, 12]
+    );
+}
+
+#[test]
+fn stepping_back_rolls_the_counters_back() {
+    let mut reference = profiled_vm();
+    reference.run_until(100);
+
+    let mut vm = profiled_vm();
+    let mut history = History::new(16, 16);
+    history.record(&vm);
+    while vm.cycles < 150 {
+        vm.tick();
+        history.record(&vm);
+    }
+    assert_eq!(history.step_back(&mut vm, 50), Some(100));
+
+    let (profile, expected) = (vm.profile().unwrap(), reference.profile().unwrap());
+    assert_eq!(profile.total(1), expected.total(1));
+    assert_eq!(profile.total(1).cycles(), 100);
+    assert_eq!(
+        profile.offsets(&vm.players[0], vm.config.mem_size),
+        expected.offsets(&reference.players[0], reference.config.mem_size)
+    );
+}
@@ -0,0 +1,184 @@
+use corewa_rs::{
+    spec::VmConfig,
+    vm::{
+        loader::LoadError,
+        replay::{state_checksum, Replay, ReplayError, ReplayRecorder},
+        VirtualMachine,
+    },
+};
+
+fn players() -> Vec<(i32, Vec<u8>)> {
+    vec![(1, sample!(zork).to_vec()), (2, sample!(bigzork).to_vec())]
+}
+
+fn record(players: &[(i32, Vec<u8>)], checkpoint_interval: u32) -> (Vec<u8>, VirtualMachine) {
+    let mut vm = VirtualMachine::new();
+    vm.load_players(players).expect("Failed to load players");
+
+    let mut recorder = ReplayRecorder::new(Vec::new(), &vm.config, players, checkpoint_interval)
+        .expect("Failed to start recording");
+    while !vm.is_over() {
+        vm.tick();
+        recorder.record(&vm).expect("Failed to record");
+    }
+    let replay = recorder.finish(&vm).expect("Failed to finish recording");
+
+    (replay, vm)
+}
+
+#[test]
+fn playback_matches_recording() {
+    let (recording, recorded_vm) = record(&players(), 500);
+
+    let mut replay = Replay::read(&recording[..]).expect("Failed to read replay");
+    assert_eq!(replay.final_cycle(), recorded_vm.cycles);
+    assert_eq!(replay.players(), &players()[..]);
+
+    let vm = replay.seek(u32::MAX).expect("Failed to seek");
+    assert!(vm.is_over());
+    assert_eq!(vm.outcome(), recorded_vm.outcome());
+    assert_eq!(state_checksum(vm), state_checksum(&recorded_vm));
+}
+
+#[test]
+fn seek_backwards() {
+    let (recording, _) = record(&players(), 500);
+    let mut replay = Replay::read(&recording[..]).expect("Failed to read replay");
+
+    let forward = state_checksum(replay.seek(1234).expect("Failed to seek"));
+    replay.seek(5000).expect("Failed to seek");
+    let vm = replay.seek(1234).expect("Failed to seek");
+
+    assert_eq!(vm.cycles, 1234);
+    assert_eq!(state_checksum(vm), forward);
+}
+
+#[test]
+fn seek_backwards_across_snapshots() {
+    let (recording, _) = record(&players(), 500);
+    let mut replay = Replay::read(&recording[..]).expect("Failed to read replay");
+
+    let cycles = [300, 1000, 4000, 4500];
+    let forward = cycles
+        .iter()
+        .map(|cycle| state_checksum(replay.seek(*cycle).expect("Failed to seek")))
+        .collect::<Vec<_>>();
+
+    for (cycle, checksum) in cycles.iter().zip(forward).rev() {
+        let vm = replay.seek(*cycle).expect("Failed to seek");
+        assert_eq!(vm.cycles, *cycle);
+        assert_eq!(state_checksum(vm), checksum);
+    }
+}
+
+#[test]
+fn seek_backwards_emits_no_events() {
+    let (recording, _) = record(&players(), 500);
+    let mut replay = Replay::read(&recording[..]).expect("Failed to read replay");
+    replay.enable_events();
+
+    replay.seek(1000).expect("Failed to seek");
+    assert!(replay.drain_events().count() > 0);
+
+    replay.seek(900).expect("Failed to seek");
+    assert_eq!(replay.drain_events().count(), 0);
+
+    // Playing forward again emits events
+    replay.seek(1000).expect("Failed to seek");
+    assert!(replay.drain_events().count() > 0);
+}
+
+#[test]
+fn too_many_players_to_record() {
+    let config = VmConfig::default();
+    let players = (1..=5)
+        .map(|id| (id, sample!(zork).to_vec()))
+        .collect::<Vec<_>>();
+
+    assert!(matches!(
+        ReplayRecorder::new(Vec::new(), &config, &players, 100),
+        Err(ReplayError::LoadError(LoadError::TooManyPlayers(5, 4)))
+    ));
+}
+
+#[test]
+fn interrupted_recording_ends_at_last_checkpoint() {
+    let players = players();
+    let mut vm = VirtualMachine::new();
+    vm.load_players(&players).expect("Failed to load players");
+
+    let mut recorder = ReplayRecorder::new(Vec::new(), &vm.config, &players, 100)
+        .expect("Failed to start recording");
+    for _ in 0..250 {
+        vm.tick();
+        recorder.record(&vm).expect("Failed to record");
+    }
+    let mut recording = recorder.finish(&vm).expect("Failed to finish recording");
+    // Strip the end record (tag + final cycle)
+    recording.truncate(recording.len() - 5);
+
+    let replay = Replay::read(&recording[..]).expect("Failed to read replay");
+    assert_eq!(replay.final_cycle(), 200);
+}
+
+#[test]
+fn tampered_recording_is_detected() {
+    let players = players();
+    let (mut recording, _) = record(&players, 500);
+
+    // Flip a byte of the first checkpoint's checksum (after its tag and cycle)
+    recording[header_len(&players) + 5] ^= 0xFF;
+
+    let mut replay = Replay::read(&recording[..]).expect("Failed to read replay");
+    assert!(matches!(replay.seek(1000), Err(ReplayError::Desync(500))));
+}
+
+#[test]
+fn seek_forwards_from_stored_snapshot() {
+    let players = players();
+    let (recording, _) = record(&players, 500);
+
+    let mut vm = VirtualMachine::new();
+    vm.load_players(&players).expect("Failed to load players");
+    while vm.cycles < 4500 {
+        vm.tick();
+    }
+
+    let mut replay = Replay::read(&recording[..]).expect("Failed to read replay");
+    let played = replay.seek(4500).expect("Failed to seek");
+    assert_eq!(played.cycles, 4500);
+    assert_eq!(state_checksum(played), state_checksum(&vm));
+}
+
+#[test]
+fn tampered_snapshot_is_detected() {
+    let players = players();
+    let (mut recording, recorded_vm) = record(&players, 500);
+
+    // The first snapshot follows the 8th checkpoint, flip a byte of its memory
+    let checkpoint_len = 1 + 4 + 8;
+    let snapshot_start = header_len(&players) + 8 * checkpoint_len + 1;
+    let player_states: usize = recorded_vm
+        .players
+        .iter()
+        .map(|player| 20 + player.name.len() + player.comment.len())
+        .sum();
+    let memory_start = snapshot_start + 6 * 4 + 4 + player_states;
+    recording[memory_start + 10] ^= 0xFF;
+
+    let mut replay = Replay::read(&recording[..]).expect("Failed to read replay");
+    assert!(matches!(replay.seek(4500), Err(ReplayError::Desync(4000))));
+}
+
+fn header_len(players: &[(i32, Vec<u8>)]) -> usize {
+    let champions: usize = players.iter().map(|(_, code)| 8 + code.len()).sum();
+    4 + 1 + 9 * 4 + 1 + champions + 4
+}
+
+#[test]
+fn invalid_magic() {
+    assert!(matches!(
+        Replay::read(&b"nope, not a replay"[..]),
+        Err(ReplayError::InvalidMagic)
+    ));
+}
@@ -0,0 +1,92 @@
+use corewa_rs::vm::{replay::state_checksum, snapshot::History, VirtualMachine};
+
+fn four_players() -> VirtualMachine {
+    let mut vm = VirtualMachine::new();
+    vm.load_players(&[
+        (1, sample!(kappa).to_vec()),
+        (2, sample!(thunder).to_vec()),
+        (3, sample!(sweepmaster).to_vec()),
+        (4, sample!(skynet).to_vec()),
+    ])
+    .expect("Failed to load players");
+    vm
+}
+
+#[test]
+fn restore_resumes_the_match_identically() {
+    let mut vm = four_players();
+    vm.run_until(3000);
+    let snapshot = vm.snapshot();
+
+    let expected = vm.run_to_completion();
+
+    vm.restore(&snapshot);
+    assert_eq!(vm.cycles, 3000);
+    assert_eq!(vm.run_to_completion(), expected);
+}
+
+#[test]
+fn restore_brings_back_the_captured_state() {
+    let mut vm = four_players();
+    vm.run_until(1500);
+    let snapshot = vm.snapshot();
+    let checksum = state_checksum(&vm);
+
+    vm.run_until(2500);
+    assert_ne!(state_checksum(&vm), checksum);
+
+    vm.restore(&snapshot);
+    assert_eq!(state_checksum(&vm), checksum);
+}
+
+#[test]
+fn step_back() {
+    let mut reference = four_players();
+    reference.run_until(1000);
+    let expected = state_checksum(&reference);
+
+    let mut vm = four_players();
+    let mut history = History::new(64, 16);
+    history.record(&vm);
+    while vm.cycles < 1100 {
+        vm.tick();
+        history.record(&vm);
+    }
+
+    assert_eq!(history.step_back(&mut vm, 100), Some(1000));
+    assert_eq!(state_checksum(&vm), expected);
+}
+
+#[test]
+fn step_back_is_limited_by_history_capacity() {
+    let mut vm = four_players();
+    let mut history = History::new(10, 4);
+    history.record(&vm);
+    while vm.cycles < 100 {
+        vm.tick();
+        history.record(&vm);
+    }
+
+    assert_eq!(history.oldest_cycle(), Some(70));
+    assert_eq!(history.step_back(&mut vm, 1000), Some(70));
+}
+
+#[test]
+fn step_back_does_not_replay_events() {
+    let mut vm = four_players();
+    vm.enable_events();
+    let mut history = History::default();
+    history.record(&vm);
+    while vm.cycles < 200 {
+        vm.tick();
+        history.record(&vm);
+    }
+    vm.drain_events().for_each(drop);
+
+    history.step_back(&mut vm, 10);
+    assert_eq!(vm.cycles, 190);
+    assert_eq!(vm.drain_events().count(), 0);
+
+    vm.tick();
+    assert!(vm.drain_events().all(|event| event.cycle == 190));
+}
@@ -0,0 +1,149 @@
+use crate::compile;
+use corewa_rs::{
+    spec::VmConfig,
+    vm::{
+        stats::{CheckIntervalChange, PlayerSample, Sample},
+        VirtualMachine,
+    },
+};
+
+const ATTACKER: &str = r#"
+.name "attacker"
+.comment ""
+
+loop:   live %1
+        st r1, 522
+        ld %0, r2
+        zjmp %:loop
+"#;
+
+// The attacker overwrites the trailing 4 bytes
+const DEFENDER: &str = r#"
+.name "defender"
+.comment ""
+
+loop:   live %2
+        ld %0, r2
+        zjmp %:loop
+        .code 0 0 0 0
+"#;
+
+/// The defender is loaded 512 bytes after the attacker.
+/// The first live check shortens the check interval and the second one kills
+/// every process
+fn vm_with_stats() -> VirtualMachine {
+    let config = VmConfig {
+        mem_size: 1024,
+        idx_mod: 1024,
+        check_interval: 10,
+        cycle_delta: 5,
+        nbr_live: 2,
+        ..VmConfig::default()
+    };
+    let mut vm = VirtualMachine::with_config(config);
+    vm.load_players(&[(1, compile(ATTACKER)), (2, compile(DEFENDER))])
+        .expect("Failed to load players");
+    vm.enable_stats(10);
+    vm
+}
+
+fn player(
+    player_id: i32,
+    owned_cells: usize,
+    overwritten: (i32, usize),
+    live_reports: u32,
+    total_live_reports: u32,
+    processes: u32,
+) -> PlayerSample {
+    PlayerSample {
+        player_id,
+        owned_cells,
+        overwritten: vec![overwritten],
+        live_reports,
+        total_live_reports,
+        processes,
+    }
+}
+
+#[test]
+fn stats_are_disabled_by_default() {
+    let mut vm = VirtualMachine::new();
+    vm.load_players(&[(1, compile(ATTACKER))])
+        .expect("Failed to load players");
+
+    vm.run_until(100);
+    assert!(vm.stats().is_none());
+}
+
+#[test]
+fn samples() {
+    let mut vm = vm_with_stats();
+    vm.run_to_completion();
+    let stats = vm.stats().unwrap();
+
+    assert_eq!(
+        stats.samples,
+        [
+            Sample {
+                cycle: 10,
+                check_interval: 5,
+                players: vec![
+                    player(1, 20, (2, 0), 1, 1, 1),
+                    player(2, 19, (1, 0), 1, 1, 1)
+                ],
+            },
+            Sample {
+                cycle: 15,
+                check_interval: 5,
+                players: vec![
+                    player(1, 24, (2, 4), 0, 1, 0),
+                    player(2, 15, (1, 0), 0, 1, 0)
+                ],
+            },
+        ]
+    );
+    assert_eq!(
+        stats.check_interval_changes,
+        [CheckIntervalChange {
+            cycle: 10,
+            check_interval: 5
+        }]
+    );
+    assert_eq!(stats.series(2, |sample| sample.processes), [1, 0]);
+}
+
+#[test]
+fn rewinding_drops_later_samples() {
+    let mut vm = vm_with_stats();
+    vm.run_until(12);
+    let snapshot = vm.snapshot();
+
+    vm.run_to_completion();
+    let samples = vm.stats().unwrap().samples.clone();
+
+    vm.restore(&snapshot);
+    assert_eq!(vm.stats().unwrap().samples.len(), 1);
+
+    vm.run_to_completion();
+    assert_eq!(vm.stats().unwrap().samples, samples);
+}
+
+#[test]
+fn csv_export() {
+    let mut vm = vm_with_stats();
+    vm.run_to_completion();
+    let stats = vm.stats().unwrap();
+
+    let mut csv = Vec::new();
+    stats.write_csv(&mut csv).unwrap();
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "\
+cycle,check_interval,player_id,owned_cells,processes,live_reports,overwritten_1,overwritten_2
+10,5,1,20,1,1,0,0
+10,5,2,19,1,1,0,0
+15,5,1,24,0,0,0,4
+15,5,2,15,0,0,0,0
+"
+    );
+}
@@ -3,18 +3,36 @@ import CodeMirror from "codemirror";
 
 import { champions } from "../assets/champions";
 import { observer } from "mobx-react";
-
-import type { CompileError, Region } from "corewa-rs";
-import { compile_champion } from "corewa-rs";
+import { comparer, reaction, IReactionDisposer } from "mobx";
+
+import type {
+  CompileError,
+  CompileWarning,
+  Region,
+  SourceMap,
+} from "corewa-rs";
+import {
+  compile_champion,
+  champion_source_map,
+  champion_warnings,
+} from "corewa-rs";
 
 type CompiledChampion = Uint8Array;
 
 interface IEditorProps {
   config: any;
-  onCodeChanged: (code: string, champion: CompiledChampion | null) => void;
+  onCodeChanged: (
+    code: string,
+    champion: CompiledChampion | null,
+    sourceMap: SourceMap | null
+  ) => void;
   onClosed: () => void;
+  // The lines currently executed by the champion's processes
+  executedLines: () => number[];
 }
 
+const EXECUTED_LINE_CLASS = "executed-line";
+
 function randomChampionName() {
   const keys = Object.keys(champions);
   return keys[(keys.length * Math.random()) << 0];
@@ -26,6 +44,8 @@ export class Editor extends React.Component<IEditorProps> {
   debounceId: number = 0;
   editor: CodeMirror.Editor | null = null;
   initialChampion = randomChampionName();
+  highlightedLines: number[] = [];
+  disposeHighlight?: IReactionDisposer;
 
   componentDidMount() {
     const container = this.domContainer.current;
@@ -63,15 +83,38 @@ export class Editor extends React.Component<IEditorProps> {
       }, 0);
 
       this.editor = editor;
+
+      this.disposeHighlight = reaction(
+        () => this.props.executedLines(),
+        (lines) => this.highlightLines(lines),
+        { equals: comparer.structural }
+      );
     }
   }
 
+  highlightLines(lines: number[]) {
+    const editor = this.editor;
+    if (!editor) return;
+
+    editor.operation(() => {
+      this.highlightedLines.forEach((line) =>
+        editor.removeLineClass(line - 1, "background", EXECUTED_LINE_CLASS)
+      );
+      lines.forEach((line) =>
+        editor.addLineClass(line - 1, "background", EXECUTED_LINE_CLASS)
+      );
+    });
+    this.highlightedLines = lines;
+  }
+
   compile(code: string) {
     let champion = compile_champion(code);
-    this.props.onCodeChanged(code, champion);
+    let sourceMap = champion_source_map(code);
+    this.props.onCodeChanged(code, champion, sourceMap);
   }
 
   componentWillUnmount() {
+    this.disposeHighlight?.();
     this.props.onClosed();
   }
 
@@ -117,31 +160,37 @@ CodeMirror.registerHelper(
   function (code: string, opts: { editor: Editor }) {
     try {
       opts.editor.compile(code);
-      return [];
+      return champion_warnings(code).map((warning: CompileWarning) => ({
+        from: CodeMirror.Pos(warning.line - 1, 0),
+        to: CodeMirror.Pos(warning.line - 1, 5000),
+        message: warning.reason(),
+        severity: "warning",
+      }));
     } catch (err) {
-      opts.editor.props.onCodeChanged(code, null);
-      const compileError = err as CompileError;
-      const region = compileError.region() as Region | null;
-      let [from_row, from_col, to_row, to_col] = (() => {
-        if (region != null)
-          return [
-            region.from_row - 1,
-            region.from_col,
-            region.to_row - 1,
-            region.to_col,
-          ];
-        else return [0, 0, 5000, 5000];
-      })();
-
-      if (from_col == to_col) ++to_col;
-
-      return [
-        {
+      opts.editor.props.onCodeChanged(code, null, null);
+      // Compilation reports every error at once
+      const compileErrors = (Array.isArray(err) ? err : [err]) as CompileError[];
+      return compileErrors.map((compileError) => {
+        const region = compileError.region() as Region | null;
+        let [from_row, from_col, to_row, to_col] = (() => {
+          if (region != null)
+            return [
+              region.from_row - 1,
+              region.from_col,
+              region.to_row - 1,
+              region.to_col,
+            ];
+          else return [0, 0, 5000, 5000];
+        })();
+
+        if (from_col == to_col) ++to_col;
+
+        return {
           from: CodeMirror.Pos(from_row, from_col),
           to: CodeMirror.Pos(to_row, to_col),
           message: compileError.reason(),
-        },
-      ];
+        };
+      });
     }
   }
 );
@@ -186,7 +235,17 @@ const COMMENT_CHAR = "#";
 CodeMirror.defineMode(ASM_LANGUAGE_ID, function (_config, _parserConfig) {
   const lineCommentStartSymbol = COMMENT_CHAR;
 
-  const directives = new Set([".name", ".comment", ".code"]);
+  const directives = new Set([
+    ".name",
+    ".comment",
+    ".code",
+    ".define",
+    ".macro",
+    ".endm",
+    ".rept",
+    ".endr",
+    ".include",
+  ]);
 
   const KEYWORDS = new Set(ALL_KEYWORDS.map(([kw, ..._]) => kw));
 
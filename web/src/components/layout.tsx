@@ -45,13 +45,15 @@ export class CorewarLayout extends React.Component<ICorewarLayoutProps> {
         return (
           <Editor
             config={config}
-            onCodeChanged={(code, champion) => {
+            onCodeChanged={(code, champion, sourceMap) => {
               config.code = code;
               this.onModelChange(this.model);
               player.champion = champion;
+              player.sourceMap = sourceMap;
               vm.compile();
             }}
             onClosed={() => vm.removePlayer(player.id)}
+            executedLines={() => vm.executedLines(player.id)}
           />
         );
       case PaneComponent.VM:
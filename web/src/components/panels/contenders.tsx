@@ -45,6 +45,7 @@ export const ContendersPanel = observer(({ vm, coverages }: Props) => {
             )} %`}</Info>
             <Info title="Processes">{championInfo.process_count}</Info>
             <Info title="Last live">{championInfo.last_live}</Info>
+            <Info title="Output">{vm.engine.player_output(player.id)}</Info>
           </details>
         );
       })}
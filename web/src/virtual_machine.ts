@@ -1,12 +1,14 @@
 import { observable, action, makeObservable } from "mobx";
 
-import type { PlayerInfo } from "corewa-rs";
-import { VMBuilder } from "corewa-rs";
+import type { PlayerInfo, SourceMap } from "corewa-rs";
+import { VMBuilder, VirtualMachine as VMEngine } from "corewa-rs";
 
 export type Player = {
   id: number;
   color: number;
   champion: Uint8Array | null;
+  // Maps the champion's code back to its source, when compiled from an editor
+  sourceMap: SourceMap | null;
 };
 
 export type MatchResult = PlayerInfo[];
@@ -44,6 +46,9 @@ export class VirtualMachine {
   playersById = new Map<number, Player>();
   matchResult?: MatchResult;
 
+  // The recorded match being played back, if any
+  replay?: Uint8Array;
+
   constructor() {
     makeObservable(this, {
       cycles: observable,
@@ -59,6 +64,8 @@ export class VirtualMachine {
       playLoop: action,
       compile: action,
       compileImpl: action,
+      loadReplay: action,
+      restart: action,
       removePlayer: action,
       togglePlay: action,
       play: action,
@@ -85,7 +92,7 @@ export class VirtualMachine {
   newPlayer() {
     const id = this.randomPlayerId();
     const color = PLAYER_COLORS[this.playersById.size];
-    const player = { id, color, champion: null };
+    const player = { id, color, champion: null, sourceMap: null };
     this.playersById.set(id, player);
     // ⚠ cannot return player directly because of the observable map
     return this.playersById.get(id) as Player;
@@ -135,20 +142,10 @@ export class VirtualMachine {
   }
 
   updateMatchResult() {
-    const info = Array.from(this.playersById.keys()).map((playerId) => [
-      this.engine.player_info(playerId),
-      this.engine.champion_info(playerId),
-    ]);
-
-    const latestLive = Math.max(
-      ...info.map(([_, championInfo]) => championInfo.last_live)
-    );
-
-    const playersWithLatestLives = info
-      .filter(([_, championInfo]) => championInfo.last_live == latestLive)
-      .map(([playerInfo, _]) => playerInfo);
+    const winner = this.engine.outcome()?.winner();
 
-    this.matchResult = playersWithLatestLives;
+    this.matchResult =
+      winner === undefined ? [] : [this.engine.player_info(winner)];
   }
 
   playLoop() {
@@ -163,6 +160,28 @@ export class VirtualMachine {
   }
 
   compile() {
+    this.replay = undefined;
+    this.restart();
+  }
+
+  loadReplay(replay: Uint8Array) {
+    try {
+      const engine = VMEngine.from_replay(replay);
+      this.pause();
+      this.replay = replay;
+      this.playersById = new Map(
+        Array.from(engine.player_ids()).map((id, idx) => [
+          id,
+          { id, color: PLAYER_COLORS[idx], champion: null, sourceMap: null },
+        ])
+      );
+      this.restart();
+    } catch (err) {
+      console.error(`Failed to load the replay: ${err}`);
+    }
+  }
+
+  restart() {
     this.pause();
     this.matchResult = undefined;
     this.cycles = undefined; // effectively resets the VM observed
@@ -170,19 +189,47 @@ export class VirtualMachine {
   }
 
   compileImpl() {
-    this.engine = Array.from(this.playersById.values())
-      .reduce(
-        (builder, player) =>
-          player.champion
-            ? builder.with_player(player.id, player.champion)
-            : builder,
-        new VMBuilder()
-      )
-      .finish();
+    if (this.replay) {
+      this.engine = VMEngine.from_replay(this.replay);
+      this.cycles = this.engine.cycles();
+      return;
+    }
+
+    try {
+      this.engine = Array.from(this.playersById.values())
+        .reduce(
+          (builder, player) =>
+            player.champion
+              ? builder.with_player(player.id, player.champion)
+              : builder,
+          new VMBuilder()
+        )
+        .finish();
+    } catch (err) {
+      console.error(`Failed to load the champions: ${err}`);
+      this.engine = new VMBuilder().finish();
+    }
 
     this.cycles = this.engine.cycles();
   }
 
+  // The source lines executed by the processes of a player, starting at 1
+  executedLines(playerId: number): number[] {
+    // Observe the cycles to be notified of every tick
+    if (this.cycles === undefined) return [];
+
+    const sourceMap = this.playersById.get(playerId)?.sourceMap;
+    if (!sourceMap) return [];
+
+    const lines = new Set<number>();
+    this.engine.process_code_offsets(playerId).forEach((offset) => {
+      const line = sourceMap.line_at(offset);
+      if (line !== undefined) lines.add(line);
+    });
+
+    return Array.from(lines).sort((a, b) => a - b);
+  }
+
   removePlayer(playerId: number) {
     this.playersById.delete(playerId);
     Array.from(this.playersById.values()).forEach((player, idx) => {
@@ -212,7 +259,7 @@ export class VirtualMachine {
 
   stop() {
     this.pause();
-    this.compile();
+    this.restart();
   }
 
   step() {
@@ -232,7 +279,7 @@ export class VirtualMachine {
     if (cycles <= cycle) {
       this.tick(cycle - cycles);
     } else {
-      this.compile();
+      this.restart();
       this.tick(cycle);
     }
   }